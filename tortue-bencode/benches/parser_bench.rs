@@ -2,7 +2,11 @@ use criterion::{
     black_box, criterion_group, criterion_main, Criterion, Throughput,
 };
 use serde::{Deserialize, Serialize};
-use tortue_bencode::{from_bytes, parser::parse};
+use tortue_bencode::{
+    from_bytes,
+    parser::{lazy, parse, parse_owned},
+    BencodedValue,
+};
 
 const DATA: &[u8] = include_bytes!("test_data");
 
@@ -71,5 +75,92 @@ pub fn throughput_benchmark(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, throughput_benchmark);
+pub fn owned_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("owned_throughput");
+    group.throughput(Throughput::Bytes(DATA.len() as u64));
+
+    group.bench_function("parse_into_owned", |b| {
+        b.iter(|| parse(black_box(DATA)).map(|(_, value)| value.into_owned()))
+    });
+
+    group.bench_function("parse_owned", |b| {
+        b.iter(|| parse_owned(black_box(DATA)))
+    });
+}
+
+pub fn lazy_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("two_keys");
+    group.throughput(Throughput::Bytes(DATA.len() as u64));
+
+    group.bench_function("parse", |b| {
+        b.iter(|| {
+            let (_, value) = parse(black_box(DATA)).unwrap();
+            let dict = value.unwrap_dict();
+            let announce = dict.get("announce").cloned();
+            let name = match dict.get("info") {
+                Some(BencodedValue::Dictionary(info)) => {
+                    info.get("name").cloned()
+                }
+                _ => None,
+            };
+
+            (announce, name)
+        })
+    });
+
+    group.bench_function("lazy", |b| {
+        b.iter(|| {
+            let value = lazy(black_box(DATA)).unwrap();
+            let announce = value.get("announce").map(|v| v.parse());
+            let name = value
+                .get("info")
+                .and_then(|info| info.get("name"))
+                .map(|v| v.parse());
+
+            (announce, name)
+        })
+    });
+}
+
+pub fn bytes_seq_benchmark(c: &mut Criterion) {
+    #[derive(Deserialize)]
+    struct Seq {
+        pieces: Vec<u8>,
+    }
+
+    #[derive(Deserialize)]
+    struct Bytes {
+        #[serde(with = "serde_bytes")]
+        pieces: Vec<u8>,
+    }
+
+    let mut data = b"d6:pieces1048576:".to_vec();
+    data.extend((0..1 << 20).map(|i| i as u8));
+    data.push(b'e');
+
+    let mut group = c.benchmark_group("bytes_seq");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+
+    group.bench_function("seq", |b| {
+        b.iter(|| {
+            let seq = from_bytes::<Seq>(black_box(&data)).unwrap();
+            black_box(seq.pieces.len())
+        })
+    });
+
+    group.bench_function("bytes", |b| {
+        b.iter(|| {
+            let bytes = from_bytes::<Bytes>(black_box(&data)).unwrap();
+            black_box(bytes.pieces.len())
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    throughput_benchmark,
+    owned_benchmark,
+    lazy_benchmark,
+    bytes_seq_benchmark
+);
 criterion_main!(benches);
@@ -3,7 +3,7 @@ use criterion::{
 };
 use serde::{Deserialize, Serialize};
 use tortue_bencode::{
-    from_value, parser::parse, to_value, to_writer, writer::write,
+    from_value, parser::parse, to_bytes, to_value, to_writer, writer::write,
 };
 
 const DATA: &[u8] = include_bytes!("test_data");
@@ -86,7 +86,32 @@ pub fn throughput_benchmark(c: &mut Criterion) {
             to_writer(black_box(&value), &mut out)
         })
     });
+
+    group
+        .bench_function("to_bytes", |b| b.iter(|| to_bytes(black_box(&value))));
+}
+
+#[derive(Serialize)]
+struct File {
+    length: i64,
+    path: Vec<String>,
+}
+
+pub fn many_files_benchmark(c: &mut Criterion) {
+    let files = (0..100_000)
+        .map(|i| File {
+            length: i,
+            path: vec!["dir".to_owned(), format!("file-{}", i)],
+        })
+        .collect::<Vec<_>>();
+
+    let mut group = c.benchmark_group("many_files");
+    group.throughput(Throughput::Elements(files.len() as u64));
+
+    group.bench_function("serialize_100k", |b| {
+        b.iter(|| to_value(black_box(&files)))
+    });
 }
 
-criterion_group!(benches, throughput_benchmark);
+criterion_group!(benches, throughput_benchmark, many_files_benchmark);
 criterion_main!(benches);
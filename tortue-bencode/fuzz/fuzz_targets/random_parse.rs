@@ -1,9 +1,21 @@
 #![no_main]
 use libfuzzer_sys::fuzz_target;
 
-use tortue_bencode::{ parse, writer::write};
+use tortue_bencode::{ parse, parser::{ parse_with_options, ParserOptions }, writer::write};
 
 fuzz_target!(|data: &[u8]| {
+    // A shallow parse either agrees with the default one or hits the limit
+    let shallow = ParserOptions { max_depth: 4, ..ParserOptions::default() };
+    if let Ok(parsed) = parse_with_options(data, &shallow) {
+        assert_eq!(Ok(parsed), parse(data));
+    }
+
+    // Parsing every string as binary only changes the classification
+    let binary = ParserOptions { binary_strings: true, ..ParserOptions::default() };
+    if let Ok(parsed) = parse_with_options(data, &binary) {
+        assert_eq!(Ok(parsed), parse(data));
+    }
+
     if let Ok((_r, parsed)) = parse(data) {
         let mut bytes = Vec::with_capacity(data.len());
         assert!(write(&parsed, &mut bytes).is_ok());
@@ -11,6 +23,9 @@ fuzz_target!(|data: &[u8]| {
         let parsed_again = parse(&bytes[..]);
         assert!(parsed_again.is_ok());
 
-        assert_eq!(parsed, parsed_again.unwrap().1);
+        let parsed_again = parsed_again.unwrap().1;
+        assert_eq!(parsed, parsed_again);
+        assert_eq!(parsed_again, parsed);
+        assert_eq!(parsed.len(), parsed_again.len());
     }
 });
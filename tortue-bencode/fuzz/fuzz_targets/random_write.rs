@@ -3,14 +3,39 @@ use libfuzzer_sys::fuzz_target;
 
 use tortue_bencode::{ BencodedValue, parse, writer::write };
 
+/// Checks if a none is nested somewhere in a list or a dictionary
+fn has_nested_none(value: &BencodedValue) -> bool {
+    match value {
+        BencodedValue::List(list) => list.iter().any(|v| v.is_none() || has_nested_none(v)),
+        BencodedValue::Dictionary(dict) => dict.values().any(|v| v.is_none() || has_nested_none(v)),
+        _ => false,
+    }
+}
+
 fuzz_target!(|data: BencodedValue| {
     let mut bytes = vec![];
-    assert!(write(&data, &mut bytes).is_ok());
+    let written = write(&data, &mut bytes);
+
+    if data.is_none() {
+        assert!(written.is_ok());
+        assert!(bytes.is_empty());
+        return;
+    }
+
+    if has_nested_none(&data) {
+        assert!(written.is_err());
+        return;
+    }
+
+    assert!(written.is_ok());
 
     let parsed = parse(&bytes);
     assert!(parsed.is_ok());
 
-    assert_eq!(parsed.unwrap().1, data);
+    let parsed = parsed.unwrap().1;
+    assert_eq!(parsed, data);
+    assert_eq!(data, parsed);
+    assert_eq!(parsed.len(), data.len());
 
     std::mem::drop(data);
 });
@@ -0,0 +1,54 @@
+//! Serializes byte containers as bencoded strings, for use with
+//! `#[serde(with = "tortue_bencode::bytes")]`
+//!
+//! Serde serializes `Vec<u8>`, `&[u8]` and `[u8; N]` as sequences, which
+//! would be written as lists of integers. The deserializer already reads
+//! strings into those types, only the serializing side needs this module.
+//! See [`bytes_array`](crate::bytes_array) to only accept strings of the
+//! exact length of an array.
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+/// Serializes the bytes as a bencoded string
+pub fn serialize<T: ?Sized + AsRef<[u8]>, S: Serializer>(
+    bytes: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bytes(bytes.as_ref())
+}
+
+/// Deserializes the bytes from a bencoded string or list of integers
+pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    T::deserialize(deserializer)
+}
+
+#[cfg(test)]
+mod bytes_tests {
+    use crate::{from_bytes, to_bytes};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Peer<'a> {
+        #[serde(with = "crate::bytes")]
+        id: [u8; 4],
+        #[serde(with = "crate::bytes", borrow)]
+        ip: &'a [u8],
+        #[serde(with = "crate::bytes")]
+        key: Vec<u8>,
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let peer = Peer {
+            id: *b"abcd",
+            ip: &[127, 0, 0, 1],
+            key: b"secret".to_vec(),
+        };
+
+        let bytes = to_bytes(&peer).unwrap();
+        assert_eq!(bytes, b"d2:id4:abcd2:ip4:\x7f\x00\x00\x013:key6:secrete");
+        assert_eq!(from_bytes::<Peer>(&bytes), Ok(peer));
+    }
+}
@@ -0,0 +1,101 @@
+//! Serializes fixed-size byte arrays such as info-hashes as bencoded
+//! strings, for use with `#[serde(with = "tortue_bencode::bytes_array")]`
+//!
+//! Serde serializes `[u8; N]` as a tuple, which would be written as a list of
+//! integers. Deserializing requires a string of exactly `N` bytes.
+
+use core::{convert::TryFrom, fmt};
+use serde::{
+    de::{self, Visitor},
+    Deserializer, Serializer,
+};
+
+/// Serializes the array as a bencoded string
+pub fn serialize<S: Serializer, const N: usize>(
+    bytes: &[u8; N],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bytes(bytes)
+}
+
+/// Deserializes the array from a bencoded string of exactly `N` bytes
+pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[u8; N], D::Error> {
+    deserializer.deserialize_bytes(ArrayVisitor)
+}
+
+struct ArrayVisitor<const N: usize>;
+
+impl<'de, const N: usize> Visitor<'de> for ArrayVisitor<N> {
+    type Value = [u8; N];
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a string of {} bytes", N)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<[u8; N], E> {
+        <[u8; N]>::try_from(v).map_err(|_| E::invalid_length(v.len(), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<[u8; N], E> {
+        self.visit_bytes(v.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod bytes_array_tests {
+    use crate::{error::Error, from_bytes, to_bytes, ParserOptions};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Hashes {
+        #[serde(with = "crate::bytes_array")]
+        v1: [u8; 20],
+        #[serde(with = "crate::bytes_array")]
+        v2: [u8; 32],
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let hashes = Hashes {
+            v1: [0xAB; 20],
+            v2: [0xCD; 32],
+        };
+
+        let bytes = to_bytes(&hashes).unwrap();
+        let mut expected = b"d2:v120:".to_vec();
+        expected.extend_from_slice(&[0xAB; 20]);
+        expected.extend_from_slice(b"2:v232:");
+        expected.extend_from_slice(&[0xCD; 32]);
+        expected.push(b'e');
+        assert_eq!(bytes, expected);
+
+        assert_eq!(from_bytes::<Hashes>(&bytes), Ok(hashes));
+
+        let binary = ParserOptions {
+            binary_strings: true,
+            ..ParserOptions::default()
+        };
+        assert!(
+            crate::from_bytes_with_options::<Hashes>(&bytes, &binary).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_wrong_length() {
+        let mut input = b"d2:v119:".to_vec();
+        input.extend_from_slice(&[0xAB; 19]);
+        input.extend_from_slice(b"2:v232:");
+        input.extend_from_slice(&[0xCD; 32]);
+        input.push(b'e');
+
+        assert_eq!(
+            from_bytes::<Hashes>(&input),
+            Err(Error::Message(
+                "invalid length 19, expected a string of 20 bytes".to_owned()
+            ))
+        );
+        assert!(from_bytes::<Hashes>(b"d2:v1li1ee2:v21:ae").is_err());
+    }
+}
@@ -1,11 +1,55 @@
-use crate::{error::Error, parser, BencodedValue};
-use serde::{de, Deserialize};
+use crate::prelude::*;
+use crate::{
+    error::Error,
+    parser::{self, BoolMode, ParserOptions},
+    ser::{CONTENT_KEY, TAG_KEY},
+    BencodedValue, Key,
+};
+use core::{any, convert::TryFrom, str::FromStr};
+use serde::{
+    de::{self, DeserializeSeed},
+    Deserialize,
+};
+use tokens::TokenDeserializer;
 
 mod map;
+mod reference;
 mod seq;
+mod tokens;
+mod variant;
 
+pub use reference::RefDeserializer;
+
+/// Deserializer of a parsed [`BencodedValue`], strings and binaries borrowed
+/// from the input can be read as `&str` and `&[u8]`
+///
+/// The free functions use it, it is public for the implementations of
+/// [`Deserialize`] and [`DeserializeSeed`] which need to deserialize a value
+/// they parsed themselves.
 pub struct Deserializer<'data> {
     input: BencodedValue<'data>,
+
+    /// Whether the input is a dictionary key, which may hold an integer
+    key: bool,
+
+    /// Options read by the deserializer, passed on to the nested values
+    settings: Settings,
+}
+
+/// Options of the parser read by the deserializer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Settings {
+    bools: BoolMode,
+    positional_structs: bool,
+}
+
+impl From<&ParserOptions> for Settings {
+    fn from(options: &ParserOptions) -> Self {
+        Settings {
+            bools: options.bools,
+            positional_structs: options.positional_structs,
+        }
+    }
 }
 
 /// Deserializes a data structure from an already parsed value
@@ -15,59 +59,261 @@ pub fn from_value<'de, T: Deserialize<'de>>(
     T::deserialize(Deserializer::from_value(value))
 }
 
+/// Deserializes a data structure borrowing from a value, `&str` and `&[u8]`
+/// can then be read out of owned strings and binaries and the value can be
+/// read again, see [`RefDeserializer`]
+pub fn from_value_ref<'de, T: Deserialize<'de>>(
+    value: &'de BencodedValue<'_>,
+) -> Result<T, Error> {
+    T::deserialize(RefDeserializer::new(value))
+}
+
+/// Deserializes a data structure from an already parsed value using a seed,
+/// such as a visitor holding state across values
+pub fn from_value_seed<'de, S: DeserializeSeed<'de>>(
+    value: BencodedValue<'de>,
+    seed: S,
+) -> Result<S::Value, Error> {
+    seed.deserialize(Deserializer::from_value(value))
+}
+
+/// Deserializes a data structure from a slice of bytes using a seed, such as
+/// a visitor interning strings or reusing buffers across inputs
+///
+/// The input is parsed before being deserialized, as the seed cannot be used
+/// again to report the errors of the parser.
+pub fn from_bytes_seed<'de, S: DeserializeSeed<'de>>(
+    data: &'de [u8],
+    seed: S,
+) -> Result<S::Value, Error> {
+    seed.deserialize(Deserializer::from_slice(data)?)
+}
+
 /// Deserializes a data structure from a slice of bytes
 pub fn from_bytes<'de, T: Deserialize<'de>>(
     data: &'de [u8],
 ) -> Result<T, Error> {
-    T::deserialize(Deserializer::new(data)?)
+    from_bytes_with_options(data, &ParserOptions::default())
+}
+
+/// Deserializes a data structure from a slice of bytes parsed using the given
+/// options, use it to bound the resources spent on untrusted input
+pub fn from_bytes_with_options<'de, T: Deserialize<'de>>(
+    data: &'de [u8],
+    options: &ParserOptions,
+) -> Result<T, Error> {
+    // The tokens are read directly when possible, the tree is only built
+    // for the inputs they read differently
+    let deserializer = if TokenDeserializer::supports(data, options) {
+        let mut deserializer = TokenDeserializer::new(data, options);
+        let error = match T::deserialize(&mut deserializer) {
+            Ok(value) => match deserializer.finish() {
+                Ok(()) => return Ok(value),
+                Err(error) => error,
+            },
+            Err(error) => error,
+        };
+
+        if TokenDeserializer::concatenated(data, options) {
+            Deserializer::with_options(data, options)?
+        } else {
+            TokenDeserializer::recover(error, options, |options| {
+                Deserializer::with_options(data, options)
+            })?
+        }
+    } else {
+        Deserializer::with_options(data, options)?
+    };
+
+    T::deserialize(deserializer)
+}
+
+/// Deserializes a data structure from the first value of a slice of bytes
+/// and returns it along with the bytes following that value
+///
+/// Useful for messages made of a bencoded header followed by a raw payload.
+pub fn from_bytes_prefix<'de, T: Deserialize<'de>>(
+    data: &'de [u8],
+) -> Result<(T, &'de [u8]), Error> {
+    from_bytes_prefix_with_options(data, &ParserOptions::default())
+}
+
+/// Same as [`from_bytes_prefix`] using the given options
+pub fn from_bytes_prefix_with_options<'de, T: Deserialize<'de>>(
+    data: &'de [u8],
+    options: &ParserOptions,
+) -> Result<(T, &'de [u8]), Error> {
+    let (value, len) = if TokenDeserializer::supports(data, options) {
+        let mut deserializer = TokenDeserializer::new(data, options);
+        match T::deserialize(&mut deserializer) {
+            Ok(value) => return Ok((value, &data[deserializer.offset()..])),
+            Err(error) => TokenDeserializer::recover(error, options, |options| {
+                parser::parse_prefix_with_options(data, options)
+            })?,
+        }
+    } else {
+        parser::parse_prefix_with_options(data, options)?
+    };
+    let deserializer = Deserializer::from_value(value).options(options);
+    Ok((T::deserialize(deserializer)?, &data[len..]))
+}
+
+/// Parses an integer written in a dictionary key
+fn parse_key<T: FromStr>(key: &str) -> Result<T, Error> {
+    key.parse().map_err(|_| Error::InvalidKey(key.to_owned()))
+}
+
+/// Converts an int to the int type `T`, failing if it does not fit
+fn narrow<T: TryFrom<i128>>(value: i128) -> Result<T, Error> {
+    T::try_from(value)
+        .map_err(|_| Error::out_of_range(value, any::type_name::<T>()))
+}
+
+fn parse_raw(raw: Cow<[u8]>) -> BencodedValue {
+    let parsed = match raw {
+        Cow::Borrowed(bytes) => parser::parse_exact(bytes).ok(),
+        Cow::Owned(ref bytes) => parser::parse_exact(bytes)
+            .ok()
+            .map(BencodedValue::into_owned),
+    };
+
+    parsed.unwrap_or(BencodedValue::Raw(raw))
+}
+
+/// Deserializes a data structure from a reader, nothing past the end of the
+/// bencoded value is read, see [`parser::parse_from_reader_with_options`]
+#[cfg(feature = "std")]
+pub fn from_reader<T, R>(reader: R) -> Result<T, Error>
+where
+    T: de::DeserializeOwned,
+    R: std::io::Read,
+{
+    from_reader_with_options(reader, &ParserOptions::default())
+}
+
+/// Same as [`from_reader`] using the given parser options
+#[cfg(feature = "std")]
+pub fn from_reader_with_options<T, R>(
+    reader: R,
+    options: &ParserOptions,
+) -> Result<T, Error>
+where
+    T: de::DeserializeOwned,
+    R: std::io::Read,
+{
+    let value = parser::parse_from_reader_with_options(reader, options)?;
+    T::deserialize(Deserializer::from_value(value).options(options))
+}
+
+/// Deserializes a data structure from an async reader, nothing past the end
+/// of the bencoded value is read
+#[cfg(feature = "tokio")]
+pub async fn from_async_reader<T, R>(reader: R) -> Result<T, Error>
+where
+    T: de::DeserializeOwned,
+    R: tokio::io::AsyncRead + Unpin,
+{
+    from_value(parser::parse_from_async_reader(reader).await?)
 }
 
 impl<'data> Deserializer<'data> {
+    /// Same as [`Deserializer::from_slice`]
     pub fn new(data: &'data [u8]) -> Result<Self, Error> {
-        Ok(Self::from_value(match parser::parse_all(data) {
-            Ok(input) => input.1,
-            Err(e) => {
-                return Err(Error::Message(format!("parse error: {:?}", e)));
-            }
-        }))
+        Self::from_slice(data)
+    }
+
+    /// Creates a deserializer of the value held by a slice of bytes, it is
+    /// parsed up front
+    pub fn from_slice(data: &'data [u8]) -> Result<Self, Error> {
+        Self::with_options(data, &ParserOptions::default())
+    }
+
+    /// Same as [`Deserializer::from_slice`] parsing using the given options
+    pub fn with_options(
+        data: &'data [u8],
+        options: &ParserOptions,
+    ) -> Result<Self, Error> {
+        let (_, input) = parser::parse_all_with_options(data, options)
+            .map_err(|e| Error::parse(data, e))?;
+        Ok(Self::from_value(input).options(options))
     }
 
+    /// Creates a deserializer from an already parsed value, raw values are
+    /// parsed up front and left untouched if they are invalid
     pub fn from_value(input: BencodedValue<'data>) -> Self {
-        Deserializer { input }
+        let input = match input {
+            BencodedValue::Raw(raw) => parse_raw(raw),
+            input => input,
+        };
+
+        Deserializer {
+            input,
+            key: false,
+            settings: Settings::default(),
+        }
+    }
+
+    /// Creates a deserializer from a dictionary key, integers are read from
+    /// their decimal form
+    pub fn from_key(key: Key<'data>) -> Self {
+        Deserializer {
+            input: BencodedValue::String(key.into_cow()),
+            key: true,
+            settings: Settings::default(),
+        }
+    }
+
+    /// Reads the values as the given options require, such as
+    /// [`ParserOptions::bools`]
+    pub fn options(self, options: &ParserOptions) -> Self {
+        self.settings(Settings::from(options))
+    }
+
+    pub(crate) fn settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
     }
 
     pub fn parse_bool(self) -> Result<bool, Error> {
+        let lenient = self.settings.bools == BoolMode::Lenient;
         match &self.input {
-            BencodedValue::Integer(value) => match value {
-                1 => Ok(true),
-                0 => Ok(false),
-                _ => Err(Error::Message(
-                    "incorrect bool from int conversion".to_owned(),
-                )),
+            _ if self.settings.bools == BoolMode::Rejected => {
+                Err(Error::Rejected("bool"))
+            }
+            BencodedValue::Integer(0) => Ok(false),
+            BencodedValue::Integer(1) => Ok(true),
+            BencodedValue::Integer(_) | BencodedValue::UnsignedInteger(_)
+                if lenient =>
+            {
+                Ok(true)
+            }
+            BencodedValue::Integer(value) => {
+                Err(Error::out_of_range(value, "bool"))
+            }
+            v if lenient => match v.as_str() {
+                Some("0") | Some("false") => Ok(false),
+                Some("1") | Some("true") => Ok(true),
+                _ => Err(Error::unexpected("bool", v)),
             },
-            v => Err(Error::Message(format!(
-                "cannot convert from {:?} to bool",
-                v
-            ))),
+            v => Err(Error::unexpected("bool", v)),
         }
     }
 
     pub fn parse_int(self) -> Result<i64, Error> {
-        match self.input {
-            BencodedValue::Integer(value) => Ok(value),
-            v => Err(Error::Message(format!(
-                "cannot convert from {:?} to int",
-                v
-            ))),
-        }
+        narrow(self.parse_i128()?)
     }
 
     pub fn parse_uint(self) -> Result<u64, Error> {
-        let value = self.parse_int()?;
-        if value < 0 {
-            Err(Error::Message("uint cannot be negative".to_owned()))
-        } else {
-            Ok(value as _)
+        narrow(self.parse_i128()?)
+    }
+
+    /// Reads an integer of any sign, which holds every bencode integer
+    pub fn parse_i128(self) -> Result<i128, Error> {
+        match self.input {
+            BencodedValue::Integer(value) => Ok(i128::from(value)),
+            BencodedValue::UnsignedInteger(value) => Ok(i128::from(value)),
+            BencodedValue::String(value) if self.key => parse_key(&value),
+            v => Err(Error::unexpected("integer", &v)),
         }
     }
 
@@ -75,77 +321,81 @@ impl<'data> Deserializer<'data> {
         Ok(self.parse_int()? as i32 as _)
     }
 
+    /// Reads a string holding a single char, or an integer holding its code
+    /// point as some producers do
     pub fn parse_char(self) -> Result<char, Error> {
-        match self.input {
-            BencodedValue::String(value) => {
-                if value.len() == 1 {
-                    Ok(value.chars().next().unwrap())
-                } else {
-                    Err(Error::Message(
-                        "incorrect char from string conversion".to_owned(),
-                    ))
-                }
-            }
-            BencodedValue::StringOwned(value) => {
-                if value.len() == 1 {
-                    Ok(value.chars().next().unwrap())
-                } else {
-                    Err(Error::Message(
-                        "incorrect char from string conversion".to_owned(),
-                    ))
+        if self.input.is_int() {
+            let code = self.parse_i128()?;
+            return u32::try_from(code)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| Error::out_of_range(code, "char"));
+        }
+
+        match self.input.as_str() {
+            Some(value) => {
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(char), None) => Ok(char),
+                    _ => Err(Error::InvalidLength {
+                        expected: 1,
+                        found: value.chars().count(),
+                    }),
                 }
             }
-            v => Err(Error::Message(format!(
-                "cannot convert from {:?} to char",
-                v
-            ))),
+            None => Err(Error::unexpected("char", &self.input)),
+        }
+    }
+
+    /// Reads any of the empty values a unit is written as
+    pub fn parse_unit(self) -> Result<(), Error> {
+        match &self.input {
+            BencodedValue::Integer(0) => Ok(()),
+            BencodedValue::String(value) if value.is_empty() => Ok(()),
+            BencodedValue::Binary(value) if value.is_empty() => Ok(()),
+            BencodedValue::Dictionary(dict) if dict.is_empty() => Ok(()),
+            v => Err(Error::unexpected("unit", v)),
         }
     }
 
     pub fn parse_str(self) -> Result<&'data str, Error> {
         match self.input {
-            BencodedValue::String(value) => Ok(value),
-            //BencodedValue::StringOwned(value) => Ok(&value),
-            v => Err(Error::Message(format!(
-                "cannot convert from {:?} to str",
-                v
-            ))),
+            BencodedValue::String(Cow::Borrowed(value)) => Ok(value),
+            BencodedValue::Binary(Cow::Borrowed(value)) => {
+                core::str::from_utf8(value).map_err(|e| Error::InvalidUtf8 {
+                    valid_up_to: e.valid_up_to(),
+                })
+            }
+            v => Err(Error::unexpected("borrowed string", &v)),
         }
     }
 
     pub fn parse_string(self) -> Result<String, Error> {
         match &self.input {
-            BencodedValue::String(value) => Ok((*value).to_owned()),
-            BencodedValue::StringOwned(value) => Ok(value.clone()),
-            v => Err(Error::Message(format!(
-                "cannot convert from {:?} to string",
-                v
-            ))),
+            BencodedValue::String(value) => Ok(value.to_string()),
+            BencodedValue::Binary(value) => match core::str::from_utf8(value) {
+                Ok(value) => Ok(value.to_owned()),
+                Err(e) => Err(Error::InvalidUtf8 {
+                    valid_up_to: e.valid_up_to(),
+                }),
+            },
+            v => Err(Error::unexpected("string", v)),
         }
     }
 
     pub fn parse_bytes(self) -> Result<&'data [u8], Error> {
         match self.input {
-            BencodedValue::Binary(value) => Ok(value),
-            BencodedValue::String(value) => Ok(value.as_bytes()),
-            //BencodedValue::BinaryOwned(value) => Ok(&value[..]),
-            v => Err(Error::Message(format!(
-                "cannot convert from {:?} to bytes",
-                v
-            ))),
+            BencodedValue::Binary(Cow::Borrowed(value)) => Ok(value),
+            BencodedValue::String(Cow::Borrowed(value)) => Ok(value.as_bytes()),
+            v => Err(Error::unexpected("borrowed bytes", &v)),
         }
     }
 
     pub fn parse_bytes_owned(self) -> Result<Vec<u8>, Error> {
         match self.input {
-            BencodedValue::Binary(value) => Ok(value.to_vec()),
-            BencodedValue::BinaryOwned(value) => Ok(value),
-            BencodedValue::String(value) => Ok(value.bytes().collect()),
-            BencodedValue::StringOwned(value) => Ok(value.bytes().collect()),
-            v => Err(Error::Message(format!(
-                "cannot convert from {:?} to owned bytes",
-                v
-            ))),
+            BencodedValue::Binary(value) => Ok(value.into_owned()),
+            BencodedValue::String(value) => Ok(value.into_owned().into_bytes()),
+            v => Err(Error::unexpected("bytes", &v)),
         }
     }
 }
@@ -163,14 +413,13 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
     {
         match &self.input {
             BencodedValue::Binary(_) => self.deserialize_bytes(visitor),
-            BencodedValue::BinaryOwned(_) => self.deserialize_byte_buf(visitor),
             BencodedValue::String(_) => self.deserialize_str(visitor),
-            BencodedValue::StringOwned(_) => self.deserialize_str(visitor),
             BencodedValue::Integer(_) => self.deserialize_i64(visitor),
+            BencodedValue::UnsignedInteger(_) => self.deserialize_u64(visitor),
             BencodedValue::List(_) => self.deserialize_seq(visitor),
             BencodedValue::Dictionary(_) => self.deserialize_map(visitor),
-            BencodedValue::DictionaryOwned(_) => self.deserialize_map(visitor),
             BencodedValue::None => self.deserialize_option(visitor),
+            v => Err(Error::unexpected("any value", v)),
         }
     }
 
@@ -185,21 +434,21 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_i8(self.parse_int()? as _)
+        visitor.visit_i8(narrow(self.parse_i128()?)?)
     }
 
     fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_i16(self.parse_int()? as _)
+        visitor.visit_i16(narrow(self.parse_i128()?)?)
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_i32(self.parse_int()? as _)
+        visitor.visit_i32(narrow(self.parse_i128()?)?)
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -209,25 +458,32 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
         visitor.visit_i64(self.parse_int()?)
     }
 
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_i128(self.parse_i128()?)
+    }
+
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_u8(self.parse_uint()? as _)
+        visitor.visit_u8(narrow(self.parse_i128()?)?)
     }
 
     fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_u16(self.parse_uint()? as _)
+        visitor.visit_u16(narrow(self.parse_i128()?)?)
     }
 
     fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_u32(self.parse_uint()? as _)
+        visitor.visit_u32(narrow(self.parse_i128()?)?)
     }
 
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -237,6 +493,13 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
         visitor.visit_u64(self.parse_uint()?)
     }
 
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_u128(narrow(self.parse_i128()?)?)
+    }
+
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
@@ -307,22 +570,23 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
         }
     }
 
-    fn deserialize_unit<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        Err(Error::Message("cannot deserialize units".to_owned()))
+        self.parse_unit()?;
+        visitor.visit_unit()
     }
 
     fn deserialize_unit_struct<V>(
         self,
         _name: &'static str,
-        _visitor: V,
+        visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        Err(Error::Message("cannot deserialize units".to_owned()))
+        self.deserialize_unit(visitor)
     }
 
     fn deserialize_newtype_struct<V>(
@@ -340,24 +604,27 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        if self.input.is_list() {
-            let list = self.input.unwrap_list();
-
-            visitor.visit_seq(seq::SeqAccess::new(list))
-        } else if self.input.is_bin() {
-            visitor.visit_seq(seq::SeqAccess::new(
-                self.input
-                    .unwrap_bin()
-                    .into_iter()
-                    .map(|e| BencodedValue::Integer(e as _))
-                    .collect(),
-            ))
-        } else {
-            Err(Error::Message(format!(
-                "cannot convert from {:?} to list",
-                self.input
-            )))
-        }
+        let bytes = match self.input {
+            BencodedValue::List(list) => {
+                return visitor
+                    .visit_seq(seq::SeqAccess::new(list, self.settings))
+            }
+            BencodedValue::Binary(bin) => bin,
+            BencodedValue::String(Cow::Borrowed(str)) => {
+                Cow::Borrowed(str.as_bytes())
+            }
+            BencodedValue::String(Cow::Owned(str)) => {
+                Cow::Owned(str.into_bytes())
+            }
+            v => return Err(Error::unexpected("list", &v)),
+        };
+
+        // Strings are sequences of `u8`, such as `Vec<u8>` or `[u8; 20]`
+        let mut seq =
+            de::value::SeqDeserializer::<_, Error>::new(bytes.iter().copied());
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.end()?;
+        Ok(value)
     }
 
     fn deserialize_tuple<V>(
@@ -388,54 +655,76 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
         V: de::Visitor<'de>,
     {
         if self.input.is_dict() {
-            match self.input {
-                BencodedValue::Dictionary(dict) => {
-                    visitor.visit_map(map::MapAccess::new(dict))
-                }
-                BencodedValue::DictionaryOwned(dict) => {
-                    visitor.visit_map(map::MapAccess::new(dict))
-                }
-                _ => unreachable!(),
-            }
+            visitor.visit_map(map::MapAccess::new(
+                self.input.unwrap_dict(),
+                self.settings,
+            ))
         } else {
-            Err(Error::Message(format!(
-                "cannot convert from {:?} to dictionary",
-                self.input
-            )))
+            Err(Error::unexpected("dictionary", &self.input))
         }
     }
 
     fn deserialize_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         _fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        if self.input.is_list() {
-            self.deserialize_seq(visitor)
-        } else if self.input.is_dict() {
-            self.deserialize_map(visitor)
-        } else {
-            Err(Error::Message(format!(
-                "cannot convert from {:?} to list/dictionary",
-                self.input
-            )))
+        match &self.input {
+            BencodedValue::List(_) if self.settings.positional_structs => {
+                self.deserialize_seq(visitor)
+            }
+            BencodedValue::List(list) => Err(Error::PositionalStruct {
+                name,
+                len: list.len(),
+            }),
+            BencodedValue::Dictionary(_) => self.deserialize_map(visitor),
+            v => Err(Error::unexpected("dictionary", v)),
         }
     }
 
+    /// Unit variants are their name, other variants are either externally
+    /// or adjacently tagged, see [`EnumRepresentation`](crate::ser::EnumRepresentation)
     fn deserialize_enum<V>(
         self,
         _name: &'static str,
         _variants: &'static [&'static str],
-        _visitor: V,
+        visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        Err(Error::Message("enums are not supported".to_owned()))
+        let access = match self.input {
+            variant @ BencodedValue::String(_)
+            | variant @ BencodedValue::Binary(_) => {
+                variant::EnumAccess::new(variant, None, self.settings)
+            }
+            BencodedValue::Dictionary(mut dict) if dict.len() == 1 => {
+                let (variant, content) = dict.drain().next().unwrap();
+                variant::EnumAccess::new(
+                    BencodedValue::String(variant.into_cow()),
+                    Some(content),
+                    self.settings,
+                )
+            }
+            BencodedValue::Dictionary(mut dict)
+                if dict.len() == 2
+                    && dict.contains_key(TAG_KEY)
+                    && dict.contains_key(CONTENT_KEY) =>
+            {
+                variant::EnumAccess::new(
+                    dict.remove(TAG_KEY).unwrap(),
+                    dict.remove(CONTENT_KEY),
+                    self.settings,
+                )
+            }
+            v => return Err(Error::unexpected("enum", &v)),
+        };
+
+        visitor.visit_enum(access)
     }
 
     fn deserialize_identifier<V>(
@@ -449,26 +738,34 @@ impl<'de> de::Deserializer<'de> for Deserializer<'de> {
     }
 
     fn deserialize_ignored_any<V>(
-        mut self,
+        self,
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.input = BencodedValue::None;
-
+        // The value is already detached from its list or dictionary, it is
+        // simply dropped
+        drop(self.input);
         visitor.visit_unit()
     }
 }
 
 #[cfg(test)]
 mod deserialize_tests {
-    use super::{from_bytes, from_value};
-    use crate::BencodedValue;
+    use super::{
+        from_bytes, from_bytes_prefix, from_bytes_prefix_with_options,
+        from_bytes_with_options, from_value, from_value_ref,
+    };
+    use crate::{
+        error::Error, to_bytes, to_value, BencodedValue, BoolMode,
+        ParserOptions,
+    };
     use maplit::hashmap;
     use serde::Deserialize;
+    use std::collections::HashMap;
 
-    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    #[derive(Deserialize, Debug, PartialEq, Eq, Clone)]
     struct TestStruct {
         name: String,
         age: i64,
@@ -480,7 +777,7 @@ mod deserialize_tests {
         let hello_world = "Hello, world!";
 
         assert_eq!(
-            from_value(BencodedValue::StringOwned(hello_world.to_owned())),
+            from_value(BencodedValue::from(hello_world.to_owned())),
             Ok(hello_world.to_owned())
         );
 
@@ -489,6 +786,74 @@ mod deserialize_tests {
         assert_eq!(from_bytes(bytes), Ok(hello_world.to_owned()));
     }
 
+    #[test]
+    fn test_ascii_hash() {
+        #[derive(Deserialize, Debug, PartialEq, Clone, Copy)]
+        struct Peer<'a> {
+            #[serde(with = "serde_bytes")]
+            hash: &'a [u8],
+            id: &'a str,
+        }
+
+        let input =
+            b"d4:hash20:abcdefghijklmnopqrst2:id20:abcdefghijklmnopqrste";
+        let expected = Peer {
+            hash: b"abcdefghijklmnopqrst",
+            id: "abcdefghijklmnopqrst",
+        };
+        let binary = ParserOptions {
+            binary_strings: true,
+            ..ParserOptions::default()
+        };
+
+        assert_eq!(from_bytes::<Peer>(input), Ok(expected));
+        assert_eq!(
+            from_bytes_with_options::<Peer>(input, &binary),
+            Ok(expected)
+        );
+        assert_eq!(
+            from_bytes_with_options::<String>(b"4:spam", &binary),
+            Ok("spam".to_owned())
+        );
+        assert!(
+            from_bytes_with_options::<&str>(b"2:\xFF\xFE", &binary).is_err()
+        );
+    }
+
+    #[test]
+    fn test_bytes() {
+        #[derive(Deserialize, Debug, PartialEq, Clone)]
+        struct Peer<'a> {
+            id: [u8; 20],
+            ip: &'a [u8],
+            key: Vec<u8>,
+        }
+
+        let input =
+            b"d2:id20:abcdefghijklmnopqrst2:ip4:\x7f\x00\x00\x013:key3:abce";
+        let expected = Peer {
+            id: *b"abcdefghijklmnopqrst",
+            ip: &[127, 0, 0, 1],
+            key: b"abc".to_vec(),
+        };
+        let binary = ParserOptions {
+            binary_strings: true,
+            ..ParserOptions::default()
+        };
+
+        assert_eq!(from_bytes::<Vec<u8>>(b"3:abc"), Ok(b"abc".to_vec()));
+        assert_eq!(from_bytes::<[u8; 3]>(b"3:abc"), Ok(*b"abc"));
+        assert_eq!(from_bytes::<Vec<u8>>(b"li1ei2ee"), Ok(vec![1, 2]));
+        assert_eq!(from_bytes::<Peer>(input), Ok(expected.clone()));
+        assert_eq!(
+            from_bytes_with_options::<Peer>(input, &binary),
+            Ok(expected)
+        );
+
+        assert!(from_bytes::<[u8; 4]>(b"3:abc").is_err());
+        assert!(from_bytes::<[u8; 2]>(b"3:abc").is_err());
+    }
+
     #[test]
     fn test_number() {
         assert_eq!(from_value(BencodedValue::Integer(64)), Ok(64_i64));
@@ -501,15 +866,211 @@ mod deserialize_tests {
     }
 
     #[test]
-    fn test_list() {
-        // TODO: there is a bug with &str instead of String, should try and fix that!
+    fn test_number_range() {
+        assert!(from_bytes::<u64>(b"i-5e").is_err());
+        assert!(from_bytes::<u8>(b"i256e").is_err());
+        assert!(from_bytes::<i8>(b"i-129e").is_err());
+        assert!(from_bytes::<u32>(b"3:abc").is_err());
+
+        assert_eq!(from_bytes(b"i-128e"), Ok(-128_i8));
+        assert_eq!(from_bytes(b"i255e"), Ok(255_u8));
+        assert_eq!(
+            from_value(BencodedValue::Integer(i64::MAX)),
+            Ok(i64::MAX as u64)
+        );
+    }
+
+    #[test]
+    fn test_bool() {
+        let strict = ParserOptions {
+            bools: BoolMode::Strict,
+            ..ParserOptions::default()
+        };
+        let rejected = ParserOptions {
+            bools: BoolMode::Rejected,
+            ..ParserOptions::default()
+        };
+
+        for &(data, expected) in &[
+            (&b"i0e"[..], false),
+            (b"i1e", true),
+            (b"i2e", true),
+            (b"i-1e", true),
+            (b"i18446744073709551615e", true),
+            (b"1:0", false),
+            (b"1:1", true),
+            (b"5:false", false),
+            (b"4:true", true),
+        ] {
+            assert_eq!(from_bytes(data), Ok(expected), "{:?}", data);
+            let strict = from_bytes_with_options::<bool>(data, &strict);
+            assert_eq!(
+                strict.is_ok(),
+                data.starts_with(b"i0") || data == b"i1e"
+            );
+            assert!(from_bytes_with_options::<bool>(data, &rejected).is_err());
+        }
+
+        for &data in &[&b"1:2"[..], b"3:yes", b"0:", b"le", b"de"] {
+            assert!(from_bytes::<bool>(data).is_err(), "{:?}", data);
+        }
+
+        // Nested values read with the options as well
+        let data = b"d1:ai2ee";
+        let lenient = from_bytes::<HashMap<String, bool>>(data);
+        assert_eq!(lenient, Ok(hashmap! { "a".to_owned() => true }));
+        for &options in &[&strict, &rejected] {
+            assert!(from_bytes_with_options::<Vec<bool>>(b"li2ee", options)
+                .is_err());
+            assert!(from_bytes_with_options::<HashMap<String, bool>>(
+                data, options
+            )
+            .is_err());
+            assert!(from_bytes_prefix_with_options::<(bool,)>(
+                b"li2ee", options
+            )
+            .is_err());
+        }
+    }
+
+    #[test]
+    fn test_char() {
+        assert_eq!(from_bytes(b"1:a"), Ok('a'));
+        assert_eq!(from_bytes(b"2:\xC3\xA9"), Ok('é'));
+        assert_eq!(from_bytes(b"4:\xF0\x9F\xA6\x80"), Ok('🦀'));
+        assert_eq!(from_bytes(b"i65e"), Ok('A'));
+        assert_eq!(from_bytes(b"i233e"), Ok('é'));
+        assert_eq!(from_value(to_value(&'é').unwrap()), Ok('é'));
+
+        let binary = ParserOptions {
+            binary_strings: true,
+            ..ParserOptions::default()
+        };
+        assert_eq!(from_bytes_with_options(b"1:A", &binary), Ok('A'));
+        assert!(from_bytes::<char>(b"1:\xFF").is_err());
+        assert!(from_bytes::<char>(b"2:ab").is_err());
+        assert!(from_bytes::<char>(b"0:").is_err());
+        assert!(from_bytes::<char>(b"le").is_err());
+
+        for &code in &["-1", "55296", "1114112", "18446744073709551615"] {
+            let data = format!("i{}e", code);
+            assert_eq!(
+                from_bytes::<char>(data.as_bytes()),
+                Err(Error::out_of_range(code, "char"))
+            );
+        }
+    }
+
+    #[test]
+    fn test_prefix() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Header {
+            msg_type: i64,
+            piece: i64,
+        }
+
+        let mut data = b"d8:msg_typei1e5:piecei0ee".to_vec();
+        data.extend_from_slice(&[0xFF, 0x00, b'e', b'd', 0x01]);
+
+        let (header, payload) = from_bytes_prefix::<Header>(&data).unwrap();
+        assert_eq!(
+            header,
+            Header {
+                msg_type: 1,
+                piece: 0
+            }
+        );
+        assert_eq!(payload, &[0xFF, 0x00, b'e', b'd', 0x01]);
+
+        assert_eq!(from_bytes_prefix::<i64>(b"i3e"), Ok((3, &b""[..])));
+        assert!(from_bytes::<Header>(&data).is_err());
+        assert_eq!(
+            from_bytes_prefix::<Header>(b"d8:msg_typei1e"),
+            Err(Error::Incomplete { needed: Some(1) })
+        );
+
+        // Exactly one value is parsed, the following values are left intact
+        assert_eq!(from_bytes_prefix::<i64>(b"i1ei2e"), Ok((1, &b"i2e"[..])));
+
+        // A whole ut_metadata piece follows the header
+        let mut data = b"d8:msg_typei1e5:piecei0ee".to_vec();
+        let block = (0..16 * 1024).map(|i| i as u8).collect::<Vec<_>>();
+        data.extend_from_slice(&block);
+
+        let (header, payload) = from_bytes_prefix::<Header>(&data).unwrap();
+        assert_eq!(header.msg_type, 1);
+        assert_eq!(payload, &block[..]);
+    }
 
+    #[test]
+    fn test_unsigned_range() {
+        let unsigned = ParserOptions {
+            unsigned_integers: true,
+            ..ParserOptions::default()
+        };
+        let signed = ParserOptions {
+            unsigned_integers: false,
+            ..ParserOptions::default()
+        };
+        let overflow = |digits: &str| {
+            Err::<u64, _>(Error::IntegerOverflow(digits.to_owned()))
+        };
+
+        assert_eq!(from_bytes(b"i9223372036854775807e"), Ok(i64::MAX as u64));
+        assert_eq!(
+            from_bytes(b"i9223372036854775808e"),
+            Ok(i64::MAX as u64 + 1)
+        );
+        assert_eq!(
+            from_bytes_with_options(b"i9223372036854775808e", &signed),
+            overflow("9223372036854775808")
+        );
+        assert_eq!(
+            from_bytes_with_options(b"i9223372036854775808e", &unsigned),
+            Ok(i64::MAX as u64 + 1)
+        );
+        assert_eq!(
+            from_bytes_with_options(b"i18446744073709551615e", &unsigned),
+            Ok(u64::MAX)
+        );
+        assert_eq!(
+            from_bytes_with_options(b"i18446744073709551616e", &unsigned),
+            overflow("18446744073709551616")
+        );
+        assert_eq!(
+            from_bytes::<i64>(b"i18446744073709551615e"),
+            Err(Error::out_of_range(u64::MAX, "i64"))
+        );
+        assert!(from_bytes_with_options::<i64>(
+            b"i18446744073709551615e",
+            &unsigned
+        )
+        .is_err());
+
+        let value: BencodedValue =
+            from_bytes_with_options(b"i18446744073709551615e", &unsigned)
+                .unwrap();
+        assert_eq!(value, BencodedValue::UnsignedInteger(u64::MAX));
+        assert_eq!(to_bytes(&u64::MAX).unwrap(), b"i18446744073709551615e");
+
+        // Every u64 roundtrips using the default options
+        for int in &[0, i64::MAX as u64, i64::MAX as u64 + 1, u64::MAX] {
+            let bytes = to_bytes(int).unwrap();
+            assert_eq!(from_bytes::<u64>(&bytes), Ok(*int));
+
+            let value: BencodedValue = from_bytes(&bytes).unwrap();
+            assert_eq!(to_bytes(&value).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_list() {
         let hello_world = vec!["hello".to_owned(), "world".to_owned()];
 
         assert_eq!(
             from_value(BencodedValue::List(vec![
-                BencodedValue::StringOwned("hello".to_owned()),
-                BencodedValue::StringOwned("world".to_owned()),
+                BencodedValue::from("hello".to_owned()),
+                BencodedValue::from("world".to_owned()),
             ])),
             Ok(hello_world.clone())
         );
@@ -517,6 +1078,15 @@ mod deserialize_tests {
         let bytes = b"l5:hello5:worlde";
 
         assert_eq!(from_bytes(bytes), Ok(hello_world));
+        assert_eq!(from_bytes::<Vec<&str>>(bytes), Ok(vec!["hello", "world"]));
+        assert_eq!(from_bytes::<&[u8]>(b"5:hello"), Ok(&b"hello"[..]));
+
+        let owned = to_value(&["hello", "world"]).unwrap();
+        assert!(from_value::<Vec<&str>>(owned.clone()).is_err());
+        assert_eq!(
+            from_value_ref::<Vec<&str>>(&owned),
+            Ok(vec!["hello", "world"])
+        );
     }
 
     #[test]
@@ -528,15 +1098,28 @@ mod deserialize_tests {
             "d".to_owned() => 4
         ];
 
-        let value = BencodedValue::DictionaryOwned(
+        let value = BencodedValue::Dictionary(
             map.iter()
-                .map(|(k, v)| ((*k).to_owned(), BencodedValue::Integer(*v)))
+                .map(|(k, v)| (k.as_str().into(), BencodedValue::Integer(*v)))
                 .collect(),
         );
 
         assert_eq!(from_value(value), Ok(map))
     }
 
+    #[test]
+    fn test_borrowed_dict() {
+        let map = hashmap! { "a" => 1, "b" => 2, "long key" => 3 };
+        let bytes = to_bytes(&map).unwrap();
+        assert_eq!(from_bytes::<HashMap<&str, i64>>(&bytes), Ok(map.clone()));
+
+        let owned = to_value(&map).unwrap();
+        assert_eq!(
+            from_value_ref::<HashMap<&str, i64>>(&owned),
+            Ok(map.clone())
+        );
+    }
+
     #[test]
     fn test_struct() {
         let test_data = TestStruct {
@@ -549,22 +1132,22 @@ mod deserialize_tests {
             ],
         };
 
-        let encoded = BencodedValue::DictionaryOwned(hashmap! {
-            "age".to_owned() => BencodedValue::Integer(
+        let encoded = BencodedValue::Dictionary(hashmap! {
+            "age".into() => BencodedValue::Integer(
                 24,
             ),
-            "name".to_owned() => BencodedValue::StringOwned(
+            "name".into() => BencodedValue::from(
                 "Tom".to_owned(),
             ),
-            "friends".to_owned() => BencodedValue::List(
+            "friends".into() => BencodedValue::List(
                 vec![
-                    BencodedValue::StringOwned(
+                    BencodedValue::from(
                         "David".to_owned(),
                     ),
-                    BencodedValue::StringOwned(
+                    BencodedValue::from(
                         "Donald".to_owned(),
                     ),
-                    BencodedValue::StringOwned(
+                    BencodedValue::from(
                         "Barrack".to_owned(),
                     ),
                 ],
@@ -577,4 +1160,88 @@ mod deserialize_tests {
             assert!(false, "could not transform value");
         }
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_reader() {
+        use super::from_reader;
+        use std::io::{self, Cursor, Read};
+
+        /// Reader handing out a few bytes per read, then failing if asked for
+        /// more than it holds
+        struct Chunked<'a> {
+            data: &'a [u8],
+            fail: bool,
+        }
+
+        impl<'a> Read for Chunked<'a> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.data.is_empty() && self.fail {
+                    return Err(io::Error::new(
+                        io::ErrorKind::ConnectionReset,
+                        "reset",
+                    ));
+                }
+
+                let len = buf.len().min(self.data.len()).min(3);
+                buf[..len].copy_from_slice(&self.data[..len]);
+                self.data = &self.data[len..];
+                Ok(len)
+            }
+        }
+
+        let input = b"d3:agei24e7:friendsl5:Davide4:name3:Tomerest";
+        let expected = TestStruct {
+            name: "Tom".to_owned(),
+            age: 24,
+            friends: vec!["David".to_owned()],
+        };
+
+        let mut cursor = Cursor::new(&input[..]);
+        assert_eq!(from_reader(&mut cursor), Ok(expected.clone()));
+        assert_eq!(cursor.position(), input.len() as u64 - 4);
+
+        let mut chunked = Chunked {
+            data: input,
+            fail: true,
+        };
+        assert_eq!(from_reader(&mut chunked), Ok(expected));
+        assert_eq!(chunked.data, b"rest");
+
+        let truncated = Chunked {
+            data: &input[..20],
+            fail: true,
+        };
+        assert_eq!(
+            from_reader::<TestStruct, _>(truncated),
+            Err(Error::from(io::Error::new(
+                io::ErrorKind::ConnectionReset,
+                "reset"
+            )))
+        );
+
+        let invalid = Cursor::new(&b"d3:agei2x4ee"[..]);
+        let error = from_reader::<TestStruct, _>(invalid).unwrap_err();
+        assert!(!matches!(error, Error::Io(..)), "{:?}", error);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_async_reader() {
+        use super::from_async_reader;
+
+        let input = &b"d3:agei24e7:friendsl5:Davide4:name3:Tomerest"[..];
+        let mut reader = input;
+        let value: TestStruct = from_async_reader(&mut reader).await.unwrap();
+
+        assert_eq!(
+            value,
+            TestStruct {
+                name: "Tom".to_owned(),
+                age: 24,
+                friends: vec!["David".to_owned()],
+            }
+        );
+        assert_eq!(reader, b"rest");
+    }
 }
@@ -1,70 +1,38 @@
-use crate::{error::Error, BencodedValue};
+use super::Settings;
+use crate::{
+    error::Error,
+    hash_map::{HashMap, IntoIter},
+    BencodedValue, Key,
+};
 use serde::de;
-use std::collections::{hash_map::IntoIter, HashMap};
 
-pub struct MapAccess<'re, KeyType> {
-    len: usize,
-    index: usize,
-    values: IntoIter<KeyType, BencodedValue<'re>>,
+pub struct MapAccess<'re> {
+    values: IntoIter<Key<'re>, BencodedValue<'re>>,
+
+    /// Value of the last key read, taken by the next value
     current_value: Option<BencodedValue<'re>>,
+    settings: Settings,
 }
 
-impl<'re, KeyType> MapAccess<'re, KeyType> {
-    pub fn new(values: HashMap<KeyType, BencodedValue<'re>>) -> Self {
+impl<'re> MapAccess<'re> {
+    pub fn new(
+        values: HashMap<Key<'re>, BencodedValue<'re>>,
+        settings: Settings,
+    ) -> Self {
         MapAccess {
-            index: 0,
-            len: values.len(),
             values: values.into_iter(),
             current_value: None,
+            settings,
         }
     }
 }
 
-impl<'de> de::MapAccess<'de> for MapAccess<'de, &'de str> {
-    type Error = Error;
-
-    fn next_key_seed<K>(
-        &mut self,
-        seed: K,
-    ) -> Result<Option<K::Value>, Self::Error>
-    where
-        K: de::DeserializeSeed<'de>,
-    {
-        if self.len <= self.index {
-            Ok(None)
-        } else {
-            if self.current_value.is_some() {
-                self.index += 1;
-            }
-
-            let (key, value) = self.values.next().unwrap();
-
-            self.current_value = Some(value);
-
-            let deser =
-                super::Deserializer::from_value(BencodedValue::String(key));
-
-            Ok(seed.deserialize(deser).map(Some)?)
-        }
-    }
-
-    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
-    where
-        V: de::DeserializeSeed<'de>,
-    {
-        if self.len <= self.index {
-            panic!("overflow")
-        } else {
-            self.index += 1;
-            let deser = super::Deserializer::from_value(
-                self.current_value.take().unwrap(),
-            );
-            seed.deserialize(deser)
-        }
-    }
+/// Error of a value requested without a key
+pub(super) fn missing_key() -> Error {
+    Error::Misuse("a key before its value")
 }
 
-impl<'de> de::MapAccess<'de> for MapAccess<'de, String> {
+impl<'de> de::MapAccess<'de> for MapAccess<'de> {
     type Error = Error;
 
     fn next_key_seed<K>(
@@ -74,22 +42,14 @@ impl<'de> de::MapAccess<'de> for MapAccess<'de, String> {
     where
         K: de::DeserializeSeed<'de>,
     {
-        if self.len <= self.index {
-            Ok(None)
-        } else {
-            if self.current_value.is_some() {
-                self.index += 1;
+        match self.values.next() {
+            Some((key, value)) => {
+                self.current_value = Some(value);
+                let deser =
+                    super::Deserializer::from_key(key).settings(self.settings);
+                seed.deserialize(deser).map(Some)
             }
-
-            let (key, value) = self.values.next().unwrap();
-
-            self.current_value = Some(value);
-
-            let deser = super::Deserializer::from_value(
-                BencodedValue::StringOwned(key),
-            );
-
-            Ok(seed.deserialize(deser).map(Some)?)
+            None => Ok(None),
         }
     }
 
@@ -97,14 +57,13 @@ impl<'de> de::MapAccess<'de> for MapAccess<'de, String> {
     where
         V: de::DeserializeSeed<'de>,
     {
-        if self.len <= self.index {
-            panic!("overflow")
-        } else {
-            self.index += 1;
-            let deser = super::Deserializer::from_value(
-                self.current_value.take().unwrap(),
-            );
-            seed.deserialize(deser)
-        }
+        let value = self.current_value.take().ok_or_else(missing_key)?;
+        seed.deserialize(
+            super::Deserializer::from_value(value).settings(self.settings),
+        )
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
     }
 }
@@ -0,0 +1,506 @@
+use super::Deserializer;
+use crate::{
+    error::Error,
+    hash_map::Iter,
+    ser::{CONTENT_KEY, TAG_KEY},
+    BencodedValue, Key,
+};
+use core::slice;
+use serde::de::{self, Deserializer as _};
+
+/// Deserializer reading a value in place, strings and binaries are borrowed
+/// from it and lists and dictionaries are walked without being copied
+pub struct RefDeserializer<'de> {
+    input: &'de BencodedValue<'de>,
+}
+
+impl<'de> RefDeserializer<'de> {
+    pub fn new(input: &'de BencodedValue<'de>) -> Self {
+        RefDeserializer { input }
+    }
+
+    /// The owning deserializer of a borrowed copy of the value, lists and
+    /// dictionaries read as something else fail as they would there without
+    /// being copied
+    fn value(self, expected: &'static str) -> Result<Deserializer<'de>, Error> {
+        match self.input {
+            BencodedValue::List(list) if !list.is_empty() => {
+                Err(Error::unexpected(expected, self.input))
+            }
+            BencodedValue::Dictionary(dict) if !dict.is_empty() => {
+                Err(Error::unexpected(expected, self.input))
+            }
+            input => Ok(Deserializer::from_value(input.to_borrowed())),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for RefDeserializer<'de> {
+    type Error = Error;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.input {
+            BencodedValue::List(_) => self.deserialize_seq(visitor),
+            BencodedValue::Dictionary(_) => self.deserialize_map(visitor),
+            _ => self.value("any value")?.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("bool")?.deserialize_bool(visitor)
+    }
+
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("integer")?.deserialize_i8(visitor)
+    }
+
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("integer")?.deserialize_i16(visitor)
+    }
+
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("integer")?.deserialize_i32(visitor)
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("integer")?.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("integer")?.deserialize_i128(visitor)
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("integer")?.deserialize_u8(visitor)
+    }
+
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("integer")?.deserialize_u16(visitor)
+    }
+
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("integer")?.deserialize_u32(visitor)
+    }
+
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("integer")?.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("integer")?.deserialize_u128(visitor)
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("integer")?.deserialize_f32(visitor)
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("integer")?.deserialize_f64(visitor)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("char")?.deserialize_char(visitor)
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("borrowed string")?.deserialize_str(visitor)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("string")?.deserialize_string(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("borrowed bytes")?.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_byte_buf<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("bytes")?.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.input {
+            BencodedValue::None => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("unit")?.deserialize_unit(visitor)
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.value("unit")?.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.input {
+            BencodedValue::List(list) => visitor.visit_seq(SeqAccess {
+                values: list.iter(),
+            }),
+            _ => self.value("list")?.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_tuple<V>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.input {
+            BencodedValue::Dictionary(dict) => visitor.visit_map(MapAccess {
+                entries: dict.iter(),
+                value: None,
+            }),
+            _ => self.value("dictionary")?.deserialize_map(visitor),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        // Lists are rejected, reading them positionally requires the options
+        // of the parser
+        match self.input {
+            BencodedValue::Dictionary(_) => self.deserialize_map(visitor),
+            BencodedValue::List(list) => Err(Error::PositionalStruct {
+                name,
+                len: list.len(),
+            }),
+            _ => self
+                .value("dictionary")?
+                .deserialize_struct(name, fields, visitor),
+        }
+    }
+
+    /// Same representations as [`Deserializer::deserialize_enum`], the
+    /// content of the variant is read in place
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let access = match self.input {
+            BencodedValue::Dictionary(dict) if dict.len() == 1 => {
+                let (variant, content) = dict.iter().next().unwrap();
+                EnumAccess {
+                    variant: Deserializer::from_key(Key::borrowed(variant)),
+                    content: Some(content),
+                }
+            }
+            BencodedValue::Dictionary(dict)
+                if dict.len() == 2
+                    && dict.contains_key(TAG_KEY)
+                    && dict.contains_key(CONTENT_KEY) =>
+            {
+                EnumAccess {
+                    variant: Deserializer::from_value(
+                        dict[TAG_KEY].to_borrowed(),
+                    ),
+                    content: dict.get(CONTENT_KEY),
+                }
+            }
+            _ => {
+                return self
+                    .value("enum")?
+                    .deserialize_enum(name, variants, visitor)
+            }
+        };
+
+        visitor.visit_enum(access)
+    }
+
+    fn deserialize_identifier<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+}
+
+/// The elements of a list read in place
+struct SeqAccess<'de> {
+    values: slice::Iter<'de, BencodedValue<'de>>,
+}
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        match self.values.next() {
+            Some(value) => {
+                seed.deserialize(RefDeserializer::new(value)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+/// The entries of a dictionary read in place
+struct MapAccess<'de> {
+    entries: Iter<'de, Key<'de>, BencodedValue<'de>>,
+    value: Option<&'de BencodedValue<'de>>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        match self.entries.next() {
+            Some((key, value)) => {
+                self.value = Some(value);
+                let deser = Deserializer::from_key(Key::borrowed(key));
+                seed.deserialize(deser).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let value = self.value.take().ok_or_else(super::map::missing_key)?;
+        seed.deserialize(RefDeserializer::new(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// An enum variant, its name along with its content read in place
+struct EnumAccess<'de> {
+    variant: Deserializer<'de>,
+    content: Option<&'de BencodedValue<'de>>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = Error;
+    type Variant = VariantAccess<'de>;
+
+    fn variant_seed<V>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(self.variant)?;
+
+        Ok((
+            variant,
+            VariantAccess {
+                content: self.content,
+            },
+        ))
+    }
+}
+
+struct VariantAccess<'de> {
+    content: Option<&'de BencodedValue<'de>>,
+}
+
+impl<'de> VariantAccess<'de> {
+    fn content(self) -> Result<RefDeserializer<'de>, Error> {
+        match self.content {
+            Some(content) => Ok(RefDeserializer::new(content)),
+            None => Err(Error::UnexpectedType {
+                expected: "variant content",
+                found: "unit variant",
+            }),
+        }
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.content {
+            None => Ok(()),
+            Some(content) => Err(Error::unexpected("unit variant", content)),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.content()?)
+    }
+
+    fn tuple_variant<V>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.content()?.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.content()?.deserialize_struct("", fields, visitor)
+    }
+}
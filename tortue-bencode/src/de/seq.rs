@@ -1,18 +1,21 @@
-use crate::{error::Error, BencodedValue};
+use super::Settings;
+use crate::{error::Error, prelude::*, BencodedValue};
 use serde::de;
 
 pub struct SeqAccess<'re> {
     len: usize,
     index: usize,
     values: Vec<BencodedValue<'re>>,
+    settings: Settings,
 }
 
 impl<'re> SeqAccess<'re> {
-    pub fn new(values: Vec<BencodedValue<'re>>) -> Self {
+    pub fn new(values: Vec<BencodedValue<'re>>, settings: Settings) -> Self {
         SeqAccess {
             index: 0,
             len: values.len(),
             values,
+            settings,
         }
     }
 }
@@ -31,10 +34,15 @@ impl<'de> de::SeqAccess<'de> for SeqAccess<'de> {
             Ok(None)
         } else {
             self.index += 1;
-            let deser = super::Deserializer::from_value(self.values.remove(0));
+            let deser = super::Deserializer::from_value(self.values.remove(0))
+                .settings(self.settings);
             let out = seed.deserialize(deser).map(Some)?;
 
             Ok(out)
         }
     }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len - self.index)
+    }
 }
@@ -0,0 +1,1019 @@
+use super::Deserializer;
+use crate::{
+    error::Error,
+    parser::{self, ParserOptions, Token, Tokenizer},
+    prelude::*,
+    ser::{CONTENT_KEY, TAG_KEY},
+    BencodedValue, Key,
+};
+use nom::error::ErrorKind;
+use serde::de::{self, Deserializer as _};
+
+/// Deserializer reading the tokens of the input as they are visited instead
+/// of building a [`BencodedValue`] first, strings and binaries are borrowed
+/// from the input and no list or dictionary is allocated
+///
+/// Values read as another type, such as a list read as an integer, are
+/// parsed into a tree which reports the same errors as before. Errors of the
+/// input itself are left to the parser, see [`TokenDeserializer::recover`].
+pub(crate) struct TokenDeserializer<'de> {
+    input: &'de [u8],
+    tokens: Tokenizer<'de>,
+    options: ParserOptions,
+
+    /// Lists and dictionaries open, including those of the parent
+    /// deserializers of an enum content
+    depth: usize,
+}
+
+impl<'de> TokenDeserializer<'de> {
+    /// Checks if reading the tokens gives the same result as the parser with
+    /// the given options, that is if the options only set limits
+    pub fn supports(input: &[u8], options: &ParserOptions) -> bool {
+        !options.strict_integers
+            && !options.reject_duplicate_keys
+            && !options.first_duplicate_wins
+            && !options.require_sorted_keys
+            && !options.binary_strings
+            && options.max_total_bytes == usize::MAX
+            && input.len() <= options.max_total_len
+    }
+
+    /// Resolves an error of the tokens by parsing the input with `parse`
+    ///
+    /// Errors of the input are the ones of the parser. Otherwise the parsed
+    /// input is only returned, to be deserialized again, if the tree reads
+    /// it differently: when it holds duplicated keys, which the tree keeps
+    /// once, or when the tokens could not read it, such as an empty input.
+    pub fn recover<T>(
+        error: Error,
+        options: &ParserOptions,
+        parse: impl Fn(&ParserOptions) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let checked = ParserOptions {
+            reject_duplicate_keys: true,
+            ..*options
+        };
+
+        match parse(&checked) {
+            Err(Error::DuplicateKey(_)) => parse(options),
+            Err(error) => Err(error),
+            Ok(parsed) if error == unexpected() => Ok(parsed),
+            Ok(_) => Err(error),
+        }
+    }
+
+    /// Checks if the input holds several values, which the tree groups in a
+    /// list
+    pub fn concatenated(input: &'de [u8], options: &ParserOptions) -> bool {
+        let mut tokens = Self::new(input, options);
+        tokens.skip().is_ok() && !tokens.input[tokens.offset()..].is_empty()
+    }
+
+    pub fn new(input: &'de [u8], options: &ParserOptions) -> Self {
+        Self::nested(input, options, 0)
+    }
+
+    fn nested(input: &'de [u8], options: &ParserOptions, depth: usize) -> Self {
+        TokenDeserializer {
+            input,
+            tokens: Tokenizer::new(input),
+            options: *options,
+            depth,
+        }
+    }
+
+    /// Number of bytes read so far
+    pub fn offset(&self) -> usize {
+        self.tokens.offset()
+    }
+
+    /// Fails if anything follows the value read
+    pub fn finish(&self) -> Result<(), Error> {
+        if self.offset() == self.input.len() {
+            Ok(())
+        } else {
+            Err(unexpected())
+        }
+    }
+
+    fn peek(&self) -> Result<u8, Error> {
+        self.input
+            .get(self.offset())
+            .copied()
+            .ok_or_else(unexpected)
+    }
+
+    fn next(&mut self) -> Result<Token<'de>, Error> {
+        let token = self.tokens.next().ok_or_else(unexpected)??;
+        match token {
+            Token::Bytes(bytes)
+                if bytes.len() > self.options.max_string_len =>
+            {
+                Err(unexpected())
+            }
+            Token::UInt(_) if !self.options.unsigned_integers => {
+                Err(unexpected())
+            }
+            token => Ok(token),
+        }
+    }
+
+    /// Checks if the next token closes the innermost list or dictionary
+    fn at_end(&self) -> Result<bool, Error> {
+        Ok(self.peek()? == b'e')
+    }
+
+    fn open(&mut self) -> Result<(), Error> {
+        self.next()?;
+        self.depth += 1;
+        if self.depth > self.options.max_depth {
+            return Err(unexpected());
+        }
+
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<(), Error> {
+        match self.next()? {
+            Token::End => {
+                self.depth -= 1;
+                Ok(())
+            }
+            _ => Err(unexpected()),
+        }
+    }
+
+    fn count(&self, items: &mut usize) -> Result<(), Error> {
+        *items += 1;
+        if *items > self.options.max_items {
+            return Err(unexpected());
+        }
+
+        Ok(())
+    }
+
+    fn key(&mut self) -> Result<&'de str, Error> {
+        match self.next()? {
+            Token::Bytes(key) => {
+                core::str::from_utf8(key).map_err(|_| unexpected())
+            }
+            _ => Err(unexpected()),
+        }
+    }
+
+    /// Reads a string, binary or integer as a value, lists and dictionaries
+    /// are parsed into a tree
+    fn scalar(&mut self) -> Result<Deserializer<'de>, Error> {
+        if let b'l' | b'd' = self.peek()? {
+            let value = self.slice()?;
+            return self.tree(value);
+        }
+
+        let value = match self.next()? {
+            Token::Int(int) => BencodedValue::Integer(int),
+            Token::UInt(int) => BencodedValue::UnsignedInteger(int),
+            Token::Bytes(bytes) => match core::str::from_utf8(bytes) {
+                Ok(str) => BencodedValue::String(Cow::Borrowed(str)),
+                Err(_) => BencodedValue::Binary(Cow::Borrowed(bytes)),
+            },
+            _ => return Err(unexpected()),
+        };
+
+        Ok(Deserializer::from_value(value).options(&self.options))
+    }
+
+    /// Skips a value, checking it as the parser does
+    fn skip(&mut self) -> Result<(), Error> {
+        match self.peek()? {
+            b'l' => {
+                self.open()?;
+                self.skip_list(0)
+            }
+            b'd' => {
+                self.open()?;
+                self.skip_dict(0)
+            }
+            _ => self.next().map(|_| ()),
+        }
+    }
+
+    /// Skips the elements of a list left unread by a visitor and closes it
+    fn skip_list(&mut self, mut items: usize) -> Result<(), Error> {
+        while !self.at_end()? {
+            self.count(&mut items)?;
+            self.skip()?;
+        }
+
+        self.close()
+    }
+
+    /// Skips the entries of a dictionary left unread by a visitor and closes
+    /// it
+    fn skip_dict(&mut self, mut items: usize) -> Result<(), Error> {
+        while !self.at_end()? {
+            self.count(&mut items)?;
+            self.key()?;
+            self.skip()?;
+        }
+
+        self.close()
+    }
+
+    /// Skips a value and returns its bytes
+    fn slice(&mut self) -> Result<&'de [u8], Error> {
+        let start = self.offset();
+        self.skip()?;
+        Ok(&self.input[start..self.offset()])
+    }
+
+    /// Deserializer of the tree of a value previously skipped
+    fn tree(&self, value: &'de [u8]) -> Result<Deserializer<'de>, Error> {
+        let (value, _) =
+            parser::parse_prefix_with_options(value, &self.options)?;
+        Ok(Deserializer::from_value(value).options(&self.options))
+    }
+
+    /// Deserializer of a value previously skipped
+    fn reread(&self, value: &'de [u8]) -> Self {
+        Self::nested(value, &self.options, self.depth)
+    }
+}
+
+/// Error of the token deserializer on an input it cannot read, never
+/// reported to the user
+fn unexpected() -> Error {
+    Error::Parser(ErrorKind::Verify)
+}
+
+impl<'de> de::Deserializer<'de> for &mut TokenDeserializer<'de> {
+    type Error = Error;
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.peek()? {
+            b'l' => self.deserialize_seq(visitor),
+            b'd' => self.deserialize_map(visitor),
+            _ => self.scalar()?.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_bool(visitor)
+    }
+
+    fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_i8(visitor)
+    }
+
+    fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_i16(visitor)
+    }
+
+    fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_i32(visitor)
+    }
+
+    fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_i64(visitor)
+    }
+
+    fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_i128(visitor)
+    }
+
+    fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_u8(visitor)
+    }
+
+    fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_u16(visitor)
+    }
+
+    fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_u32(visitor)
+    }
+
+    fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_u64(visitor)
+    }
+
+    fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_u128(visitor)
+    }
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_f32(visitor)
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_f64(visitor)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_char(visitor)
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_str(visitor)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_string(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_byte_buf<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.scalar()?.deserialize_byte_buf(visitor)
+    }
+
+    /// The parser never produces a none
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if !self.input[self.offset()..].starts_with(b"de") {
+            return self.scalar()?.deserialize_unit(visitor);
+        }
+
+        self.open()?;
+        self.close()?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if self.peek()? != b'l' {
+            return self.scalar()?.deserialize_seq(visitor);
+        }
+
+        self.open()?;
+        let mut access = SeqAccess {
+            de: &mut *self,
+            items: 0,
+        };
+        let value = visitor.visit_seq(&mut access)?;
+        let items = access.items;
+        self.skip_list(items)?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if self.peek()? != b'd' {
+            return self.scalar()?.deserialize_map(visitor);
+        }
+
+        self.open()?;
+        let mut access = MapAccess {
+            de: &mut *self,
+            items: 0,
+            value: false,
+        };
+        let value = visitor.visit_map(&mut access)?;
+        let (items, unread) = (access.items, access.value);
+        if unread {
+            self.skip()?;
+        }
+        self.skip_dict(items)?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.peek()? {
+            b'l' if self.options.positional_structs => {
+                self.deserialize_seq(visitor)
+            }
+            b'd' => self.deserialize_map(visitor),
+            _ => self.scalar()?.deserialize_struct(name, fields, visitor),
+        }
+    }
+
+    /// Same representations as [`Deserializer::deserialize_enum`], the
+    /// entries of a dictionary are skipped to find the representation then
+    /// the content is read again
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        match self.peek()? {
+            b'd' => {}
+            b'l' => {
+                return self.scalar()?.deserialize_enum(name, variants, visitor)
+            }
+            _ => {
+                let variant = self.scalar()?;
+                return visitor.visit_enum(EnumAccess {
+                    variant,
+                    content: None,
+                });
+            }
+        }
+
+        let start = self.offset();
+        self.open()?;
+        let mut entries = [None; 2];
+        let mut len = 0;
+        while !self.at_end()? {
+            let entry = (self.key()?, self.slice()?);
+            if let Some(slot) = entries.get_mut(len) {
+                *slot = Some(entry);
+            }
+            len += 1;
+        }
+        self.close()?;
+
+        let (variant, content) = match entries {
+            [Some((variant, content)), None] => (
+                Deserializer::from_value(BencodedValue::String(Cow::Borrowed(
+                    variant,
+                ))),
+                content,
+            ),
+            [Some((CONTENT_KEY, content)), Some((TAG_KEY, tag))]
+            | [Some((TAG_KEY, tag)), Some((CONTENT_KEY, content))]
+                if len == 2 =>
+            {
+                (self.reread(tag).scalar()?, content)
+            }
+            // The tree keeps duplicated keys once and reports the other
+            // dictionaries
+            _ => {
+                let dict = &self.input[start..self.offset()];
+                return self
+                    .tree(dict)?
+                    .deserialize_enum(name, variants, visitor);
+            }
+        };
+
+        visitor.visit_enum(EnumAccess {
+            variant,
+            content: Some(self.reread(content)),
+        })
+    }
+
+    fn deserialize_identifier<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.skip()?;
+        visitor.visit_unit()
+    }
+}
+
+/// The elements of a list read one token at a time
+struct SeqAccess<'a, 'de> {
+    de: &'a mut TokenDeserializer<'de>,
+    items: usize,
+}
+
+impl<'a, 'de> de::SeqAccess<'de> for SeqAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        if self.de.at_end()? {
+            return Ok(None);
+        }
+
+        self.de.count(&mut self.items)?;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+}
+
+/// The entries of a dictionary read one token at a time
+struct MapAccess<'a, 'de> {
+    de: &'a mut TokenDeserializer<'de>,
+    items: usize,
+
+    /// Whether a key was read without its value
+    value: bool,
+}
+
+impl<'a, 'de> de::MapAccess<'de> for MapAccess<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: de::DeserializeSeed<'de>,
+    {
+        if self.value {
+            self.de.skip()?;
+            self.value = false;
+        }
+        if self.de.at_end()? {
+            return Ok(None);
+        }
+
+        self.de.count(&mut self.items)?;
+        let key = self.de.key()?;
+        self.value = true;
+        seed.deserialize(Deserializer::from_key(Key::borrowed(key)))
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        if !self.value {
+            return Err(unexpected());
+        }
+
+        self.value = false;
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+/// An enum variant, its name along with the deserializer of its content for
+/// the variants holding data
+struct EnumAccess<'de> {
+    variant: Deserializer<'de>,
+    content: Option<TokenDeserializer<'de>>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = Error;
+    type Variant = VariantAccess<'de>;
+
+    fn variant_seed<V>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(self.variant)?;
+
+        Ok((
+            variant,
+            VariantAccess {
+                content: self.content,
+            },
+        ))
+    }
+}
+
+struct VariantAccess<'de> {
+    content: Option<TokenDeserializer<'de>>,
+}
+
+impl<'de> VariantAccess<'de> {
+    /// Reads the content of the variant with `f`, which must read all of it
+    fn content<T>(
+        self,
+        f: impl FnOnce(&mut TokenDeserializer<'de>) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut content = self.content.ok_or(Error::UnexpectedType {
+            expected: "variant content",
+            found: "unit variant",
+        })?;
+        let value = f(&mut content)?;
+        content.finish()?;
+        Ok(value)
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.content {
+            None => Ok(()),
+            Some(content) => {
+                let (content, _) = parser::parse_prefix_with_options(
+                    content.input,
+                    &content.options,
+                )?;
+                Err(Error::unexpected("unit variant", &content))
+            }
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        self.content(|content| seed.deserialize(content))
+    }
+
+    fn tuple_variant<V>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.content(|content| content.deserialize_seq(visitor))
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.content(|content| content.deserialize_struct("", fields, visitor))
+    }
+}
+
+#[cfg(test)]
+mod tokens_tests {
+    use super::{Deserializer, TokenDeserializer};
+    use crate::{
+        error::Error, from_bytes, from_bytes_prefix, from_bytes_with_options,
+        parse_all, parser::Limit, BencodedValue, ParserOptions,
+    };
+    use maplit::hashmap;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    const TORRENT: &[u8] = include_bytes!("../../benches/test_data");
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Message<'a> {
+        Choke,
+        Have(i64),
+        Request(i64, i64, i64),
+        Piece { index: i64, block: &'a str },
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Info<'a> {
+        name: &'a str,
+        length: i64,
+        #[serde(rename = "piece length")]
+        piece_length: u32,
+        #[serde(with = "serde_bytes")]
+        pieces: &'a [u8],
+    }
+
+    /// Reads the tokens without falling back to the tree
+    fn tokens<'de, T: Deserialize<'de>>(data: &'de [u8]) -> Result<T, Error> {
+        let mut deserializer =
+            TokenDeserializer::new(data, &ParserOptions::default());
+        let value = T::deserialize(&mut deserializer)?;
+        deserializer.finish()?;
+        Ok(value)
+    }
+
+    fn assert_same<'de, T>(data: &'de [u8])
+    where
+        T: Deserialize<'de> + PartialEq + std::fmt::Debug,
+    {
+        let (_, value) = parse_all(data).unwrap();
+        let expected = crate::from_value::<T>(value).unwrap();
+        assert_eq!(tokens::<T>(data).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_values() {
+        assert_same::<i64>(b"i-42e");
+        assert_same::<u8>(b"i200e");
+        assert_same::<&str>(b"5:hello");
+        assert_same::<&[u8]>(b"3:\xff\x00\x01");
+        assert_same::<Vec<u8>>(b"3:abc");
+        assert_same::<Option<String>>(b"4:some");
+        assert_same::<Vec<&str>>(b"l1:a2:bce");
+        assert_same::<(i64, &str)>(b"li1e1:ae");
+        assert_same::<Vec<Vec<i64>>>(b"lli1eeleli2ei3eee");
+        assert_same::<HashMap<&str, i64>>(b"d1:ai1e1:bi2ee");
+        assert_same::<HashMap<u16, &str>>(b"d2:101:a3:2001:be");
+        assert_same::<BencodedValue>(TORRENT);
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Metainfo<'a> {
+            #[serde(borrow)]
+            info: Info<'a>,
+        }
+        assert_same::<Metainfo>(TORRENT);
+    }
+
+    #[test]
+    fn test_enums() {
+        let messages: [(&[u8], Message); 7] = [
+            (b"5:Choke", Message::Choke),
+            (b"d4:Havei3ee", Message::Have(3)),
+            (b"d7:Requestli1ei0ei16384eee", Message::Request(1, 0, 16384)),
+            (
+                b"d5:Pieced5:block4:data5:indexi1eee",
+                Message::Piece {
+                    index: 1,
+                    block: "data",
+                },
+            ),
+            (b"d1:ci3e1:t4:Havee", Message::Have(3)),
+            (b"d1:t4:Have1:ci3ee", Message::Have(3)),
+            (
+                b"d1:cd5:block4:data5:indexi1ee1:t5:Piecee",
+                Message::Piece {
+                    index: 1,
+                    block: "data",
+                },
+            ),
+        ];
+
+        for (data, message) in messages.iter() {
+            assert_eq!(tokens::<Message>(data).as_ref(), Ok(message));
+        }
+    }
+
+    #[test]
+    fn test_unread() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Pair(i64, i64);
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Name<'a> {
+            name: &'a str,
+        }
+
+        // Elements and entries unread by the visitor are skipped
+        assert_eq!(tokens::<Pair>(b"li1ei2ei3ee"), Ok(Pair(1, 2)));
+        assert_eq!(
+            tokens::<Name>(b"d1:ali1ed1:bi2eee4:name3:Tom1:zi0ee"),
+            Ok(Name { name: "Tom" })
+        );
+
+        // But they are still checked
+        assert!(tokens::<Pair>(b"li1ei2ei3xe").is_err());
+        assert!(tokens::<Name>(b"d1:ali1ed1:bi2ee4:name3:Tome").is_err());
+        assert!(tokens::<Name>(b"d4:name3:Tom2:\xff\xffi1ee").is_err());
+        assert!(tokens::<i64>(b"i1ei2e").is_err());
+    }
+
+    #[test]
+    fn test_unsigned() {
+        // Integers above `i64::MAX` are read as the parser reads them
+        let max = b"i18446744073709551615e";
+        assert_eq!(tokens::<u64>(max), Ok(u64::MAX));
+        assert_eq!(
+            tokens::<Vec<u64>>(b"li1ei9223372036854775808ee"),
+            Ok(vec![1, 1 << 63])
+        );
+        assert!(tokens::<i64>(max).is_err());
+
+        let signed = ParserOptions {
+            unsigned_integers: false,
+            ..ParserOptions::default()
+        };
+        assert!(TokenDeserializer::new(max, &signed).next().is_err());
+        assert!(from_bytes_with_options::<u64>(max, &signed).is_err());
+    }
+
+    #[test]
+    fn test_fallback() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Name<'a> {
+            name: &'a str,
+        }
+
+        // The tree keeps the last duplicated key where serde fails
+        let duplicated = b"d4:name1:a4:name1:be";
+        assert!(tokens::<Name>(duplicated).is_err());
+        assert_eq!(from_bytes(duplicated), Ok(Name { name: "b" }));
+
+        // Errors of the input are the ones of the parser
+        let shallow = ParserOptions {
+            max_depth: 1,
+            ..ParserOptions::default()
+        };
+        assert_eq!(
+            from_bytes_with_options::<Vec<Vec<i64>>>(b"llee", &shallow),
+            Err(Error::Limit(Limit::Depth))
+        );
+        assert_eq!(from_bytes::<HashMap<&str, i64>>(b"de"), Ok(hashmap! {}));
+        assert_eq!(from_bytes::<Option<i64>>(b""), Ok(None));
+        assert_eq!(from_bytes::<(i64, i64)>(b"i1ei2e"), Ok((1, 2)));
+        assert!(matches!(
+            from_bytes::<Name>(b"d4:name3:Tom"),
+            Err(Error::Incomplete { .. })
+        ));
+    }
+
+    #[test]
+    fn test_errors() {
+        fn assert_same_error<'de, T>(data: &'de [u8])
+        where
+            T: Deserialize<'de> + std::fmt::Debug,
+        {
+            let tree = Deserializer::from_slice(data).unwrap();
+            let expected = T::deserialize(tree).unwrap_err();
+            assert_eq!(tokens::<T>(data).unwrap_err(), expected);
+            assert_eq!(from_bytes::<T>(data).unwrap_err(), expected);
+        }
+
+        #[derive(Deserialize, Debug)]
+        struct Name<'a> {
+            #[allow(dead_code)]
+            name: &'a str,
+        }
+
+        // The tokens report the errors of the tree themselves
+        assert_same_error::<Name>(b"d4:namei1ee");
+        assert_same_error::<Name>(b"d3:agei1ee");
+        assert_same_error::<Name>(b"l3:Tome");
+        assert_same_error::<Name>(b"i1e");
+        assert_same_error::<i64>(b"li1ee");
+        assert_same_error::<&str>(b"d1:ai1ee");
+        assert_same_error::<Vec<i64>>(b"d1:ai1ee");
+        assert_same_error::<HashMap<&str, i64>>(b"li1ee");
+        assert_same_error::<()>(b"d1:ai1ee");
+        assert_same_error::<Message>(b"li1ee");
+        assert_same_error::<Message>(b"d4:Havei1e5:Piecei2ee");
+        assert_same_error::<Message>(b"d1:ci1e1:t4:Have1:xi0ee");
+        assert_same_error::<Message>(b"d5:Chokei1ee");
+        assert_same_error::<Message>(b"4:Have");
+
+        // Duplicated keys of an enum are kept once as in the tree
+        assert_eq!(tokens(b"d4:Havei1e4:Havei2ee"), Ok(Message::Have(2)));
+    }
+
+    #[test]
+    fn test_deserialize_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        #[derive(Debug)]
+        struct Counted;
+
+        impl<'de> Deserialize<'de> for Counted {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                CALLS.fetch_add(1, Ordering::Relaxed);
+                i64::deserialize(deserializer)?;
+                Err(serde::de::Error::custom("counted"))
+            }
+        }
+
+        // User implementations failing are not run again on the tree
+        for data in &[&b"i1e"[..], b"3:abc", b"li1ee"] {
+            CALLS.store(0, Ordering::Relaxed);
+            assert!(from_bytes::<Counted>(data).is_err());
+            assert!(from_bytes_prefix::<Counted>(data).is_err());
+            assert_eq!(CALLS.load(Ordering::Relaxed), 2, "{:?}", data);
+        }
+    }
+}
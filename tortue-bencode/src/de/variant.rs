@@ -0,0 +1,107 @@
+use super::Settings;
+use crate::{error::Error, BencodedValue};
+use serde::de::{self, Deserializer as _};
+
+/// An enum variant, its name along with its content for the variants
+/// holding data
+pub struct EnumAccess<'re> {
+    variant: BencodedValue<'re>,
+    content: Option<BencodedValue<'re>>,
+    settings: Settings,
+}
+
+impl<'re> EnumAccess<'re> {
+    pub fn new(
+        variant: BencodedValue<'re>,
+        content: Option<BencodedValue<'re>>,
+        settings: Settings,
+    ) -> Self {
+        EnumAccess {
+            variant,
+            content,
+            settings,
+        }
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = Error;
+    type Variant = VariantAccess<'de>;
+
+    fn variant_seed<V>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: de::DeserializeSeed<'de>,
+    {
+        let deser = super::Deserializer::from_value(self.variant);
+        let variant = seed.deserialize(deser)?;
+
+        Ok((
+            variant,
+            VariantAccess {
+                content: self.content,
+                settings: self.settings,
+            },
+        ))
+    }
+}
+
+pub struct VariantAccess<'re> {
+    content: Option<BencodedValue<'re>>,
+    settings: Settings,
+}
+
+impl<'re> VariantAccess<'re> {
+    fn content(self) -> Result<super::Deserializer<'re>, Error> {
+        match self.content {
+            Some(content) => Ok(super::Deserializer::from_value(content)
+                .settings(self.settings)),
+            None => Err(Error::UnexpectedType {
+                expected: "variant content",
+                found: "unit variant",
+            }),
+        }
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        match self.content {
+            None => Ok(()),
+            Some(content) => Err(Error::unexpected("unit variant", &content)),
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: de::DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.content()?)
+    }
+
+    fn tuple_variant<V>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.content()?.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.content()?.deserialize_struct("", fields, visitor)
+    }
+}
@@ -1,17 +1,166 @@
-use nom::error::ErrorKind;
-use serde::{de, ser};
-use std::{
-    fmt::{self, Display},
+use crate::{
     io,
+    parser::{
+        duplicate_key, integer_overflow, syntax_error, truncation,
+        unsorted_keys, Limit,
+    },
+    prelude::*,
+    BencodedValue,
 };
+use alloc::sync::Arc;
+use core::fmt::{self, Display};
+use nom::error::ErrorKind;
+use serde::{de, ser};
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
-/// This is a bare-bones implementation. I might come back and improve that later!
+/// Errors of the parser, the serializer and the deserializer
+///
+/// Errors can be cloned and compared: they hold static names, offsets and
+/// owned strings, io errors being shared by their clones (see [`IoError`]).
+/// Values of the wrong type are reported without allocating, which keeps
+/// deserializers trying several types cheap.
 #[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
 pub enum Error {
     Parser(ErrorKind),
+
+    /// A custom error of a `Serialize` or `Deserialize` implementation
     Message(String),
+
+    /// A resource limit of the parser was exceeded
+    Limit(Limit),
+
+    /// A dictionary contains the same key twice
+    DuplicateKey(String),
+
+    /// A dictionary key is not greater than the key preceding it
+    UnsortedKeys(String, String),
+
+    /// An integer is out of range, contains its digits
+    IntegerOverflow(String),
+
+    /// A value of the given type has no exact bencode representation
+    UnsupportedType(&'static str),
+
+    /// Values of the given type are rejected by the options
+    Rejected(&'static str),
+
+    /// A value cannot be read as the type expected, see
+    /// [`BencodedValue::kind`](crate::BencodedValue::kind)
+    UnexpectedType {
+        expected: &'static str,
+        found: &'static str,
+    },
+
+    /// A struct lacks a field which is not optional
+    MissingField(&'static str),
+
+    /// A struct denying unknown fields got one
+    UnknownField {
+        field: String,
+        expected: &'static [&'static str],
+    },
+
+    /// A dictionary key cannot be read as an integer, contains the key
+    InvalidKey(String),
+
+    /// A string does not have the expected number of chars
+    InvalidLength {
+        expected: usize,
+        found: usize,
+    },
+
+    /// A string is not valid UTF-8 after its first `valid_up_to` bytes
+    InvalidUtf8 {
+        valid_up_to: usize,
+    },
+
+    /// A struct with named fields got a list, see
+    /// [`ParserOptions::positional_structs`](crate::ParserOptions)
+    PositionalStruct {
+        name: &'static str,
+        len: usize,
+    },
+
+    /// Bytes follow the value deserialized
+    TrailingData {
+        /// Number of bytes following the value
+        remaining: usize,
+    },
+
+    /// An integer does not fit the target type
+    OutOfRange {
+        /// Digits of the integer
+        value: String,
+        target: &'static str,
+    },
+
+    /// A none cannot be written in a list or dictionary
+    NestedNone,
+
+    /// The buffer is too small for the encoding of the value
+    BufferTooSmall {
+        needed: usize,
+        len: usize,
+    },
+
+    /// A method was called out of order, describes what was expected
+    Misuse(&'static str),
+
+    /// The input ends in the middle of a value, more bytes may complete it
+    Incomplete {
+        /// Minimum number of additional bytes needed, if known
+        needed: Option<usize>,
+    },
+
+    /// The input is not valid bencode
+    Parse(ParseError),
+
+    /// Reading or writing failed
+    Io(IoError),
+}
+
+/// An io error, shared by the clones of the [`Error`] holding it
+///
+/// Errors are equal when their kinds and messages are.
+#[derive(Clone, Debug)]
+pub struct IoError(Arc<io::Error>);
+
+impl IoError {
+    /// Kind of the io error
+    pub fn kind(&self) -> io::ErrorKind {
+        self.0.kind()
+    }
+
+    /// The io error
+    pub fn get_ref(&self) -> &io::Error {
+        &self.0
+    }
+
+    /// Returns the io error, a copy of its kind and message if it was cloned
+    pub fn into_inner(self) -> io::Error {
+        Arc::try_unwrap(self.0)
+            .unwrap_or_else(|e| io::Error::new(e.kind(), e.to_string()))
+    }
+}
+
+impl From<io::Error> for IoError {
+    fn from(e: io::Error) -> Self {
+        IoError(Arc::new(e))
+    }
+}
+
+impl PartialEq for IoError {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind() == other.kind() && self.0.to_string() == other.0.to_string()
+    }
+}
+
+impl Display for IoError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(formatter)
+    }
 }
 
 impl ser::Error for Error {
@@ -24,21 +173,437 @@ impl de::Error for Error {
     fn custom<T: Display>(msg: T) -> Self {
         Error::Message(msg.to_string())
     }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error::MissingField(field)
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Self {
+        Error::UnknownField {
+            field: field.to_owned(),
+            expected,
+        }
+    }
 }
 
 impl Display for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Error::Parser(e) => formatter.write_str(e.description()),
+            Error::Parser(kind) => formatter.write_str(describe(kind)),
             Error::Message(e) => formatter.write_str(e),
+            Error::Limit(limit) => {
+                write!(formatter, "parser limit exceeded: {}", limit)
+            }
+            Error::DuplicateKey(key) => {
+                write!(formatter, "duplicate dictionary key {:?}", key)
+            }
+            Error::IntegerOverflow(digits) => {
+                write!(formatter, "integer out of range: {}", digits)
+            }
+            Error::UnsupportedType(name) => {
+                write!(formatter, "{} cannot be serialized without loss", name)
+            }
+            Error::Rejected(name) => {
+                write!(formatter, "{} values are rejected by the options", name)
+            }
+            Error::UnexpectedType { expected, found } => {
+                write!(formatter, "expected {}, found {}", expected, found)
+            }
+            Error::MissingField(field) => {
+                write!(formatter, "missing field `{}`", field)
+            }
+            Error::UnknownField {
+                field,
+                expected: [],
+            } => {
+                write!(formatter, "unknown field `{}`, there are no fields", field)
+            }
+            Error::UnknownField { field, expected } => {
+                write!(formatter, "unknown field `{}`, expected one of ", field)?;
+                for (i, name) in expected.iter().enumerate() {
+                    let separator = if i == 0 { "" } else { ", " };
+                    write!(formatter, "{}`{}`", separator, name)?;
+                }
+                Ok(())
+            }
+            Error::InvalidKey(key) => {
+                write!(formatter, "cannot convert key {:?} to int", key)
+            }
+            Error::InvalidLength { expected, found } => write!(
+                formatter,
+                "expected {} chars, found {}",
+                expected, found
+            ),
+            Error::InvalidUtf8 { valid_up_to } => write!(
+                formatter,
+                "invalid utf-8 in string after {} bytes",
+                valid_up_to
+            ),
+            Error::PositionalStruct { name, len } => write!(
+                formatter,
+                "expected dictionary for struct `{}`, found list of {} elements",
+                name, len
+            ),
+            Error::TrailingData { remaining } => write!(
+                formatter,
+                "{} bytes of trailing data after the value",
+                remaining
+            ),
+            Error::OutOfRange { value, target } => write!(
+                formatter,
+                "integer {} is out of range for {}",
+                value, target
+            ),
+            Error::NestedNone => formatter
+                .write_str("cannot write a none nested in a list or a dictionary"),
+            Error::BufferTooSmall { needed, len } => write!(
+                formatter,
+                "buffer too small, need {} bytes but got {}",
+                needed, len
+            ),
+            Error::Misuse(expected) => write!(formatter, "expected {}", expected),
+            Error::UnsortedKeys(previous, key) => write!(
+                formatter,
+                "dictionary key {:?} is not sorted after {:?}",
+                key, previous
+            ),
+            Error::Incomplete {
+                needed: Some(needed),
+            } => write!(
+                formatter,
+                "truncated input, at least {} more bytes needed",
+                needed
+            ),
+            Error::Incomplete { needed: None } => {
+                formatter.write_str("truncated input")
+            }
+            Error::Parse(error) => error.fmt(formatter),
+            Error::Io(e) => write!(formatter, "io error: {}", e),
+        }
+    }
+}
+
+// `std::error::Error` with the `std` feature, its `no_std` equivalent otherwise
+impl ser::StdError for Error {
+    fn source(&self) -> Option<&(dyn ser::StdError + 'static)> {
+        match self {
+            Error::Parse(e) => Some(e),
+            Error::Io(e) => Some(e.get_ref()),
+            _ => None,
         }
     }
 }
 
-impl std::error::Error for Error {}
+/// Number of bytes of the input shown by [`ParseError`] on each side of the
+/// offset of the error
+const PREVIEW_LEN: usize = 8;
 
-impl Into<io::Error> for Error {
-    fn into(self) -> io::Error {
-        io::Error::new(io::ErrorKind::Other, format!("{}", self))
+/// A parse error located in the original input
+///
+/// Errors in nested values are reported at the offset of the top-level value
+/// that could not be parsed.
+///
+/// It can be used as the error type of nom combinators, the offset is then
+/// relative to the slice the error was created from until
+/// [`ParseError::locate`] is called.
+#[derive(Clone, PartialEq)]
+pub struct ParseError {
+    /// Offset (in bytes) of the error from the start of the input
+    pub offset: usize,
+
+    /// Kind of the error, `None` if the input is truncated
+    pub kind: Option<ErrorKind>,
+
+    /// First bytes of the input at the offset of the error
+    pub preview: Vec<u8>,
+
+    /// Last bytes of the input before the offset of the error, empty until
+    /// the error is located
+    pub preceding: Vec<u8>,
+
+    /// Description of what was expected, if known
+    pub expected: Option<String>,
+
+    /// Number of bytes of the input from the offset of the error
+    remaining: usize,
+}
+
+impl ParseError {
+    /// Creates an error at the start of `input`
+    fn at(input: &[u8], kind: Option<ErrorKind>) -> Self {
+        Self {
+            offset: 0,
+            kind,
+            preview: input[..input.len().min(PREVIEW_LEN)].to_vec(),
+            preceding: Vec::new(),
+            expected: None,
+            remaining: input.len(),
+        }
+    }
+
+    /// Computes the offset of the error in `input`, the slice the parser was
+    /// called on
+    pub fn locate(self, input: &[u8]) -> Self {
+        let offset = input.len().saturating_sub(self.remaining);
+        Self {
+            offset,
+            preceding: input[offset.saturating_sub(PREVIEW_LEN)..offset]
+                .to_vec(),
+            ..self
+        }
+    }
+
+    /// Sets what was expected at the offset of the error
+    pub(crate) fn expecting(self, expected: &str) -> Self {
+        Self {
+            expected: Some(expected.to_owned()),
+            ..self
+        }
+    }
+
+    /// Creates an error on a single `byte` at `offset`, for scanners which do
+    /// not keep their input
+    #[cfg(feature = "std")]
+    pub(crate) fn byte(offset: usize, byte: u8, kind: ErrorKind) -> Self {
+        Self {
+            offset,
+            kind: Some(kind),
+            preview: [byte].to_vec(),
+            preceding: Vec::new(),
+            expected: None,
+            remaining: 1,
+        }
+    }
+
+    /// Locates a parser error in `input`, the slice the parser was called on
+    pub fn new(input: &[u8], error: &nom::Err<(&[u8], ErrorKind)>) -> Self {
+        let (offset, kind) = match error {
+            nom::Err::Incomplete(_) => (input.len(), None),
+            nom::Err::Error((rest, kind)) | nom::Err::Failure((rest, kind)) => {
+                // The remaining input is always a subslice of `input`
+                let offset = rest.as_ptr() as usize - input.as_ptr() as usize;
+                (offset.min(input.len()), Some(*kind))
+            }
+        };
+
+        Self::at(&input[offset..], kind).locate(input)
+    }
+}
+
+impl<'a> nom::error::ParseError<&'a [u8]> for ParseError {
+    fn from_error_kind(input: &'a [u8], kind: ErrorKind) -> Self {
+        Self::at(input, Some(kind))
+    }
+
+    fn append(_: &'a [u8], _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    fn from_char(input: &'a [u8], expected: char) -> Self {
+        Self {
+            expected: Some(format!("{:?}", expected)),
+            ..Self::at(input, Some(ErrorKind::Char))
+        }
+    }
+
+    fn add_context(_: &'a [u8], context: &'static str, other: Self) -> Self {
+        Self {
+            expected: other.expected.or_else(|| Some(context.to_owned())),
+            ..other
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "parse error at byte {}", self.offset)?;
+
+        match self.kind {
+            Some(kind) => write!(formatter, " ({})", describe(&kind))?,
+            None => formatter.write_str(" (truncated input)")?,
+        }
+
+        if let Some(expected) = &self.expected {
+            write!(formatter, ", expected {}", expected)?;
+        }
+
+        if !self.preview.is_empty() {
+            write!(formatter, ", found {}", Escaped(&self.preview))?;
+        }
+
+        if !self.preceding.is_empty() {
+            write!(formatter, " after {}", Escaped(&self.preceding))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ParseError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("ParseError")
+            .field("offset", &self.offset)
+            .field("kind", &self.kind)
+            .field("expected", &self.expected)
+            .field("preview", &Escaped(&self.preview))
+            .field("preceding", &Escaped(&self.preceding))
+            .finish()
+    }
+}
+
+/// Bytes written as a quoted string, those which are not printable ASCII
+/// are escaped as hexadecimal
+struct Escaped<'a>(&'a [u8]);
+
+impl Display for Escaped<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("\"")?;
+        for &byte in self.0 {
+            for escaped in core::ascii::escape_default(byte) {
+                write!(formatter, "{}", escaped as char)?;
+            }
+        }
+        formatter.write_str("\"")
+    }
+}
+
+impl fmt::Debug for Escaped<'_> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(self, formatter)
+    }
+}
+
+/// Describes the kinds of errors returned by the parser, the description of
+/// nom is only used for the kinds it never returns
+fn describe(kind: &ErrorKind) -> &str {
+    match kind {
+        ErrorKind::Tag => "missing delimiter",
+        ErrorKind::Char => "unexpected character",
+        ErrorKind::TakeWhileMN => "invalid integer digits",
+        ErrorKind::Digit => "integer out of range",
+        ErrorKind::MapOpt => "string length out of range",
+        ErrorKind::Verify => "non-canonical integer or invalid string",
+        ErrorKind::Eof => "unexpected data after the values",
+        ErrorKind::Permutation => "duplicate dictionary key",
+        ErrorKind::SeparatedList => "unsorted dictionary keys",
+        ErrorKind::TooLarge => "nesting too deep",
+        ErrorKind::LengthValue => "string too long",
+        ErrorKind::LengthValueFn => "input too long",
+        ErrorKind::Count => "too many items",
+        ErrorKind::ManyMN => "too many bytes",
+        kind => kind.description(),
+    }
+}
+
+impl ser::StdError for ParseError {}
+
+impl Error {
+    /// An integer does not fit the `target` type
+    pub(crate) fn out_of_range(
+        value: impl Display,
+        target: &'static str,
+    ) -> Self {
+        Error::OutOfRange {
+            value: value.to_string(),
+            target,
+        }
+    }
+
+    /// A value of the wrong kind was found where `expected` was
+    pub(crate) fn unexpected(
+        expected: &'static str,
+        found: &BencodedValue,
+    ) -> Self {
+        Error::UnexpectedType {
+            expected,
+            found: found.kind(),
+        }
+    }
+
+    /// Converts a parser error on `input`, reporting its offset and what was
+    /// expected there
+    ///
+    /// Errors caused by the input ending in the middle of a value, including
+    /// unterminated lists and dictionaries, are reported as
+    /// [`Error::Incomplete`].
+    pub fn parse(input: &[u8], error: nom::Err<(&[u8], ErrorKind)>) -> Self {
+        if let Some(limit) = Limit::from_error(&error) {
+            Error::Limit(limit)
+        } else if let Some(key) = duplicate_key(&error) {
+            Error::DuplicateKey(key.to_owned())
+        } else if let Some(digits) = integer_overflow(&error) {
+            Error::IntegerOverflow(digits.to_owned())
+        } else if let Some((previous, key)) = unsorted_keys(&error) {
+            Error::UnsortedKeys(previous.to_owned(), key.to_owned())
+        } else if let Some(needed) = truncation(input) {
+            Error::Incomplete {
+                needed: Some(needed),
+            }
+        } else if let nom::Err::Incomplete(_) = error {
+            Error::Incomplete { needed: None }
+        } else {
+            // The parser reports errors at the start of the value it failed
+            // to parse, the tokenizer finds the offending byte within it
+            let error = ParseError::new(input, &error);
+            match syntax_error(input) {
+                Some((value, located)) if value == error.offset => {
+                    Error::Parse(located)
+                }
+                _ => Error::Parse(error),
+            }
+        }
+    }
+}
+
+impl<I: fmt::Debug> From<nom::Err<(I, ErrorKind)>> for Error {
+    fn from(e: nom::Err<(I, ErrorKind)>) -> Self {
+        match (Limit::from_error(&e), e) {
+            (Some(limit), _) => Error::Limit(limit),
+            (None, nom::Err::Incomplete(needed)) => Error::Incomplete {
+                needed: match needed {
+                    nom::Needed::Size(size) => Some(size),
+                    nom::Needed::Unknown => None,
+                },
+            },
+            (None, nom::Err::Error((_, kind)))
+            | (None, nom::Err::Failure((_, kind))) => Error::Parser(kind),
+        }
+    }
+}
+
+/// Errors of the combinators of [`ParseError`] keep the offset they were
+/// given, see [`ParseError::locate`]
+impl From<nom::Err<ParseError>> for Error {
+    fn from(e: nom::Err<ParseError>) -> Self {
+        match e {
+            nom::Err::Incomplete(needed) => Error::Incomplete {
+                needed: match needed {
+                    nom::Needed::Size(size) => Some(size),
+                    nom::Needed::Unknown => None,
+                },
+            },
+            nom::Err::Error(e) | nom::Err::Failure(e) => Error::Parse(e),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e.into())
+    }
+}
+
+/// Io errors are returned as they were, the other errors are wrapped in an
+/// error of kind `Other`
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => e.into_inner(),
+            #[cfg(feature = "std")]
+            e => io::Error::other(e),
+            #[cfg(not(feature = "std"))]
+            e => io::Error::new(io::ErrorKind::Other, e.to_string()),
+        }
     }
 }
@@ -0,0 +1,91 @@
+//! The IO types used by the writer, those of `std::io` with the `std` feature
+//! and a minimal replacement writing into byte vecs otherwise
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind, Result, Write};
+
+#[cfg(not(feature = "std"))]
+pub use self::alloc_io::{Error, ErrorKind, Result, Write};
+
+#[cfg(not(feature = "std"))]
+mod alloc_io {
+    use crate::prelude::*;
+    use core::fmt;
+
+    /// Result of the writer
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// Kind of a writer error, the subset of `std::io::ErrorKind` the writer
+    /// reports
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum ErrorKind {
+        InvalidInput,
+        InvalidData,
+        Other,
+    }
+
+    /// Error of the writer
+    #[derive(Debug)]
+    pub struct Error {
+        kind: ErrorKind,
+        message: String,
+    }
+
+    impl Error {
+        /// Creates an error from a kind and a message
+        pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+            Self {
+                kind,
+                message: message.into(),
+            }
+        }
+
+        /// Kind of the error
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str(&self.message)
+        }
+    }
+
+    impl serde::ser::StdError for Error {}
+
+    /// Sink of the writer
+    pub trait Write {
+        /// Writes the whole buffer
+        fn write_all(&mut self, buf: &[u8]) -> Result<()>;
+    }
+
+    impl Write for Vec<u8> {
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            self.extend_from_slice(buf);
+            Ok(())
+        }
+    }
+
+    impl Write for &mut [u8] {
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            if buf.len() > self.len() {
+                return Err(Error::new(
+                    ErrorKind::Other,
+                    "failed to write whole buffer",
+                ));
+            }
+
+            let (head, tail) = core::mem::take(self).split_at_mut(buf.len());
+            head.copy_from_slice(buf);
+            *self = tail;
+            Ok(())
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            (**self).write_all(buf)
+        }
+    }
+}
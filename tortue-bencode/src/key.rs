@@ -0,0 +1,352 @@
+//! Key of bencoded dictionaries
+//!
+//! Keys behave like a `Cow<'a, str>`: parsed keys borrow from the input while
+//! owned keys are copied. Nearly all keys found in torrents are short (`name`,
+//! `length`, `pieces`, `path`, ...), storing owned keys inline avoids one
+//! allocation per key.
+
+use crate::prelude::*;
+use core::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+use serde::{
+    de::{Error, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// Maximum length (in bytes) of an owned key stored inline
+pub const INLINE_CAPACITY: usize = 22;
+
+/// A dictionary key, either borrowed from the input or owned. Owned keys of up
+/// to [`INLINE_CAPACITY`] bytes are stored inline while longer keys are stored
+/// on the heap.
+///
+/// Equality, hashing and ordering are the ones of the underlying `str` so a
+/// `HashMap<Key, _>` can be queried using a `&str`.
+#[derive(Clone)]
+pub struct Key<'a>(Repr<'a>);
+
+#[derive(Clone)]
+enum Repr<'a> {
+    Borrowed(&'a str),
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_CAPACITY],
+    },
+    Heap(Box<str>),
+}
+
+impl<'a> Key<'a> {
+    /// Creates a new owned key, copying the string
+    pub fn new(value: &str) -> Self {
+        if value.len() <= INLINE_CAPACITY {
+            let mut bytes = [0; INLINE_CAPACITY];
+            bytes[..value.len()].copy_from_slice(value.as_bytes());
+
+            Key(Repr::Inline {
+                len: value.len() as u8,
+                bytes,
+            })
+        } else {
+            Key(Repr::Heap(value.into()))
+        }
+    }
+
+    /// Creates a new key borrowing the string
+    pub fn borrowed(value: &'a str) -> Self {
+        Key(Repr::Borrowed(value))
+    }
+
+    /// Returns the key as a string slice
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Repr::Borrowed(str) => str,
+            Repr::Inline { len, bytes } => {
+                // Safety: the bytes were copied from a valid `str` and `len`
+                // is on a char boundary
+                unsafe {
+                    core::str::from_utf8_unchecked(&bytes[..*len as usize])
+                }
+            }
+            Repr::Heap(str) => str,
+        }
+    }
+
+    /// Checks if the key is owned (i.e. does not borrow from the input)
+    pub fn is_owned(&self) -> bool {
+        match self.0 {
+            Repr::Borrowed(_) => false,
+            Repr::Inline { .. } | Repr::Heap(_) => true,
+        }
+    }
+
+    /// Checks if the key is stored inline (i.e. owned without allocation)
+    pub fn is_inline(&self) -> bool {
+        match self.0 {
+            Repr::Inline { .. } => true,
+            Repr::Borrowed(_) | Repr::Heap(_) => false,
+        }
+    }
+
+    /// Converts the key into an owned key, copying it if it is borrowed
+    pub fn into_owned(self) -> Key<'static> {
+        match self.0 {
+            Repr::Borrowed(str) => Key::new(str),
+            Repr::Inline { len, bytes } => Key(Repr::Inline { len, bytes }),
+            Repr::Heap(str) => Key(Repr::Heap(str)),
+        }
+    }
+
+    /// Converts the key into a `Cow`, allocating only if it is stored inline
+    pub fn into_cow(self) -> Cow<'a, str> {
+        match self.0 {
+            Repr::Borrowed(str) => Cow::Borrowed(str),
+            Repr::Inline { .. } => Cow::Owned(self.as_str().to_owned()),
+            Repr::Heap(str) => Cow::Owned(str.into_string()),
+        }
+    }
+}
+
+impl<'a> Deref for Key<'a> {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'a> AsRef<str> for Key<'a> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'a> Borrow<str> for Key<'a> {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'a, 'b> PartialEq<Key<'b>> for Key<'a> {
+    fn eq(&self, other: &Key<'b>) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<'a> Eq for Key<'a> {}
+
+impl<'a> PartialEq<str> for Key<'a> {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'a, 'b> PartialEq<&'b str> for Key<'a> {
+    fn eq(&self, other: &&'b str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl<'a> PartialEq<String> for Key<'a> {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'a> PartialOrd for Key<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> Ord for Key<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl<'a> Hash for Key<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl<'a> fmt::Debug for Key<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl<'a> fmt::Display for Key<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl<'a> From<&'a str> for Key<'a> {
+    fn from(value: &'a str) -> Self {
+        Key::borrowed(value)
+    }
+}
+
+impl<'a> From<String> for Key<'a> {
+    fn from(value: String) -> Self {
+        if value.len() <= INLINE_CAPACITY {
+            Key::new(&value)
+        } else {
+            Key(Repr::Heap(value.into_boxed_str()))
+        }
+    }
+}
+
+impl<'a> From<Cow<'a, str>> for Key<'a> {
+    fn from(value: Cow<'a, str>) -> Self {
+        match value {
+            Cow::Borrowed(str) => Key::borrowed(str),
+            Cow::Owned(str) => Key::from(str),
+        }
+    }
+}
+
+impl<'a> From<Key<'a>> for String {
+    fn from(value: Key<'a>) -> Self {
+        value.into_cow().into_owned()
+    }
+}
+
+impl<'a> Serialize for Key<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+struct KeyVisitor;
+
+impl<'de> Visitor<'de> for KeyVisitor {
+    type Value = Key<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("expected string")
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(Key::borrowed(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(Key::new(v))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        Ok(Key::from(v))
+    }
+}
+
+impl<'de> Deserialize<'de> for Key<'de> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(KeyVisitor)
+    }
+}
+
+#[cfg(test)]
+mod key_tests {
+    use super::{Key, INLINE_CAPACITY};
+    use std::{
+        borrow::Cow,
+        collections::{hash_map::DefaultHasher, HashMap},
+        hash::{Hash, Hasher},
+        mem::size_of,
+    };
+
+    fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_inline() {
+        assert_eq!(size_of::<Key>(), size_of::<String>());
+
+        let short = Key::new("piece length");
+        assert!(short.is_inline());
+        assert!(short.is_owned());
+        assert_eq!(short, "piece length");
+
+        let limit = "a".repeat(INLINE_CAPACITY);
+        assert!(Key::new(&limit).is_inline());
+
+        let long = "a".repeat(INLINE_CAPACITY + 1);
+        let key = Key::from(long.clone());
+        assert!(!key.is_inline());
+        assert!(key.is_owned());
+        assert_eq!(key, long);
+        assert_eq!(String::from(key), long);
+
+        assert_eq!(Key::new(""), "");
+        assert_eq!(Key::new("héllo"), "héllo");
+    }
+
+    #[test]
+    fn test_borrowed() {
+        let key = Key::from("name");
+        assert!(!key.is_owned());
+        assert!(!key.is_inline());
+        assert_eq!(key, Key::new("name"));
+        assert_eq!(key.clone().into_cow(), Cow::Borrowed("name"));
+
+        let owned = key.into_owned();
+        assert!(owned.is_inline());
+        assert_eq!(owned, "name");
+
+        assert!(!Key::from(Cow::Borrowed("name")).is_owned());
+        assert!(Key::from(Cow::Owned("name".to_owned())).is_owned());
+    }
+
+    #[test]
+    fn test_semantics() {
+        let long = "a".repeat(INLINE_CAPACITY + 1);
+
+        for str in &["", "name", "piece length", long.as_str()] {
+            assert_eq!(hash(&Key::new(str)), hash(*str));
+            assert_eq!(hash(&Key::borrowed(str)), hash(*str));
+        }
+
+        let (a, ab, b) = (Key::new("a"), Key::new("ab"), Key::borrowed("b"));
+        assert!(a < b);
+        assert!(ab > a);
+
+        let long = Key::new(&long);
+        assert!(long > a);
+
+        let map: HashMap<Key, i64> =
+            vec![(Key::borrowed("name"), 1), (long.clone(), 2)]
+                .into_iter()
+                .collect();
+
+        assert_eq!(map.get("name"), Some(&1));
+        assert_eq!(map.get(long.as_str()), Some(&2));
+        assert_eq!(map.get("length"), None);
+    }
+}
@@ -2,12 +2,61 @@
 //!
 //! Written in pure rust, should be plenty fast for any torrent related use
 //!
-//! ⚠ Note that tupple struct, and enums are supported in serialization but require
-//!   deserialization code as there is no way in bincode to encode the variant used.
+//! The free serialization functions use the default settings,
+//! [`SerializerBuilder`] changes them: the [`EnumRepresentation`], the
+//! [`KeyOrder`], the [`UnitRepresentation`], canonical output, lossy
+//! conversions and none values.
 //!
+//! Enums are externally tagged by default, unit variants being their plain
+//! name. The deserializer reads both tagged representations while untagged
+//! enums require `#[serde(untagged)]`.
+//!
+//! Structs skip the keys they do not know: `#[serde(deny_unknown_fields)]`
+//! rejects them instead, while a `#[serde(flatten, borrow)]` field of type
+//! `HashMap<&str, BencodedValue>` captures them without copying the input.
+//!
+//! The `std` feature is enabled by default, disabling it and enabling `alloc`
+//! instead keeps the parser, the writer and serde support in `no_std` crates.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(any(feature = "std", feature = "alloc")))]
+compile_error!("either the `std` or the `alloc` feature must be enabled");
+
+extern crate alloc;
+
+use crate::prelude::*;
+use core::{convert::TryFrom, fmt, mem::size_of};
+
+/// Map of the dictionaries, the one of `std` or hashbrown's without `std`
+#[cfg(feature = "std")]
+pub use std::collections::HashMap;
+
+#[cfg(not(feature = "std"))]
+pub use hashbrown::HashMap;
+
+#[cfg(feature = "std")]
+pub(crate) use std::collections::hash_map;
 
-use std::{collections::HashMap, fmt};
+#[cfg(not(feature = "std"))]
+pub(crate) use hashbrown::hash_map;
 
+/// The part of the std prelude provided by alloc
+mod prelude {
+    pub use alloc::{
+        borrow::{Cow, ToOwned},
+        boxed::Box,
+        format,
+        string::{String, ToString},
+        vec::Vec,
+    };
+}
+
+pub mod bytes;
+pub mod bytes_array;
+pub mod io;
+pub mod key;
+mod merge;
 pub mod parser;
 pub mod writer;
 
@@ -15,9 +64,31 @@ pub mod de;
 pub mod error;
 pub mod ser;
 
-pub use de::{from_bytes, from_value};
-pub use parser::{parse, parse_all, parse_all_incomplete};
-pub use ser::{to_bytes, to_value, to_writer};
+use error::Error;
+
+#[cfg(feature = "tokio")]
+pub use de::from_async_reader;
+#[cfg(feature = "std")]
+pub use de::{from_reader, from_reader_with_options};
+pub use de::{
+    from_bytes, from_bytes_prefix, from_bytes_seed, from_bytes_with_options,
+    from_value, from_value_ref, from_value_seed,
+};
+pub use key::Key;
+pub use merge::MergeStrategy;
+pub use parser::{
+    parse, parse_all, parse_all_incomplete, parse_prefix, parse_with_options,
+    BoolMode, ParserOptions,
+};
+#[cfg(feature = "tokio")]
+pub use ser::to_async_writer;
+pub use ser::{
+    to_bytes, to_bytes_canonical, to_bytes_strict, to_value, to_writer,
+    to_writer_canonical, EnumRepresentation, KeyOrder, SerializerBuilder,
+    SerializerOptions, UnitRepresentation,
+};
+#[cfg(feature = "std")]
+pub use ser::to_writer_buffered;
 use serde::{
     de::{MapAccess, SeqAccess, Visitor},
     ser::{SerializeMap, SerializeSeq},
@@ -27,37 +98,51 @@ use serde::{
 #[cfg(feature = "arbitrary")]
 use arbitrary::Unstructured;
 
+/// Maximum number of elements preallocated from an untrusted size hint,
+/// collections grow organically beyond that point
+pub(crate) const MAX_PREALLOCATED: usize = 4096;
+
+/// Caps a size hint to [`MAX_PREALLOCATED`] so that a hostile input cannot
+/// force a huge allocation before any element has actually been read
+#[inline]
+pub(crate) fn cautious_capacity(hint: Option<usize>) -> usize {
+    hint.map_or(0, |hint| hint.min(MAX_PREALLOCATED))
+}
+
 /// A bencoded value that has been parsed
 ///
 /// This value implements Serialize and Deserialized, this is useful if you are writing data
 /// structure that can contain "any" bencoded value as you can just make the field BencodedValue<'a>
+///
+/// Strings, binaries and dictionary keys either borrow from the parsed input
+/// or are owned, see [`BencodedValue::is_owned`].
 #[derive(Clone, Eq)]
 pub enum BencodedValue<'a> {
     /// A binary array, this is a convinience method at binary data is stored in string-like
     /// fiels inside of the data
-    Binary(&'a [u8]),
-
-    /// An owned binary array
-    BinaryOwned(Vec<u8>),
-
-    /// A string, uses a substring to make it more allocation friendly
-    String(&'a str),
+    Binary(Cow<'a, [u8]>),
 
-    /// An owned string
-    StringOwned(String),
+    /// A string, borrows from the input when parsed to make it more allocation friendly
+    String(Cow<'a, str>),
 
     /// A parsed integer, note that it is recommended to use i64 for numbers
     /// otherwise files larger than 4GB cannot be supported
     Integer(i64),
 
+    /// An integer above `i64::MAX`, not produced by the parser when
+    /// [`ParserOptions::unsigned_integers`] is unset
+    UnsignedInteger(u64),
+
     /// A list of bencoded values
     List(Vec<BencodedValue<'a>>),
 
-    /// A dictionary (map) of bencoded values
-    Dictionary(HashMap<&'a str, BencodedValue<'a>>),
+    /// A dictionary (map) of bencoded values, short owned keys are stored inline
+    Dictionary(HashMap<Key<'a>, BencodedValue<'a>>),
 
-    /// A dictionary (map) with owned keys
-    DictionaryOwned(HashMap<String, BencodedValue<'a>>),
+    /// Pre-encoded bencode, written verbatim by the writer. It is never
+    /// produced by the parser and is only parsed when compared against
+    /// structured values or deserialized.
+    Raw(Cow<'a, [u8]>),
 
     /// An empty value. Note that this does **not** exist in bencode, it is used
     /// as a helper value internally to represent empty values and Option::None.
@@ -71,73 +156,33 @@ impl<'a> PartialEq for BencodedValue<'a> {
                 bin1 == bin2
             }
 
-            (BencodedValue::Binary(bin1), BencodedValue::BinaryOwned(bin2)) => {
-                bin2 == bin1
+            (BencodedValue::Binary(bin), BencodedValue::String(str))
+            | (BencodedValue::String(str), BencodedValue::Binary(bin)) => {
+                bin[..] == *str.as_bytes()
             }
 
-            (BencodedValue::BinaryOwned(bin1), BencodedValue::Binary(bin2)) => {
-                bin1 == bin2
-            }
-
-            (
-                BencodedValue::BinaryOwned(bin1),
-                BencodedValue::BinaryOwned(bin2),
-            ) => bin1 == bin2,
-
-            (BencodedValue::Binary(bin), BencodedValue::String(str)) => {
-                *bin == str.as_bytes()
-            }
-
-            (BencodedValue::BinaryOwned(bin), BencodedValue::String(str)) => {
-                *bin == str.as_bytes()
-            }
-
-            (BencodedValue::Binary(bin), BencodedValue::StringOwned(str)) => {
-                *bin == str.as_bytes()
-            }
-
-            (
-                BencodedValue::BinaryOwned(bin),
-                BencodedValue::StringOwned(str),
-            ) => *bin == str.as_bytes(),
-
-            (BencodedValue::String(str), BencodedValue::Binary(bin)) => {
-                *bin == str.as_bytes()
-            }
-
-            (BencodedValue::String(str), BencodedValue::BinaryOwned(bin)) => {
-                *bin == str.as_bytes()
-            }
-
-            (BencodedValue::StringOwned(str), BencodedValue::Binary(bin)) => {
-                *bin == str.as_bytes()
-            }
-
-            (
-                BencodedValue::StringOwned(str),
-                BencodedValue::BinaryOwned(bin),
-            ) => *bin == str.as_bytes(),
-
             (BencodedValue::String(str1), BencodedValue::String(str2)) => {
                 str1 == str2
             }
 
-            (BencodedValue::StringOwned(str1), BencodedValue::String(str2)) => {
-                str1 == str2
-            }
-
-            (BencodedValue::String(str1), BencodedValue::StringOwned(str2)) => {
-                str1 == str2
-            }
-
-            (BencodedValue::StringOwned(str1), BencodedValue::StringOwned(str2)) => {
-                str1 == str2
-            }
-
             (BencodedValue::Integer(int1), BencodedValue::Integer(int2)) => {
                 int1 == int2
             }
 
+            (
+                BencodedValue::UnsignedInteger(int1),
+                BencodedValue::UnsignedInteger(int2),
+            ) => int1 == int2,
+
+            (
+                BencodedValue::Integer(int),
+                BencodedValue::UnsignedInteger(uint),
+            )
+            | (
+                BencodedValue::UnsignedInteger(uint),
+                BencodedValue::Integer(int),
+            ) => u64::try_from(*int) == Ok(*uint),
+
             (BencodedValue::List(list1), BencodedValue::List(list2)) => {
                 list1 == list2
             }
@@ -147,59 +192,57 @@ impl<'a> PartialEq for BencodedValue<'a> {
                 BencodedValue::Dictionary(dict2),
             ) => dict1 == dict2,
 
-            (
-                BencodedValue::DictionaryOwned(dict1),
-                BencodedValue::DictionaryOwned(dict2),
-            ) => dict1 == dict2,
+            (BencodedValue::None, BencodedValue::None) => true,
 
-            (
-                BencodedValue::DictionaryOwned(dict1),
-                BencodedValue::Dictionary(dict2),
-            ) => !dict1
-                .iter()
-                .any(|(k1, v1)| dict2.get(k1 as &str) != Some(v1)),
+            (BencodedValue::Raw(raw1), BencodedValue::Raw(raw2))
+                if raw1 == raw2 =>
+            {
+                true
+            }
 
-            (
-                BencodedValue::Dictionary(dict1),
-                BencodedValue::DictionaryOwned(dict2),
-            ) => !dict1
-                    .iter()
-                    .any(|(k1, v1)| dict2.get(*k1) != Some(v1)),
+            (BencodedValue::Raw(raw), value)
+            | (value, BencodedValue::Raw(raw)) => {
+                match parser::parse_exact(raw) {
+                    Ok(parsed) => parsed == *value,
+                    Err(_) => false,
+                }
+            }
 
             _ => false,
         }
     }
 }
 
-
 #[cfg(feature = "arbitrary")]
 impl arbitrary::Arbitrary for BencodedValue<'static> {
     fn arbitrary(u: &mut Unstructured<'_>) -> Result<Self, arbitrary::Error> {
-        Ok(match u.int_in_range(0..=4)? {
+        Ok(match u.int_in_range(0..=5)? {
             0 => {
                 let mut buffer = Vec::<u8>::new();
                 u.fill_buffer(&mut buffer)?;
 
-                BencodedValue::BinaryOwned(buffer)
+                BencodedValue::Binary(Cow::Owned(buffer))
             }
-            1 => BencodedValue::StringOwned(u.arbitrary::<String>()?),
+            1 => BencodedValue::String(Cow::Owned(u.arbitrary::<String>()?)),
             2 => BencodedValue::Integer(u.arbitrary()?),
             3 => BencodedValue::List(
                 u.arbitrary_iter()?
-                    .collect::<Result<Vec<BencodedValue<'static>>, _>>()?
+                    .collect::<Result<Vec<BencodedValue<'static>>, _>>()?,
             ),
             4 => {
                 let len = u.arbitrary_len::<(String, BencodedValue)>()? / 2;
                 let keys = (0..len)
-                    .map(|_| u.arbitrary::<String>())
+                    .map(|_| u.arbitrary::<String>().map(Key::from))
                     .collect::<Result<Vec<_>, _>>()?;
                 let values = (0..len)
-                    .map(|_| u.arbitrary::<BencodedValue>()).collect::<Result<Vec<_>, _>>()?;
+                    .map(|_| u.arbitrary::<BencodedValue>())
+                    .collect::<Result<Vec<_>, _>>()?;
 
-                BencodedValue::DictionaryOwned(
+                BencodedValue::Dictionary(
                     keys.into_iter().zip(values.into_iter()).collect(),
                 )
             }
+            5 => BencodedValue::None,
             _ => unreachable!(),
         })
     }
@@ -212,19 +255,15 @@ impl<'a> fmt::Debug for BencodedValue<'a> {
                 .debug_struct("Binary")
                 .field("length", &bin_value.len())
                 .finish(),
-            (&BencodedValue::BinaryOwned(ref bin_value),) => f
-                .debug_struct("BinaryOwned")
-                .field("length", &bin_value.len())
-                .finish(),
             (&BencodedValue::String(ref str),) => {
                 f.debug_tuple("String").field(str).finish()
             }
-            (&BencodedValue::StringOwned(ref str),) => {
-                f.debug_tuple("StringOwned").field(str).finish()
-            }
             (&BencodedValue::Integer(ref int),) => {
                 f.debug_tuple("Integer").field(int).finish()
             }
+            (&BencodedValue::UnsignedInteger(ref int),) => {
+                f.debug_tuple("UnsignedInteger").field(int).finish()
+            }
             (&BencodedValue::List(ref list),) => {
                 if list.len() > 32 {
                     f.debug_struct("List").field("length", &list.len()).finish()
@@ -241,16 +280,10 @@ impl<'a> fmt::Debug for BencodedValue<'a> {
                     f.debug_tuple("Dictionary").field(dict).finish()
                 }
             }
-            (&BencodedValue::DictionaryOwned(ref dict),) => {
-                if dict.len() > 32 {
-                    f.debug_struct("DictionaryOwned")
-                        .field("length", &dict.len())
-                        .finish()
-                } else {
-                    f.debug_tuple("DictionaryOwned").field(dict).finish()
-                }
-            }
             (&BencodedValue::None,) => f.debug_tuple("None").finish(),
+            (&BencodedValue::Raw(ref raw),) => {
+                f.debug_struct("Raw").field("length", &raw.len()).finish()
+            }
         }
     }
 }
@@ -262,13 +295,150 @@ impl<'a> Default for BencodedValue<'a> {
 }
 
 impl<'a> BencodedValue<'a> {
-    /// Checks if this is an owned value (string, binary, dictionary)
+    /// Returns the length of the value: the number of bytes for strings,
+    /// binaries and raw values, the number of elements for lists and
+    /// dictionaries and zero for integers and none
+    pub fn len(&self) -> usize {
+        match self {
+            BencodedValue::Binary(bin) | BencodedValue::Raw(bin) => bin.len(),
+            BencodedValue::String(str) => str.len(),
+            BencodedValue::List(list) => list.len(),
+            BencodedValue::Dictionary(dict) => dict.len(),
+            BencodedValue::Integer(_)
+            | BencodedValue::UnsignedInteger(_)
+            | BencodedValue::None => 0,
+        }
+    }
+
+    /// Checks if the value has a length of zero, see [`BencodedValue::len`]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the name of the kind of value, as reported by errors
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BencodedValue::Binary(_) => "binary",
+            BencodedValue::String(_) => "string",
+            BencodedValue::Integer(_) | BencodedValue::UnsignedInteger(_) => {
+                "integer"
+            }
+            BencodedValue::List(_) => "list",
+            BencodedValue::Dictionary(_) => "dictionary",
+            BencodedValue::Raw(_) => "raw",
+            BencodedValue::None => "none",
+        }
+    }
+
+    /// Checks if this is an owned value (string, binary, dictionary with only
+    /// owned keys), i.e. a value which does not borrow from the input
     pub fn is_owned(&self) -> bool {
         match self {
-            BencodedValue::BinaryOwned(_)
-            | BencodedValue::StringOwned(_)
-            | BencodedValue::DictionaryOwned(_)
+            BencodedValue::Binary(Cow::Owned(_))
+            | BencodedValue::String(Cow::Owned(_))
+            | BencodedValue::Raw(Cow::Owned(_))
             | BencodedValue::None => true,
+            BencodedValue::Dictionary(dict) => dict.keys().all(Key::is_owned),
+            _ => false,
+        }
+    }
+
+    /// Converts the value into one that does not borrow from the input,
+    /// copying every borrowed string, binary and key
+    pub fn into_owned(self) -> BencodedValue<'static> {
+        match self {
+            BencodedValue::Binary(bin) => {
+                BencodedValue::Binary(Cow::Owned(bin.into_owned()))
+            }
+            BencodedValue::String(str) => {
+                BencodedValue::String(Cow::Owned(str.into_owned()))
+            }
+            BencodedValue::Integer(int) => BencodedValue::Integer(int),
+            BencodedValue::UnsignedInteger(int) => {
+                BencodedValue::UnsignedInteger(int)
+            }
+            BencodedValue::List(list) => BencodedValue::List(
+                list.into_iter().map(BencodedValue::into_owned).collect(),
+            ),
+            BencodedValue::Dictionary(dict) => BencodedValue::Dictionary(
+                dict.into_iter()
+                    .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                    .collect(),
+            ),
+            BencodedValue::Raw(raw) => {
+                BencodedValue::Raw(Cow::Owned(raw.into_owned()))
+            }
+            BencodedValue::None => BencodedValue::None,
+        }
+    }
+
+    /// Creates a value borrowing every string, binary and key from this one,
+    /// only the lists and dictionaries are allocated
+    pub fn to_borrowed(&self) -> BencodedValue<'_> {
+        match self {
+            BencodedValue::Binary(bin) => {
+                BencodedValue::Binary(Cow::Borrowed(bin))
+            }
+            BencodedValue::String(str) => {
+                BencodedValue::String(Cow::Borrowed(str))
+            }
+            BencodedValue::Integer(int) => BencodedValue::Integer(*int),
+            BencodedValue::UnsignedInteger(int) => {
+                BencodedValue::UnsignedInteger(*int)
+            }
+            BencodedValue::List(list) => BencodedValue::List(
+                list.iter().map(BencodedValue::to_borrowed).collect(),
+            ),
+            BencodedValue::Dictionary(dict) => BencodedValue::Dictionary(
+                dict.iter()
+                    .map(|(key, value)| {
+                        (Key::borrowed(key), value.to_borrowed())
+                    })
+                    .collect(),
+            ),
+            BencodedValue::Raw(raw) => BencodedValue::Raw(Cow::Borrowed(raw)),
+            BencodedValue::None => BencodedValue::None,
+        }
+    }
+
+    /// Approximates the memory used by the value in bytes: the size of every
+    /// node plus the capacity of owned strings, binaries and lists. Borrowed
+    /// data is not counted and dictionaries are approximated by their length
+    /// times the size of an entry, ignoring empty buckets.
+    pub fn deep_size(&self) -> usize {
+        size_of::<Self>() + self.heap_size()
+    }
+
+    /// Owned heap memory of the value, see [`BencodedValue::deep_size`]
+    fn heap_size(&self) -> usize {
+        match self {
+            BencodedValue::Binary(Cow::Owned(bin))
+            | BencodedValue::Raw(Cow::Owned(bin)) => bin.capacity(),
+            BencodedValue::String(Cow::Owned(str)) => str.capacity(),
+            BencodedValue::List(list) => {
+                list.capacity() * size_of::<Self>()
+                    + list.iter().map(Self::heap_size).sum::<usize>()
+            }
+            BencodedValue::Dictionary(dict) => dict
+                .iter()
+                .map(|(key, value)| {
+                    let key_size = if key.is_owned() && !key.is_inline() {
+                        key.len()
+                    } else {
+                        0
+                    };
+
+                    size_of::<(Key, Self)>() + key_size + value.heap_size()
+                })
+                .sum(),
+            _ => 0,
+        }
+    }
+
+    /// Checks if the value is pre-encoded bencode
+    pub fn is_raw(&self) -> bool {
+        match self {
+            BencodedValue::Raw(_) => true,
             _ => false,
         }
     }
@@ -276,7 +446,7 @@ impl<'a> BencodedValue<'a> {
     /// Checks if the value is a binary (owned or not)
     pub fn is_bin(&self) -> bool {
         match self {
-            BencodedValue::Binary(_) | BencodedValue::BinaryOwned(_) => true,
+            BencodedValue::Binary(_) => true,
             _ => false,
         }
     }
@@ -284,8 +454,7 @@ impl<'a> BencodedValue<'a> {
     /// Assumes self to be a binary (owned or not), consumes it and output its owned content
     pub fn unwrap_bin(self) -> Vec<u8> {
         match self {
-            BencodedValue::Binary(v) => v.to_vec(),
-            BencodedValue::BinaryOwned(v) => v,
+            BencodedValue::Binary(v) => v.into_owned(),
             _ => panic!("not a bin"),
         }
     }
@@ -293,19 +462,55 @@ impl<'a> BencodedValue<'a> {
     /// Checks if the value is a string (owned or not)
     pub fn is_string(&self) -> bool {
         match self {
-            BencodedValue::String(_) | BencodedValue::StringOwned(_) => true,
+            BencodedValue::String(_) => true,
             _ => false,
         }
     }
 
+    /// Returns the text of a string, or of a binary if it is valid UTF-8
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            BencodedValue::String(str) => Some(str),
+            BencodedValue::Binary(bin) => core::str::from_utf8(bin).ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the bytes of a string or binary
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            BencodedValue::String(str) => Some(str.as_bytes()),
+            BencodedValue::Binary(bin) => Some(bin),
+            _ => None,
+        }
+    }
+
     /// Checks if the value is an int
     pub fn is_int(&self) -> bool {
         match self {
-            BencodedValue::Integer(_) => true,
+            BencodedValue::Integer(_) | BencodedValue::UnsignedInteger(_) => {
+                true
+            }
             _ => false,
         }
     }
 
+    /// Returns the value of an int if it is positive, none if it is negative
+    /// or not an int
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            BencodedValue::Integer(int) => u64::try_from(*int).ok(),
+            BencodedValue::UnsignedInteger(int) => Some(*int),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of an int if it is positive and fits in a usize,
+    /// none otherwise
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_u64().and_then(|int| usize::try_from(int).ok())
+    }
+
     /// Checks if the value is a list
     pub fn is_list(&self) -> bool {
         match self {
@@ -325,20 +530,15 @@ impl<'a> BencodedValue<'a> {
     /// Checks if the value is a dictionary (owned or not)
     pub fn is_dict(&self) -> bool {
         match self {
-            BencodedValue::Dictionary(_)
-            | BencodedValue::DictionaryOwned(_) => true,
+            BencodedValue::Dictionary(_) => true,
             _ => false,
         }
     }
 
-    /// Assumes self to be a dictionary, consumes it and output its owned content
-    pub fn unwrap_dict(self) -> HashMap<String, BencodedValue<'a>> {
+    /// Assumes self to be a dictionary, consumes it and output its content
+    pub fn unwrap_dict(self) -> HashMap<Key<'a>, BencodedValue<'a>> {
         match self {
-            BencodedValue::Dictionary(dict) => {
-                dict.into_iter().map(|(k, v)| (k.to_owned(), v)).collect()
-            }
-
-            BencodedValue::DictionaryOwned(dict) => dict,
+            BencodedValue::Dictionary(dict) => dict,
             _ => panic!("not a dictionary"),
         }
     }
@@ -351,6 +551,135 @@ impl<'a> BencodedValue<'a> {
             _ => false,
         }
     }
+
+    /// Appends a value at the end of a list, fails if self is not a list
+    pub fn push(
+        &mut self,
+        value: impl Into<BencodedValue<'a>>,
+    ) -> Result<(), Error> {
+        match self {
+            BencodedValue::List(list) => {
+                list.push(value.into());
+                Ok(())
+            }
+            v => Err(Error::unexpected("list", v)),
+        }
+    }
+
+    /// Inserts a value in a dictionary, returning the value previously stored
+    /// under that key. Fails if self is not a dictionary.
+    pub fn insert(
+        &mut self,
+        key: impl Into<Key<'a>>,
+        value: impl Into<BencodedValue<'a>>,
+    ) -> Result<Option<BencodedValue<'a>>, Error> {
+        match self {
+            BencodedValue::Dictionary(dict) => {
+                Ok(dict.insert(key.into(), value.into()))
+            }
+            v => Err(Error::unexpected("dictionary", v)),
+        }
+    }
+
+    /// Removes a key from a dictionary, returning its value. If self is not a
+    /// dictionary this is a no-op returning `None`.
+    pub fn remove(&mut self, key: &str) -> Option<BencodedValue<'a>> {
+        match self {
+            BencodedValue::Dictionary(dict) => dict.remove(key),
+            _ => None,
+        }
+    }
+
+    /// Keeps only the entries of a dictionary for which `f` returns true. If
+    /// self is not a dictionary this is a no-op.
+    pub fn retain_keys(
+        &mut self,
+        mut f: impl FnMut(&str, &BencodedValue<'a>) -> bool,
+    ) {
+        if let BencodedValue::Dictionary(dict) = self {
+            dict.retain(|key, value| f(key, value));
+        }
+    }
+
+    /// Keeps only the elements of a list for which `f` returns true. If self
+    /// is not a list this is a no-op.
+    pub fn retain_items(&mut self, f: impl FnMut(&BencodedValue<'a>) -> bool) {
+        if let BencodedValue::List(list) = self {
+            list.retain(f);
+        }
+    }
+
+    /// Keeps only the entries for which `f` returns true in every dictionary
+    /// of the tree, including dictionaries nested in lists. Entries are
+    /// filtered before their values are visited.
+    pub fn retain_recursive(
+        &mut self,
+        mut f: impl FnMut(&str, &BencodedValue<'a>) -> bool,
+    ) {
+        self.retain_recursive_dyn(&mut f)
+    }
+
+    fn retain_recursive_dyn(
+        &mut self,
+        f: &mut dyn FnMut(&str, &BencodedValue<'a>) -> bool,
+    ) {
+        match self {
+            BencodedValue::Dictionary(dict) => {
+                dict.retain(|key, value| f(key, value));
+                for value in dict.values_mut() {
+                    value.retain_recursive_dyn(f);
+                }
+            }
+            BencodedValue::List(list) => {
+                for value in list.iter_mut() {
+                    value.retain_recursive_dyn(f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'a> From<i64> for BencodedValue<'a> {
+    fn from(value: i64) -> Self {
+        BencodedValue::Integer(value)
+    }
+}
+
+impl<'a> From<&'a str> for BencodedValue<'a> {
+    fn from(value: &'a str) -> Self {
+        BencodedValue::String(Cow::Borrowed(value))
+    }
+}
+
+impl<'a> From<String> for BencodedValue<'a> {
+    fn from(value: String) -> Self {
+        BencodedValue::String(Cow::Owned(value))
+    }
+}
+
+impl<'a> From<&'a [u8]> for BencodedValue<'a> {
+    fn from(value: &'a [u8]) -> Self {
+        BencodedValue::Binary(Cow::Borrowed(value))
+    }
+}
+
+impl<'a> From<Vec<u8>> for BencodedValue<'a> {
+    fn from(value: Vec<u8>) -> Self {
+        BencodedValue::Binary(Cow::Owned(value))
+    }
+}
+
+impl<'a> From<Vec<BencodedValue<'a>>> for BencodedValue<'a> {
+    fn from(value: Vec<BencodedValue<'a>>) -> Self {
+        BencodedValue::List(value)
+    }
+}
+
+impl<'a> From<HashMap<Key<'a>, BencodedValue<'a>>> for BencodedValue<'a> {
+    fn from(value: HashMap<Key<'a>, BencodedValue<'a>>) -> Self {
+        BencodedValue::Dictionary(value)
+    }
 }
 
 impl<'de> Deserialize<'de> for BencodedValue<'de> {
@@ -369,10 +698,9 @@ impl<'a> Serialize for BencodedValue<'a> {
     {
         match self {
             BencodedValue::Binary(bin) => serializer.serialize_bytes(bin),
-            BencodedValue::BinaryOwned(bin) => serializer.serialize_bytes(bin),
             BencodedValue::String(str) => serializer.serialize_str(str),
-            BencodedValue::StringOwned(str) => serializer.serialize_str(str),
             BencodedValue::Integer(i) => serializer.serialize_i64(*i),
+            BencodedValue::UnsignedInteger(i) => serializer.serialize_u64(*i),
             BencodedValue::List(list) => {
                 let mut seq = serializer.serialize_seq(Some(list.len()))?;
                 for e in list.iter() {
@@ -389,15 +717,14 @@ impl<'a> Serialize for BencodedValue<'a> {
 
                 seq.end()
             }
-            BencodedValue::DictionaryOwned(dict) => {
-                let mut seq = serializer.serialize_map(Some(dict.len()))?;
-                for (k, v) in dict.iter() {
-                    seq.serialize_entry(k, v)?;
-                }
-
-                seq.end()
-            }
             BencodedValue::None => serializer.serialize_none(),
+            BencodedValue::Raw(raw) => match parser::parse_exact(raw) {
+                Ok(parsed) => parsed.serialize(serializer),
+                Err(e) => Err(serde::ser::Error::custom(format!(
+                    "invalid raw value: {}",
+                    e
+                ))),
+            },
         }
     }
 }
@@ -407,10 +734,7 @@ struct BencodedValueVisitor;
 impl<'de> Visitor<'de> for BencodedValueVisitor {
     type Value = BencodedValue<'de>;
 
-    fn expecting(
-        &self,
-        formatter: &mut std::fmt::Formatter,
-    ) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         formatter.write_str("expected string|bytes|int|list|map")
     }
 
@@ -421,11 +745,21 @@ impl<'de> Visitor<'de> for BencodedValueVisitor {
         Ok(BencodedValue::Integer(value))
     }
 
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(match i64::try_from(value) {
+            Ok(value) => BencodedValue::Integer(value),
+            Err(_) => BencodedValue::UnsignedInteger(value),
+        })
+    }
+
     fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
     where
         E: serde::de::Error,
     {
-        Ok(BencodedValue::String(v))
+        Ok(BencodedValue::String(Cow::Borrowed(v)))
     }
 
     fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
@@ -439,7 +773,7 @@ impl<'de> Visitor<'de> for BencodedValueVisitor {
     where
         E: serde::de::Error,
     {
-        Ok(BencodedValue::StringOwned(v))
+        Ok(BencodedValue::String(Cow::Owned(v)))
     }
 
     fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
@@ -454,7 +788,7 @@ impl<'de> Visitor<'de> for BencodedValueVisitor {
     where
         E: serde::de::Error,
     {
-        Ok(BencodedValue::Binary(v))
+        Ok(BencodedValue::Binary(Cow::Borrowed(v)))
     }
 
     #[inline]
@@ -462,7 +796,7 @@ impl<'de> Visitor<'de> for BencodedValueVisitor {
     where
         E: serde::de::Error,
     {
-        Ok(BencodedValue::BinaryOwned(v))
+        Ok(BencodedValue::Binary(Cow::Owned(v)))
     }
 
     fn visit_none<E>(self) -> Result<Self::Value, E>
@@ -485,11 +819,7 @@ impl<'de> Visitor<'de> for BencodedValueVisitor {
     {
         let mut seq = seq;
 
-        let mut out = if let Some(size) = seq.size_hint() {
-            Vec::with_capacity(size)
-        } else {
-            Vec::new()
-        };
+        let mut out = Vec::with_capacity(cautious_capacity(seq.size_hint()));
 
         while let Some(v) = seq.next_element()? {
             out.push(v);
@@ -504,11 +834,8 @@ impl<'de> Visitor<'de> for BencodedValueVisitor {
     {
         let mut map = map;
 
-        let mut out = if let Some(size) = map.size_hint() {
-            HashMap::with_capacity(size)
-        } else {
-            HashMap::new()
-        };
+        let mut out =
+            HashMap::with_capacity(cautious_capacity(map.size_hint()));
 
         while let Some((k, v)) = map.next_entry()? {
             out.insert(k, v);
@@ -520,7 +847,12 @@ impl<'de> Visitor<'de> for BencodedValueVisitor {
 
 #[cfg(test)]
 mod test_value {
-    use crate::{from_bytes, to_bytes, BencodedValue};
+    use crate::{
+        from_bytes, key::INLINE_CAPACITY, parse_all, to_bytes, to_value,
+        BencodedValue, Key,
+    };
+    use maplit::hashmap;
+    use std::{collections::HashMap, convert::TryFrom, mem::size_of};
 
     #[test]
     pub fn test_deser() {
@@ -528,20 +860,272 @@ mod test_value {
         assert_eq!(from_bytes(bytes), Ok(BencodedValue::Integer(3)));
 
         let bytes = b"3:abc";
-        assert_eq!(from_bytes(bytes), Ok(BencodedValue::String("abc")));
+        assert_eq!(from_bytes(bytes), Ok(BencodedValue::from("abc")));
 
         // A bunch of values is decoded as a tupple (the opposite is not true)
         let bytes = b"3:abci64e";
         assert_eq!(
             from_bytes(bytes),
-            Ok((BencodedValue::String("abc"), BencodedValue::Integer(64)))
+            Ok((BencodedValue::from("abc"), BencodedValue::Integer(64)))
         );
 
         // However a list can also be decoded as a tupple!
         let bytes = b"l3:abci64ee";
         assert_eq!(
             from_bytes(bytes),
-            Ok((BencodedValue::String("abc"), BencodedValue::Integer(64)))
+            Ok((BencodedValue::from("abc"), BencodedValue::Integer(64)))
+        );
+    }
+
+    #[test]
+    pub fn test_dict_eq() {
+        let borrowed = BencodedValue::Dictionary(hashmap! {
+            Key::borrowed("a") => BencodedValue::Integer(1),
+            Key::borrowed("b") => BencodedValue::Integer(2),
+        });
+
+        let owned = BencodedValue::Dictionary(hashmap! {
+            Key::new("a") => BencodedValue::Integer(1),
+            Key::new("b") => BencodedValue::Integer(2),
+        });
+
+        let owned_superset = BencodedValue::Dictionary(hashmap! {
+            Key::new("a") => BencodedValue::Integer(1),
+            Key::new("b") => BencodedValue::Integer(2),
+            Key::new("c") => BencodedValue::Integer(3),
+        });
+
+        let borrowed_superset = BencodedValue::Dictionary(hashmap! {
+            Key::borrowed("a") => BencodedValue::Integer(1),
+            Key::borrowed("b") => BencodedValue::Integer(2),
+            Key::borrowed("c") => BencodedValue::Integer(3),
+        });
+
+        assert!(!borrowed.is_owned());
+        assert!(owned.is_owned());
+
+        assert_eq!(borrowed, owned);
+        assert_eq!(owned, borrowed);
+        assert_eq!(borrowed.len(), owned.len());
+
+        // A strict subset is not equal, whichever side it is on
+        assert_ne!(borrowed, owned_superset);
+        assert_ne!(owned_superset, borrowed);
+        assert_ne!(owned, borrowed_superset);
+        assert_ne!(borrowed_superset, owned);
+
+        assert_eq!(borrowed_superset, owned_superset);
+        assert_eq!(owned_superset, borrowed_superset);
+    }
+
+    #[test]
+    pub fn test_string_eq() {
+        let borrowed = BencodedValue::from("abc");
+        let owned = BencodedValue::from("abc".to_owned());
+        let binary = BencodedValue::from(b"abc" as &[u8]);
+
+        assert!(!borrowed.is_owned());
+        assert!(owned.is_owned());
+
+        assert_eq!(borrowed, owned);
+        assert_eq!(owned, borrowed);
+        assert_eq!(binary, owned);
+        assert_eq!(owned, binary);
+        assert_ne!(binary, BencodedValue::from(b"abcd".to_vec()));
+    }
+
+    #[test]
+    pub fn test_mutation() {
+        let mut list = BencodedValue::List(vec![]);
+        assert!(list.push(3).is_ok());
+        assert!(list.push("abc").is_ok());
+        assert_eq!(
+            list,
+            BencodedValue::List(vec![
+                BencodedValue::Integer(3),
+                BencodedValue::from("abc")
+            ])
+        );
+
+        assert!(list.insert("key", 3).is_err());
+        assert_eq!(list.remove("key"), None);
+
+        let mut int = BencodedValue::Integer(3);
+        assert!(int.push(3).is_err());
+        assert_eq!(int, BencodedValue::Integer(3));
+
+        let mut dict = BencodedValue::Dictionary(hashmap! {
+            "a".into() => BencodedValue::Integer(1),
+            "b".into() => BencodedValue::Integer(2),
+        });
+
+        assert!(dict.push(3).is_err());
+
+        // Owned and borrowed keys live in the same dictionary
+        assert_eq!(dict.insert("c".to_owned(), 3), Ok(None));
+        assert_eq!(
+            dict,
+            BencodedValue::Dictionary(hashmap! {
+                Key::new("a") => BencodedValue::Integer(1),
+                Key::new("b") => BencodedValue::Integer(2),
+                Key::new("c") => BencodedValue::Integer(3),
+            })
+        );
+        assert!(!dict.is_owned());
+
+        assert_eq!(
+            dict.insert("a", "one".to_owned()),
+            Ok(Some(BencodedValue::Integer(1)))
+        );
+        assert_eq!(dict.remove("b"), Some(BencodedValue::Integer(2)));
+        assert_eq!(dict.remove("b"), None);
+        assert_eq!(
+            dict,
+            BencodedValue::Dictionary(hashmap! {
+                "a".into() => BencodedValue::from("one".to_owned()),
+                "c".into() => BencodedValue::Integer(3),
+            })
+        );
+
+        // An empty dictionary does not borrow anything
+        let mut dict = BencodedValue::Dictionary(hashmap! {
+            "a".into() => BencodedValue::Integer(1),
+        });
+        assert!(!dict.is_owned());
+        assert_eq!(dict.remove("a"), Some(BencodedValue::Integer(1)));
+        assert!(dict.is_empty());
+        assert!(dict.is_owned());
+    }
+
+    #[test]
+    pub fn test_deep_size() {
+        let node = size_of::<BencodedValue>();
+        let entry = size_of::<(Key, BencodedValue)>();
+
+        assert_eq!(BencodedValue::Integer(3).deep_size(), node);
+        assert_eq!(BencodedValue::from("abc").deep_size(), node);
+        assert_eq!(BencodedValue::from("abc".to_owned()).deep_size(), node + 3);
+
+        let (_, parsed) = parse_all(b"d1:a3:abc1:bi1ee").unwrap();
+        assert_eq!(parsed.deep_size(), node + 2 * entry);
+
+        let mut list = BencodedValue::List(Vec::with_capacity(4));
+        let empty = list.deep_size();
+        for _ in 0..4 {
+            list.push(0).unwrap();
+        }
+        assert_eq!(list.deep_size(), empty);
+
+        list.push("abc".to_owned()).unwrap();
+        assert!(list.deep_size() > empty + node);
+
+        let long = "a".repeat(INLINE_CAPACITY + 1);
+        let mut dict = BencodedValue::from(HashMap::new());
+        dict.insert(Key::new("name"), 0).unwrap();
+        assert_eq!(dict.deep_size(), node + entry);
+        dict.insert(Key::new(&long), 0).unwrap();
+        assert_eq!(dict.deep_size(), node + 2 * entry + long.len());
+    }
+
+    #[test]
+    pub fn test_to_borrowed() {
+        let metainfo = BencodedValue::from(hashmap! {
+            "info".into() => BencodedValue::from(hashmap! {
+                "name".into() => BencodedValue::from("bbb.mp4".to_owned()),
+                "pieces".into() => BencodedValue::from(vec![0xAB; 60]),
+            }),
+        });
+        let pieces = |value: &BencodedValue| match value {
+            BencodedValue::Dictionary(dict) => match &dict["info"] {
+                BencodedValue::Dictionary(info) => {
+                    info["pieces"].as_bytes().unwrap().as_ptr()
+                }
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        };
+
+        let borrowed = metainfo.to_borrowed();
+        assert_eq!(borrowed, metainfo);
+        assert!(!borrowed.is_owned());
+        assert_eq!(pieces(&borrowed), pieces(&metainfo));
+
+        // Serde only hands out short-lived references, `to_value` copies
+        let serialized = to_value(&metainfo).unwrap();
+        assert_eq!(serialized, metainfo);
+        assert_ne!(pieces(&serialized), pieces(&metainfo));
+    }
+
+    #[test]
+    pub fn test_unsigned() {
+        assert_eq!(BencodedValue::Integer(0).as_u64(), Some(0));
+        assert_eq!(BencodedValue::Integer(-5).as_u64(), None);
+        assert_eq!(BencodedValue::Integer(-5).as_usize(), None);
+        assert_eq!(
+            BencodedValue::Integer(i64::MAX).as_u64(),
+            Some(i64::MAX as u64)
+        );
+        assert_eq!(
+            BencodedValue::Integer(i64::MAX).as_usize(),
+            usize::try_from(i64::MAX).ok()
+        );
+        assert_eq!(BencodedValue::from("5").as_u64(), None);
+        assert_eq!(BencodedValue::from("5").as_usize(), None);
+    }
+
+    #[test]
+    pub fn test_retain() {
+        let bytes = b"d8:announce3:url4:infod5:filesld6:lengthi1e6:md5sum\
+                      1:ae\
+                      d6:lengthi2e6:md5sum1:bee4:name4:test6:md5sum1:cee";
+        let (_, mut value) = parse_all(bytes).unwrap();
+
+        value.retain_recursive(|key, _| key != "md5sum");
+        assert!(value.clone().remove("md5sum").is_none());
+
+        let info = match &value {
+            BencodedValue::Dictionary(dict) => &dict["info"],
+            _ => unreachable!(),
+        };
+        assert_eq!(
+            *info,
+            BencodedValue::from(hashmap! {
+                "files".into() => BencodedValue::from(vec![
+                    BencodedValue::from(hashmap! {
+                        "length".into() => BencodedValue::Integer(1),
+                    }),
+                    BencodedValue::from(hashmap! {
+                        "length".into() => BencodedValue::Integer(2),
+                    }),
+                ]),
+                "name".into() => BencodedValue::from("test"),
+            })
+        );
+
+        let mut top = value.clone();
+        top.retain_keys(|_, value| !value.is_dict());
+        assert_eq!(
+            top,
+            BencodedValue::from(hashmap! {
+                "announce".into() => BencodedValue::from("url"),
+            })
+        );
+
+        let bytes = value.to_vec().unwrap();
+        assert_eq!(parse_all(&bytes).unwrap().1, value);
+
+        let mut list = BencodedValue::from(vec![
+            BencodedValue::Integer(1),
+            BencodedValue::from("a"),
+            BencodedValue::Integer(2),
+        ]);
+        list.retain_items(BencodedValue::is_int);
+        assert_eq!(
+            list,
+            BencodedValue::from(vec![
+                BencodedValue::Integer(1),
+                BencodedValue::Integer(2),
+            ])
         );
     }
 
@@ -551,16 +1135,13 @@ mod test_value {
         assert_eq!(to_bytes(&BencodedValue::Integer(3)).unwrap(), bytes);
 
         let bytes = b"3:abc";
-        assert_eq!(to_bytes(&BencodedValue::String("abc")).unwrap(), bytes);
+        assert_eq!(to_bytes(&BencodedValue::from("abc")).unwrap(), bytes);
 
         // A tupple is encoded as a list (the opposite is not nescessarily true)
         let bytes = b"l3:abci64ee";
         assert_eq!(
-            to_bytes(&(
-                BencodedValue::String("abc"),
-                BencodedValue::Integer(64)
-            ))
-            .unwrap(),
+            to_bytes(&(BencodedValue::from("abc"), BencodedValue::Integer(64)))
+                .unwrap(),
             bytes
         );
     }
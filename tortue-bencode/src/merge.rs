@@ -0,0 +1,178 @@
+//! Deep merging of bencoded dictionaries
+
+use crate::{error::Error, BencodedValue};
+
+/// How lists present on both sides of a merge are combined
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// The list of the merged value replaces the existing one
+    Replace,
+
+    /// The list of the merged value is appended to the existing one
+    Concatenate,
+}
+
+impl<'a> BencodedValue<'a> {
+    /// Recursively merges `other` into this dictionary. Keys from `other`
+    /// win, nested dictionaries are merged recursively, lists are combined
+    /// according to `strategy` and any other value is replaced.
+    ///
+    /// Fails if either value is not a dictionary, in which case self is left
+    /// untouched.
+    pub fn merge(
+        &mut self,
+        other: BencodedValue<'a>,
+        strategy: MergeStrategy,
+    ) -> Result<(), Error> {
+        match (self, other) {
+            (
+                BencodedValue::Dictionary(dict),
+                BencodedValue::Dictionary(other),
+            ) => {
+                for (key, value) in other {
+                    match dict.get_mut(key.as_str()) {
+                        Some(existing) => {
+                            merge_value(existing, value, strategy)
+                        }
+                        None => {
+                            dict.insert(key, value);
+                        }
+                    }
+                }
+
+                Ok(())
+            }
+            (BencodedValue::Dictionary(_), other) => {
+                Err(Error::unexpected("dictionary", &other))
+            }
+            (this, _) => Err(Error::unexpected("dictionary", this)),
+        }
+    }
+}
+
+fn merge_value<'a>(
+    existing: &mut BencodedValue<'a>,
+    value: BencodedValue<'a>,
+    strategy: MergeStrategy,
+) {
+    match (&mut *existing, value) {
+        (existing, value) if existing.is_dict() && value.is_dict() => {
+            existing
+                .merge(value, strategy)
+                .expect("merging two dictionaries cannot fail");
+        }
+        (BencodedValue::List(list), BencodedValue::List(other))
+            if strategy == MergeStrategy::Concatenate =>
+        {
+            list.extend(other);
+        }
+        (_, value) => *existing = value,
+    }
+}
+
+#[cfg(test)]
+mod merge_tests {
+    use super::MergeStrategy;
+    use crate::{BencodedValue, Key};
+    use maplit::hashmap;
+
+    fn template() -> BencodedValue<'static> {
+        BencodedValue::Dictionary(hashmap! {
+            "port".into() => BencodedValue::Integer(6881),
+            "event".into() => BencodedValue::from("started"),
+            "trackers".into() => BencodedValue::List(vec![
+                BencodedValue::from("a"),
+            ]),
+            "nested".into() => BencodedValue::Dictionary(hashmap! {
+                "kept".into() => BencodedValue::Integer(1),
+                "overriden".into() => BencodedValue::Integer(2),
+            }),
+        })
+    }
+
+    #[test]
+    fn test_nested_override() {
+        let mut value = template();
+
+        let overrides = BencodedValue::Dictionary(hashmap! {
+            Key::new("port") => BencodedValue::Integer(51413),
+            Key::new("nested") => BencodedValue::Dictionary(hashmap! {
+                Key::new("overriden") => BencodedValue::Integer(3),
+                Key::new("added") => BencodedValue::Integer(4),
+            }),
+        });
+
+        assert!(value.merge(overrides, MergeStrategy::Replace).is_ok());
+
+        assert_eq!(
+            value,
+            BencodedValue::Dictionary(hashmap! {
+                "port".into() => BencodedValue::Integer(51413),
+                "event".into() => BencodedValue::from("started"),
+                "trackers".into() => BencodedValue::List(vec![
+                    BencodedValue::from("a"),
+                ]),
+                "nested".into() => BencodedValue::Dictionary(hashmap! {
+                    "kept".into() => BencodedValue::Integer(1),
+                    "overriden".into() => BencodedValue::Integer(3),
+                    "added".into() => BencodedValue::Integer(4),
+                }),
+            })
+        );
+    }
+
+    #[test]
+    fn test_list_strategy() {
+        let overrides = BencodedValue::Dictionary(hashmap! {
+            "trackers".into() => BencodedValue::List(vec![
+                BencodedValue::from("b"),
+            ]),
+        });
+
+        let mut value = template();
+        assert!(value
+            .merge(overrides.clone(), MergeStrategy::Replace)
+            .is_ok());
+        assert_eq!(
+            value.remove("trackers"),
+            Some(BencodedValue::List(vec![BencodedValue::from("b")]))
+        );
+
+        let mut value = template();
+        assert!(value.merge(overrides, MergeStrategy::Concatenate).is_ok());
+        assert_eq!(
+            value.remove("trackers"),
+            Some(BencodedValue::List(vec![
+                BencodedValue::from("a"),
+                BencodedValue::from("b")
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_scalar_replaces() {
+        let mut value = template();
+
+        let overrides = BencodedValue::Dictionary(hashmap! {
+            "nested".into() => BencodedValue::Integer(0),
+            "event".into() => BencodedValue::List(vec![]),
+        });
+
+        assert!(value.merge(overrides, MergeStrategy::Concatenate).is_ok());
+        assert_eq!(value.remove("nested"), Some(BencodedValue::Integer(0)));
+        assert_eq!(value.remove("event"), Some(BencodedValue::List(vec![])));
+    }
+
+    #[test]
+    fn test_non_dict() {
+        let mut value = BencodedValue::List(vec![]);
+        assert!(value.merge(template(), MergeStrategy::Replace).is_err());
+        assert_eq!(value, BencodedValue::List(vec![]));
+
+        let mut value = template();
+        assert!(value
+            .merge(BencodedValue::Integer(3), MergeStrategy::Replace)
+            .is_err());
+        assert_eq!(value, template());
+    }
+}
@@ -3,11 +3,25 @@
 
 mod bytes;
 mod dictionary;
+#[cfg(feature = "std")]
+mod file;
 mod int;
+mod lazy;
 mod list;
+mod options;
+mod owned;
+#[cfg(feature = "std")]
+mod reader;
 mod string;
+mod tokenizer;
 
-pub use self::{bytes::*, dictionary::*, int::*, list::*, string::*};
+pub use self::{
+    bytes::*, dictionary::*, int::*, lazy::*, list::*, options::*, owned::*,
+    string::*, tokenizer::*,
+};
+
+#[cfg(feature = "std")]
+pub use self::{file::*, reader::*};
 
 use nom::{
     branch::alt,
@@ -16,33 +30,204 @@ use nom::{
 };
 
 pub use crate::BencodedValue;
+use crate::{
+    error::{Error, ParseError},
+    prelude::*,
+    Key,
+};
 
 /// Parses an input string and returns a BencodedValue
 #[inline]
 pub fn parse<'a>(input: &'a [u8]) -> IResult<&'a [u8], BencodedValue<'a>> {
+    parse_with_options(input, &ParserOptions::default())
+}
+
+/// Parses an input string using the given options and returns a BencodedValue
+#[inline]
+pub fn parse_with_options<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+) -> IResult<&'a [u8], BencodedValue<'a>> {
+    value_with(input, options, &Budget::new(options))
+}
+
+/// Same as [`parse_with_options`] taking the values it builds from the budget
+/// of the parse
+pub(crate) fn value_with<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+    budget: &Budget,
+) -> IResult<&'a [u8], BencodedValue<'a>> {
+    options.check_total_len(input)?;
+
     alt((
-        map(string::parse_string, BencodedValue::String),
-        map(bytes::parse_bytes, BencodedValue::Binary),
-        map(int::parse_int, BencodedValue::Integer),
-        list::parse_list,
-        map(dictionary::parse_dictionary, BencodedValue::Dictionary),
+        |input| bytes::bytes_with(input, options, budget),
+        |input| int::parse_integer_with_options(input, options),
+        map(
+            |input| list::list_with(input, options, budget, value_with),
+            BencodedValue::List,
+        ),
+        map(
+            |input| {
+                dictionary::dictionary_with(
+                    input,
+                    options,
+                    budget,
+                    Key::borrowed,
+                    value_with,
+                )
+            },
+            BencodedValue::Dictionary,
+        ),
     ))(input)
 }
 
+/// Parses exactly one value at the start of the input and returns it along
+/// with the number of bytes consumed, trailing bytes are left untouched
+#[inline]
+pub fn parse_prefix<'a>(
+    input: &'a [u8],
+) -> Result<(BencodedValue<'a>, usize), Error> {
+    parse_prefix_with_options(input, &ParserOptions::default())
+}
+
+/// Same as [`parse_prefix`] using the given options
+pub fn parse_prefix_with_options<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+) -> Result<(BencodedValue<'a>, usize), Error> {
+    let (rest, value) = parse_with_options(input, options)
+        .map_err(|e| Error::parse(input, e))?;
+
+    Ok((value, input.len() - rest.len()))
+}
+
+/// Parses exactly one value spanning the whole input, unlike [`parse_all`]
+/// concatenated values are not grouped into a list
+pub(crate) fn parse_exact(input: &[u8]) -> Result<BencodedValue<'_>, Error> {
+    let (value, len) = parse_prefix(input)?;
+
+    if len == input.len() {
+        Ok(value)
+    } else {
+        Err(Error::TrailingData {
+            remaining: input.len() - len,
+        })
+    }
+}
+
+/// Lazily parses the successive values of the input, yielding each one along
+/// with the number of bytes it spans
+///
+/// Stops at the end of the input or after yielding the first error, errors
+/// are located relative to the start of the whole input.
+#[inline]
+pub fn parse_iter(
+    input: &[u8],
+) -> impl Iterator<Item = Result<(BencodedValue<'_>, usize), Error>> {
+    parse_iter_with_options(input, &ParserOptions::default())
+}
+
+/// Same as [`parse_iter`] using the given options
+pub fn parse_iter_with_options<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+) -> impl Iterator<Item = Result<(BencodedValue<'a>, usize), Error>> + 'a {
+    let options = *options;
+    let mut rest = input;
+    let mut failed = false;
+
+    core::iter::from_fn(move || {
+        if failed || rest.is_empty() {
+            return None;
+        }
+
+        match parse_with_options(rest, &options) {
+            Ok((next, value)) => {
+                let len = rest.len() - next.len();
+                rest = next;
+                Some(Ok((value, len)))
+            }
+            Err(e) => {
+                failed = true;
+                Some(Err(Error::parse(input, e)))
+            }
+        }
+    })
+}
+
+/// Same as [`parse`] returning a [`ParseError`] located in the input
+#[inline]
+pub fn try_parse(
+    input: &[u8],
+) -> Result<(&[u8], BencodedValue<'_>), ParseError> {
+    try_parse_with_options(input, &ParserOptions::default())
+}
+
+/// Same as [`parse_with_options`] returning a [`ParseError`] located in the
+/// input
+pub fn try_parse_with_options<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+) -> Result<(&'a [u8], BencodedValue<'a>), ParseError> {
+    parse_with_options(input, options).map_err(|e| ParseError::new(input, &e))
+}
+
+/// Same as [`parse_all`] returning a [`ParseError`] located in the input
+#[inline]
+pub fn try_parse_all(input: &[u8]) -> Result<BencodedValue<'_>, ParseError> {
+    try_parse_all_with_options(input, &ParserOptions::default())
+}
+
+/// Same as [`parse_all_with_options`] returning a [`ParseError`] located in
+/// the input
+pub fn try_parse_all_with_options<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+) -> Result<BencodedValue<'a>, ParseError> {
+    parse_all_with_options(input, options)
+        .map(|(_, value)| value)
+        .map_err(|e| ParseError::new(input, &e))
+}
+
 /// Parses an input string and returns a Vec<BencodedValue>, fails if the string is not fully consummed
 #[inline]
 pub fn parse_all<'a>(input: &'a [u8]) -> IResult<&'a [u8], BencodedValue<'a>> {
-    all_consuming(parse_all_incomplete)(input)
+    parse_all_with_options(input, &ParserOptions::default())
+}
+
+/// Same as [`parse_all`] using the given options
+#[inline]
+pub fn parse_all_with_options<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+) -> IResult<&'a [u8], BencodedValue<'a>> {
+    all_consuming(|input| parse_all_incomplete_with_options(input, options))(
+        input,
+    )
 }
 
 /// Parses an input string and returns a grouped BencodedValue, does **not** fail if the string is not fully consummed
+///
+/// Fails as incomplete if the values are followed by a truncated one.
 #[inline]
 pub fn parse_all_incomplete<'a>(
     input: &'a [u8],
 ) -> IResult<&'a [u8], BencodedValue<'a>> {
-    let mut iter = iterator(input, parse);
-    let values = iter.collect::<Vec<_>>();
-    let (rest, _) = iter.finish()?;
+    parse_all_incomplete_with_options(input, &ParserOptions::default())
+}
+
+/// Same as [`parse_all_incomplete`] using the given options
+#[inline]
+pub fn parse_all_incomplete_with_options<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+) -> IResult<&'a [u8], BencodedValue<'a>> {
+    let (rest, values) = parse_all_no_group_with_options(input, options)?;
+    if let Some(needed) = tokenizer::truncation(rest) {
+        return Err(nom::Err::Incomplete(nom::Needed::Size(needed)));
+    }
+
     if values.is_empty() {
         Ok((rest, BencodedValue::None))
     } else if values.len() == 1 {
@@ -57,18 +242,54 @@ pub fn parse_all_incomplete<'a>(
 pub fn parse_all_no_group<'a>(
     input: &'a [u8],
 ) -> IResult<&'a [u8], Vec<BencodedValue<'a>>> {
-    let mut iter = iterator(input, parse);
-    let values = iter.collect::<Vec<_>>();
+    parse_all_no_group_with_options(input, &ParserOptions::default())
+}
+
+/// Same as [`parse_all_no_group`] using the given options
+#[inline]
+pub fn parse_all_no_group_with_options<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+) -> IResult<&'a [u8], Vec<BencodedValue<'a>>> {
+    many_with(input, options, &Budget::new(options), value_with)
+}
+
+/// Parses values using `value` until it fails, does **not** fail if the
+/// string is not fully consummed
+///
+/// Every value takes its slot from the budget once it is parsed.
+pub(crate) fn many_with<'a, V, P>(
+    input: &'a [u8],
+    options: &ParserOptions,
+    budget: &Budget,
+    value: P,
+) -> IResult<&'a [u8], Vec<V>>
+where
+    P: Fn(&'a [u8], &ParserOptions, &Budget) -> IResult<&'a [u8], V>,
+{
+    let mut iter = iterator(input, |input| {
+        let (rest, value) = value(input, options, budget)?;
+        budget.charge_entry::<V>(input)?;
+        Ok((rest, value))
+    });
+    let values = iter
+        .take(options.max_items.saturating_add(1))
+        .collect::<Vec<_>>();
     let (rest, _) = iter.finish()?;
+    options.check_items(input, values.len())?;
     Ok((rest, values))
 }
 
 #[cfg(test)]
 mod parse_tests {
-    use super::{parse, parse_all, BencodedValue};
+    use super::{
+        parse, parse_all, parse_all_with_options, parse_iter, parse_prefix,
+        BencodedValue, ParserOptions, DEFAULT_MAX_DEPTH,
+    };
+    use crate::error;
     use nom::{
         error::ErrorKind,
-        Err::{Error, Incomplete},
+        Err::{Error, Failure, Incomplete},
         Needed,
     };
 
@@ -96,7 +317,7 @@ mod parse_tests {
                 b"" as _,
                 BencodedValue::List(vec![
                     BencodedValue::Integer(3),
-                    BencodedValue::String("abcd")
+                    BencodedValue::from("abcd")
                 ])
             ))
         );
@@ -107,6 +328,49 @@ mod parse_tests {
         );
     }
 
+    #[test]
+    pub fn test_prefix() {
+        assert_eq!(
+            parse_prefix(b"i3e\xFF\x00payload"),
+            Ok((BencodedValue::Integer(3), 3))
+        );
+        assert_eq!(
+            parse_prefix(b"l1:ae"),
+            Ok((BencodedValue::List(vec![BencodedValue::from("a")]), 5))
+        );
+        assert!(parse_prefix(b"").is_err());
+        assert!(parse_prefix(b"x").is_err());
+    }
+
+    #[test]
+    pub fn test_iter() {
+        let values = parse_iter(b"i1e4:spamli2ee")
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            values,
+            vec![
+                (BencodedValue::Integer(1), 3),
+                (BencodedValue::from("spam"), 6),
+                (BencodedValue::List(vec![BencodedValue::Integer(2)]), 5),
+            ]
+        );
+
+        let mut iter = parse_iter(b"d1:ai1eexi3e");
+        assert_eq!(iter.next().unwrap().unwrap().1, 8);
+        match iter.next() {
+            Some(Err(error::Error::Parse(e))) => assert_eq!(e.offset, 8),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(iter.next().is_none());
+
+        assert!(parse_iter(b"").next().is_none());
+        assert!(matches!(
+            parse_iter(b"i1e3:ab").nth(1),
+            Some(Err(error::Error::Incomplete { needed: Some(1) }))
+        ));
+    }
+
     #[test]
     pub fn test_int() {
         assert_eq!(parse(b"i3e"), Ok((b"" as _, BencodedValue::Integer(3))));
@@ -138,56 +402,131 @@ mod parse_tests {
             Ok((b"" as _, BencodedValue::Integer(-1234567890)))
         );
 
+        assert_eq!(parse(b"i3"), Err(Error((b"i3" as _, ErrorKind::Char))));
+
+        assert_eq!(parse(b"ie"), Err(Error((b"ie" as _, ErrorKind::Char))));
+    }
+
+    #[test]
+    pub fn test_string() {
+        assert_eq!(parse(b"3e"), Err(Error((b"3e" as _, ErrorKind::Char))));
+
+        assert_eq!(parse(b"3:abc"), Ok((b"" as _, BencodedValue::from("abc"))));
+
         assert_eq!(
-            parse(b"i3"),
-            Err(Error((b"i3" as _, ErrorKind::Char)))
+            parse(b"3:abcdef"),
+            Ok((b"def" as _, BencodedValue::from("abc")))
         );
 
+        assert_eq!(parse(b"0:"), Ok((b"" as _, BencodedValue::from(""))));
+
+        assert_eq!(parse(b"0:abc"), Ok((b"abc" as _, BencodedValue::from(""))));
+
+        assert_eq!(parse(b"e:"), Err(Error((b"e:" as _, ErrorKind::Char))));
+
+        // This is actually parsed by string/bytes
         assert_eq!(
-            parse(b"ie"),
-            Err(Error((b"ie" as _, ErrorKind::Char)))
+            parse(b"3abcd"),
+            Err(Error((b"3abcd" as _, ErrorKind::Char)))
         );
+
+        assert_eq!(parse(b"3:ab"), Err(Incomplete(Needed::Size(3))));
+        assert_eq!(parse(b"10:abc"), Err(Incomplete(Needed::Size(10))));
+        assert_eq!(parse_all(b"i1e10:abc"), Err(Incomplete(Needed::Size(10))));
     }
 
     #[test]
-    pub fn test_string() {
-        assert_eq!(parse(b"3e"), Err(Incomplete(Needed::Size(4))));
+    pub fn test_bytes() {
+        assert_eq!(parse(b"3e"), Err(Error((b"3e" as _, ErrorKind::Char))));
 
         assert_eq!(
-            parse(b"3:abc"),
-            Ok((b"" as _, BencodedValue::String("abc")))
+            parse(b"3:ab\xFF"),
+            Ok((b"" as _, BencodedValue::from(b"ab\xFF" as &[u8])))
         );
+    }
+
+    #[test]
+    pub fn test_strict() {
+        let strict = ParserOptions::strict();
+
+        for input in &[&b"li03ee"[..], b"d1:ai-0ee"] {
+            assert!(parse_all(input).is_ok());
+            assert_eq!(
+                parse_all_with_options(input, &ParserOptions::default()),
+                parse_all(input)
+            );
+            assert!(parse_all_with_options(input, &strict).is_err());
+        }
 
         assert_eq!(
-            parse(b"3:abcdef"),
-            Ok((b"def" as _, BencodedValue::String("abc")))
+            parse_all_with_options(b"li3ei-3ee", &strict),
+            parse_all(b"li3ei-3ee")
         );
+    }
 
-        assert_eq!(parse(b"0:"), Ok((b"" as _, BencodedValue::String(""))));
+    fn nested(depth: usize) -> Vec<u8> {
+        let mut input = b"l".repeat(depth);
+        input.extend(b"e".repeat(depth));
+        input
+    }
 
+    #[test]
+    pub fn test_depth() {
+        let input = nested(1000);
         assert_eq!(
-            parse(b"0:abc"),
-            Ok((b"abc" as _, BencodedValue::String("")))
+            parse(&input),
+            Err(Failure((&input[DEFAULT_MAX_DEPTH..], ErrorKind::TooLarge)))
         );
-
+        assert!(parse_all(&input).is_err());
+        assert!(parse_all(&nested(DEFAULT_MAX_DEPTH)).is_ok());
+
+        let input = b"d1:ad1:ad1:ai1eeee";
+        let shallow = ParserOptions {
+            max_depth: 2,
+            ..ParserOptions::default()
+        };
         assert_eq!(
-            parse(b"e:"),
-            Err(Error((b"e:" as _, ErrorKind::Char)))
+            parse_all_with_options(input, &shallow),
+            Err(Failure((&input[8..], ErrorKind::TooLarge)))
         );
 
-        // This is actually parsed by string/bytes
-        assert_eq!(parse(b"3abcd"), Err(Error((b"3abcd" as _, ErrorKind::Char))));
-
-        assert_eq!(parse(b"3:ab"), Err(Incomplete(Needed::Size(4))));
+        let deep = ParserOptions {
+            max_depth: 200,
+            ..ParserOptions::default()
+        };
+        assert!(parse_all_with_options(&nested(200), &deep).is_ok());
+        assert!(parse_all_with_options(&nested(201), &deep).is_err());
     }
 
     #[test]
-    pub fn test_bytes() {
-        assert_eq!(parse(b"3e"), Err(Incomplete(Needed::Size(4))));
+    pub fn test_messages() {
+        let error = |input: &[u8]| {
+            error::Error::parse(input, parse_all(input).unwrap_err())
+        };
+
+        let messages = [
+            (
+                &b"i12ae"[..],
+                "(missing delimiter), expected a valid integer",
+            ),
+            (b"i-e", "(invalid integer digits), expected a valid integer"),
+            (b"3x:abc", "(missing delimiter), expected a valid string"),
+            (b"di1ei2ee", "(unexpected character), expected a key or 'e'"),
+            (b"x", "(unexpected character), expected a value"),
+        ];
+        for &(input, message) in &messages {
+            let error = error(input).to_string();
+            assert!(error.contains(message), "{}", error);
+        }
 
         assert_eq!(
-            parse(b"3:ab\xFF"),
-            Ok((b"" as _, BencodedValue::Binary(b"ab\xFF" as _)))
+            format!("{:?}", error(b"i12ae")),
+            "Parse(ParseError { offset: 3, kind: Some(Tag), expected: \
+             Some(\"a valid integer\"), preview: \"ae\", preceding: \"i12\" })"
+        );
+        assert_eq!(
+            error::Error::Parser(ErrorKind::TakeWhileMN).to_string(),
+            "invalid integer digits"
         );
     }
 }
@@ -1,18 +1,71 @@
 //! Parser module for bencoding
 //! Provides functions to parse bencoded bytes
 
-use super::base10_length;
-use nom::{
-    bytes::complete::tag, multi::length_value, sequence::preceded, IResult,
-};
+use super::{base10_length, Budget, Limit, ParserOptions};
+use crate::BencodedValue;
+use alloc::borrow::Cow;
+use nom::{bytes::complete::tag, sequence::terminated, Err, IResult, Needed};
 
-/// Nom parse compinator to parse a bencoded string
+/// Nom parse compinator to parse a bencoded string, fails as incomplete if the
+/// declared length exceeds the remaining input
 #[inline]
 pub fn parse_bytes<'a>(input: &'a [u8]) -> IResult<&'a [u8], &'a [u8]> {
-    length_value(
-        base10_length,
-        preceded(tag(":"), |input| Ok((&[] as &[u8], input))),
-    )(input)
+    take_bytes(input, usize::MAX)
+}
+
+/// Same as [`parse_bytes`], fails if the declared length exceeds
+/// [`ParserOptions::max_string_len`] before reading the string
+#[inline]
+pub(crate) fn parse_bytes_limited<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+) -> IResult<&'a [u8], &'a [u8]> {
+    take_bytes(input, options.max_string_len)
+}
+
+#[inline]
+fn take_bytes(input: &[u8], max_len: usize) -> IResult<&[u8], &[u8]> {
+    let (rest, len) = terminated(base10_length, tag(":"))(input)?;
+    if len > max_len {
+        return Err(Limit::StringLength.fail(input));
+    }
+
+    if len > rest.len() {
+        return Err(Err::Incomplete(Needed::Size(len)));
+    }
+
+    let (bytes, rest) = rest.split_at(len);
+    Ok((rest, bytes))
+}
+
+/// Parses a bencoded string into a [`BencodedValue::String`] if it is valid
+/// UTF-8 and into a [`BencodedValue::Binary`] otherwise, or always into a
+/// binary if [`ParserOptions::binary_strings`] is set
+#[inline]
+pub fn parse_bytes_with_options<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+) -> IResult<&'a [u8], BencodedValue<'a>> {
+    bytes_with(input, options, &Budget::new(options))
+}
+
+/// Same as [`parse_bytes_with_options`] taking the length of the string from
+/// the budget of the parse
+pub(crate) fn bytes_with<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+    budget: &Budget,
+) -> IResult<&'a [u8], BencodedValue<'a>> {
+    let (rest, bytes) = parse_bytes_limited(input, options)?;
+    budget.charge(input, bytes.len())?;
+    let value = match core::str::from_utf8(bytes) {
+        Ok(str) if !options.binary_strings => {
+            BencodedValue::String(Cow::Borrowed(str))
+        }
+        _ => BencodedValue::Binary(Cow::Borrowed(bytes)),
+    };
+
+    Ok((rest, value))
 }
 
 #[cfg(test)]
@@ -51,6 +104,10 @@ mod string_tests {
             Err(Error((b"abcd" as &_, ErrorKind::Tag)))
         );
 
-        assert_eq!(parse_bytes(b"3:ab"), Err(Incomplete(Needed::Size(4))));
+        assert_eq!(parse_bytes(b"3:ab"), Err(Incomplete(Needed::Size(3))));
+        assert_eq!(
+            parse_bytes(b"4294967296:\xFF"),
+            Err(Incomplete(Needed::Size(u32::MAX as usize + 1)))
+        );
     }
 }
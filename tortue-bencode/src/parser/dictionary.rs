@@ -1,22 +1,143 @@
 //! Parser module for bencoding
 //! Provides functions to parse bencoded lists (arrays)
 
-use super::{parse, parse_string, BencodedValue};
+use super::{
+    many_with, parse, parse_bytes_limited, parse_string, parse_utf8_str,
+    value_with, BencodedValue, Budget, ParserOptions,
+};
+use crate::{
+    hash_map::{Entry, HashMap},
+    Key,
+};
+use core::{cmp::Ordering, hash::Hash};
 use nom::{
-    character::complete::char,
-    multi::many0,
-    sequence::{delimited, pair},
-    IResult,
+    character::complete::char, error::ErrorKind, sequence::pair, Err, IResult,
 };
-use std::collections::HashMap;
 
-/// Nom parse compinator to parse a bencoded HashMap<&str, BencodedValue>
+/// Error kind of the nom failure reported for a duplicated key when
+/// [`ParserOptions::reject_duplicate_keys`] is set, the failure points at the
+/// second occurrence of the key
+pub const DUPLICATE_KEY: ErrorKind = ErrorKind::Permutation;
+
+/// Error kind of the nom failure reported for keys out of order when
+/// [`ParserOptions::require_sorted_keys`] is set, the failure points at the
+/// first of the two offending keys
+pub const UNSORTED_KEYS: ErrorKind = ErrorKind::SeparatedList;
+
+/// Returns the key duplicated in the input of a parser error, if any
+pub fn duplicate_key<'a>(
+    error: &Err<(&'a [u8], ErrorKind)>,
+) -> Option<&'a str> {
+    match error {
+        Err::Failure((input, DUPLICATE_KEY)) => {
+            parse_string(input).ok().map(|(_, key)| key)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the two keys out of order in the input of a parser error, if any
+pub fn unsorted_keys<'a>(
+    error: &Err<(&'a [u8], ErrorKind)>,
+) -> Option<(&'a str, &'a str)> {
+    match error {
+        Err::Failure((input, UNSORTED_KEYS)) => {
+            let (rest, previous) = parse_string(input).ok()?;
+            let (rest, _) = parse(rest).ok()?;
+            let (_, key) = parse_string(rest).ok()?;
+            Some((previous, key))
+        }
+        _ => None,
+    }
+}
+
+/// Nom parse compinator to parse a bencoded HashMap<Key, BencodedValue>
 #[inline]
 pub fn parse_dictionary<'a>(
     input: &'a [u8],
-) -> IResult<&'a [u8], HashMap<&'a str, BencodedValue<'a>>> {
-    delimited(char('d'), many0(pair(parse_string, parse)), char('e'))(input)
-        .map(|(res, value)| (res, value.into_iter().collect()))
+) -> IResult<&'a [u8], HashMap<Key<'a>, BencodedValue<'a>>> {
+    parse_dictionary_with_options(input, &ParserOptions::default())
+}
+
+/// Same as [`parse_dictionary`] using the given options
+#[inline]
+pub fn parse_dictionary_with_options<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+) -> IResult<&'a [u8], HashMap<Key<'a>, BencodedValue<'a>>> {
+    let budget = Budget::new(options);
+    dictionary_with(input, options, &budget, Key::borrowed, value_with)
+}
+
+/// Parses a dictionary whose keys are converted by `key` and whose values are
+/// parsed by `value` using the options of the nested values
+pub(crate) fn dictionary_with<'a, K, V, F, P>(
+    input: &'a [u8],
+    options: &ParserOptions,
+    budget: &Budget,
+    key: F,
+    value: P,
+) -> IResult<&'a [u8], HashMap<K, V>>
+where
+    K: Eq + Hash,
+    F: Fn(&'a str) -> K,
+    P: Fn(&'a [u8], &ParserOptions, &Budget) -> IResult<&'a [u8], V>,
+{
+    let (rest, _) = char('d')(input)?;
+    let nested = options.nested(input)?;
+    let raw_key = |input| {
+        let (rest, key) = parse_bytes_limited(input, options)?;
+        budget.charge(input, key.len())?;
+        let (_, key) = parse_utf8_str(key)?;
+        Ok((rest, (input, key)))
+    };
+    let value = |input| value(input, &nested, budget);
+    let entry = pair(raw_key, value);
+
+    let (rest, entries) =
+        many_with(rest, options, budget, |input, _, _| entry(input))?;
+    let (rest, _) = char('e')(rest)?;
+
+    let mut dict = HashMap::with_capacity(entries.len());
+    let mut previous: Option<(&[u8], &str)> = None;
+    for ((position, raw), value) in entries {
+        if options.require_sorted_keys {
+            if let Some((previous_position, previous_key)) = previous {
+                // Equal keys are reported as duplicates if those are rejected
+                let unsorted = match previous_key.as_bytes().cmp(raw.as_bytes())
+                {
+                    Ordering::Less => false,
+                    Ordering::Equal => !options.reject_duplicate_keys,
+                    Ordering::Greater => true,
+                };
+
+                if unsorted {
+                    return Err(Err::Failure((
+                        previous_position,
+                        UNSORTED_KEYS,
+                    )));
+                }
+            }
+
+            previous = Some((position, raw));
+        }
+
+        match dict.entry(key(raw)) {
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+            Entry::Occupied(_) if options.reject_duplicate_keys => {
+                return Err(Err::Failure((position, DUPLICATE_KEY)));
+            }
+            Entry::Occupied(mut entry) => {
+                if !options.first_duplicate_wins {
+                    entry.insert(value);
+                }
+            }
+        }
+    }
+
+    Ok((rest, dict))
 }
 
 #[cfg(test)]
@@ -24,8 +145,13 @@ mod dictionary_tests {
     #[cfg(test)]
     extern crate maplit;
 
-    use super::{super::BencodedValue, parse_dictionary};
+    use super::{
+        super::{BencodedValue, ParserOptions},
+        duplicate_key, parse_dictionary, parse_dictionary_with_options,
+        unsorted_keys, DUPLICATE_KEY, UNSORTED_KEYS,
+    };
     use maplit::hashmap;
+    use nom::Err::Failure;
 
     #[test]
     pub fn test_dict() {
@@ -36,7 +162,7 @@ mod dictionary_tests {
             Ok((
                 b"" as _,
                 hashmap! {
-                    "a" => BencodedValue::Integer(4)
+                    "a".into() => BencodedValue::Integer(4)
                 }
             ))
         );
@@ -46,8 +172,8 @@ mod dictionary_tests {
             Ok((
                 b"" as _,
                 hashmap! {
-                    "a" => BencodedValue::Integer(4),
-                    "b" => BencodedValue::String("cow")
+                    "a".into() => BencodedValue::Integer(4),
+                    "b".into() => BencodedValue::from("cow")
                 }
             ))
         );
@@ -57,9 +183,9 @@ mod dictionary_tests {
             Ok((
                 b"" as _,
                 hashmap! {
-                    "a" => BencodedValue::Integer(4),
-                    "b" => BencodedValue::String("cow"),
-                    "c" => BencodedValue::List(vec![
+                    "a".into() => BencodedValue::Integer(4),
+                    "b".into() => BencodedValue::from("cow"),
+                    "c".into() => BencodedValue::List(vec![
                         BencodedValue::Integer(1),
                         BencodedValue::Integer(2),
                         BencodedValue::Integer(3),
@@ -68,4 +194,81 @@ mod dictionary_tests {
             ))
         );
     }
+
+    #[test]
+    pub fn test_duplicate_keys() {
+        let input = b"d1:ai1e1:bi2e1:ai3ee";
+        let mut options = ParserOptions::default();
+
+        assert_eq!(
+            parse_dictionary_with_options(input, &options),
+            Ok((
+                b"" as _,
+                hashmap! {
+                    "a".into() => BencodedValue::Integer(3),
+                    "b".into() => BencodedValue::Integer(2)
+                }
+            ))
+        );
+
+        options.first_duplicate_wins = true;
+        assert_eq!(
+            parse_dictionary_with_options(input, &options),
+            Ok((
+                b"" as _,
+                hashmap! {
+                    "a".into() => BencodedValue::Integer(1),
+                    "b".into() => BencodedValue::Integer(2)
+                }
+            ))
+        );
+
+        options.reject_duplicate_keys = true;
+        let error = parse_dictionary_with_options(input, &options).unwrap_err();
+        assert_eq!(error, Failure((&input[13..], DUPLICATE_KEY)));
+        assert_eq!(duplicate_key(&error), Some("a"));
+    }
+
+    #[test]
+    pub fn test_sorted_keys() {
+        let options = ParserOptions {
+            require_sorted_keys: true,
+            ..ParserOptions::default()
+        };
+
+        for input in &[&b"de"[..], b"d1:ai1ee", b"d1:ai1e2:abi2e1:bi3ee"] {
+            assert_eq!(
+                parse_dictionary_with_options(input, &options),
+                parse_dictionary(input)
+            );
+        }
+
+        let input = b"d1:bi1e1:ai2ee";
+        let error = parse_dictionary_with_options(input, &options).unwrap_err();
+        assert_eq!(error, Failure((&input[1..], UNSORTED_KEYS)));
+        assert_eq!(unsorted_keys(&error), Some(("b", "a")));
+
+        let input = b"d2:abi1e1:ai2ee";
+        let error = parse_dictionary_with_options(input, &options).unwrap_err();
+        assert_eq!(unsorted_keys(&error), Some(("ab", "a")));
+
+        // Raw bytes order, not Unicode collation: "Z" < "a" < "é"
+        let input = "d1:Zi1e1:ai2e2:éi3ee".as_bytes();
+        assert!(parse_dictionary_with_options(input, &options).is_ok());
+
+        let input = "d2:éi1e1:ai2ee".as_bytes();
+        let error = parse_dictionary_with_options(input, &options).unwrap_err();
+        assert_eq!(unsorted_keys(&error), Some(("é", "a")));
+
+        let input = b"d1:ai1e1:ai2ee";
+        let error = parse_dictionary_with_options(input, &options).unwrap_err();
+        assert_eq!(unsorted_keys(&error), Some(("a", "a")));
+
+        let options = ParserOptions {
+            reject_duplicate_keys: true,
+            ..options
+        };
+        let error = parse_dictionary_with_options(input, &options).unwrap_err();
+        assert_eq!(duplicate_key(&error), Some("a"));
+    }
 }
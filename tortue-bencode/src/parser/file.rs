@@ -0,0 +1,211 @@
+//! Parser module for bencoding
+//! Provides functions to parse bencoded files into self-contained documents
+
+use super::{parse_all_with_options, BencodedValue, ParserOptions};
+use crate::{de::from_value, error::Error};
+use serde::Deserialize;
+use std::{fmt, fs, path::Path};
+
+/// Parses the whole content of a file, see [`parse_file_with_options`]
+pub fn parse_file(path: impl AsRef<Path>) -> Result<OwnedDocument, Error> {
+    parse_file_with_options(path, &ParserOptions::default())
+}
+
+/// Parses the whole content of a file using the given options, the file is
+/// read in memory
+pub fn parse_file_with_options(
+    path: impl AsRef<Path>,
+    options: &ParserOptions,
+) -> Result<OwnedDocument, Error> {
+    let buffer = Buffer::Heap(fs::read(path).map_err(Error::from)?);
+    OwnedDocument::from_buffer(buffer, options)
+}
+
+/// Same as [`parse_file`] memory-mapping the file instead of reading it
+///
+/// # Safety
+///
+/// See [`parse_file_mapped_with_options`].
+#[cfg(feature = "mmap")]
+pub unsafe fn parse_file_mapped(
+    path: impl AsRef<Path>,
+) -> Result<OwnedDocument, Error> {
+    parse_file_mapped_with_options(path, &ParserOptions::default())
+}
+
+/// Same as [`parse_file_with_options`] memory-mapping the file instead of
+/// reading it
+///
+/// # Safety
+///
+/// The file must not be modified or truncated, by this process or any other,
+/// as long as the document lives: the values borrow from the mapping and
+/// changing it under them is undefined behaviour.
+#[cfg(feature = "mmap")]
+pub unsafe fn parse_file_mapped_with_options(
+    path: impl AsRef<Path>,
+    options: &ParserOptions,
+) -> Result<OwnedDocument, Error> {
+    let file = fs::File::open(path).map_err(Error::from)?;
+    // Safety: the caller guarantees that the file is left untouched
+    let map = unsafe { memmap2::Mmap::map(&file) }.map_err(Error::from)?;
+    OwnedDocument::from_buffer(Buffer::Mapped(map), options)
+}
+
+/// Backing buffer of an [`OwnedDocument`], the bytes never move once the
+/// buffer is created
+enum Buffer {
+    Heap(Vec<u8>),
+
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl Buffer {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Buffer::Heap(vec) => vec,
+            #[cfg(feature = "mmap")]
+            Buffer::Mapped(map) => map,
+        }
+    }
+}
+
+/// A parsed value owning the buffer it borrows from
+///
+/// The value is only accessible through [`OwnedDocument::value`] which ties
+/// its lifetime to the document.
+pub struct OwnedDocument {
+    // Borrows from `buffer`, declared first so that it is dropped first
+    value: BencodedValue<'static>,
+    buffer: Buffer,
+}
+
+impl OwnedDocument {
+    /// Parses all the values of a buffer
+    pub fn new(buffer: Vec<u8>) -> Result<Self, Error> {
+        Self::with_options(buffer, &ParserOptions::default())
+    }
+
+    /// Same as [`OwnedDocument::new`] using the given options
+    pub fn with_options(
+        buffer: Vec<u8>,
+        options: &ParserOptions,
+    ) -> Result<Self, Error> {
+        Self::from_buffer(Buffer::Heap(buffer), options)
+    }
+
+    fn from_buffer(
+        buffer: Buffer,
+        options: &ParserOptions,
+    ) -> Result<Self, Error> {
+        // Safety: the bytes live as long as the buffer, which is stored next
+        // to the value, and never move. The value is never exposed with a
+        // lifetime longer than the document.
+        let bytes: &'static [u8] =
+            unsafe { &*(buffer.as_slice() as *const [u8]) };
+
+        let (_, value) = parse_all_with_options(bytes, options)
+            .map_err(|e| Error::parse(bytes, e))?;
+
+        Ok(Self { value, buffer })
+    }
+
+    /// The parsed value, borrowing from the document
+    pub fn value<'a>(&'a self) -> &'a BencodedValue<'a> {
+        &self.value
+    }
+
+    /// The raw bytes of the document
+    pub fn bytes(&self) -> &[u8] {
+        self.buffer.as_slice()
+    }
+
+    /// Deserializes a data structure borrowing from the document
+    pub fn deserialize<'a, T: Deserialize<'a>>(&'a self) -> Result<T, Error> {
+        from_value(self.value().clone())
+    }
+}
+
+impl fmt::Debug for OwnedDocument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OwnedDocument")
+            .field("value", self.value())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod file_tests {
+    use super::{parse_file, OwnedDocument};
+    use crate::{error::Error, parser::parse_all, BencodedValue};
+    use std::{env, fs, path::PathBuf, process};
+
+    const TORRENT: &[u8] = include_bytes!("../../benches/test_data");
+
+    fn temp_file(name: &str, content: &[u8]) -> PathBuf {
+        let path = env::temp_dir().join(format!(
+            "tortue-bencode-{}-{}",
+            process::id(),
+            name
+        ));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    fn load(name: &str, content: &[u8]) -> Result<OwnedDocument, Error> {
+        let path = temp_file(name, content);
+        let document = parse_file(&path);
+        fs::remove_file(&path).unwrap();
+        document
+    }
+
+    #[test]
+    pub fn test_parse_file() {
+        let document = load("torrent", TORRENT).unwrap();
+
+        assert_eq!(document.bytes(), TORRENT);
+        assert_eq!(document.value(), &parse_all(TORRENT).unwrap().1);
+
+        match document.value() {
+            BencodedValue::Dictionary(dict) => {
+                assert!(dict.contains_key("info"))
+            }
+            _ => panic!("expected a dictionary"),
+        }
+    }
+
+    #[test]
+    pub fn test_parse_file_errors() {
+        assert!(matches!(load("invalid", b"lxe"), Err(Error::Parse(_))));
+        assert!(matches!(
+            load("truncated", b"li3e"),
+            Err(Error::Incomplete { needed: Some(1) })
+        ));
+        assert!(parse_file(env::temp_dir().join("tortue-missing")).is_err());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    pub fn test_parse_file_mapped() {
+        use super::parse_file_mapped;
+
+        let path = temp_file("mapped", TORRENT);
+        // Safety: the file is private to the test and left untouched
+        let document = unsafe { parse_file_mapped(&path) }.unwrap();
+        assert_eq!(document.bytes(), TORRENT);
+        assert_eq!(document.value(), &parse_all(TORRENT).unwrap().1);
+
+        drop(document);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    pub fn test_document() {
+        let document = OwnedDocument::new(b"l4:spami3ee".to_vec()).unwrap();
+        let (spam, int): (&str, i64) = document.deserialize().unwrap();
+
+        assert_eq!((spam, int), ("spam", 3));
+        assert!(!document.value().is_owned());
+    }
+}
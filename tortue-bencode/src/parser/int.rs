@@ -1,15 +1,33 @@
 //! Parser module for bencoding
 //! Provides functions to parse bencoded ints
 
-use super::parse_utf8_str;
+use super::{parse_utf8_str, BencodedValue, ParserOptions};
+use core::{num::ParseIntError, str::FromStr};
 use nom::{
     bytes::complete::{tag, take_while_m_n},
     character::is_digit,
-    combinator::map_res,
-    sequence::delimited,
-    IResult,
+    combinator::{map, opt, recognize},
+    error::ErrorKind,
+    sequence::{delimited, pair},
+    Err, IResult,
 };
-use std::num::ParseIntError;
+
+/// Error kind of the nom failure reported for integers out of range, the
+/// failure points at the digits of the integer
+pub const INTEGER_OVERFLOW: ErrorKind = ErrorKind::Digit;
+
+/// Returns the digits of the integer out of range of a parser error, if any
+pub fn integer_overflow<'a>(
+    error: &Err<(&'a [u8], ErrorKind)>,
+) -> Option<&'a str> {
+    match error {
+        Err::Failure((input, INTEGER_OVERFLOW)) => base10_digits(input)
+            .ok()
+            .and_then(|(_, digits)| parse_utf8_str(digits).ok())
+            .map(|(_, digits)| digits),
+        _ => None,
+    }
+}
 
 /// Parse a base 10 number from an input string
 #[inline]
@@ -17,14 +35,50 @@ pub fn parse_base10(input: &str) -> Result<i64, ParseIntError> {
     i64::from_str_radix(input, 10)
 }
 
+/// Recognizes a base 10 number, `-` is only accepted as first character
+#[inline]
+fn base10_digits(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    recognize(pair(opt(tag("-")), take_while_m_n(1, usize::MAX, is_digit)))(
+        input,
+    )
+}
+
+/// Parses digits, fails with an [`INTEGER_OVERFLOW`] failure if they are out
+/// of range
+#[inline]
+fn base10_value<'a, T: FromStr>(
+    input: &'a [u8],
+    digits: &'a [u8],
+) -> Result<T, Err<(&'a [u8], ErrorKind)>> {
+    let (_, digits) = parse_utf8_str(digits)?;
+    digits
+        .parse()
+        .map_err(|_| Err::Failure((input, INTEGER_OVERFLOW)))
+}
+
 /// Parse a base 10 encoded i64
 #[inline]
-pub fn base10_primary<'a>(input: &'a [u8]) -> IResult<&'a [u8], i64> {
-    map_res(
-        take_while_m_n(1, 20, |d| is_digit(d) || d == b'-'),
-        map_res(parse_utf8_str, parse_base10),
-    )(input)
-    .map(|(r, (_res, v))| (r, v))
+pub fn base10_primary(input: &[u8]) -> IResult<&[u8], i64> {
+    let (rest, digits) = base10_digits(input)?;
+    Ok((rest, base10_value(input, digits)?))
+}
+
+/// Parse a base 10 encoded u64
+#[inline]
+pub fn base10_unsigned(input: &[u8]) -> IResult<&[u8], u64> {
+    let (rest, digits) = take_while_m_n(1, usize::MAX, is_digit)(input)?;
+    Ok((rest, base10_value(input, digits)?))
+}
+
+/// Parse a base 10 encoded i64, rejecting leading zeros and negative zero
+#[inline]
+pub fn base10_strict(input: &[u8]) -> IResult<&[u8], i64> {
+    let (_, digits) = base10_digits(input)?;
+    if digits.starts_with(b"-0") || (digits.len() > 1 && digits[0] == b'0') {
+        Err(Err::Error((input, ErrorKind::Verify)))
+    } else {
+        base10_primary(input)
+    }
 }
 
 /// Nom parse compinator to parse a bencoded i64
@@ -33,10 +87,55 @@ pub fn parse_int<'a>(input: &'a [u8]) -> IResult<&'a [u8], i64> {
     delimited(tag("i"), base10_primary, tag("e"))(input)
 }
 
+/// Nom parse compinator to parse a bencoded i64, rejecting leading zeros and
+/// negative zero
+#[inline]
+pub fn parse_int_strict(input: &[u8]) -> IResult<&[u8], i64> {
+    delimited(tag("i"), base10_strict, tag("e"))(input)
+}
+
+/// Nom parse compinator to parse a bencoded u64
+#[inline]
+pub fn parse_uint(input: &[u8]) -> IResult<&[u8], u64> {
+    delimited(tag("i"), base10_unsigned, tag("e"))(input)
+}
+
+/// Parses an integer using the given options, integers above `i64::MAX` are
+/// parsed as unsigned unless [`ParserOptions::unsigned_integers`] is unset
+#[inline]
+pub fn parse_integer_with_options<'a, 'b>(
+    input: &'a [u8],
+    options: &ParserOptions,
+) -> IResult<&'a [u8], BencodedValue<'b>> {
+    let parse_int = if options.strict_integers {
+        parse_int_strict
+    } else {
+        parse_int
+    };
+
+    match parse_int(input) {
+        Ok((rest, int)) => Ok((rest, BencodedValue::Integer(int))),
+        Err(Err::Failure((digits, INTEGER_OVERFLOW)))
+            if options.unsigned_integers =>
+        {
+            map(parse_uint, BencodedValue::UnsignedInteger)(input)
+                .map_err(|_| Err::Failure((digits, INTEGER_OVERFLOW)))
+        }
+        Err(e) => Err(e),
+    }
+}
+
 #[cfg(test)]
 mod string_tests {
-    use super::parse_int;
-    use nom::{error::ErrorKind, Err::Error};
+    use super::{
+        integer_overflow, parse_int, parse_int_strict,
+        parse_integer_with_options, BencodedValue, ParserOptions,
+        INTEGER_OVERFLOW,
+    };
+    use nom::{
+        error::ErrorKind,
+        Err::{Error, Failure},
+    };
 
     #[test]
     pub fn test_int() {
@@ -64,4 +163,101 @@ mod string_tests {
             Err(Error((b"e" as &_, ErrorKind::TakeWhileMN)))
         );
     }
+
+    #[test]
+    pub fn test_int_lenient() {
+        assert_eq!(parse_int(b"i03e"), Ok((b"" as &_, 3)));
+        assert_eq!(parse_int(b"i-0e"), Ok((b"" as &_, 0)));
+
+        assert_eq!(
+            parse_int(b"i--3e"),
+            Err(Error((b"-3e" as &_, ErrorKind::TakeWhileMN)))
+        );
+        assert_eq!(
+            parse_int(b"i3-e"),
+            Err(Error((b"-e" as &_, ErrorKind::Tag)))
+        );
+        assert_eq!(
+            parse_int(b"i-e"),
+            Err(Error((b"e" as &_, ErrorKind::TakeWhileMN)))
+        );
+    }
+
+    #[test]
+    pub fn test_int_strict() {
+        assert_eq!(parse_int_strict(b"i0e"), Ok((b"" as &_, 0)));
+        assert_eq!(parse_int_strict(b"i-30e"), Ok((b"" as &_, -30)));
+
+        assert_eq!(
+            parse_int_strict(b"i03e"),
+            Err(Error((b"03e" as &_, ErrorKind::Verify)))
+        );
+        assert_eq!(
+            parse_int_strict(b"i-0e"),
+            Err(Error((b"-0e" as &_, ErrorKind::Verify)))
+        );
+        assert_eq!(
+            parse_int_strict(b"i--3e"),
+            Err(Error((b"-3e" as &_, ErrorKind::TakeWhileMN)))
+        );
+        assert_eq!(
+            parse_int_strict(b"i3-e"),
+            Err(Error((b"-e" as &_, ErrorKind::Tag)))
+        );
+    }
+
+    #[test]
+    pub fn test_int_overflow() {
+        let max = b"i9223372036854775807e";
+        let unsigned = ParserOptions {
+            unsigned_integers: true,
+            ..ParserOptions::default()
+        };
+
+        assert_eq!(parse_int(max), Ok((b"" as &_, i64::MAX)));
+        assert_eq!(
+            parse_int(b"i-9223372036854775808e"),
+            Ok((b"" as &_, i64::MIN))
+        );
+
+        for input in &[
+            &b"i9223372036854775808e"[..],
+            b"i18446744073709551615e",
+            b"i18446744073709551616e",
+            b"i-9223372036854775809e",
+        ] {
+            let digits = &input[1..input.len() - 1];
+            let error = parse_int(input).unwrap_err();
+
+            assert_eq!(error, Failure((&input[1..], INTEGER_OVERFLOW)));
+            assert_eq!(
+                integer_overflow(&error).map(str::as_bytes),
+                Some(digits)
+            );
+        }
+
+        assert_eq!(
+            parse_integer_with_options(max, &unsigned),
+            Ok((b"" as &_, BencodedValue::Integer(i64::MAX)))
+        );
+        assert_eq!(
+            parse_integer_with_options(b"i9223372036854775808e", &unsigned),
+            Ok((
+                b"" as &_,
+                BencodedValue::UnsignedInteger(i64::MAX as u64 + 1)
+            ))
+        );
+        assert_eq!(
+            parse_integer_with_options(b"i18446744073709551615e", &unsigned),
+            Ok((b"" as &_, BencodedValue::UnsignedInteger(u64::MAX)))
+        );
+        assert_eq!(
+            parse_integer_with_options(b"i18446744073709551616e", &unsigned),
+            Err(Failure((&b"18446744073709551616e"[..], INTEGER_OVERFLOW)))
+        );
+        assert_eq!(
+            parse_integer_with_options(b"i-9223372036854775809e", &unsigned),
+            Err(Failure((&b"-9223372036854775809e"[..], INTEGER_OVERFLOW)))
+        );
+    }
 }
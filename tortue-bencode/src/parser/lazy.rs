@@ -0,0 +1,219 @@
+//! Parser module for bencoding
+//! Provides lazily parsed values only decoding the parts that are accessed
+
+use super::{parse_all, parse_bytes, BencodedValue};
+use crate::{de::from_bytes, error::Error};
+use core::fmt;
+use nom::{error::ErrorKind, Err, IResult, Needed};
+use serde::Deserialize;
+
+/// Finds the extent of the single value of the input and returns it as a
+/// [`LazyValue`]
+///
+/// Strings are skipped using their length and nothing is parsed until it is
+/// accessed through [`LazyValue::get`], [`LazyValue::get_index`] or
+/// [`LazyValue::parse`], only the nesting and the string lengths are checked
+/// up front.
+pub fn lazy(input: &[u8]) -> Result<LazyValue<'_>, Error> {
+    match skip(input) {
+        Ok(([], ())) => Ok(LazyValue { raw: input }),
+        Ok((rest, ())) => Err(Error::TrailingData {
+            remaining: rest.len(),
+        }),
+        Err(e) => Err(Error::parse(input, e)),
+    }
+}
+
+/// Skips the first value of the input without parsing it, returning the rest
+fn skip(input: &[u8]) -> IResult<&[u8], ()> {
+    let mut depth = 0usize;
+    let mut rest = input;
+    loop {
+        rest = match rest.first() {
+            None => return Err(Err::Incomplete(Needed::Size(1))),
+            Some(b'i') => match rest.iter().position(|&b| b == b'e') {
+                Some(end) => &rest[end + 1..],
+                None => return Err(Err::Incomplete(Needed::Size(1))),
+            },
+            Some(b'0'..=b'9') => parse_bytes(rest)?.0,
+            Some(b'l') | Some(b'd') => {
+                depth += 1;
+                &rest[1..]
+            }
+            Some(b'e') if depth > 0 => {
+                depth -= 1;
+                &rest[1..]
+            }
+            Some(_) => return Err(Err::Error((rest, ErrorKind::Char))),
+        };
+
+        if depth == 0 {
+            return Ok((rest, ()));
+        }
+    }
+}
+
+/// A bencoded value that is only parsed when accessed
+///
+/// Lists and dictionaries are scanned on every access, the values they
+/// contain are skipped without being built.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct LazyValue<'a> {
+    raw: &'a [u8],
+}
+
+impl<'a> LazyValue<'a> {
+    /// The bytes of the value, exactly as found in the input
+    pub fn raw(&self) -> &'a [u8] {
+        self.raw
+    }
+
+    /// Checks if the value is a list
+    pub fn is_list(&self) -> bool {
+        self.raw[0] == b'l'
+    }
+
+    /// Checks if the value is a dictionary
+    pub fn is_dict(&self) -> bool {
+        self.raw[0] == b'd'
+    }
+
+    /// Returns the value of a key if this is a dictionary containing it, the
+    /// last one wins if the key is duplicated
+    pub fn get(&self, key: &str) -> Option<LazyValue<'a>> {
+        if !self.is_dict() {
+            return None;
+        }
+
+        let mut elements = self.elements();
+        let mut found = None;
+        while let (Some(raw_key), Some(value)) =
+            (elements.next(), elements.next())
+        {
+            let (_, bytes) = parse_bytes(raw_key.raw).ok()?;
+            if bytes == key.as_bytes() {
+                found = Some(value);
+            }
+        }
+
+        found
+    }
+
+    /// Returns an element if this is a list long enough
+    pub fn get_index(&self, index: usize) -> Option<LazyValue<'a>> {
+        if self.is_list() {
+            self.elements().nth(index)
+        } else {
+            None
+        }
+    }
+
+    /// Parses the value
+    pub fn parse(&self) -> Result<BencodedValue<'a>, Error> {
+        parse_all(self.raw)
+            .map(|(_, value)| value)
+            .map_err(|e| Error::parse(self.raw, e))
+    }
+
+    /// Deserializes a data structure from the value
+    pub fn deserialize<T: Deserialize<'a>>(&self) -> Result<T, Error> {
+        from_bytes(self.raw)
+    }
+
+    /// Elements of a list, or keys and values of a dictionary
+    fn elements(&self) -> impl Iterator<Item = LazyValue<'a>> {
+        let mut rest = &self.raw[1..];
+        core::iter::from_fn(move || {
+            if rest[0] == b'e' {
+                return None;
+            }
+
+            // The extent was checked when the value was created
+            let (next, _) = skip(rest).ok()?;
+            let raw = &rest[..rest.len() - next.len()];
+            rest = next;
+            Some(LazyValue { raw })
+        })
+    }
+}
+
+impl fmt::Debug for LazyValue<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LazyValue")
+            .field("len", &self.raw.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod lazy_tests {
+    use super::lazy;
+    use crate::{
+        error::Error, parser::parse_all, writer::is_canonical, BencodedValue,
+    };
+
+    const TORRENT: &[u8] = include_bytes!("../../benches/test_data");
+
+    #[test]
+    pub fn test_get() {
+        let torrent = lazy(TORRENT).unwrap();
+        let info = torrent.get("info").unwrap();
+
+        assert_eq!(
+            torrent.get("announce").unwrap().parse(),
+            Ok(BencodedValue::from(
+                "udp://tracker.openbittorrent.com:80/announce"
+            ))
+        );
+        assert_eq!(
+            info.get("name").unwrap().deserialize(),
+            Ok("bbb_sunflower_1080p_60fps_normal.mp4")
+        );
+        assert_eq!(info.get("length").unwrap().deserialize(), Ok(355856562));
+        assert!(info.get("missing").is_none());
+        assert!(torrent.get_index(0).is_none());
+
+        let tiers = torrent.get("announce-list").unwrap();
+        assert_eq!(
+            tiers
+                .get_index(1)
+                .and_then(|tier| tier.get_index(0))
+                .unwrap()
+                .parse(),
+            Ok(BencodedValue::from(
+                "udp://tracker.publicbt.com:80/announce"
+            ))
+        );
+        assert!(tiers.get_index(2).is_none());
+        assert!(tiers.get("info").is_none());
+
+        assert_eq!(torrent.parse(), Ok(parse_all(TORRENT).unwrap().1));
+    }
+
+    #[test]
+    pub fn test_raw() {
+        let torrent = lazy(TORRENT).unwrap();
+        let info = torrent.get("info").unwrap();
+        let pieces = info.get("pieces").unwrap();
+
+        // The skipped subtrees are found verbatim in the input
+        let start = info.raw().as_ptr() as usize - TORRENT.as_ptr() as usize;
+        assert_eq!(&TORRENT[start - 6..start], b"4:info");
+        assert!(is_canonical(info.raw()));
+
+        let pieces_value = pieces.parse().unwrap();
+        assert_eq!(pieces.raw(), &pieces_value.to_vec().unwrap()[..]);
+        assert_eq!(pieces_value.as_bytes().unwrap().len() % 20, 0);
+
+        let duplicated = lazy(b"d1:ai1e1:bi2e1:ai3ee").unwrap();
+        assert_eq!(duplicated.get("a").unwrap().raw(), b"i3e");
+    }
+
+    #[test]
+    pub fn test_errors() {
+        assert!(matches!(lazy(b"d1:ai1e"), Err(Error::Incomplete { .. })));
+        assert!(matches!(lazy(b""), Err(Error::Incomplete { .. })));
+        assert_eq!(lazy(b"i1ei2e"), Err(Error::TrailingData { remaining: 3 }));
+        assert!(matches!(lazy(b"lxe"), Err(Error::Parse(_))));
+    }
+}
@@ -1,21 +1,47 @@
 //! Parser module for bencoding
 //! Provides functions to parse bencoded lists (arrays)
 
-use super::{parse_all_no_group, BencodedValue};
-use nom::{
-    bytes::complete::tag, combinator::map, sequence::delimited, IResult,
-};
+use super::{many_with, value_with, BencodedValue, Budget, ParserOptions};
+use crate::prelude::*;
+use nom::{bytes::complete::tag, combinator::map, IResult};
 
 /// Nom parse compinator to parse a bencoded Vec<BencodedValue>
 #[inline]
 pub fn parse_list<'a>(input: &'a [u8]) -> IResult<&'a [u8], BencodedValue<'a>> {
-    delimited(
-        tag("l"),
-        map(parse_all_no_group, BencodedValue::List),
-        tag("e"),
+    parse_list_with_options(input, &ParserOptions::default())
+}
+
+/// Same as [`parse_list`] using the given options
+#[inline]
+pub fn parse_list_with_options<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+) -> IResult<&'a [u8], BencodedValue<'a>> {
+    map(
+        |input| list_with(input, options, &Budget::new(options), value_with),
+        BencodedValue::List,
     )(input)
 }
 
+/// Parses a list whose values are parsed by `value` using the options of the
+/// nested values
+pub(crate) fn list_with<'a, V, P>(
+    input: &'a [u8],
+    options: &ParserOptions,
+    budget: &Budget,
+    value: P,
+) -> IResult<&'a [u8], Vec<V>>
+where
+    P: Fn(&'a [u8], &ParserOptions, &Budget) -> IResult<&'a [u8], V>,
+{
+    let (rest, _) = tag("l")(input)?;
+    let nested = options.nested(input)?;
+
+    let (rest, values) = many_with(rest, &nested, budget, value)?;
+    let (rest, _) = tag("e")(rest)?;
+    Ok((rest, values))
+}
+
 #[cfg(test)]
 mod list_tests {
     use super::{super::BencodedValue, parse_list};
@@ -46,7 +72,7 @@ mod list_tests {
                 BencodedValue::List(vec![
                     BencodedValue::Integer(3),
                     BencodedValue::Integer(4),
-                    BencodedValue::String("abcd")
+                    BencodedValue::from("abcd")
                 ])
             ))
         );
@@ -0,0 +1,275 @@
+//! Parser module for bencoding
+//! Provides the options of the parser
+
+use core::{cell::Cell, fmt, mem};
+use nom::{error::ErrorKind, Err};
+
+/// Default maximum nesting depth of lists and dictionaries
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Default maximum length of a single string or binary (128 MiB)
+pub const DEFAULT_MAX_STRING_LEN: usize = 128 << 20;
+
+/// Default maximum length of the whole input (1 GiB)
+pub const DEFAULT_MAX_TOTAL_LEN: usize = 1 << 30;
+
+/// Default maximum number of elements of a single list or dictionary
+pub const DEFAULT_MAX_ITEMS: usize = 1 << 20;
+
+/// Default allocation budget of a whole parse, unlimited as the total length
+/// of the input already bounds it
+pub const DEFAULT_MAX_TOTAL_BYTES: usize = usize::MAX;
+
+/// Options of the parser, the default is lenient and accepts everything the
+/// parser has historically accepted within generous resource limits
+///
+/// Exceeding a limit is reported as a nom failure whose error kind is given
+/// by [`Limit::error_kind`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParserOptions {
+    /// Rejects integers with leading zeros (`i03e`) or negative zero
+    /// (`i-0e`) with an `ErrorKind::Verify` error
+    pub strict_integers: bool,
+
+    /// Maximum nesting depth of lists and dictionaries, deeper inputs are
+    /// rejected with an `ErrorKind::TooLarge` failure instead of overflowing
+    /// the stack
+    pub max_depth: usize,
+
+    /// Parses integers above `i64::MAX` that fit in a u64 as
+    /// [`BencodedValue::UnsignedInteger`](crate::BencodedValue), enabled by
+    /// default so that every `u64` roundtrips. When unset they fail with an
+    /// [`INTEGER_OVERFLOW`](super::INTEGER_OVERFLOW) failure.
+    pub unsigned_integers: bool,
+
+    /// Maximum length in bytes of a single string, binary or key, checked
+    /// against the declared length before reading it
+    pub max_string_len: usize,
+
+    /// Maximum length in bytes of the whole input
+    pub max_total_len: usize,
+
+    /// Maximum number of elements of a single list or dictionary
+    pub max_items: usize,
+
+    /// Allocation budget in bytes of a whole parse, every string costs its
+    /// length and every list or dictionary entry the size of its slot, so
+    /// that a small input cannot expand into a huge tree
+    pub max_total_bytes: usize,
+
+    /// Rejects dictionaries containing the same key twice with a
+    /// [`DUPLICATE_KEY`](super::DUPLICATE_KEY) failure
+    pub reject_duplicate_keys: bool,
+
+    /// Keeps the first occurrence of a duplicated key instead of the last one
+    /// when duplicates are not rejected
+    pub first_duplicate_wins: bool,
+
+    /// Rejects dictionaries whose keys are not sorted by their raw bytes with
+    /// an [`UNSORTED_KEYS`](super::UNSORTED_KEYS) failure
+    pub require_sorted_keys: bool,
+
+    /// Parses every string as a [`BencodedValue::Binary`](crate::BencodedValue)
+    /// instead of guessing text from UTF-8 validity, see
+    /// [`BencodedValue::as_str`](crate::BencodedValue::as_str)
+    pub binary_strings: bool,
+
+    /// Values read as booleans when deserializing, the parser ignores it
+    pub bools: BoolMode,
+
+    /// Deserializes structs with named fields from lists, their fields in
+    /// order, as some compact formats do. Unset by default so that a list
+    /// found instead of a dictionary is an error rather than garbage fields,
+    /// the parser ignores it
+    pub positional_structs: bool,
+}
+
+/// Values the deserializer reads as booleans, bencode has none
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BoolMode {
+    /// Any integer, true unless it is zero, and the strings `0`, `1`,
+    /// `false` and `true` as written by some torrent makers
+    #[default]
+    Lenient,
+
+    /// The integers `0` and `1` only
+    Strict,
+
+    /// Nothing, booleans are rejected
+    Rejected,
+}
+
+/// A resource limit of the parser
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    /// See [`ParserOptions::max_depth`]
+    Depth,
+
+    /// See [`ParserOptions::max_string_len`]
+    StringLength,
+
+    /// See [`ParserOptions::max_total_len`]
+    TotalLength,
+
+    /// See [`ParserOptions::max_items`]
+    Items,
+
+    /// See [`ParserOptions::max_total_bytes`]
+    TotalBytes,
+}
+
+impl Limit {
+    /// Error kind of the nom failure reported when the limit is exceeded
+    pub fn error_kind(self) -> ErrorKind {
+        match self {
+            Limit::Depth => ErrorKind::TooLarge,
+            Limit::StringLength => ErrorKind::LengthValue,
+            Limit::TotalLength => ErrorKind::LengthValueFn,
+            Limit::Items => ErrorKind::Count,
+            Limit::TotalBytes => ErrorKind::ManyMN,
+        }
+    }
+
+    /// Returns the limit exceeded by a parser error, if any
+    pub fn from_error<I>(error: &Err<(I, ErrorKind)>) -> Option<Self> {
+        match error {
+            Err::Failure((_, ErrorKind::TooLarge)) => Some(Limit::Depth),
+            Err::Failure((_, ErrorKind::LengthValue)) => {
+                Some(Limit::StringLength)
+            }
+            Err::Failure((_, ErrorKind::LengthValueFn)) => {
+                Some(Limit::TotalLength)
+            }
+            Err::Failure((_, ErrorKind::Count)) => Some(Limit::Items),
+            Err::Failure((_, ErrorKind::ManyMN)) => Some(Limit::TotalBytes),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn fail<I>(self, input: I) -> Err<(I, ErrorKind)> {
+        Err::Failure((input, self.error_kind()))
+    }
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Limit::Depth => "max_depth",
+            Limit::StringLength => "max_string_len",
+            Limit::TotalLength => "max_total_len",
+            Limit::Items => "max_items",
+            Limit::TotalBytes => "max_total_bytes",
+        })
+    }
+}
+
+impl ParserOptions {
+    /// Options rejecting every encoding forbidden by the specification
+    pub fn strict() -> Self {
+        ParserOptions {
+            strict_integers: true,
+            reject_duplicate_keys: true,
+            require_sorted_keys: true,
+            ..Self::default()
+        }
+    }
+
+    /// Options of the values nested in a list or dictionary starting at
+    /// `input`, fails if the maximum depth is reached
+    pub(crate) fn nested<'a>(
+        &self,
+        input: &'a [u8],
+    ) -> Result<Self, Err<(&'a [u8], ErrorKind)>> {
+        if self.max_depth == 0 {
+            Err(Limit::Depth.fail(input))
+        } else {
+            Ok(ParserOptions {
+                max_depth: self.max_depth - 1,
+                ..*self
+            })
+        }
+    }
+
+    /// Fails if the input is longer than the maximum total length
+    pub(crate) fn check_total_len<'a>(
+        &self,
+        input: &'a [u8],
+    ) -> Result<(), Err<(&'a [u8], ErrorKind)>> {
+        if input.len() > self.max_total_len {
+            Err(Limit::TotalLength.fail(input))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Fails if a collection starting at `input` has more elements than the
+    /// maximum number of items
+    pub(crate) fn check_items<'a>(
+        &self,
+        input: &'a [u8],
+        items: usize,
+    ) -> Result<(), Err<(&'a [u8], ErrorKind)>> {
+        if items > self.max_items {
+            Err(Limit::Items.fail(input))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Remaining allocation budget of a parse, shared by all the values it builds
+pub(crate) struct Budget {
+    remaining: Cell<usize>,
+}
+
+impl Budget {
+    /// Budget of a new parse using the given options
+    pub(crate) fn new(options: &ParserOptions) -> Self {
+        Budget {
+            remaining: Cell::new(options.max_total_bytes),
+        }
+    }
+
+    /// Takes `bytes` from the budget, fails at `input` once it is exhausted
+    pub(crate) fn charge<'a>(
+        &self,
+        input: &'a [u8],
+        bytes: usize,
+    ) -> Result<(), Err<(&'a [u8], ErrorKind)>> {
+        match self.remaining.get().checked_sub(bytes) {
+            Some(remaining) => {
+                self.remaining.set(remaining);
+                Ok(())
+            }
+            None => Err(Limit::TotalBytes.fail(input)),
+        }
+    }
+
+    /// Takes the slot of a list or dictionary entry from the budget
+    pub(crate) fn charge_entry<'a, V>(
+        &self,
+        input: &'a [u8],
+    ) -> Result<(), Err<(&'a [u8], ErrorKind)>> {
+        self.charge(input, mem::size_of::<V>())
+    }
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            strict_integers: false,
+            unsigned_integers: true,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_string_len: DEFAULT_MAX_STRING_LEN,
+            max_total_len: DEFAULT_MAX_TOTAL_LEN,
+            max_items: DEFAULT_MAX_ITEMS,
+            max_total_bytes: DEFAULT_MAX_TOTAL_BYTES,
+            reject_duplicate_keys: false,
+            first_duplicate_wins: false,
+            require_sorted_keys: false,
+            binary_strings: false,
+            bools: BoolMode::default(),
+            positional_structs: false,
+        }
+    }
+}
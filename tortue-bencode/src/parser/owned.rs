@@ -0,0 +1,112 @@
+//! Parser module for bencoding
+//! Provides functions to parse bencoded values into owned values
+
+use super::{
+    bytes_with, dictionary_with, list_with, parse_integer_with_options,
+    BencodedValue, Budget, ParserOptions,
+};
+use crate::{error::Error, Key};
+use nom::{branch::alt, combinator::map, IResult};
+
+/// Parses a single value of the input into an owned value, copying the
+/// strings while parsing instead of borrowing them from the input
+///
+/// Returns the number of bytes consumed along with the value.
+#[inline]
+pub fn parse_owned(
+    input: &[u8],
+) -> Result<(usize, BencodedValue<'static>), Error> {
+    parse_owned_with_options(input, &ParserOptions::default())
+}
+
+/// Same as [`parse_owned`] using the given options
+pub fn parse_owned_with_options(
+    input: &[u8],
+    options: &ParserOptions,
+) -> Result<(usize, BencodedValue<'static>), Error> {
+    let (rest, value) =
+        parse_value_owned(input, options, &Budget::new(options))
+            .map_err(|e| Error::parse(input, e))?;
+
+    Ok((input.len() - rest.len(), value))
+}
+
+/// Same as [`parse_with_options`](super::parse_with_options) producing an
+/// owned value
+fn parse_value_owned<'a>(
+    input: &'a [u8],
+    options: &ParserOptions,
+    budget: &Budget,
+) -> IResult<&'a [u8], BencodedValue<'static>> {
+    options.check_total_len(input)?;
+
+    alt((
+        map(
+            |input| bytes_with(input, options, budget),
+            BencodedValue::into_owned,
+        ),
+        |input| parse_integer_with_options(input, options),
+        map(
+            |input| list_with(input, options, budget, parse_value_owned),
+            BencodedValue::List,
+        ),
+        map(
+            |input| {
+                dictionary_with(
+                    input,
+                    options,
+                    budget,
+                    Key::new,
+                    parse_value_owned,
+                )
+            },
+            BencodedValue::Dictionary,
+        ),
+    ))(input)
+}
+
+#[cfg(test)]
+mod owned_tests {
+    use super::{parse_owned, parse_owned_with_options};
+    use crate::{
+        error::Error,
+        parser::{parse, ParserOptions},
+        BencodedValue,
+    };
+
+    const TORRENT: &[u8] = include_bytes!("../../benches/test_data");
+
+    #[test]
+    pub fn test_owned() {
+        let (read, value) = parse_owned(TORRENT).unwrap();
+        assert_eq!(read, TORRENT.len());
+        assert!(value.is_owned());
+        assert_eq!(value, parse(TORRENT).unwrap().1.into_owned());
+
+        let (read, value) = parse_owned(b"4:spami3e").unwrap();
+        assert_eq!(read, 6);
+        assert_eq!(value, BencodedValue::from("spam"));
+
+        let (read, value) = parse_owned(b"d1:al2:\xFF\xFEee").unwrap();
+        assert_eq!(read, 11);
+        assert!(value.is_owned());
+    }
+
+    #[test]
+    pub fn test_owned_errors() {
+        assert!(matches!(parse_owned(b"lxe"), Err(Error::Parse(_))));
+        assert!(matches!(
+            parse_owned(b"li3e"),
+            Err(Error::Incomplete { .. })
+        ));
+
+        let options = ParserOptions {
+            reject_duplicate_keys: true,
+            ..ParserOptions::default()
+        };
+        assert_eq!(
+            parse_owned_with_options(b"d1:ai1e1:ai2ee", &options),
+            Err(Error::DuplicateKey("a".to_owned()))
+        );
+    }
+}
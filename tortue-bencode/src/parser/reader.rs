@@ -0,0 +1,378 @@
+//! Parser module for bencoding
+//! Provides functions to parse bencoded values from readers
+
+use super::{parse_all_with_options, BencodedValue, Limit, ParserOptions};
+use crate::error::{Error, ParseError};
+use nom::error::ErrorKind;
+use std::io::{self, Read};
+
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Maximum number of digits of a string length
+const MAX_LENGTH_DIGITS: usize = 20;
+
+/// Parses a single bencoded value from a reader, see
+/// [`parse_from_reader_with_options`]
+pub fn parse_from_reader<R: Read>(
+    reader: R,
+) -> Result<BencodedValue<'static>, Error> {
+    parse_from_reader_with_options(reader, &ParserOptions::default())
+}
+
+/// Parses a single bencoded value from a reader using the given options
+///
+/// The value is buffered before being parsed, strings are read in one go
+/// while the rest is read byte per byte so that nothing past the end of the
+/// value is consumed: wrap unbuffered readers in a `BufReader` if the
+/// remaining data is not needed.
+pub fn parse_from_reader_with_options<R: Read>(
+    mut reader: R,
+    options: &ParserOptions,
+) -> Result<BencodedValue<'static>, Error> {
+    let mut scanner = Scanner::new(options);
+    let mut buffer = Vec::new();
+
+    loop {
+        let wanted = scanner.wanted()?;
+        if wanted == 0 {
+            break;
+        }
+
+        let start = buffer.len();
+        (&mut reader)
+            .take(wanted as u64)
+            .read_to_end(&mut buffer)
+            .map_err(Error::from)?;
+        scanner.feed(&buffer[start..], wanted)?;
+    }
+
+    let (_, value) = parse_all_with_options(&buffer, options)
+        .map_err(|e| Error::parse(&buffer, e))?;
+    Ok(value.into_owned())
+}
+
+/// Parses a single bencoded value from an async reader, see
+/// [`parse_from_reader_with_options`]
+#[cfg(feature = "tokio")]
+pub async fn parse_from_async_reader<R: AsyncRead + Unpin>(
+    reader: R,
+) -> Result<BencodedValue<'static>, Error> {
+    parse_from_async_reader_with_options(reader, &ParserOptions::default())
+        .await
+}
+
+/// Parses a single bencoded value from an async reader using the given
+/// options, see [`parse_from_reader_with_options`]
+#[cfg(feature = "tokio")]
+pub async fn parse_from_async_reader_with_options<R: AsyncRead + Unpin>(
+    mut reader: R,
+    options: &ParserOptions,
+) -> Result<BencodedValue<'static>, Error> {
+    let mut scanner = Scanner::new(options);
+    let mut buffer = Vec::new();
+
+    loop {
+        let wanted = scanner.wanted()?;
+        if wanted == 0 {
+            break;
+        }
+
+        let start = buffer.len();
+        (&mut reader)
+            .take(wanted as u64)
+            .read_to_end(&mut buffer)
+            .await
+            .map_err(Error::from)?;
+        scanner.feed(&buffer[start..], wanted)?;
+    }
+
+    let (_, value) = parse_all_with_options(&buffer, options)
+        .map_err(|e| Error::parse(&buffer, e))?;
+    Ok(value.into_owned())
+}
+
+/// An open list or dictionary
+struct Frame {
+    dict: bool,
+    items: usize,
+
+    /// Whether the next value of a dictionary is a value (or a key)
+    value: bool,
+}
+
+/// What the scanner expects next
+enum State {
+    Value,
+    Int,
+    Length { len: usize, digits: usize },
+    Bytes(usize),
+    Done,
+}
+
+/// Incremental scanner finding the end of a bencoded value without reading
+/// past it. Only the structure is checked, the scanned bytes must then be
+/// parsed to validate the value.
+struct Scanner {
+    options: ParserOptions,
+    stack: Vec<Frame>,
+    state: State,
+    len: usize,
+}
+
+impl Scanner {
+    fn new(options: &ParserOptions) -> Self {
+        Scanner {
+            options: *options,
+            stack: Vec::new(),
+            state: State::Value,
+            len: 0,
+        }
+    }
+
+    /// Number of bytes to read next, zero once the value is complete
+    fn wanted(&self) -> Result<usize, Error> {
+        let wanted = match self.state {
+            State::Bytes(len) => len,
+            State::Done => return Ok(0),
+            _ => 1,
+        };
+
+        if self.len.saturating_add(wanted) > self.options.max_total_len {
+            Err(Error::Limit(Limit::TotalLength))
+        } else {
+            Ok(wanted)
+        }
+    }
+
+    /// Feeds the bytes read after a call to [`Scanner::wanted`]
+    fn feed(&mut self, bytes: &[u8], wanted: usize) -> Result<(), Error> {
+        if bytes.len() < wanted {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        self.len += bytes.len();
+        match self.state {
+            State::Bytes(_) => self.end_value(),
+            _ => self.feed_byte(bytes[0]),
+        }
+    }
+
+    fn feed_byte(&mut self, byte: u8) -> Result<(), Error> {
+        match (&self.state, byte) {
+            (State::Value, b'e') if self.can_close() => {
+                self.stack.pop();
+                self.end_value()
+            }
+            (State::Value, byte) => self.start_value(byte),
+            (State::Int, b'e') => self.end_value(),
+            (State::Int, _) => Ok(()),
+            (State::Length { len, .. }, b':') => {
+                let len = *len;
+                if len > self.options.max_string_len {
+                    Err(Error::Limit(Limit::StringLength))
+                } else if len == 0 {
+                    self.end_value()
+                } else {
+                    self.state = State::Bytes(len);
+                    Ok(())
+                }
+            }
+            (State::Length { len, digits }, b'0'..=b'9')
+                if *digits < MAX_LENGTH_DIGITS =>
+            {
+                let len = len
+                    .checked_mul(10)
+                    .and_then(|len| len.checked_add((byte - b'0') as usize))
+                    .ok_or(Error::Limit(Limit::StringLength))?;
+
+                self.state = State::Length {
+                    len,
+                    digits: digits + 1,
+                };
+                Ok(())
+            }
+            (State::Length { .. }, _) => {
+                Err(self.syntax_error(byte, ErrorKind::Digit, "a valid string"))
+            }
+            (State::Bytes(_), _) | (State::Done, _) => unreachable!(),
+        }
+    }
+
+    /// Error on the last byte fed, which is not what was `expected`
+    fn syntax_error(&self, byte: u8, kind: ErrorKind, expected: &str) -> Error {
+        Error::Parse(
+            ParseError::byte(self.len - 1, byte, kind).expecting(expected),
+        )
+    }
+
+    /// Whether an `e` closes the innermost list or dictionary
+    fn can_close(&self) -> bool {
+        match self.stack.last() {
+            Some(frame) => !frame.dict || !frame.value,
+            None => false,
+        }
+    }
+
+    fn start_value(&mut self, byte: u8) -> Result<(), Error> {
+        if let Some(frame) = self.stack.last_mut() {
+            if frame.dict && !frame.value && !byte.is_ascii_digit() {
+                return Err(self.syntax_error(
+                    byte,
+                    ErrorKind::Digit,
+                    "a key or 'e'",
+                ));
+            }
+
+            if !frame.value {
+                frame.items += 1;
+                if frame.items > self.options.max_items {
+                    return Err(Error::Limit(Limit::Items));
+                }
+            }
+        }
+
+        self.state = match byte {
+            b'i' => State::Int,
+            b'l' | b'd' => {
+                if self.stack.len() >= self.options.max_depth {
+                    return Err(Error::Limit(Limit::Depth));
+                }
+
+                self.stack.push(Frame {
+                    dict: byte == b'd',
+                    items: 0,
+                    value: false,
+                });
+                State::Value
+            }
+            b'0'..=b'9' => State::Length {
+                len: (byte - b'0') as usize,
+                digits: 1,
+            },
+            byte => {
+                return Err(self.syntax_error(byte, ErrorKind::Char, "a value"))
+            }
+        };
+
+        Ok(())
+    }
+
+    fn end_value(&mut self) -> Result<(), Error> {
+        self.state = match self.stack.last_mut() {
+            Some(frame) => {
+                if frame.dict {
+                    frame.value = !frame.value;
+                }
+
+                State::Value
+            }
+            None => State::Done,
+        };
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod reader_tests {
+    use super::{parse_from_reader, parse_from_reader_with_options};
+    use crate::{
+        error::Error,
+        parser::{parse_all, Limit, ParserOptions},
+    };
+    use std::io::{self, Cursor, Read};
+
+    /// A reader yielding a single byte per call
+    struct OneByte<'a>(&'a [u8]);
+
+    impl<'a> Read for OneByte<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() || buf.is_empty() {
+                return Ok(0);
+            }
+
+            buf[0] = self.0[0];
+            self.0 = &self.0[1..];
+            Ok(1)
+        }
+    }
+
+    const VALUE: &[u8] =
+        b"d4:infod6:lengthi12e4:name5:a.txte4:listli-3e3:abcee";
+
+    #[test]
+    fn test_one_byte() {
+        let value = parse_from_reader(OneByte(VALUE)).unwrap();
+        assert_eq!(value, parse_all(VALUE).unwrap().1);
+    }
+
+    #[test]
+    fn test_trailing() {
+        let input = [VALUE, b"garbage"].concat();
+        let mut cursor = Cursor::new(&input);
+
+        let value = parse_from_reader(&mut cursor).unwrap();
+        assert_eq!(value, parse_all(VALUE).unwrap().1);
+        assert_eq!(cursor.position(), VALUE.len() as u64);
+
+        let mut rest = Vec::new();
+        cursor.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"garbage");
+
+        let mut reader = OneByte(&input);
+        parse_from_reader(&mut reader).unwrap();
+        assert_eq!(reader.0, b"garbage");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse_from_reader(&b""[..]).is_err());
+        assert!(parse_from_reader(&b"li1e"[..]).is_err());
+        assert!(parse_from_reader(&b"5:abc"[..]).is_err());
+        assert!(parse_from_reader(&b"x"[..]).is_err());
+        assert!(parse_from_reader(&b"i1-e"[..]).is_err());
+        assert!(parse_from_reader(&b"d1:ae"[..]).is_err());
+
+        let options = ParserOptions {
+            max_depth: 1,
+            max_string_len: 3,
+            max_items: 2,
+            ..ParserOptions::default()
+        };
+        let limit = |input: &[u8]| match parse_from_reader_with_options(
+            input, &options,
+        ) {
+            Err(Error::Limit(limit)) => Some(limit),
+            _ => None,
+        };
+
+        assert_eq!(limit(b"999999999:abc"), Some(Limit::StringLength));
+        assert_eq!(limit(b"llee"), Some(Limit::Depth));
+        assert_eq!(limit(b"li1ei2ei3ee"), Some(Limit::Items));
+        assert_eq!(limit(b"li1ei2ee"), None);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_async() {
+        use super::parse_from_async_reader;
+        use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+        let (mut client, mut server) = duplex(16);
+        let writer = tokio::spawn(async move {
+            let input = [VALUE, b"garbage"].concat();
+            for chunk in input.chunks(3) {
+                server.write_all(chunk).await.unwrap();
+            }
+        });
+
+        let value = parse_from_async_reader(&mut client).await.unwrap();
+        assert_eq!(value, parse_all(VALUE).unwrap().1);
+
+        writer.await.unwrap();
+        let mut rest = Vec::new();
+        client.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, b"garbage");
+    }
+}
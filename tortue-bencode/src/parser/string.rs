@@ -1,48 +1,43 @@
 //! Parser module for bencoding
 //! Provides functions to parse bencoded strings
 
+use super::parse_bytes;
 use nom::{
-    bytes::complete::{tag, take_while_m_n},
-    character::is_digit,
-    combinator::map_res,
-    error::ErrorKind,
-    multi::length_value,
-    sequence::preceded,
-    IResult,
+    bytes::complete::take_while_m_n, character::is_digit, combinator::map_opt,
+    error::ErrorKind, IResult,
 };
-use std::num::ParseIntError;
 
-/// Parse a base 10 number from an input string
+/// Computes the value of base 10 digits, `None` if it does not fit in a usize
 #[inline]
-pub fn parse_base10(input: &str) -> Result<u32, ParseIntError> {
-    u32::from_str_radix(input, 10)
+fn digits_value(digits: &[u8]) -> Option<usize> {
+    digits.iter().try_fold(0usize, |value, digit| {
+        value.checked_mul(10)?.checked_add((digit - b'0') as usize)
+    })
 }
 
 pub fn parse_utf8_str<'a>(input: &'a [u8]) -> IResult<&'a [u8], &'a str> {
-    std::str::from_utf8(input)
+    core::str::from_utf8(input)
         .map(|v| (&[] as &'a [u8], v as &'a str))
         .map_err(|_e| nom::Err::Error((input, ErrorKind::Verify)))
 }
 
-/// Parse a base 10 encoded u32
+/// Parse the base 10 encoded length of a string, fails if it overflows a usize
 #[inline]
-pub fn base10_length(input: &[u8]) -> IResult<&[u8], u32> {
-    map_res(
-        take_while_m_n(1, 20, is_digit),
-        map_res(parse_utf8_str, parse_base10),
-    )(input)
-    .map(|(r, (_res, v))| (r, v + 1))
+pub fn base10_length(input: &[u8]) -> IResult<&[u8], usize> {
+    map_opt(take_while_m_n(1, 20, is_digit), digits_value)(input)
 }
 
 /// Nom parse compinator to parse a bencoded string
 #[inline]
 pub fn parse_string<'a>(input: &'a [u8]) -> IResult<&'a [u8], &'a str> {
-    length_value(base10_length, preceded(tag(":"), parse_utf8_str))(input)
+    let (rest, bytes) = parse_bytes(input)?;
+    let (_, str) = parse_utf8_str(bytes)?;
+    Ok((rest, str))
 }
 
 #[cfg(test)]
 mod string_tests {
-    use super::parse_string;
+    use super::{base10_length, parse_string};
     use nom::{
         error::ErrorKind,
         Err::{Error, Incomplete},
@@ -64,6 +59,44 @@ mod string_tests {
             parse_string(b"3abcd"),
             Err(Error((b"abcd" as &_, ErrorKind::Tag)))
         );
-        assert_eq!(parse_string(b"3:ab"), Err(Incomplete(Needed::Size(4))));
+        assert_eq!(parse_string(b"3:ab"), Err(Incomplete(Needed::Size(3))));
+        assert_eq!(
+            parse_string(b"2:\xFF\xFE"),
+            Err(Error((b"\xFF\xFE" as &_, ErrorKind::Verify)))
+        );
+    }
+
+    #[test]
+    pub fn test_length() {
+        let max = u32::MAX as usize;
+
+        assert_eq!(base10_length(b"0:"), Ok((b":" as &_, 0)));
+        assert_eq!(base10_length(b"4294967295:"), Ok((b":" as &_, max)));
+        assert_eq!(base10_length(b"4294967296:"), Ok((b":" as &_, max + 1)));
+        assert_eq!(
+            base10_length(b"18446744073709551615:"),
+            Ok((b":" as &_, usize::MAX))
+        );
+        assert_eq!(
+            base10_length(b"18446744073709551616:"),
+            Err(Error((b"18446744073709551616:" as &_, ErrorKind::MapOpt)))
+        );
+        assert_eq!(
+            base10_length(b"99999999999999999999:"),
+            Err(Error((b"99999999999999999999:" as &_, ErrorKind::MapOpt)))
+        );
+
+        assert_eq!(
+            parse_string(b"4294967295:abc"),
+            Err(Incomplete(Needed::Size(max)))
+        );
+        assert_eq!(
+            parse_string(b"4294967296:abc"),
+            Err(Incomplete(Needed::Size(max + 1)))
+        );
+        assert_eq!(
+            parse_string(b"18446744073709551615:abc"),
+            Err(Incomplete(Needed::Size(usize::MAX)))
+        );
     }
 }
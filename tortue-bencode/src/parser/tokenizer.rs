@@ -0,0 +1,333 @@
+//! Parser module for bencoding
+//! Provides a pull parser yielding the tokens of bencoded values
+
+use super::{parse_bytes, parse_integer_with_options, ParserOptions};
+use crate::{
+    error::{Error, ParseError},
+    prelude::*,
+    BencodedValue,
+};
+use nom::{error::ErrorKind, Err, Needed};
+
+/// A token of a bencoded input
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// An integer
+    Int(i64),
+
+    /// An integer above `i64::MAX`, read as the default parser does
+    UInt(u64),
+
+    /// A string or binary, keys of dictionaries are also bytes
+    Bytes(&'a [u8]),
+
+    /// Start of a list, closed by a matching [`Token::End`]
+    ListStart,
+
+    /// Start of a dictionary, closed by a matching [`Token::End`]
+    DictStart,
+
+    /// End of the innermost list or dictionary
+    End,
+}
+
+/// A pull parser yielding the tokens of a sequence of bencoded values
+/// without building them
+///
+/// The tokenizer tracks the nesting of lists and dictionaries and reports
+/// structural errors (unbalanced ends, non bytes keys, dictionaries missing
+/// a value). It stops after the first error.
+#[derive(Clone, Debug)]
+pub struct Tokenizer<'a> {
+    input: &'a [u8],
+    offset: usize,
+
+    /// Open collections, `Some(entries)` counting the keys and values read so
+    /// far for dictionaries and `None` for lists
+    stack: Vec<Option<usize>>,
+    failed: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    /// Creates a tokenizer over the input
+    pub fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            offset: 0,
+            stack: Vec::new(),
+            failed: false,
+        }
+    }
+
+    /// Offset (in bytes) of the next token from the start of the input
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Number of lists and dictionaries currently open
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Checks if the next token is expected to be a dictionary key
+    pub fn expects_key(&self) -> bool {
+        matches!(self.stack.last(), Some(Some(entries)) if entries % 2 == 0)
+    }
+
+    fn error(&mut self, error: Err<(&'a [u8], ErrorKind)>) -> Error {
+        self.failed = true;
+        Error::parse(self.input, error)
+    }
+
+    fn token(&mut self) -> Result<Token<'a>, Err<(&'a [u8], ErrorKind)>> {
+        let input = &self.input[self.offset..];
+        let (rest, token) = match input[0] {
+            b'i' if !self.expects_key() => {
+                let options = ParserOptions::default();
+                match parse_integer_with_options(input, &options)? {
+                    (rest, BencodedValue::UnsignedInteger(int)) => {
+                        (rest, Token::UInt(int))
+                    }
+                    (rest, BencodedValue::Integer(int)) => {
+                        (rest, Token::Int(int))
+                    }
+                    _ => unreachable!("the integer parser only gives integers"),
+                }
+            }
+            b'0'..=b'9' => {
+                let (rest, bytes) = parse_bytes(input)?;
+                (rest, Token::Bytes(bytes))
+            }
+            b'l' if !self.expects_key() => (&input[1..], Token::ListStart),
+            b'd' if !self.expects_key() => (&input[1..], Token::DictStart),
+            b'e' => match self.stack.last() {
+                Some(None) => (&input[1..], Token::End),
+                Some(Some(_)) if self.expects_key() => {
+                    (&input[1..], Token::End)
+                }
+                _ => return Err(Err::Error((input, ErrorKind::Char))),
+            },
+            _ => return Err(Err::Error((input, ErrorKind::Char))),
+        };
+
+        if let Some(Some(entries)) = self.stack.last_mut() {
+            if token != Token::End {
+                *entries += 1;
+            }
+        }
+
+        match token {
+            Token::ListStart => self.stack.push(None),
+            Token::DictStart => self.stack.push(Some(0)),
+            Token::End => {
+                self.stack.pop();
+            }
+            Token::Int(_) | Token::UInt(_) | Token::Bytes(_) => {}
+        }
+
+        self.offset = self.input.len() - rest.len();
+        Ok(token)
+    }
+}
+
+/// Checks if the input is a truncated sequence of values, that is if it
+/// ends in the middle of a value, list or dictionary, and returns the minimum
+/// number of bytes missing
+pub(crate) fn truncation(input: &[u8]) -> Option<usize> {
+    let mut tokenizer = Tokenizer::new(input);
+    while tokenizer.offset < input.len() {
+        let start = &input[tokenizer.offset..];
+        match tokenizer.token() {
+            Ok(_) => {}
+            // Only strings are incomplete, `len` bytes follow the colon
+            Err(Err::Incomplete(Needed::Size(len))) => {
+                let colon = start.iter().position(|&b| b == b':')?;
+                let end = (colon + 1).saturating_add(len);
+                return Some(end.saturating_sub(start.len()));
+            }
+            Err(Err::Incomplete(Needed::Unknown)) => return Some(1),
+            Err(Err::Error((rest, _))) | Err(Err::Failure((rest, _))) => {
+                return if rest.is_empty() { Some(1) } else { None };
+            }
+        }
+    }
+
+    // Every open list or dictionary still needs its end
+    Some(tokenizer.depth()).filter(|&depth| depth > 0)
+}
+
+/// Locates the first syntax error of a sequence of values, returns the
+/// offset of the top-level value containing it along with the error
+///
+/// Truncated inputs have no syntax error, see [`truncation`].
+pub(crate) fn syntax_error(input: &[u8]) -> Option<(usize, ParseError)> {
+    let mut tokenizer = Tokenizer::new(input);
+    let mut value = 0;
+    while tokenizer.offset < input.len() {
+        if tokenizer.depth() == 0 {
+            value = tokenizer.offset;
+        }
+
+        let expected = match input[tokenizer.offset] {
+            _ if tokenizer.expects_key() => "a key or 'e'",
+            b'e' if matches!(tokenizer.stack.last(), Some(Some(_))) => {
+                "a value"
+            }
+            b'i' => "a valid integer",
+            b'0'..=b'9' => "a valid string",
+            _ if tokenizer.depth() > 0 => "a value or 'e'",
+            _ if tokenizer.offset > 0 => "a value or the end of the input",
+            _ => "a value",
+        };
+
+        match tokenizer.token() {
+            Ok(_) => {}
+            Err(Err::Incomplete(_)) => return None,
+            Err(error) => {
+                let error = ParseError::new(input, &error).expecting(expected);
+                return Some((value, error));
+            }
+        }
+    }
+
+    None
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<Token<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            None
+        } else if self.offset < self.input.len() {
+            Some(self.token().map_err(|e| self.error(e)))
+        } else if self.stack.is_empty() {
+            None
+        } else {
+            Some(Err(self.error(Err::Incomplete(nom::Needed::Size(1)))))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tokenizer_tests {
+    use super::{Token, Tokenizer};
+    use crate::{parser::parse_all, BencodedValue, Key};
+    use std::{borrow::Cow, collections::HashMap};
+
+    const TORRENT: &[u8] = include_bytes!("../../benches/test_data");
+
+    fn tokens(input: &[u8]) -> Vec<Token<'_>> {
+        Tokenizer::new(input).map(Result::unwrap).collect()
+    }
+
+    fn is_err(input: &[u8]) -> bool {
+        Tokenizer::new(input).any(|token| token.is_err())
+    }
+
+    /// Builds a value from tokens the same way the parser does
+    fn build<'a>(
+        tokens: &mut impl Iterator<Item = Token<'a>>,
+        token: Token<'a>,
+    ) -> BencodedValue<'a> {
+        match token {
+            Token::Int(int) => BencodedValue::Integer(int),
+            Token::UInt(int) => BencodedValue::UnsignedInteger(int),
+            Token::Bytes(bytes) => match std::str::from_utf8(bytes) {
+                Ok(str) => BencodedValue::String(Cow::Borrowed(str)),
+                Err(_) => BencodedValue::Binary(Cow::Borrowed(bytes)),
+            },
+            Token::ListStart => {
+                let mut list = Vec::new();
+                while let Some(token) =
+                    tokens.next().filter(|t| *t != Token::End)
+                {
+                    list.push(build(tokens, token));
+                }
+                BencodedValue::List(list)
+            }
+            Token::DictStart => {
+                let mut dict = HashMap::new();
+                while let Some(Token::Bytes(key)) = tokens.next() {
+                    let key = Key::borrowed(std::str::from_utf8(key).unwrap());
+                    let token = tokens.next().unwrap();
+                    dict.insert(key, build(tokens, token));
+                }
+                BencodedValue::Dictionary(dict)
+            }
+            Token::End => panic!("unexpected end"),
+        }
+    }
+
+    #[test]
+    pub fn test_tokens() {
+        assert_eq!(
+            tokens(b"d1:ali1e2:\xFF\xFEe1:bdee"),
+            vec![
+                Token::DictStart,
+                Token::Bytes(b"a"),
+                Token::ListStart,
+                Token::Int(1),
+                Token::Bytes(b"\xFF\xFE"),
+                Token::End,
+                Token::Bytes(b"b"),
+                Token::DictStart,
+                Token::End,
+                Token::End,
+            ]
+        );
+
+        assert_eq!(tokens(b"i1e0:"), vec![Token::Int(1), Token::Bytes(b"")]);
+        assert_eq!(
+            tokens(b"i9223372036854775808ei18446744073709551615e"),
+            vec![Token::UInt(1 << 63), Token::UInt(u64::MAX)]
+        );
+        assert_eq!(
+            tokens(b"i-9223372036854775808e"),
+            vec![Token::Int(i64::MIN)]
+        );
+        assert!(tokens(b"").is_empty());
+
+        for input in &[
+            &b"e"[..],
+            b"l",
+            b"li1e",
+            b"di1ei2ee",
+            b"d1:ae",
+            b"x",
+            b"i18446744073709551616e",
+            b"i-9223372036854775809e",
+        ] {
+            assert!(is_err(input), "{:?}", input);
+        }
+
+        let mut tokenizer = Tokenizer::new(b"ll3:abcee");
+        tokenizer.by_ref().take(3).for_each(drop);
+        assert_eq!(tokenizer.offset(), 7);
+        assert_eq!(tokenizer.depth(), 2);
+    }
+
+    #[test]
+    pub fn test_torrent() {
+        let mut tokens = Tokenizer::new(TORRENT).map(Result::unwrap);
+        let first = tokens.next().unwrap();
+        let value = build(&mut tokens, first);
+
+        assert!(tokens.next().is_none());
+        assert_eq!(value, parse_all(TORRENT).unwrap().1);
+
+        // Scans for the pieces without building the dictionaries
+        let mut tokenizer = Tokenizer::new(TORRENT);
+        let pieces = loop {
+            let key = tokenizer.expects_key();
+            match tokenizer.next().unwrap().unwrap() {
+                Token::Bytes(b"pieces") if key => {
+                    break tokenizer.next().unwrap().unwrap();
+                }
+                _ => continue,
+            }
+        };
+
+        assert!(matches!(pieces, Token::Bytes(bytes) if bytes.len() % 20 == 0));
+    }
+}
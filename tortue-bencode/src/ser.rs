@@ -1,52 +1,394 @@
 use crate::{
     error::{Error, Result},
-    writer, BencodedValue,
+    io::Write,
+    prelude::*,
+    BencodedValue, HashMap, Key,
 };
 use compound::Compound;
-use serde::{ser, Serialize};
-use std::{
-    io::{self, Write},
-    marker::PhantomData,
-    mem::size_of,
-};
+use core::{cell::Cell, convert::TryFrom, marker::PhantomData};
+use serde::{ser, ser::SerializeMap, Serialize};
+use size::SizeSerializer;
+use stream::{Output, StreamSerializer};
+
+#[cfg(any(feature = "std", feature = "tokio"))]
+use crate::writer;
 
 mod compound;
+mod size;
+mod stream;
+
+/// Rounds a float to the nearest int, halfway cases away from zero
+#[cfg(feature = "std")]
+fn round(v: f64) -> i64 {
+    v.round() as i64
+}
+
+/// Rounds a float to the nearest int, halfway cases away from zero
+#[cfg(not(feature = "std"))]
+fn round(v: f64) -> i64 {
+    let int = v as i64;
+    let fract = v - int as f64;
+    if fract >= 0.5 {
+        int.saturating_add(1)
+    } else if fract <= -0.5 {
+        int.saturating_sub(1)
+    } else {
+        int
+    }
+}
+
+/// Converts a serialized map key to a dictionary key, integers are written
+/// in their decimal form as bencode only has string keys
+fn map_key(key: BencodedValue) -> Result<Key> {
+    match key {
+        BencodedValue::String(key) => Ok(Key::from(key)),
+        BencodedValue::Integer(key) => Ok(Key::from(key.to_string())),
+        BencodedValue::UnsignedInteger(key) => Ok(Key::from(key.to_string())),
+        key => Err(Error::unexpected("string or integer key", &key)),
+    }
+}
+
+/// Key holding the variant name of adjacently tagged enums
+pub const TAG_KEY: &str = "t";
+
+/// Key holding the content of adjacently tagged enums
+pub const CONTENT_KEY: &str = "c";
+
+/// How enum variants holding data are represented, unit variants are always
+/// the plain variant name
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EnumRepresentation {
+    /// `d7:Variant<content>e`, a dictionary with the variant name as only key
+    #[default]
+    External,
+
+    /// `d1:c<content>1:t7:Variante`, see [`TAG_KEY`] and [`CONTENT_KEY`]
+    Adjacent,
+
+    /// The content alone, it can only be deserialized by an enum marked with
+    /// `#[serde(untagged)]`
+    Untagged,
+}
+
+/// Empty value written for `()` and unit structs, the deserializer reads any
+/// of them back as a unit
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UnitRepresentation {
+    /// `0:`, as the values of the dictionaries used as sets
+    #[default]
+    EmptyString,
+
+    /// `i0e`
+    Zero,
+
+    /// `de`
+    EmptyDictionary,
+}
+
+/// Order of the keys of the dictionaries written by the serializer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// Sorted by their raw bytes as bencode requires, the last value of a
+    /// duplicated key is kept
+    #[default]
+    SortedBytewise,
+
+    /// In the order the fields are declared or the entries are given, to
+    /// reproduce an existing encoding byte-for-byte, duplicated keys are
+    /// rejected
+    Declaration,
+}
+
+/// Settings of the serializer, see [`SerializerBuilder`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SerializerOptions {
+    /// How enum variants holding data are represented
+    pub enums: EnumRepresentation,
+
+    /// Rejects the values without an exact bencode representation instead of
+    /// converting them: floats are rounded, bools become `0` or `1`, chars
+    /// become strings, units become empty values and integers above
+    /// `i64::MAX` cannot be read by the implementations limited to 64-bit
+    /// signed integers
+    pub deny_lossy: bool,
+
+    /// Empty value written for units
+    pub units: UnitRepresentation,
+
+    /// Order of the dictionary keys when writing bytes, a [`BencodedValue`]
+    /// does not keep the order of its dictionaries
+    pub key_order: KeyOrder,
+
+    /// Writes the canonical form as [`to_bytes_canonical`] does, the key
+    /// order is ignored
+    pub canonical: bool,
+
+    /// Omits the dictionary entries holding a none, such as `Option::None`
+    /// fields, instead of rejecting them
+    pub skip_none: bool,
+}
+
+impl Default for SerializerOptions {
+    fn default() -> Self {
+        SerializerOptions {
+            enums: EnumRepresentation::default(),
+            deny_lossy: false,
+            units: UnitRepresentation::default(),
+            key_order: KeyOrder::default(),
+            canonical: false,
+            skip_none: true,
+        }
+    }
+}
 
-#[derive(Default)]
-pub(crate) struct Serializer<'se>(PhantomData<BencodedValue<'se>>);
+impl SerializerOptions {
+    /// Checks that a value of the given type can be converted
+    fn lossy(&self, name: &'static str) -> Result<()> {
+        if self.deny_lossy {
+            Err(Error::UnsupportedType(name))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The settings bytes are written with, the canonical form is sorted
+    /// whatever the key order
+    fn writing(self) -> Self {
+        if self.canonical {
+            SerializerOptions {
+                key_order: KeyOrder::SortedBytewise,
+                ..self
+            }
+        } else {
+            self
+        }
+    }
+}
+
+/// Serializes data structures using non default settings
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SerializerBuilder {
+    options: SerializerOptions,
+}
+
+impl SerializerBuilder {
+    /// Creates a builder with the default settings of [`to_bytes`]
+    pub fn new() -> Self {
+        SerializerBuilder::default()
+    }
+
+    /// Creates a builder with the given settings
+    pub fn with_options(options: SerializerOptions) -> Self {
+        SerializerBuilder { options }
+    }
+
+    /// Sets how enum variants are represented, externally tagged by default
+    pub fn enum_representation(mut self, enums: EnumRepresentation) -> Self {
+        self.options.enums = enums;
+        self
+    }
+
+    /// Sets whether lossy conversions are rejected, see
+    /// [`SerializerOptions::deny_lossy`]
+    pub fn deny_lossy(mut self, deny: bool) -> Self {
+        self.options.deny_lossy = deny;
+        self
+    }
+
+    /// Sets the empty value written for units, an empty string by default
+    pub fn unit_representation(mut self, units: UnitRepresentation) -> Self {
+        self.options.units = units;
+        self
+    }
+
+    /// Sets the order of the dictionary keys, sorted by default
+    pub fn key_order(mut self, order: KeyOrder) -> Self {
+        self.options.key_order = order;
+        self
+    }
+
+    /// Sets whether the canonical form is written, see
+    /// [`SerializerOptions::canonical`]
+    pub fn canonical(mut self, canonical: bool) -> Self {
+        self.options.canonical = canonical;
+        self
+    }
+
+    /// Sets whether the dictionary entries holding a none are omitted,
+    /// they are by default
+    pub fn skip_none(mut self, skip: bool) -> Self {
+        self.options.skip_none = skip;
+        self
+    }
+
+    /// Same as [`to_bytes`] using the settings of the builder
+    pub fn to_bytes<T>(&self, value: &T) -> Result<Vec<u8>>
+    where
+        T: Serialize,
+    {
+        let options = self.options.writing();
+
+        // Measuring first allocates the output once instead of growing it
+        let slack = Cell::new(0);
+        let len = value.serialize(SizeSerializer::new(options, &slack))?;
+        let mut output = Output::buffered(options, len + slack.get());
+        value.serialize(StreamSerializer::new(&mut output))?;
+        Ok(output.into_buffer())
+    }
+
+    /// Same as [`to_writer`] using the settings of the builder
+    pub fn to_writer<T, W>(&self, value: &T, writer: &mut W) -> Result<usize>
+    where
+        T: Serialize,
+        W: Write,
+    {
+        let mut output = Output::new(writer, self.options.writing());
+        value.serialize(StreamSerializer::new(&mut output))?;
+        Ok(output.written())
+    }
+
+    /// Same as [`to_value`] using the settings of the builder
+    pub fn to_value<'a, T>(&self, value: &'a T) -> Result<BencodedValue<'a>>
+    where
+        T: Serialize,
+    {
+        value.serialize(Serializer::new(self.options))
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Serializer<'se> {
+    options: SerializerOptions,
+    marker: PhantomData<BencodedValue<'se>>,
+}
+
+impl<'se> Serializer<'se> {
+    pub fn new(options: SerializerOptions) -> Self {
+        Serializer {
+            options,
+            marker: PhantomData,
+        }
+    }
+
+    /// Tags the content of an enum variant according to the representation
+    pub fn tag(
+        self,
+        variant: &'static str,
+        content: BencodedValue<'se>,
+    ) -> BencodedValue<'se> {
+        let mut dict = HashMap::with_capacity(2);
+        match self.options.enums {
+            EnumRepresentation::External => {
+                dict.insert(Key::borrowed(variant), content);
+            }
+            EnumRepresentation::Adjacent => {
+                dict.insert(
+                    Key::borrowed(TAG_KEY),
+                    BencodedValue::from(variant),
+                );
+                dict.insert(Key::borrowed(CONTENT_KEY), content);
+            }
+            EnumRepresentation::Untagged => return content,
+        }
+
+        BencodedValue::Dictionary(dict)
+    }
+}
 
 /// Serializes a data structure into a byte vec
-pub fn to_bytes<T>(value: &T) -> std::result::Result<Vec<u8>, io::Error>
+///
+/// The encoded length is computed first so that the vec is allocated once.
+pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    SerializerBuilder::new().to_bytes(value)
+}
+
+/// Serializes a data structure into a byte vec, failing on the values that
+/// cannot be represented exactly, see [`SerializerOptions::deny_lossy`]
+pub fn to_bytes_strict<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    SerializerBuilder::new().deny_lossy(true).to_bytes(value)
+}
+
+/// Serializes a data structure into a writer, returns the number of bytes
+/// written
+///
+/// The value is written as it is serialized, only the content of the
+/// dictionaries is buffered to sort their keys.
+pub fn to_writer<T, W>(value: &T, writer: &mut W) -> Result<usize>
+where
+    T: Serialize,
+    W: Write,
+{
+    SerializerBuilder::new().to_writer(value, writer)
+}
+
+/// Serializes a data structure into a byte vec in canonical form, suitable
+/// for hashing such as computing the info-hash of a torrent
+///
+/// Dictionaries are sorted by their raw key bytes at every level and raw
+/// values are decoded and written again in that form, like every other value
+/// they are streamed to the output.
+pub fn to_bytes_canonical<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    SerializerBuilder::new().canonical(true).to_bytes(value)
+}
+
+/// Same as [`to_bytes_canonical`] into a writer, returns the number of bytes
+/// written
+pub fn to_writer_canonical<T, W>(value: &T, writer: &mut W) -> Result<usize>
 where
     T: Serialize,
+    W: Write,
 {
-    let mut out = Vec::with_capacity(size_of::<T>());
-    to_writer(value, &mut out)?;
-    Ok(out)
+    SerializerBuilder::new()
+        .canonical(true)
+        .to_writer(value, writer)
 }
 
-/// Serializes a data structure into a writer
-pub fn to_writer<T, W>(
-    value: &T,
-    writer: &mut W,
-) -> std::result::Result<(), io::Error>
+/// Same as [`to_writer`] coalescing small writes, see
+/// [`writer::write_buffered`]
+#[cfg(feature = "std")]
+pub fn to_writer_buffered<T, W>(value: &T, writer: &mut W) -> Result<usize>
 where
     T: Serialize,
     W: Write,
 {
-    writer::write(&to_value(value)?, writer)
+    let mut buffered =
+        std::io::BufWriter::with_capacity(writer::BUFFER_LEN, writer);
+    let written = to_writer(value, &mut buffered)?;
+    buffered.flush()?;
+    Ok(written)
+}
+
+/// Serializes a data structure into an async writer, returns the number of
+/// bytes written
+#[cfg(feature = "tokio")]
+pub async fn to_async_writer<T, W>(value: &T, writer: &mut W) -> Result<usize>
+where
+    T: Serialize,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    Ok(writer::write_async(&to_value(value)?, writer).await?)
 }
 
 /// Serializes a data structure into a BencodedValue
-pub fn to_value<T>(
-    value: &'_ T,
-) -> std::result::Result<BencodedValue<'_>, io::Error>
+///
+/// Every string and binary is copied: serde hands them to the serializer
+/// with a lifetime limited to the call, even when the data outlives `value`.
+/// Use [`BencodedValue::to_borrowed`] to borrow from an existing value and
+/// [`to_writer`] to write a data structure without building the value.
+pub fn to_value<T>(value: &'_ T) -> Result<BencodedValue<'_>>
 where
     T: Serialize,
 {
-    value
-        .serialize(Serializer::default())
-        .map_err(Into::<io::Error>::into)
+    SerializerBuilder::new().to_value(value)
 }
 
 impl<'serializer> ser::Serializer for Serializer<'serializer> {
@@ -65,19 +407,16 @@ impl<'serializer> ser::Serializer for Serializer<'serializer> {
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok> {
-        if cfg!(test) {
-            eprintln!("[bencode] casting char to string of length 1");
-        }
-
-        self.serialize_str(&format!("{}", v))
+        self.options.lossy("char")?;
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok> {
-        Ok(BencodedValue::StringOwned(v.to_owned()))
+        Ok(BencodedValue::String(Cow::Owned(v.to_owned())))
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
-        Ok(BencodedValue::BinaryOwned(v.to_vec()))
+        Ok(BencodedValue::Binary(Cow::Owned(v.to_vec())))
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
@@ -85,12 +424,7 @@ impl<'serializer> ser::Serializer for Serializer<'serializer> {
     }
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
-        if cfg!(test) {
-            eprintln!(
-                "[bencode] casting boolean to int (true => 1, false => 0)"
-            );
-        }
-
+        self.options.lossy("bool")?;
         self.serialize_i64(if v { 1 } else { 0 })
     }
 
@@ -119,23 +453,40 @@ impl<'serializer> ser::Serializer for Serializer<'serializer> {
     }
 
     fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
-        self.serialize_i64(v as i64)
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            Err(_) if self.options.deny_lossy => {
+                Err(Error::out_of_range(v, "i64"))
+            }
+            Err(_) => Ok(BencodedValue::UnsignedInteger(v)),
+        }
     }
 
-    fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
-        if cfg!(test) {
-            eprintln!("[bencode] rounding f32 to nearest int");
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok> {
+        match u128::try_from(v) {
+            Ok(v) => self.serialize_u128(v),
+            Err(_) => match i64::try_from(v) {
+                Ok(v) => self.serialize_i64(v),
+                Err(_) => Err(Error::out_of_range(v, "i64")),
+            },
         }
-
-        self.serialize_i64(v.round() as i64)
     }
 
-    fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
-        if cfg!(test) {
-            eprintln!("[bencode] rounding f64 to nearest int");
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok> {
+        match u64::try_from(v) {
+            Ok(v) => self.serialize_u64(v),
+            Err(_) => Err(Error::out_of_range(v, "u64")),
         }
+    }
 
-        self.serialize_i64(v.round() as i64)
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
+        self.options.lossy("f32")?;
+        self.serialize_i64(round(f64::from(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
+        self.options.lossy("f64")?;
+        self.serialize_i64(round(v))
     }
 
     fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok>
@@ -145,11 +496,14 @@ impl<'serializer> ser::Serializer for Serializer<'serializer> {
         value.serialize(self)
     }
     fn serialize_unit(self) -> Result<Self::Ok> {
-        if cfg!(test) {
-            eprintln!("[bencode] unit cannot be serialize");
+        self.options.lossy("unit")?;
+        match self.options.units {
+            UnitRepresentation::EmptyString => self.serialize_str(""),
+            UnitRepresentation::Zero => self.serialize_i64(0),
+            UnitRepresentation::EmptyDictionary => {
+                self.serialize_map(Some(0))?.end()
+            }
         }
-
-        Err(Error::Message("cannot serialize units".to_owned()))
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
@@ -186,17 +540,15 @@ impl<'serializer> ser::Serializer for Serializer<'serializer> {
     where
         T: Serialize,
     {
-        Ok(BencodedValue::Dictionary(maplit::hashmap! {
-            variant => value.serialize(self)?
-        }))
+        Ok(self.tag(variant, value.serialize(self)?))
     }
 
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
-        Ok(Compound::new_array(len))
+        Ok(Compound::new_array(self, len))
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
-        Ok(Compound::new_array(Some(len)))
+        Ok(Compound::new_array(self, Some(len)))
     }
 
     fn serialize_tuple_struct(
@@ -204,21 +556,21 @@ impl<'serializer> ser::Serializer for Serializer<'serializer> {
         _name: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        Ok(Compound::new_map(Some(len)))
+        Ok(Compound::new_array(self, Some(len)))
     }
 
     fn serialize_tuple_variant(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        Ok(Compound::new_map(Some(0)))
+        Ok(Compound::new_array(self, Some(len)).variant(variant))
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
-        Ok(Compound::new_map(len))
+        Ok(Compound::new_map(self, len))
     }
 
     fn serialize_struct(
@@ -226,26 +578,31 @@ impl<'serializer> ser::Serializer for Serializer<'serializer> {
         _name: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStruct> {
-        Ok(Compound::new_map(Some(len)))
+        Ok(Compound::new_map(self, Some(len)))
     }
 
     fn serialize_struct_variant(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        Ok(Compound::new_map(Some(0)))
+        Ok(Compound::new_map(self, Some(len)).variant(variant))
     }
 }
 
 #[cfg(test)]
 mod serialize_tests {
-    use super::{to_bytes, to_value};
-    use crate::BencodedValue;
+    use super::{
+        to_bytes, to_bytes_canonical, to_bytes_strict, to_value, to_writer,
+        to_writer_canonical, EnumRepresentation, KeyOrder, SerializerBuilder,
+        SerializerOptions, SizeSerializer,
+    };
+    use crate::{error::Error, from_bytes, parse_all, writer, BencodedValue};
+    use core::cell::Cell;
     use maplit::hashmap;
-    use serde::Serialize;
+    use serde::{Deserialize, Serialize, Serializer};
 
     #[derive(Serialize)]
     struct TestStruct {
@@ -259,7 +616,7 @@ mod serialize_tests {
         let hello_world = "Hello, world!";
         assert_eq!(
             to_value(&hello_world).unwrap(),
-            BencodedValue::StringOwned(hello_world.to_owned())
+            BencodedValue::from(hello_world.to_owned())
         );
 
         let bytes = to_bytes(&hello_world).unwrap();
@@ -282,7 +639,7 @@ mod serialize_tests {
             to_value(&list).unwrap(),
             BencodedValue::List(
                 list.iter()
-                    .map(|v| BencodedValue::StringOwned((*v).to_owned()))
+                    .map(|v| BencodedValue::from((*v).to_owned()))
                     .collect()
             )
         );
@@ -302,14 +659,330 @@ mod serialize_tests {
 
         assert_eq!(
             to_value(&map).unwrap(),
-            BencodedValue::DictionaryOwned(
+            BencodedValue::Dictionary(
                 map.iter()
-                    .map(|(k, v)| ((*k).to_owned(), BencodedValue::Integer(*v)))
+                    .map(|(k, v)| ((*k).into(), BencodedValue::Integer(*v)))
                     .collect()
             )
         );
     }
 
+    #[test]
+    fn test_canonical() {
+        let value = TestStruct {
+            name: "Tom".to_owned(),
+            age: 24,
+            friends: vec!["David".to_owned()],
+        };
+        let nested = hashmap! {
+            "z" => hashmap! { "b" => 1, "a" => 2 },
+            "y" => hashmap! { "d" => 3, "c" => 4 },
+        };
+
+        let bytes = to_bytes_canonical(&value).unwrap();
+        assert_eq!(bytes, b"d3:agei24e7:friendsl5:Davide4:name3:Tome");
+        for _ in 0..100 {
+            assert_eq!(to_bytes_canonical(&value).unwrap(), bytes);
+        }
+
+        let mut written = Vec::new();
+        assert_eq!(to_writer_canonical(&nested, &mut written), Ok(36));
+        assert_eq!(written, b"d1:yd1:ci4e1:di3ee1:zd1:ai2e1:bi1eee");
+    }
+
+    #[test]
+    fn test_canonical_raw() {
+        // Raw values are written again sorted
+        let raw = BencodedValue::Raw(b"d1:bi1e1:ai2ee"[..].into());
+        assert_eq!(to_bytes_canonical(&raw).unwrap(), b"d1:ai2e1:bi1ee");
+
+        let mut written = Vec::new();
+        assert_eq!(to_writer_canonical(&vec![raw], &mut written), Ok(16));
+        assert_eq!(written, b"ld1:ai2e1:bi1eee");
+    }
+
+    #[test]
+    fn test_nested_none() {
+        let list = vec![Some(1), None];
+        let dict = hashmap! { "a" => None::<i64> };
+        let keep_none = SerializerBuilder::new().skip_none(false);
+
+        // Every way of writing bytes reports the same error
+        assert_eq!(to_bytes(&list), Err(Error::NestedNone));
+        assert_eq!(to_writer(&list, &mut Vec::new()), Err(Error::NestedNone));
+        assert_eq!(to_bytes_canonical(&list), Err(Error::NestedNone));
+        assert_eq!(
+            to_writer_canonical(&list, &mut Vec::new()),
+            Err(Error::NestedNone)
+        );
+        assert_eq!(to_value(&list).unwrap().to_vec(), Err(Error::NestedNone));
+        assert_eq!(keep_none.to_bytes(&dict), Err(Error::NestedNone));
+        assert_eq!(
+            keep_none.canonical(true).to_bytes(&dict),
+            Err(Error::NestedNone)
+        );
+        assert_eq!(keep_none.to_value(&dict), Err(Error::NestedNone));
+    }
+
+    #[test]
+    fn test_streaming() {
+        #[derive(Serialize)]
+        enum Variant {
+            Unit,
+            Newtype(u64),
+            Struct { b: i8, a: bool },
+        }
+
+        /// Map with duplicated and unsorted keys
+        struct Entries(Vec<(&'static str, i64)>);
+
+        impl Serialize for Entries {
+            fn serialize<S: Serializer>(
+                &self,
+                s: S,
+            ) -> Result<S::Ok, S::Error> {
+                s.collect_map(self.0.iter().cloned())
+            }
+        }
+
+        fn assert_same<T: Serialize>(value: &T) {
+            let mut expected = Vec::new();
+            writer::write(&to_value(value).unwrap(), &mut expected).unwrap();
+
+            let mut written = Vec::new();
+            assert_eq!(to_writer(value, &mut written).unwrap(), expected.len());
+            assert_eq!(written, expected);
+            assert_eq!(to_bytes(value).unwrap(), expected);
+        }
+
+        assert_same(&TestStruct {
+            name: "Tom".to_owned(),
+            age: 24,
+            friends: vec!["David".to_owned()],
+        });
+        assert_same(&vec![
+            hashmap! { "z" => vec![hashmap! { "b" => 1, "a" => 2 }] },
+            hashmap! { "y" => vec![], "x" => vec![hashmap! {}] },
+        ]);
+        assert_same(&Entries(vec![("b", 1), ("a", 2), ("b", 3), ("c", 4)]));
+        assert_same(&Entries(vec![("a", 1), ("a", 2), ("b", 3)]));
+        assert_same(&hashmap! {
+            "unit" => Variant::Unit,
+            "newtype" => Variant::Newtype(u64::MAX),
+            "struct" => Variant::Struct { b: -1, a: true },
+        });
+        assert_same(&(1.5f32, 'c', Some("some")));
+
+        assert_eq!(to_bytes(&None::<i64>).unwrap(), b"");
+        assert!(to_bytes(&vec![None::<i64>]).is_err());
+        assert_eq!(to_bytes(&hashmap! { "a" => None::<i64> }).unwrap(), b"de");
+        assert_eq!(to_bytes(&hashmap! { 1 => 2 }).unwrap(), b"d1:1i2ee");
+        assert!(to_bytes(&hashmap! { (1, 2) => 2 }).is_err());
+    }
+
+    #[test]
+    fn test_size() {
+        #[derive(Serialize)]
+        enum Variant {
+            Unit,
+            Newtype(Option<i64>),
+            Tuple(i64, String),
+            Struct { b: i8, a: Option<char> },
+        }
+
+        fn assert_size<T: Serialize>(value: &T) {
+            for &enums in &[
+                EnumRepresentation::External,
+                EnumRepresentation::Adjacent,
+                EnumRepresentation::Untagged,
+            ] {
+                let options = SerializerOptions {
+                    enums,
+                    ..SerializerOptions::default()
+                };
+                let builder = SerializerBuilder::with_options(options);
+                let bytes = builder.to_bytes(value).unwrap();
+                let slack = Cell::new(0);
+                let size =
+                    value.serialize(SizeSerializer::new(options, &slack));
+                assert_eq!(size, Ok(bytes.len()));
+                assert_eq!(bytes.capacity(), bytes.len() + slack.get());
+            }
+        }
+
+        assert_size(&TestStruct {
+            name: "Tom".to_owned(),
+            age: -24,
+            friends: vec!["David".to_owned(), "Donald".to_owned()],
+        });
+        assert_size(&hashmap! {
+            "unit" => Variant::Unit,
+            "newtype" => Variant::Newtype(Some(3)),
+            "tuple" => Variant::Tuple(1, "one".to_owned()),
+            "struct" => Variant::Struct { b: -1, a: None },
+        });
+        assert_size(&hashmap! { 10 => u64::MAX, 200 => 0 });
+        assert_size(&(1.5f32, 'é', true, Some("some")));
+        assert_size(&vec![vec![0u8; 1000]; 3]);
+    }
+
+    #[test]
+    fn test_key_order() {
+        #[derive(Serialize)]
+        struct Torrent {
+            info: Info,
+            announce: &'static str,
+            comment: Option<&'static str>,
+        }
+
+        #[derive(Serialize)]
+        struct Info {
+            pieces: &'static str,
+            name: &'static str,
+            length: i64,
+        }
+
+        /// Map given the same key twice
+        struct Duplicated;
+
+        impl Serialize for Duplicated {
+            fn serialize<S: Serializer>(
+                &self,
+                s: S,
+            ) -> Result<S::Ok, S::Error> {
+                s.collect_map(vec![("b", 1), ("a", 2), ("b", 3)])
+            }
+        }
+
+        let torrent = Torrent {
+            info: Info {
+                pieces: "abcd",
+                name: "file",
+                length: 5,
+            },
+            announce: "url",
+            comment: None,
+        };
+        let declaration =
+            SerializerBuilder::new().key_order(KeyOrder::Declaration);
+
+        assert_eq!(
+            declaration.to_bytes(&torrent).unwrap(),
+            &b"d4:infod6:pieces4:abcd4:name4:file6:lengthi5ee8:announce3:urle"
+                [..]
+        );
+        assert_eq!(
+            to_bytes(&torrent).unwrap(),
+            &b"d8:announce3:url4:infod6:lengthi5e4:name4:file6:pieces4:abcdee"
+                [..]
+        );
+        assert_eq!(
+            to_bytes_canonical(&torrent).unwrap(),
+            to_bytes(&torrent).unwrap()
+        );
+
+        let duplicate = Error::DuplicateKey("b".to_owned());
+        assert_eq!(declaration.to_bytes(&Duplicated), Err(duplicate.clone()));
+        assert_eq!(declaration.to_value(&Duplicated), Err(duplicate));
+        assert_eq!(to_bytes(&Duplicated).unwrap(), b"d1:ai2e1:bi3ee");
+    }
+
+    #[test]
+    fn test_skip_none() {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Torrent {
+            announce: String,
+            comment: Option<String>,
+            #[serde(rename = "creation date")]
+            creation_date: Option<i64>,
+            #[serde(rename = "created by")]
+            created_by: Option<String>,
+            private: Option<i64>,
+        }
+
+        let torrent = Torrent {
+            announce: "udp://tracker:80".to_owned(),
+            comment: None,
+            creation_date: Some(1_600_000_000),
+            created_by: None,
+            private: None,
+        };
+        let expected =
+            b"d8:announce16:udp://tracker:8013:creation datei1600000000ee";
+
+        let bytes = to_bytes(&torrent).unwrap();
+        assert_eq!(bytes, &expected[..]);
+        let parsed = parse_all(&bytes).unwrap().1;
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(to_value(&torrent).unwrap(), parsed);
+        assert_eq!(from_bytes::<Torrent>(&bytes), Ok(torrent));
+
+        let entries = hashmap! { "a" => Some(1), "b" => None, "c" => Some(3) };
+        assert_eq!(to_bytes(&entries).unwrap(), b"d1:ai1e1:ci3ee");
+        assert_eq!(
+            to_value(&entries).unwrap(),
+            parse_all(b"d1:ai1e1:ci3ee").unwrap().1
+        );
+
+        // None elements of a list have no representation
+        assert!(to_bytes(&vec![Some(1), None]).is_err());
+    }
+
+    #[test]
+    fn test_strict() {
+        #[derive(Serialize)]
+        struct Peer {
+            port: u16,
+            seed: bool,
+        }
+
+        assert_eq!(
+            to_bytes_strict(&1.5f32),
+            Err(Error::UnsupportedType("f32"))
+        );
+        assert_eq!(
+            to_bytes_strict(&1.5f64),
+            Err(Error::UnsupportedType("f64"))
+        );
+        assert_eq!(to_bytes_strict(&true), Err(Error::UnsupportedType("bool")));
+        assert_eq!(to_bytes_strict(&'c'), Err(Error::UnsupportedType("char")));
+        assert_eq!(
+            to_bytes_strict(&u64::MAX),
+            Err(Error::out_of_range(u64::MAX, "i64"))
+        );
+
+        let peer = Peer {
+            port: 6881,
+            seed: false,
+        };
+        let strict = SerializerBuilder::new().deny_lossy(true);
+        assert_eq!(strict.to_bytes(&peer), Err(Error::UnsupportedType("bool")));
+        assert_eq!(strict.to_value(&peer), Err(Error::UnsupportedType("bool")));
+        assert_eq!(
+            strict.to_value(&vec![1.5f64]),
+            Err(Error::UnsupportedType("f64"))
+        );
+        assert_eq!(
+            to_bytes_strict(&(i64::MAX as u64, "c")),
+            Ok(b"li9223372036854775807e1:ce".to_vec())
+        );
+
+        // Lenient mode converts as before
+        assert_eq!(to_bytes(&1.5f32).unwrap(), b"i2e");
+        assert_eq!(to_bytes(&-1.5f64).unwrap(), b"i-2e");
+        assert_eq!(to_bytes(&true).unwrap(), b"i1e");
+        assert_eq!(to_bytes(&'c').unwrap(), b"1:c");
+        assert_eq!(to_bytes(&u64::MAX).unwrap(), b"i18446744073709551615e");
+        assert_eq!(to_bytes(&peer).unwrap(), b"d4:porti6881e4:seedi0ee");
+        assert_eq!(
+            to_value(&peer).unwrap(),
+            BencodedValue::from(hashmap! {
+                "port".into() => BencodedValue::Integer(6881),
+                "seed".into() => BencodedValue::Integer(0),
+            })
+        );
+    }
+
     #[test]
     fn test_struct() {
         let value = TestStruct {
@@ -325,22 +998,22 @@ mod serialize_tests {
         if let Ok(value) = to_value(&value) {
             assert_eq!(
                 value,
-                BencodedValue::DictionaryOwned(hashmap! {
-                    "age".to_owned() => BencodedValue::Integer(
+                BencodedValue::Dictionary(hashmap! {
+                    "age".into() => BencodedValue::Integer(
                         24,
                     ),
-                    "name".to_owned() => BencodedValue::StringOwned(
+                    "name".into() => BencodedValue::from(
                         "Tom".to_owned(),
                     ),
-                    "friends".to_owned() => BencodedValue::List(
+                    "friends".into() => BencodedValue::List(
                         vec![
-                            BencodedValue::StringOwned(
+                            BencodedValue::from(
                                 "David".to_owned(),
                             ),
-                            BencodedValue::StringOwned(
+                            BencodedValue::from(
                                 "Donald".to_owned(),
                             ),
-                            BencodedValue::StringOwned(
+                            BencodedValue::from(
                                 "Barrack".to_owned(),
                             ),
                         ],
@@ -1,14 +1,23 @@
-use super::Serializer;
-use crate::{error::Error, BencodedValue};
+use super::{map_key, KeyOrder, Serializer};
+use crate::{
+    cautious_capacity, error::Error, prelude::*, BencodedValue, HashMap, Key,
+};
 use serde::ser::{
     SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
     SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
 };
-use std::collections::HashMap;
-pub(crate) enum Compound<'se> {
+pub(crate) struct Compound<'se> {
+    serializer: Serializer<'se>,
+
+    /// Variant tagging the compound once it ends, if any
+    variant: Option<&'static str>,
+    values: Values<'se>,
+}
+
+enum Values<'se> {
     Map {
-        current_key: Option<String>,
-        values: HashMap<String, BencodedValue<'se>>,
+        current_key: Option<Key<'se>>,
+        values: HashMap<Key<'se>, BencodedValue<'se>>,
     },
     Array {
         values: Vec<BencodedValue<'se>>,
@@ -16,26 +25,76 @@ pub(crate) enum Compound<'se> {
 }
 
 impl<'serializer> Compound<'serializer> {
-    pub fn new_array(capacity_hint: Option<usize>) -> Self {
-        Compound::Array {
-            values: if let Some(hint) = capacity_hint {
-                Vec::with_capacity(hint)
-            } else {
-                Vec::new()
+    pub fn new_array(
+        serializer: Serializer<'serializer>,
+        capacity_hint: Option<usize>,
+    ) -> Self {
+        Compound {
+            serializer,
+            variant: None,
+            values: Values::Array {
+                values: Vec::with_capacity(cautious_capacity(capacity_hint)),
             },
         }
     }
 
-    pub fn new_map(capacity_hint: Option<usize>) -> Self {
-        Compound::Map {
-            current_key: None,
-            values: if let Some(hint) = capacity_hint {
-                HashMap::with_capacity(hint)
-            } else {
-                HashMap::new()
+    pub fn new_map(
+        serializer: Serializer<'serializer>,
+        capacity_hint: Option<usize>,
+    ) -> Self {
+        Compound {
+            serializer,
+            variant: None,
+            values: Values::Map {
+                current_key: None,
+                values: HashMap::with_capacity(cautious_capacity(
+                    capacity_hint,
+                )),
             },
         }
     }
+
+    /// Tags the compound with an enum variant once it ends
+    pub fn variant(mut self, variant: &'static str) -> Self {
+        self.variant = Some(variant);
+        self
+    }
+
+    /// Inserts an entry, absent optional values are omitted unless rejected
+    /// and duplicated keys are rejected when written in declaration order
+    fn insert(
+        &mut self,
+        key: Key<'serializer>,
+        value: BencodedValue<'serializer>,
+    ) -> Result<(), Error> {
+        let declaration =
+            self.serializer.options.key_order == KeyOrder::Declaration;
+        match &mut self.values {
+            Values::Map { values, .. } => {
+                if declaration && values.contains_key(&key) {
+                    return Err(Error::DuplicateKey(key.as_str().to_owned()));
+                }
+                if !value.is_none() {
+                    values.insert(key, value);
+                } else if !self.serializer.options.skip_none {
+                    return Err(Error::NestedNone);
+                }
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    fn end_value(
+        self,
+        content: BencodedValue<'serializer>,
+    ) -> BencodedValue<'serializer> {
+        match self.variant {
+            Some(variant) => self.serializer.tag(variant, content),
+            None => content,
+        }
+    }
 }
 
 impl<'serializer> SerializeSeq for Compound<'serializer> {
@@ -48,9 +107,9 @@ impl<'serializer> SerializeSeq for Compound<'serializer> {
     where
         T: serde::Serialize,
     {
-        match self {
-            Compound::Array { values, .. } => {
-                values.push(value.serialize(Serializer::default())?);
+        match &mut self.values {
+            Values::Array { values, .. } => {
+                values.push(value.serialize(self.serializer)?);
             }
             _ => unreachable!(),
         }
@@ -58,9 +117,12 @@ impl<'serializer> SerializeSeq for Compound<'serializer> {
         Ok(())
     }
 
-    fn end(self) -> Result<Self::Ok, Self::Error> {
-        match self {
-            Compound::Array { values, .. } => Ok(BencodedValue::List(values)),
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        match &mut self.values {
+            Values::Array { values, .. } => {
+                let list = BencodedValue::List(core::mem::take(values));
+                Ok(self.end_value(list))
+            }
             _ => unreachable!(),
         }
     }
@@ -135,24 +197,15 @@ impl<'serializer> SerializeStruct for Compound<'serializer> {
     where
         T: serde::Serialize,
     {
-        match *self {
-            Compound::Map { ref mut values, .. } => {
-                //keys.push(key.to_owned());
-                values.insert(
-                    key.to_owned(),
-                    value.serialize(Serializer::default())?,
-                );
-            }
-            _ => unreachable!(),
-        }
-
-        Ok(())
+        let value = value.serialize(self.serializer)?;
+        self.insert(Key::from(key), value)
     }
 
-    fn end(self) -> Result<Self::Ok, Self::Error> {
-        match self {
-            Compound::Map { values, .. } => {
-                Ok(BencodedValue::DictionaryOwned(values))
+    fn end(mut self) -> Result<Self::Ok, Self::Error> {
+        match &mut self.values {
+            Values::Map { values, .. } => {
+                let dict = BencodedValue::Dictionary(core::mem::take(values));
+                Ok(self.end_value(dict))
             }
             _ => unreachable!(),
         }
@@ -167,24 +220,13 @@ impl<'serializer> SerializeMap for Compound<'serializer> {
     where
         T: serde::Serialize,
     {
-        match *self {
-            Compound::Map {
+        match self.values {
+            Values::Map {
                 ref mut current_key,
                 ..
             } => {
-                match key.serialize(Serializer::default())? {
-                    BencodedValue::String(value) => {
-                        current_key.replace(value.to_owned())
-                    }
-                    BencodedValue::StringOwned(value) => {
-                        current_key.replace(value)
-                    }
-                    _ => {
-                        return Err(Error::Message(
-                            "Only string keys are supported in maps".to_owned(),
-                        ))
-                    }
-                };
+                let key = map_key(key.serialize(Serializer::default())?)?;
+                current_key.replace(key);
             }
             _ => unreachable!(),
         }
@@ -199,21 +241,12 @@ impl<'serializer> SerializeMap for Compound<'serializer> {
     where
         T: serde::Serialize,
     {
-        match self {
-            Compound::Map {
-                current_key,
-                values,
-                ..
-            } => {
-                values.insert(
-                    current_key.take().unwrap(),
-                    value.serialize(Serializer::default())?,
-                );
-            }
+        let key = match &mut self.values {
+            Values::Map { current_key, .. } => current_key.take().unwrap(),
             _ => unreachable!(),
-        }
-
-        Ok(())
+        };
+        let value = value.serialize(self.serializer)?;
+        self.insert(key, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -0,0 +1,429 @@
+use super::{
+    map_key, round, stream::Position, EnumRepresentation, Serializer,
+    SerializerOptions, UnitRepresentation, CONTENT_KEY, TAG_KEY,
+};
+use crate::{
+    error::{Error, Result},
+    writer::{bytes_len, int_len, uint_len},
+};
+use core::{cell::Cell, convert::TryFrom};
+use serde::{ser, ser::SerializeMap, Serialize};
+
+/// Serializer computing the number of bytes written by a
+/// [`StreamSerializer`](super::stream::StreamSerializer) without writing
+/// them, used to allocate the output of [`to_bytes`](super::to_bytes) once
+///
+/// Duplicated keys are counted as many times as they are given, the size is
+/// then an upper bound rather than the exact length.
+pub(crate) struct SizeSerializer<'s> {
+    options: SerializerOptions,
+    position: Position,
+
+    /// Longest key of an entry omitted for its none value, the stream writes
+    /// such a key before dropping it and needs that much room past the size
+    slack: &'s Cell<usize>,
+}
+
+/// A list or a dictionary being measured by a [`SizeSerializer`]
+pub(crate) struct SizeCompound<'s> {
+    options: SerializerOptions,
+    len: usize,
+    slack: &'s Cell<usize>,
+
+    /// Length of the last key, counted along with a value writing something
+    key: usize,
+}
+
+impl<'s> SizeSerializer<'s> {
+    pub fn new(options: SerializerOptions, slack: &'s Cell<usize>) -> Self {
+        SizeSerializer {
+            options,
+            position: Position::Root,
+            slack,
+        }
+    }
+
+    fn nested(&self, position: Position) -> Self {
+        SizeSerializer {
+            options: self.options,
+            position,
+            slack: self.slack,
+        }
+    }
+
+    /// Length of what surrounds the content of an enum variant
+    fn variant_len(&self, variant: &str) -> usize {
+        match self.options.enums {
+            EnumRepresentation::External => 2 + bytes_len(variant.len()),
+            EnumRepresentation::Adjacent => {
+                2 + bytes_len(CONTENT_KEY.len())
+                    + bytes_len(TAG_KEY.len())
+                    + bytes_len(variant.len())
+            }
+            EnumRepresentation::Untagged => 0,
+        }
+    }
+
+    fn compound(self, len: usize) -> SizeCompound<'s> {
+        SizeCompound {
+            options: self.options,
+            len,
+            slack: self.slack,
+            key: 0,
+        }
+    }
+}
+
+impl<'s> ser::Serializer for SizeSerializer<'s> {
+    type Ok = usize;
+    type Error = Error;
+    type SerializeSeq = SizeCompound<'s>;
+    type SerializeTuple = SizeCompound<'s>;
+    type SerializeTupleStruct = SizeCompound<'s>;
+    type SerializeTupleVariant = SizeCompound<'s>;
+    type SerializeMap = SizeCompound<'s>;
+    type SerializeStruct = SizeCompound<'s>;
+    type SerializeStructVariant = SizeCompound<'s>;
+
+    fn serialize_i64(self, v: i64) -> Result<usize> {
+        Ok(int_len(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<usize> {
+        self.options.lossy("char")?;
+        Ok(bytes_len(v.len_utf8()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<usize> {
+        Ok(bytes_len(v.len()))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<usize> {
+        Ok(bytes_len(v.len()))
+    }
+
+    fn serialize_none(self) -> Result<usize> {
+        match self.position {
+            Position::Root => Ok(0),
+            Position::Value if self.options.skip_none => Ok(0),
+            _ => Err(Error::NestedNone),
+        }
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<usize> {
+        self.options.lossy("bool")?;
+        Ok(int_len(0))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<usize> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<usize> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<usize> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<usize> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<usize> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<usize> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<usize> {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            Err(_) if self.options.deny_lossy => {
+                Err(Error::out_of_range(v, "i64"))
+            }
+            Err(_) => Ok(uint_len(v)),
+        }
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<usize> {
+        match u128::try_from(v) {
+            Ok(v) => self.serialize_u128(v),
+            Err(_) => match i64::try_from(v) {
+                Ok(v) => self.serialize_i64(v),
+                Err(_) => Err(Error::out_of_range(v, "i64")),
+            },
+        }
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<usize> {
+        match u64::try_from(v) {
+            Ok(v) => self.serialize_u64(v),
+            Err(_) => Err(Error::out_of_range(v, "u64")),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<usize> {
+        self.options.lossy("f32")?;
+        self.serialize_i64(round(f64::from(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<usize> {
+        self.options.lossy("f64")?;
+        self.serialize_i64(round(v))
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<usize> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<usize> {
+        self.options.lossy("unit")?;
+        match self.options.units {
+            UnitRepresentation::EmptyString => self.serialize_str(""),
+            UnitRepresentation::Zero => self.serialize_i64(0),
+            UnitRepresentation::EmptyDictionary => {
+                self.serialize_map(Some(0))?.end()
+            }
+        }
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<usize> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<usize> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<usize> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<usize> {
+        let position = match self.options.enums {
+            EnumRepresentation::Untagged => self.position,
+            _ => Position::Element,
+        };
+        let content = value.serialize(self.nested(position))?;
+        Ok(self.variant_len(variant) + content)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<SizeCompound<'s>> {
+        Ok(self.compound(2))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SizeCompound<'s>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SizeCompound<'s>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SizeCompound<'s>> {
+        let len = 2 + self.variant_len(variant);
+        Ok(self.compound(len))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<SizeCompound<'s>> {
+        Ok(self.compound(2))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<SizeCompound<'s>> {
+        Ok(self.compound(2))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<SizeCompound<'s>> {
+        let len = 2 + self.variant_len(variant);
+        Ok(self.compound(len))
+    }
+}
+
+impl<'s> SizeCompound<'s> {
+    fn nested(&self, position: Position) -> SizeSerializer<'s> {
+        SizeSerializer {
+            options: self.options,
+            position,
+            slack: self.slack,
+        }
+    }
+
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.len += value.serialize(self.nested(Position::Element))?;
+        Ok(())
+    }
+
+    /// Counts the last key along with its value, unless the value is a none
+    /// omitting the entry
+    fn value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let len = value.serialize(self.nested(Position::Value))?;
+        if len > 0 {
+            self.len += self.key + len;
+        } else if self.key > self.slack.get() {
+            self.slack.set(self.key);
+        }
+
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SizeCompound<'_> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<usize> {
+        Ok(self.len)
+    }
+}
+
+impl ser::SerializeTuple for SizeCompound<'_> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<usize> {
+        Ok(self.len)
+    }
+}
+
+impl ser::SerializeTupleStruct for SizeCompound<'_> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<usize> {
+        Ok(self.len)
+    }
+}
+
+impl ser::SerializeTupleVariant for SizeCompound<'_> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<usize> {
+        Ok(self.len)
+    }
+}
+
+impl ser::SerializeStruct for SizeCompound<'_> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.key = bytes_len(key.len());
+        self.value(value)
+    }
+
+    fn end(self) -> Result<usize> {
+        Ok(self.len)
+    }
+}
+
+impl ser::SerializeStructVariant for SizeCompound<'_> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.key = bytes_len(key.len());
+        self.value(value)
+    }
+
+    fn end(self) -> Result<usize> {
+        Ok(self.len)
+    }
+}
+
+impl ser::SerializeMap for SizeCompound<'_> {
+    type Ok = usize;
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        let key = map_key(key.serialize(Serializer::default())?)?;
+        self.key = bytes_len(key.len());
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        self.value(value)
+    }
+
+    fn end(self) -> Result<usize> {
+        Ok(self.len)
+    }
+}
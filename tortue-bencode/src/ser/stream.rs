@@ -0,0 +1,621 @@
+use super::{
+    map_key, round, EnumRepresentation, KeyOrder, Serializer,
+    SerializerOptions, UnitRepresentation, CONTENT_KEY, TAG_KEY,
+};
+use crate::{
+    cautious_capacity,
+    error::{Error, Result},
+    io::{self, Write},
+    prelude::*,
+    writer::{write_bin, write_int, write_uint},
+};
+use core::convert::TryFrom;
+use serde::{ser, ser::SerializeMap, Serialize};
+
+/// Destination of a [`StreamSerializer`], the content of the dictionaries
+/// is buffered until they end so that their keys can be sorted
+pub(crate) struct Output<W: Write> {
+    writer: W,
+    options: SerializerOptions,
+    buffer: Vec<u8>,
+    open_maps: usize,
+    written: usize,
+}
+
+impl<W: Write> Output<W> {
+    pub fn new(writer: W, options: SerializerOptions) -> Self {
+        Output {
+            writer,
+            options,
+            buffer: Vec::new(),
+            open_maps: 0,
+            written: 0,
+        }
+    }
+
+    /// Number of bytes written into the writer
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Writes into the buffer inside of a dictionary, into the writer
+    /// otherwise
+    fn emit<F>(&mut self, write: F) -> Result<()>
+    where
+        F: FnOnce(&mut &mut dyn Write) -> io::Result<usize>,
+    {
+        if self.open_maps > 0 {
+            let mut buffer: &mut dyn Write = &mut self.buffer;
+            write(&mut buffer).map_err(Error::from)?;
+        } else {
+            let mut writer: &mut dyn Write = &mut self.writer;
+            self.written += write(&mut writer).map_err(Error::from)?;
+        }
+
+        Ok(())
+    }
+
+    fn emit_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.emit(|w| w.write_all(bytes).map(|_| bytes.len()))
+    }
+
+    /// Writes what precedes the content of an enum variant, keys being
+    /// sorted the tag of adjacently tagged variants comes after the content
+    fn begin_variant(&mut self, variant: &'static str) -> Result<()> {
+        match self.options.enums {
+            EnumRepresentation::External => {
+                self.emit(|w| write_bin_prefixed(b"d", variant, w))
+            }
+            EnumRepresentation::Adjacent => {
+                self.emit(|w| write_bin_prefixed(b"d", CONTENT_KEY, w))
+            }
+            EnumRepresentation::Untagged => Ok(()),
+        }
+    }
+
+    /// Writes what follows the content of an enum variant
+    fn end_variant(&mut self, variant: &'static str) -> Result<()> {
+        match self.options.enums {
+            EnumRepresentation::External => self.emit_bytes(b"e"),
+            EnumRepresentation::Adjacent => {
+                self.emit(|w| write_bin(TAG_KEY.as_bytes(), w))?;
+                self.emit(|w| write_bin(variant.as_bytes(), w))?;
+                self.emit_bytes(b"e")
+            }
+            EnumRepresentation::Untagged => Ok(()),
+        }
+    }
+}
+
+impl Output<Vec<u8>> {
+    /// Creates an output writing everything into its buffer, which is
+    /// then taken with [`Output::into_buffer`] instead of being copied into
+    /// a writer
+    pub fn buffered(options: SerializerOptions, capacity: usize) -> Self {
+        Output {
+            writer: Vec::new(),
+            options,
+            buffer: Vec::with_capacity(capacity),
+            // As if inside of a dictionary, nothing is ever flushed
+            open_maps: 1,
+            written: 0,
+        }
+    }
+
+    /// Takes the bytes written by a buffered output
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+/// Serializer writing the values as they are given instead of building a
+/// [`BencodedValue`] first, producing the same bytes as
+/// [`writer::write`](crate::writer::write) of [`to_value`](super::to_value)
+pub(crate) struct StreamSerializer<'o, W: Write> {
+    output: &'o mut Output<W>,
+    position: Position,
+}
+
+/// Where a value is written, a none writes nothing at the root and omits
+/// the entry of a dictionary but cannot be an element
+#[derive(Clone, Copy)]
+pub(super) enum Position {
+    Root,
+    Element,
+    Value,
+}
+
+impl<'o, W: Write> StreamSerializer<'o, W> {
+    pub fn new(output: &'o mut Output<W>) -> Self {
+        StreamSerializer {
+            output,
+            position: Position::Root,
+        }
+    }
+
+    fn nested(output: &'o mut Output<W>, position: Position) -> Self {
+        StreamSerializer { output, position }
+    }
+}
+
+/// A list being written by a [`StreamSerializer`]
+pub(crate) struct StreamSeq<'o, W: Write> {
+    output: &'o mut Output<W>,
+
+    /// Enum variant of the list, closed along with it
+    variant: Option<&'static str>,
+}
+
+/// A dictionary being written by a [`StreamSerializer`]
+pub(crate) struct StreamMap<'o, W: Write> {
+    output: &'o mut Output<W>,
+    start: usize,
+    entries: Vec<Entry>,
+
+    /// Enum variant of the dictionary, closed along with it
+    variant: Option<&'static str>,
+}
+
+/// Offsets in the buffer of an entry of a [`StreamMap`]
+struct Entry {
+    start: usize,
+    key: usize,
+    value: usize,
+}
+
+impl<'o, W: Write> StreamMap<'o, W> {
+    fn new(output: &'o mut Output<W>, len: Option<usize>) -> Result<Self> {
+        output.open_maps += 1;
+        output.emit_bytes(b"d")?;
+        Ok(StreamMap {
+            start: output.buffer.len(),
+            output,
+            entries: Vec::with_capacity(cautious_capacity(len)),
+            variant: None,
+        })
+    }
+
+    fn key(&mut self, key: &str) -> Result<()> {
+        let start = self.output.buffer.len();
+        write_bin(key.as_bytes(), &mut self.output.buffer)
+            .map_err(Error::from)?;
+        self.entries.push(Entry {
+            start,
+            key: self.output.buffer.len() - key.len(),
+            value: self.output.buffer.len(),
+        });
+        Ok(())
+    }
+
+    /// Writes the value of the last entry, dropping the entry if the value
+    /// is a none as every other value writes something
+    fn value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let start = self.output.buffer.len();
+        value.serialize(StreamSerializer::nested(
+            self.output,
+            Position::Value,
+        ))?;
+
+        if self.output.buffer.len() == start {
+            if let Some(entry) = self.entries.pop() {
+                self.output.buffer.truncate(entry.start);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sorts the entries by key, keeping the last one of duplicated keys,
+    /// and closes the dictionary
+    fn finish(self) -> Result<()> {
+        let StreamMap {
+            output,
+            start,
+            entries,
+            variant,
+        } = self;
+        let buffer = &output.buffer;
+        let end = |i: usize| {
+            entries.get(i + 1).map_or(buffer.len(), |entry| entry.start)
+        };
+        let key = |i: usize| &buffer[entries[i].key..entries[i].value];
+
+        if (1..entries.len()).any(|i| key(i - 1) >= key(i)) {
+            let mut order = (0..entries.len()).collect::<Vec<_>>();
+            order.sort_by(|&a, &b| key(a).cmp(key(b)));
+
+            if output.options.key_order == KeyOrder::Declaration {
+                // The entries stay in place, only duplicates are looked for
+                let duplicate =
+                    order.windows(2).find(|pair| key(pair[0]) == key(pair[1]));
+                if let Some(pair) = duplicate {
+                    let key = String::from_utf8_lossy(key(pair[0]));
+                    return Err(Error::DuplicateKey(key.into_owned()));
+                }
+            } else {
+                order.dedup_by(|next, last| {
+                    let duplicate = key(*next) == key(*last);
+                    if duplicate {
+                        *last = *next;
+                    }
+                    duplicate
+                });
+
+                let mut sorted = Vec::with_capacity(buffer.len() - start);
+                for &i in &order {
+                    sorted.extend_from_slice(&buffer[entries[i].start..end(i)]);
+                }
+
+                output.buffer.truncate(start);
+                output.buffer.extend_from_slice(&sorted);
+            }
+        }
+
+        output.buffer.push(b'e');
+        output.open_maps -= 1;
+        if output.open_maps == 0 {
+            output
+                .writer
+                .write_all(&output.buffer)
+                .map_err(Error::from)?;
+            output.written += output.buffer.len();
+            output.buffer.clear();
+        }
+
+        match variant {
+            Some(variant) => output.end_variant(variant),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'o, W: Write> ser::Serializer for StreamSerializer<'o, W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = StreamSeq<'o, W>;
+    type SerializeTuple = StreamSeq<'o, W>;
+    type SerializeTupleStruct = StreamSeq<'o, W>;
+    type SerializeTupleVariant = StreamSeq<'o, W>;
+    type SerializeMap = StreamMap<'o, W>;
+    type SerializeStruct = StreamMap<'o, W>;
+    type SerializeStructVariant = StreamMap<'o, W>;
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.output.emit(|w| write_int(v, w))
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.output.options.lossy("char")?;
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.output.emit(|w| write_bin(v, w))
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        match self.position {
+            Position::Root => Ok(()),
+            Position::Value if self.output.options.skip_none => Ok(()),
+            _ => Err(Error::NestedNone),
+        }
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.output.options.lossy("bool")?;
+        self.serialize_i64(if v { 1 } else { 0 })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            Err(_) if self.output.options.deny_lossy => {
+                Err(Error::out_of_range(v, "i64"))
+            }
+            Err(_) => self.output.emit(|w| write_uint(v, w)),
+        }
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        match u128::try_from(v) {
+            Ok(v) => self.serialize_u128(v),
+            Err(_) => match i64::try_from(v) {
+                Ok(v) => self.serialize_i64(v),
+                Err(_) => Err(Error::out_of_range(v, "i64")),
+            },
+        }
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        match u64::try_from(v) {
+            Ok(v) => self.serialize_u64(v),
+            Err(_) => Err(Error::out_of_range(v, "u64")),
+        }
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.output.options.lossy("f32")?;
+        self.serialize_i64(round(f64::from(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.output.options.lossy("f64")?;
+        self.serialize_i64(round(v))
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.output.options.lossy("unit")?;
+        match self.output.options.units {
+            UnitRepresentation::EmptyString => self.serialize_str(""),
+            UnitRepresentation::Zero => self.serialize_i64(0),
+            UnitRepresentation::EmptyDictionary => {
+                self.serialize_map(Some(0))?.end()
+            }
+        }
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let position = match self.output.options.enums {
+            EnumRepresentation::Untagged => self.position,
+            _ => Position::Element,
+        };
+        self.output.begin_variant(variant)?;
+        value.serialize(StreamSerializer::nested(self.output, position))?;
+        self.output.end_variant(variant)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        self.output.emit_bytes(b"l")?;
+        Ok(StreamSeq {
+            output: self.output,
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.output.begin_variant(variant)?;
+        self.output.emit_bytes(b"l")?;
+        Ok(StreamSeq {
+            output: self.output,
+            variant: Some(variant),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        StreamMap::new(self.output, len)
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct> {
+        StreamMap::new(self.output, Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.output.begin_variant(variant)?;
+        let mut map = StreamMap::new(self.output, Some(len))?;
+        map.variant = Some(variant);
+        Ok(map)
+    }
+}
+
+/// Writes `prefix` followed by a string
+fn write_bin_prefixed<T: Write>(
+    prefix: &[u8],
+    str: &str,
+    writer: &mut T,
+) -> io::Result<usize> {
+    writer.write_all(prefix)?;
+    Ok(prefix.len() + write_bin(str.as_bytes(), writer)?)
+}
+
+impl<'o, W: Write> ser::SerializeSeq for StreamSeq<'o, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        value
+            .serialize(StreamSerializer::nested(self.output, Position::Element))
+    }
+
+    fn end(self) -> Result<()> {
+        self.output.emit_bytes(b"e")?;
+        match self.variant {
+            Some(variant) => self.output.end_variant(variant),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'o, W: Write> ser::SerializeTuple for StreamSeq<'o, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        <Self as ser::SerializeSeq>::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        <Self as ser::SerializeSeq>::end(self)
+    }
+}
+
+impl<'o, W: Write> ser::SerializeTupleStruct for StreamSeq<'o, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        <Self as ser::SerializeSeq>::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        <Self as ser::SerializeSeq>::end(self)
+    }
+}
+
+impl<'o, W: Write> ser::SerializeTupleVariant for StreamSeq<'o, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        <Self as ser::SerializeSeq>::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        <Self as ser::SerializeSeq>::end(self)
+    }
+}
+
+impl<'o, W: Write> ser::SerializeStruct for StreamMap<'o, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.key(key)?;
+        self.value(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<'o, W: Write> ser::SerializeStructVariant for StreamMap<'o, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.key(key)?;
+        self.value(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<'o, W: Write> ser::SerializeMap for StreamMap<'o, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        let key = map_key(key.serialize(Serializer::default())?)?;
+        self.key(key.as_str())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(
+        &mut self,
+        value: &T,
+    ) -> Result<()> {
+        self.value(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
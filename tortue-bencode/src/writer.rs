@@ -1,80 +1,847 @@
-use crate::BencodedValue;
-use std::{
-    collections::HashMap,
+mod encoder;
+
+pub use self::encoder::*;
+
+use crate::{
+    error::Error,
     io::{self, Write},
+    prelude::*,
+    BencodedValue, HashMap, Key,
 };
 
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+impl<'a> BencodedValue<'a> {
+    /// Returns the number of bytes written when encoding this value
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            BencodedValue::Binary(bin) => bytes_len(bin.len()),
+            BencodedValue::String(str) => bytes_len(str.len()),
+            BencodedValue::Integer(int) => int_len(*int),
+            BencodedValue::UnsignedInteger(int) => uint_len(*int),
+            BencodedValue::List(list) => {
+                2 + list.iter().map(BencodedValue::encoded_len).sum::<usize>()
+            }
+            BencodedValue::Dictionary(dict) => {
+                2 + dict
+                    .iter()
+                    .map(|(key, value)| {
+                        bytes_len(key.len()) + value.encoded_len()
+                    })
+                    .sum::<usize>()
+            }
+            BencodedValue::None => 0,
+            BencodedValue::Raw(raw) => raw.len(),
+        }
+    }
+
+    /// Encodes the value into an exactly sized byte vec, fails with
+    /// [`Error::NestedNone`] on a none nested in a list or a dictionary
+    pub fn to_vec(&self) -> Result<Vec<u8>, Error> {
+        let mut out = Vec::with_capacity(self.encoded_len());
+        // Writing into a vec only fails on a nested none
+        write(self, &mut out).map_err(|_| Error::NestedNone)?;
+        Ok(out)
+    }
+
+    /// Encodes the value into a writer, returns the number of bytes written
+    pub fn to_writer<T: Write>(&self, writer: &mut T) -> io::Result<usize> {
+        write(self, writer)
+    }
+}
+
+/// Length of a length-prefixed byte string
+pub(crate) fn bytes_len(len: usize) -> usize {
+    decimal_len(len as u64) + 1 + len
+}
+
+/// Length of an encoded integer
+pub(crate) fn int_len(int: i64) -> usize {
+    2 + (int < 0) as usize + decimal_len(int.unsigned_abs())
+}
+
+/// Length of an encoded unsigned integer
+pub(crate) fn uint_len(int: u64) -> usize {
+    2 + decimal_len(int)
+}
+
+/// Number of decimal digits of an integer
+fn decimal_len(mut int: u64) -> usize {
+    let mut len = 1;
+    while int >= 10 {
+        int /= 10;
+        len += 1;
+    }
+
+    len
+}
+
+/// Writes a bencoded value, raw values are copied verbatim without any check
+///
+/// A top-level none writes nothing so that `Option::None` can be encoded,
+/// a none nested in a list or a dictionary has no bencode representation
+/// and is an error. Like every writing function of this module, returns the
+/// number of bytes written.
 pub fn write<'a, T: Write>(
     value: &BencodedValue<'a>,
     writer: &mut T,
-) -> io::Result<()> {
+) -> io::Result<usize> {
     match value {
         BencodedValue::Binary(ref bin) => write_bin(bin, writer),
         BencodedValue::String(ref str) => write_str(str, writer),
         BencodedValue::Integer(int) => write_int(*int, writer),
+        BencodedValue::UnsignedInteger(int) => write_uint(*int, writer),
         BencodedValue::List(lst) => write_list(lst, writer),
         BencodedValue::Dictionary(dict) => write_dict(dict, writer),
-        BencodedValue::None => Ok(()),
-        BencodedValue::BinaryOwned(bin) => write_bin(&bin[..], writer),
-        BencodedValue::StringOwned(str) => write_str(&str, writer),
-        BencodedValue::DictionaryOwned(dict) => write_owned_dict(dict, writer),
+        BencodedValue::None => Ok(0),
+        BencodedValue::Raw(raw) => write_all(raw, writer),
     }
 }
 
-pub fn write_bin<'a, T: Write>(
-    value: &'a [u8],
+/// Size of the buffer of [`write_buffered`]
+#[cfg(feature = "std")]
+pub(crate) const BUFFER_LEN: usize = 8 << 10;
+
+/// Same as [`write`] coalescing the small pieces of the encoding into an
+/// 8 KiB buffer, large strings are passed through directly
+///
+/// Use it with unbuffered writers such as files or sockets, where every
+/// write is a system call.
+#[cfg(feature = "std")]
+pub fn write_buffered<'a, T: Write>(
+    value: &BencodedValue<'a>,
+    writer: &mut T,
+) -> io::Result<usize> {
+    let mut buffered = std::io::BufWriter::with_capacity(BUFFER_LEN, writer);
+    let written = write(value, &mut buffered)?;
+    buffered.flush()?;
+    Ok(written)
+}
+
+/// Writes a bencoded value after checking that every raw value it contains
+/// is a single canonical bencoded value, see [`is_canonical`]
+pub fn write_checked<'a, T: Write>(
+    value: &BencodedValue<'a>,
     writer: &mut T,
-) -> io::Result<()> {
-    writer.write_all(format!("{}", value.len()).as_bytes())?;
-    writer.write_all(b":")?;
-    writer.write_all(value)
+) -> io::Result<usize> {
+    check_raw(value)?;
+    write(value, writer)
+}
+
+/// Writes a bencoded value at the start of a buffer and returns its length,
+/// fails if the buffer is shorter than [`BencodedValue::encoded_len`]
+///
+/// Nothing is allocated, dictionary keys are sorted by scanning the
+/// dictionary once per key.
+pub fn write_to_slice(
+    value: &BencodedValue,
+    buf: &mut [u8],
+) -> Result<usize, Error> {
+    let len = value.encoded_len();
+    if len > buf.len() {
+        return Err(Error::BufferTooSmall {
+            needed: len,
+            len: buf.len(),
+        });
+    }
+
+    write_in_place(value, &mut &mut buf[..len]).map_err(Error::from)
+}
+
+/// Same as [`write`] sorting dictionary keys without allocating
+fn write_in_place(
+    value: &BencodedValue,
+    writer: &mut &mut [u8],
+) -> io::Result<usize> {
+    match value {
+        BencodedValue::List(list) => {
+            let mut written = write_all(b"l", writer)?;
+            for element in list {
+                written += write_nested_in_place(element, writer)?;
+            }
+
+            Ok(written + write_all(b"e", writer)?)
+        }
+        BencodedValue::Dictionary(dict) => {
+            let mut written = write_all(b"d", writer)?;
+            let mut previous = None;
+            while let Some((key, value)) = dict
+                .iter()
+                .filter(|(key, _)| previous < Some(*key))
+                .min_by_key(|(key, _)| *key)
+            {
+                written += write_str(key, writer)?;
+                written += write_nested_in_place(value, writer)?;
+                previous = Some(key);
+            }
+
+            Ok(written + write_all(b"e", writer)?)
+        }
+        value => write(value, writer),
+    }
+}
+
+fn write_nested_in_place(
+    value: &BencodedValue,
+    writer: &mut &mut [u8],
+) -> io::Result<usize> {
+    if value.is_none() {
+        Err(nested_none())
+    } else {
+        write_in_place(value, writer)
+    }
+}
+
+fn check_raw(value: &BencodedValue) -> io::Result<()> {
+    match value {
+        BencodedValue::Raw(raw) if !is_canonical(raw) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "raw value is not canonical bencode",
+        )),
+        BencodedValue::List(list) => list.iter().try_for_each(check_raw),
+        BencodedValue::Dictionary(dict) => {
+            dict.values().try_for_each(check_raw)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks that the input is exactly one bencoded value in canonical form:
+/// dictionary keys sorted and unique, no leading zeros or negative zero in
+/// integers and lengths
+pub fn is_canonical(input: &[u8]) -> bool {
+    match skip_canonical(input) {
+        Some(rest) => rest.is_empty(),
+        None => false,
+    }
+}
+
+/// An open container while skipping a canonical value
+enum Container<'a> {
+    List,
+    Dictionary { previous: Option<&'a [u8]> },
+}
+
+/// Skips a canonical value at the start of the input, returning the rest
+///
+/// Open containers are kept on an explicit stack so that deeply nested
+/// untrusted input cannot overflow the call stack.
+fn skip_canonical(mut input: &[u8]) -> Option<&[u8]> {
+    let mut stack = Vec::new();
+
+    loop {
+        match stack.last_mut() {
+            Some(_) if *input.first()? == b'e' => {
+                stack.pop();
+                input = &input[1..];
+                if stack.is_empty() {
+                    return Some(input);
+                }
+
+                continue;
+            }
+            Some(Container::Dictionary { previous }) => {
+                let (key, rest) = split_canonical_bytes(input)?;
+                if *previous >= Some(key) {
+                    return None;
+                }
+
+                *previous = Some(key);
+                input = rest;
+            }
+            Some(Container::List) | None => {}
+        }
+
+        input = match input.first()? {
+            b'i' => skip_canonical_int(&input[1..])?,
+            b'l' => {
+                stack.push(Container::List);
+                &input[1..]
+            }
+            b'd' => {
+                stack.push(Container::Dictionary { previous: None });
+                &input[1..]
+            }
+            b'0'..=b'9' => split_canonical_bytes(input)?.1,
+            _ => return None,
+        };
+
+        if stack.is_empty() {
+            return Some(input);
+        }
+    }
+}
+
+/// Whether the digits are canonical: non empty, no leading zero and no sign
+fn is_canonical_digits(digits: &[u8]) -> bool {
+    match digits {
+        [] => false,
+        [b'0'] => true,
+        [b'0', ..] => false,
+        _ => digits.iter().all(u8::is_ascii_digit),
+    }
+}
+
+/// Skips the digits and the end of a canonical integer, which must fit either
+/// an `i64` or a `u64`
+fn skip_canonical_int(input: &[u8]) -> Option<&[u8]> {
+    let end = input.iter().position(|&b| b == b'e')?;
+    let digits = &input[..end];
+    let canonical = match digits {
+        [b'-', b'0', ..] => false,
+        [b'-', magnitude @ ..] => is_canonical_digits(magnitude),
+        _ => is_canonical_digits(digits),
+    };
+    if !canonical {
+        return None;
+    }
+
+    let int = core::str::from_utf8(digits).ok()?;
+    if int.parse::<i64>().is_ok() || int.parse::<u64>().is_ok() {
+        Some(&input[end + 1..])
+    } else {
+        None
+    }
+}
+
+/// Splits a canonical length-prefixed byte string from the rest of the input
+fn split_canonical_bytes(input: &[u8]) -> Option<(&[u8], &[u8])> {
+    let colon = input.iter().position(|&b| b == b':')?;
+    let digits = &input[..colon];
+    if !is_canonical_digits(digits) {
+        return None;
+    }
+
+    let len: usize = core::str::from_utf8(digits).ok()?.parse().ok()?;
+    let rest = &input[colon + 1..];
+    if len <= rest.len() {
+        Some(rest.split_at(len))
+    } else {
+        None
+    }
+}
+
+/// Writes all the bytes and returns their number
+fn write_all<T: Write>(bytes: &[u8], writer: &mut T) -> io::Result<usize> {
+    writer.write_all(bytes)?;
+    Ok(bytes.len())
+}
+
+/// Large enough for `i-9223372036854775808e` and `18446744073709551615:`
+const SCALAR_LEN: usize = 22;
+
+/// Formats an integer followed by `suffix` at the end of a stack buffer,
+/// preceded by `prefix` and a minus sign if it is negative
+fn format_scalar<'a>(
+    buf: &'a mut [u8; SCALAR_LEN],
+    prefix: &[u8],
+    negative: bool,
+    mut int: u64,
+    suffix: u8,
+) -> &'a [u8] {
+    let mut start = SCALAR_LEN - 1;
+    buf[start] = suffix;
+
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (int % 10) as u8;
+        int /= 10;
+        if int == 0 {
+            break;
+        }
+    }
+
+    if negative {
+        start -= 1;
+        buf[start] = b'-';
+    }
+
+    start -= prefix.len();
+    buf[start..start + prefix.len()].copy_from_slice(prefix);
+    &buf[start..]
+}
+
+pub fn write_bin<T: Write>(value: &[u8], writer: &mut T) -> io::Result<usize> {
+    let mut buf = [0; SCALAR_LEN];
+    let len = format_scalar(&mut buf, b"", false, value.len() as u64, b':');
+    Ok(write_all(len, writer)? + write_all(value, writer)?)
+}
+
+pub fn write_str<T: Write>(str: &str, writer: &mut T) -> io::Result<usize> {
+    write_bin(str.as_bytes(), writer)
+}
+
+pub fn write_int<T: Write>(int: i64, writer: &mut T) -> io::Result<usize> {
+    let mut buf = [0; SCALAR_LEN];
+    let int = format_scalar(&mut buf, b"i", int < 0, int.unsigned_abs(), b'e');
+    write_all(int, writer)
+}
+
+/// Writes a bencoded u64
+pub fn write_uint<T: Write>(int: u64, writer: &mut T) -> io::Result<usize> {
+    let mut buf = [0; SCALAR_LEN];
+    write_all(format_scalar(&mut buf, b"i", false, int, b'e'), writer)
 }
 
-pub fn write_str<'a, T: Write>(str: &'a str, writer: &mut T) -> io::Result<()> {
-    writer.write_all(format!("{}:{}", str.len(), str).as_bytes())
+/// Writes a value nested in a list or a dictionary
+fn write_nested<T: Write>(
+    value: &BencodedValue,
+    writer: &mut T,
+) -> io::Result<usize> {
+    if value.is_none() {
+        Err(nested_none())
+    } else {
+        write(value, writer)
+    }
 }
 
-pub fn write_int<T: Write>(int: i64, writer: &mut T) -> io::Result<()> {
-    writer.write_all(format!("i{}e", int).as_bytes())
+fn nested_none() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "cannot write a none nested in a list or a dictionary",
+    )
 }
 
 pub fn write_list<'a, T: Write>(
     list: &[BencodedValue<'a>],
     writer: &mut T,
-) -> io::Result<()> {
-    writer.write_all(b"l")?;
+) -> io::Result<usize> {
+    let mut written = write_all(b"l", writer)?;
 
     for element in list.iter() {
-        write(element, writer)?;
+        written += write_nested(element, writer)?;
     }
 
-    writer.write_all(b"e")
+    Ok(written + write_all(b"e", writer)?)
 }
 
+/// Writes a dictionary with its keys sorted by their raw bytes as required by
+/// the specification, so that the same map is always written the same way
 pub fn write_dict<'a, T: Write>(
-    list: &HashMap<&'a str, BencodedValue<'a>>,
+    list: &HashMap<Key<'a>, BencodedValue<'a>>,
     writer: &mut T,
-) -> io::Result<()> {
-    writer.write_all(b"d")?;
+) -> io::Result<usize> {
+    let mut written = write_all(b"d", writer)?;
 
-    for (key, value) in list.iter() {
-        write_str(key, writer)?;
-        write(value, writer)?;
+    for (key, value) in sorted_entries(list) {
+        written += write_str(key, writer)?;
+        written += write_nested(value, writer)?;
     }
 
-    writer.write_all(b"e")
+    Ok(written + write_all(b"e", writer)?)
 }
 
-pub fn write_owned_dict<'a, T: Write>(
-    list: &HashMap<String, BencodedValue<'a>>,
-    writer: &mut T,
-) -> io::Result<()> {
-    writer.write_all(b"d")?;
+/// Entries of a dictionary sorted by their raw key bytes
+fn sorted_entries<'d, 'a>(
+    dict: &'d HashMap<Key<'a>, BencodedValue<'a>>,
+) -> Vec<(&'d Key<'a>, &'d BencodedValue<'a>)> {
+    let mut entries = dict.iter().collect::<Vec<_>>();
+    entries.sort_unstable_by_key(|(key, _)| *key);
+    entries
+}
+
+/// A list or dictionary being written by [`write_async`]
+#[cfg(feature = "tokio")]
+enum Frame<'v, 'a> {
+    List(core::slice::Iter<'v, BencodedValue<'a>>),
+    Dict(alloc::vec::IntoIter<(&'v Key<'a>, &'v BencodedValue<'a>)>),
+}
+
+/// Same as [`write`] into an async writer, the nested values are written as
+/// they are visited without buffering the whole value
+#[cfg(feature = "tokio")]
+pub async fn write_async<'a, W: AsyncWrite + Unpin>(
+    value: &BencodedValue<'a>,
+    writer: &mut W,
+) -> io::Result<usize> {
+    let mut stack = Vec::new();
+    let mut written = write_async_value(value, &mut stack, writer).await?;
 
-    for (key, value) in list.iter() {
-        write_str(key, writer)?;
-        write(value, writer)?;
+    while let Some(frame) = stack.last_mut() {
+        let next = match frame {
+            Frame::List(values) => values.next(),
+            Frame::Dict(entries) => match entries.next() {
+                Some((key, value)) => {
+                    written += write_bin_async(key.as_bytes(), writer).await?;
+                    Some(value)
+                }
+                None => None,
+            },
+        };
+
+        written += match next {
+            Some(value) if value.is_none() => return Err(nested_none()),
+            Some(value) => write_async_value(value, &mut stack, writer).await?,
+            None => {
+                stack.pop();
+                write_all_async(b"e", writer).await?
+            }
+        };
     }
 
-    writer.write_all(b"e")
+    Ok(written)
+}
+
+/// Writes a scalar value or opens a list or dictionary, pushing it on the
+/// stack of the values being written
+#[cfg(feature = "tokio")]
+async fn write_async_value<'v, 'a, W: AsyncWrite + Unpin>(
+    value: &'v BencodedValue<'a>,
+    stack: &mut Vec<Frame<'v, 'a>>,
+    writer: &mut W,
+) -> io::Result<usize> {
+    let mut buf = [0; SCALAR_LEN];
+    match value {
+        BencodedValue::Binary(bin) => write_bin_async(bin, writer).await,
+        BencodedValue::String(str) => {
+            write_bin_async(str.as_bytes(), writer).await
+        }
+        BencodedValue::Integer(int) => {
+            let int = format_scalar(
+                &mut buf,
+                b"i",
+                *int < 0,
+                int.unsigned_abs(),
+                b'e',
+            );
+            write_all_async(int, writer).await
+        }
+        BencodedValue::UnsignedInteger(int) => {
+            let int = format_scalar(&mut buf, b"i", false, *int, b'e');
+            write_all_async(int, writer).await
+        }
+        BencodedValue::List(list) => {
+            stack.push(Frame::List(list.iter()));
+            write_all_async(b"l", writer).await
+        }
+        BencodedValue::Dictionary(dict) => {
+            stack.push(Frame::Dict(sorted_entries(dict).into_iter()));
+            write_all_async(b"d", writer).await
+        }
+        BencodedValue::None => Ok(0),
+        BencodedValue::Raw(raw) => write_all_async(raw, writer).await,
+    }
+}
+
+#[cfg(feature = "tokio")]
+async fn write_bin_async<W: AsyncWrite + Unpin>(
+    value: &[u8],
+    writer: &mut W,
+) -> io::Result<usize> {
+    let mut buf = [0; SCALAR_LEN];
+    let len = format_scalar(&mut buf, b"", false, value.len() as u64, b':');
+    Ok(write_all_async(len, writer).await?
+        + write_all_async(value, writer).await?)
+}
+
+#[cfg(feature = "tokio")]
+async fn write_all_async<W: AsyncWrite + Unpin>(
+    bytes: &[u8],
+    writer: &mut W,
+) -> io::Result<usize> {
+    writer.write_all(bytes).await?;
+    Ok(bytes.len())
+}
+
+#[cfg(test)]
+mod writer_tests {
+    use super::{
+        is_canonical, write, write_bin, write_checked, write_dict, write_int,
+        write_list, write_str, write_uint,
+    };
+    use crate::{
+        error::Error, parse_all, to_bytes, to_value, to_writer, BencodedValue,
+        HashMap,
+    };
+    use maplit::hashmap;
+    use std::borrow::Cow;
+
+    #[test]
+    fn test_roundtrip() {
+        let values = vec![
+            BencodedValue::from(b"ab\xFF" as &[u8]),
+            BencodedValue::from(vec![0xFF; 1000]),
+            BencodedValue::from(""),
+            BencodedValue::from("hello".to_owned()),
+            BencodedValue::Integer(0),
+            BencodedValue::Integer(-10),
+            BencodedValue::Integer(i64::MIN),
+            BencodedValue::Integer(i64::MAX),
+            BencodedValue::List(vec![]),
+            BencodedValue::from(vec![
+                BencodedValue::Integer(1),
+                BencodedValue::from(vec![BencodedValue::from("a")]),
+            ]),
+            BencodedValue::from(hashmap! {
+                "a".into() => BencodedValue::Integer(1),
+                "piece length".into() => BencodedValue::from(hashmap! {
+                    "b".into() => BencodedValue::from("c"),
+                }),
+            }),
+            BencodedValue::Raw(Cow::Borrowed(b"d1:ai1ee")),
+            BencodedValue::None,
+        ];
+
+        for value in values {
+            let bytes = value.to_vec().unwrap();
+            assert_eq!(bytes.len(), value.encoded_len());
+            assert_eq!(bytes.capacity(), value.encoded_len());
+            assert_eq!(parse_all(&bytes).unwrap().1, value);
+
+            let mut written = Vec::new();
+            let len = value.to_writer(&mut written).unwrap();
+            assert_eq!(written, bytes);
+            assert_eq!(len, bytes.len());
+        }
+    }
+
+    #[test]
+    fn test_written_len() {
+        fn check(
+            write: impl Fn(&mut Vec<u8>) -> std::io::Result<usize>,
+            expected: &[u8],
+        ) {
+            let mut bytes = b"prefix".to_vec();
+            assert_eq!(write(&mut bytes).unwrap(), expected.len());
+            assert_eq!(&bytes[6..], expected);
+        }
+
+        let dict = hashmap! {
+            "b".into() => BencodedValue::from(vec![BencodedValue::from("")]),
+            "a".into() => BencodedValue::from(hashmap! {}),
+        };
+
+        check(|w| write_bin(b"", w), b"0:");
+        check(|w| write_bin(b"\xFF\xFE", w), b"2:\xFF\xFE");
+        check(|w| write_str("", w), b"0:");
+        check(|w| write_str("spam", w), b"4:spam");
+        check(|w| write_int(-42, w), b"i-42e");
+        check(|w| write_int(0, w), b"i0e");
+        check(|w| write_int(i64::MIN, w), b"i-9223372036854775808e");
+        check(|w| write_uint(u64::MAX, w), b"i18446744073709551615e");
+        check(|w| write_list(&[], w), b"le");
+        check(|w| write_dict(&HashMap::new(), w), b"de");
+        check(|w| write_dict(&dict, w), b"d1:ade1:bl0:ee");
+        check(|w| write(&BencodedValue::None, w), b"");
+        check(
+            |w| write(&BencodedValue::Raw(Cow::Borrowed(b"i1e")), w),
+            b"i1e",
+        );
+        check(
+            |w| write_checked(&BencodedValue::from(dict.clone()), w),
+            b"d1:ade1:bl0:ee",
+        );
+        check(
+            |w| to_writer(&dict, w).map_err(Into::into),
+            b"d1:ade1:bl0:ee",
+        );
+    }
+
+    #[test]
+    fn test_sorted_keys() {
+        let map = |keys: &[&str]| {
+            keys.iter()
+                .map(|key| (key.to_string(), key.len()))
+                .collect::<std::collections::HashMap<_, _>>()
+        };
+
+        let keys = (0..100).map(|i| format!("key{}", i)).collect::<Vec<_>>();
+        let keys = keys.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut reversed = keys.clone();
+        reversed.reverse();
+        let bytes = to_bytes(&map(&keys)).unwrap();
+        assert_eq!(to_bytes(&map(&keys)).unwrap(), bytes);
+        assert_eq!(to_bytes(&map(&reversed)).unwrap(), bytes);
+        assert!(is_canonical(&bytes));
+
+        let torrent = BencodedValue::from(hashmap! {
+            "info".into() => BencodedValue::Integer(1),
+            "announce-list".into() => BencodedValue::Integer(2),
+            "announce".into() => BencodedValue::Integer(3),
+        });
+        assert_eq!(
+            torrent.to_vec().unwrap(),
+            b"d8:announcei3e13:announce-listi2e4:infoi1ee"
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_write_async() {
+        use super::write_async;
+        use crate::{parser::parse_from_async_reader, ser::to_async_writer};
+        use tokio::io::duplex;
+
+        const TORRENT: &[u8] = include_bytes!("../benches/test_data");
+        let torrent = parse_all(TORRENT).unwrap().1;
+        let map = hashmap! { "b" => 1, "a" => 2 };
+
+        let (mut client, mut server) = duplex(64);
+        let expected = torrent.to_vec().unwrap();
+        let writer = tokio::spawn(async move {
+            let written = write_async(&torrent, &mut server).await.unwrap();
+            assert_eq!(written, torrent.encoded_len());
+            to_async_writer(&map, &mut server).await.unwrap()
+        });
+
+        let value = parse_from_async_reader(&mut client).await.unwrap();
+        assert_eq!(value.to_vec().unwrap(), expected);
+        assert!(is_canonical(&expected));
+
+        let value = parse_from_async_reader(&mut client).await.unwrap();
+        assert_eq!(value.to_vec().unwrap(), b"d1:ai2e1:bi1ee");
+        assert_eq!(writer.await.unwrap(), value.encoded_len());
+
+        let nested = BencodedValue::from(vec![BencodedValue::None]);
+        assert!(write_async(&nested, &mut Vec::new()).await.is_err());
+        assert_eq!(
+            write_async(&BencodedValue::None, &mut Vec::new())
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_write_buffered() {
+        use super::write_buffered;
+        use crate::ser::to_writer_buffered;
+        use std::io::{self, Write};
+
+        /// Counts the write calls of a byte vec
+        #[derive(Default)]
+        struct Counting {
+            bytes: Vec<u8>,
+            writes: usize,
+        }
+
+        impl Write for Counting {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.writes += 1;
+                self.bytes.write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let map = (0..1000)
+            .map(|i| (format!("key{:04}", i), i))
+            .collect::<std::collections::HashMap<_, _>>();
+        let value = to_value(&map).unwrap();
+
+        let mut unbuffered = Counting::default();
+        write(&value, &mut unbuffered).unwrap();
+        assert!(unbuffered.writes > 2000);
+
+        let mut buffered = Counting::default();
+        let written = write_buffered(&value, &mut buffered).unwrap();
+        assert_eq!(written, unbuffered.bytes.len());
+        assert_eq!(buffered.bytes, unbuffered.bytes);
+        assert!(buffered.writes <= 3, "{} writes", buffered.writes);
+
+        let mut serialized = Counting::default();
+        to_writer_buffered(&map, &mut serialized).unwrap();
+        assert_eq!(serialized.bytes, unbuffered.bytes);
+        assert!(serialized.writes <= 3, "{} writes", serialized.writes);
+
+        // Large strings are written directly
+        let large = BencodedValue::from(vec![0xFF; 1 << 20]);
+        let mut buffered = Counting::default();
+        write_buffered(&large, &mut buffered).unwrap();
+        assert_eq!(buffered.bytes, large.to_vec().unwrap());
+        assert!(buffered.writes <= 2, "{} writes", buffered.writes);
+    }
+
+    #[test]
+    fn test_none() {
+        let mut bytes = Vec::new();
+        write(&BencodedValue::None, &mut bytes).unwrap();
+        assert!(bytes.is_empty());
+
+        let list = BencodedValue::from(vec![
+            BencodedValue::Integer(1),
+            BencodedValue::None,
+        ]);
+        assert!(write(&list, &mut Vec::new()).is_err());
+
+        let dict = BencodedValue::from(hashmap! {
+            "key".into() => BencodedValue::None,
+        });
+        assert!(write(&dict, &mut Vec::new()).is_err());
+
+        let nested = BencodedValue::from(vec![dict]);
+        assert!(write(&nested, &mut Vec::new()).is_err());
+
+        // Encoding to a vec fails the same way as serializing
+        assert_eq!(BencodedValue::None.to_vec(), Ok(Vec::new()));
+        assert_eq!(list.to_vec(), Err(Error::NestedNone));
+        assert_eq!(nested.to_vec(), Err(Error::NestedNone));
+        assert_eq!(to_bytes(&vec![Some(1), None]), Err(Error::NestedNone));
+    }
+
+    #[test]
+    fn test_canonical() {
+        assert!(is_canonical(b"i0e"));
+        assert!(is_canonical(b"i-12e"));
+        assert!(is_canonical(b"0:"));
+        assert!(is_canonical(b"3:abc"));
+        assert!(is_canonical(b"le"));
+        assert!(is_canonical(b"d1:ai1e1:bli2e3:cowee"));
+
+        assert!(!is_canonical(b""));
+        assert!(!is_canonical(b"i-0e"));
+        assert!(!is_canonical(b"i03e"));
+        assert!(!is_canonical(b"03:abc"));
+        assert!(!is_canonical(b"4:abc"));
+        assert!(!is_canonical(b"d1:bi1e1:ai2ee"));
+        assert!(!is_canonical(b"d1:ai1e1:ai2ee"));
+        assert!(!is_canonical(b"li1e"));
+        assert!(!is_canonical(b"i1ei2e"));
+        assert!(!is_canonical(b"ie"));
+        assert!(!is_canonical(b"i-e"));
+        assert!(!is_canonical(b"i+1e"));
+        assert!(!is_canonical(b"i1.5e"));
+        assert!(!is_canonical(b"+3:abc"));
+        assert!(!is_canonical(b"d1:ai1e"));
+        assert!(!is_canonical(b"d1:ae"));
+        assert!(!is_canonical(b"dli1ee1:ae"));
+
+        assert!(is_canonical(b"i18446744073709551615e"));
+        assert!(!is_canonical(b"i18446744073709551616e"));
+        assert!(is_canonical(b"i-9223372036854775808e"));
+        assert!(!is_canonical(b"i-9223372036854775809e"));
+    }
+
+    #[test]
+    fn test_canonical_deep() {
+        let depth = 200_000;
+        let mut nested = vec![b'l'; depth];
+        assert!(!is_canonical(&nested));
+
+        nested.extend(vec![b'e'; depth]);
+        assert!(is_canonical(&nested));
+
+        nested.push(b'e');
+        assert!(!is_canonical(&nested));
+
+        let raw = BencodedValue::Raw(Cow::Owned(vec![b'l'; depth]));
+        assert!(write_checked(&raw, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_write_raw() {
+        let value = BencodedValue::from(vec![
+            BencodedValue::Raw(Cow::Borrowed(b"d1:bi1e1:ai2ee")),
+            BencodedValue::Integer(3),
+        ]);
+
+        let mut unchecked = Vec::new();
+        write(&value, &mut unchecked).unwrap();
+        assert_eq!(unchecked, b"ld1:bi1e1:ai2eei3ee");
+
+        assert!(write_checked(&value, &mut Vec::new()).is_err());
+    }
 }
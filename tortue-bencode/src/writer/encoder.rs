@@ -0,0 +1,254 @@
+use super::{write_bin, write_int};
+use crate::{error::Error, io::Write, prelude::*};
+
+/// A list or dictionary opened by an [`Encoder`]
+enum Frame {
+    List,
+    Dict {
+        /// Last key written, the next one must be greater
+        previous: Option<String>,
+
+        /// Whether a key was written and its value is expected
+        value: bool,
+    },
+}
+
+/// Writes a single bencoded value piece by piece without building it first
+///
+/// The structure is checked as it is written: every dictionary key must be
+/// followed by a value, keys must be given in sorted order without
+/// duplicates and [`Encoder::end`] must close an open list or dictionary.
+/// Misuses are reported as errors before anything is written.
+pub struct Encoder<W: Write> {
+    writer: W,
+    stack: Vec<Frame>,
+    done: bool,
+}
+
+impl<W: Write> Encoder<W> {
+    /// Creates an encoder writing into the given writer
+    pub fn new(writer: W) -> Self {
+        Encoder {
+            writer,
+            stack: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// Opens a dictionary, closed by [`Encoder::end`]
+    pub fn begin_dict(&mut self) -> Result<&mut Self, Error> {
+        self.begin_value()?;
+        self.writer.write_all(b"d").map_err(Error::from)?;
+        self.stack.push(Frame::Dict {
+            previous: None,
+            value: false,
+        });
+        Ok(self)
+    }
+
+    /// Opens a list, closed by [`Encoder::end`]
+    pub fn begin_list(&mut self) -> Result<&mut Self, Error> {
+        self.begin_value()?;
+        self.writer.write_all(b"l").map_err(Error::from)?;
+        self.stack.push(Frame::List);
+        Ok(self)
+    }
+
+    /// Writes the next key of the innermost dictionary, it must be greater
+    /// than the previous one
+    pub fn key(&mut self, key: &str) -> Result<&mut Self, Error> {
+        match self.stack.last_mut() {
+            Some(Frame::Dict {
+                previous,
+                value: value @ false,
+            }) => {
+                match previous.as_deref() {
+                    Some(previous) if previous == key => {
+                        return Err(Error::DuplicateKey(key.to_owned()))
+                    }
+                    Some(previous) if previous > key => {
+                        return Err(Error::UnsortedKeys(
+                            previous.to_owned(),
+                            key.to_owned(),
+                        ))
+                    }
+                    _ => {}
+                }
+
+                write_bin(key.as_bytes(), &mut self.writer)
+                    .map_err(Error::from)?;
+                *previous = Some(key.to_owned());
+                *value = true;
+                Ok(self)
+            }
+            Some(Frame::Dict { .. }) => {
+                Err(Error::Misuse("the value of the previous key"))
+            }
+            _ => Err(Error::Misuse("a dictionary around the key")),
+        }
+    }
+
+    /// Writes an integer
+    pub fn int(&mut self, int: i64) -> Result<&mut Self, Error> {
+        self.begin_value()?;
+        write_int(int, &mut self.writer).map_err(Error::from)?;
+        self.end_value();
+        Ok(self)
+    }
+
+    /// Writes a byte string
+    pub fn bytes(&mut self, bytes: &[u8]) -> Result<&mut Self, Error> {
+        self.begin_value()?;
+        write_bin(bytes, &mut self.writer).map_err(Error::from)?;
+        self.end_value();
+        Ok(self)
+    }
+
+    /// Writes a string
+    pub fn str(&mut self, str: &str) -> Result<&mut Self, Error> {
+        self.bytes(str.as_bytes())
+    }
+
+    /// Closes the innermost list or dictionary
+    pub fn end(&mut self) -> Result<&mut Self, Error> {
+        match self.stack.last() {
+            Some(Frame::Dict { value: true, .. }) => {
+                return Err(Error::Misuse("the value of the last key"))
+            }
+            Some(_) => {}
+            None => return Err(Error::Misuse("an open list or dictionary")),
+        }
+
+        self.writer.write_all(b"e").map_err(Error::from)?;
+        self.stack.pop();
+        self.end_value();
+        Ok(self)
+    }
+
+    /// Returns the writer once a complete value was written
+    pub fn finish(self) -> Result<W, Error> {
+        if self.done {
+            Ok(self.writer)
+        } else if self.stack.is_empty() {
+            Err(Error::Misuse("a value"))
+        } else {
+            Err(Error::Misuse("closed lists and dictionaries"))
+        }
+    }
+
+    /// Checks that a value can be written next
+    fn begin_value(&self) -> Result<(), Error> {
+        match self.stack.last() {
+            None if self.done => Err(Error::Misuse("a single value")),
+            Some(Frame::Dict { value: false, .. }) => {
+                Err(Error::Misuse("a dictionary key"))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Records that a value was written
+    fn end_value(&mut self) {
+        match self.stack.last_mut() {
+            Some(Frame::Dict { value, .. }) => *value = false,
+            Some(Frame::List) => {}
+            None => self.done = true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod encoder_tests {
+    use super::Encoder;
+    use crate::{error::Error, parse_all, BencodedValue};
+    use maplit::hashmap;
+
+    #[test]
+    fn test_metainfo() -> Result<(), Error> {
+        let pieces = [0xAB; 60];
+        let mut encoder = Encoder::new(Vec::new());
+        encoder
+            .begin_dict()?
+            .key("announce")?
+            .str("udp://tracker:80")?
+            .key("announce-list")?
+            .begin_list()?
+            .begin_list()?
+            .str("udp://tracker:80")?
+            .end()?
+            .end()?
+            .key("info")?
+            .begin_dict()?
+            .key("length")?
+            .int(355856562)?
+            .key("name")?
+            .str("bbb.mp4")?
+            .key("piece length")?
+            .int(1 << 18)?
+            .key("pieces")?
+            .bytes(&pieces)?
+            .end()?
+            .end()?;
+        let bytes = encoder.finish()?;
+
+        let expected = BencodedValue::from(hashmap! {
+            "announce".into() => BencodedValue::from("udp://tracker:80"),
+            "announce-list".into() => BencodedValue::from(vec![
+                BencodedValue::from(vec![
+                    BencodedValue::from("udp://tracker:80"),
+                ]),
+            ]),
+            "info".into() => BencodedValue::from(hashmap! {
+                "length".into() => BencodedValue::Integer(355856562),
+                "name".into() => BencodedValue::from("bbb.mp4"),
+                "piece length".into() => BencodedValue::Integer(1 << 18),
+                "pieces".into() => BencodedValue::from(&pieces[..]),
+            }),
+        });
+        assert_eq!(parse_all(&bytes).unwrap().1, expected);
+        assert_eq!(bytes, expected.to_vec()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_misuse() {
+        fn misused(
+            encode: impl Fn(&mut Encoder<Vec<u8>>) -> Result<(), Error>,
+        ) -> (Error, Vec<u8>) {
+            let mut encoder = Encoder::new(Vec::new());
+            let error = encode(&mut encoder).unwrap_err();
+            (error, encoder.writer)
+        }
+
+        let (error, written) =
+            misused(|e| e.begin_dict()?.key("b")?.int(1)?.key("a").map(drop));
+        assert_eq!(error, Error::UnsortedKeys("b".into(), "a".into()));
+        assert_eq!(written, b"d1:bi1e");
+
+        let (error, _) =
+            misused(|e| e.begin_dict()?.key("a")?.int(1)?.key("a").map(drop));
+        assert_eq!(error, Error::DuplicateKey("a".into()));
+
+        // Nothing is written by a misuse
+        type Misuse = fn(&mut Encoder<Vec<u8>>) -> Result<(), Error>;
+        let misuses: [Misuse; 6] = [
+            |e| e.begin_dict()?.int(1).map(drop),
+            |e| e.begin_dict()?.key("a")?.end().map(drop),
+            |e| e.begin_dict()?.key("a")?.key("b").map(drop),
+            |e| e.begin_list()?.key("a").map(drop),
+            |e| e.end().map(drop),
+            |e| e.int(1)?.int(2).map(drop),
+        ];
+        let written: [&[u8]; 6] = [b"d", b"d1:a", b"d1:a", b"l", b"", b"i1e"];
+        for (misuse, expected) in misuses.iter().zip(&written) {
+            let (error, written) = misused(misuse);
+            assert!(matches!(error, Error::Misuse(_)));
+            assert_eq!(written, *expected);
+        }
+
+        assert!(Encoder::new(Vec::new()).finish().is_err());
+        let mut unclosed = Encoder::new(Vec::new());
+        unclosed.begin_list().unwrap();
+        assert!(unclosed.finish().is_err());
+    }
+}
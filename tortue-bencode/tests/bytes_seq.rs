@@ -0,0 +1,53 @@
+//! Makes sure that binaries read as sequences of bytes are not copied into
+//! intermediate values
+
+use serde::Deserialize;
+use tortue_bencode::{from_bytes, from_value_ref, parse_all, to_bytes};
+
+mod common;
+
+use common::{measure, CountingAllocator, LOCK};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const LEN: usize = 1 << 20;
+
+#[derive(Deserialize)]
+struct Seq {
+    pieces: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct Bytes {
+    #[serde(with = "serde_bytes")]
+    pieces: Vec<u8>,
+}
+
+#[test]
+fn test_allocations() {
+    let _guard = LOCK.lock().unwrap();
+
+    let pieces = (0..LEN).map(|i| i as u8).collect::<Vec<_>>();
+    let mut data = b"d6:pieces".to_vec();
+    data.extend(to_bytes(&serde_bytes::Bytes::new(&pieces)).unwrap());
+    data.push(b'e');
+
+    // Read as bytes, the output is allocated once at its final size
+    let (bytes, usage) = measure(|| from_bytes::<Bytes>(&data).unwrap());
+    assert_eq!(bytes.pieces, pieces);
+    assert_eq!(usage.reallocations, 0, "{:?}", usage);
+    assert!(usage.peak < LEN + 1024, "{:?}", usage);
+
+    // Read as a sequence, serde may cap the size hint and grow the output as
+    // it goes, which costs at most the output and half of it but never a copy
+    // of the binary
+    let (seq, usage) = measure(|| from_bytes::<Seq>(&data).unwrap());
+    assert_eq!(seq.pieces, pieces);
+    assert!(usage.peak < LEN * 3 / 2 + 1024, "{:?}", usage);
+
+    let (_, value) = parse_all(&data).unwrap();
+    let (seq, usage) = measure(|| from_value_ref::<Seq>(&value).unwrap());
+    assert_eq!(seq.pieces, pieces);
+    assert!(usage.peak < LEN * 3 / 2 + 1024, "{:?}", usage);
+}
@@ -0,0 +1,132 @@
+//! Helpers shared by the integration tests: deserializing with every
+//! deserializer, and a counting allocator for the tests measuring memory
+//! usage.
+//!
+//! Each test binary using the allocator must register it with:
+//! `#[global_allocator] static ALLOCATOR: CountingAllocator = CountingAllocator;`
+
+#![allow(dead_code)]
+
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fmt::Debug,
+    ptr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+use tortue_bencode::{
+    error::Error, from_bytes, from_value, from_value_ref, parse_all,
+    BencodedValue,
+};
+
+/// Deserializes with every deserializer, checking that they agree
+///
+/// `value` is the parsed `data`, both live as long so that structures
+/// borrowing from the input can be deserialized from either.
+pub fn deserialize_parsed<'de, T>(
+    data: &'de [u8],
+    value: &'de BencodedValue,
+) -> Result<T, Error>
+where
+    T: Deserialize<'de> + PartialEq + Debug,
+{
+    let tokens = from_bytes::<T>(data);
+    assert_eq!(from_value::<T>(value.to_borrowed()), tokens);
+    assert_eq!(from_value_ref::<T>(value), tokens);
+    tokens
+}
+
+/// Same as [`deserialize_parsed`] parsing the data first, for structures
+/// which do not borrow from the input
+pub fn deserialize<T>(data: &[u8]) -> Result<T, Error>
+where
+    T: DeserializeOwned + PartialEq + Debug,
+{
+    let (_, value) = parse_all(data).unwrap();
+    deserialize_parsed(data, &value)
+}
+
+/// Allocator keeping track of the number of allocations and reallocations,
+/// the current and the peak heap usage
+pub struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static REALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// Tests share the counters so they must not run concurrently
+pub static LOCK: Mutex<()> = Mutex::new(());
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            ALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+            let current = CURRENT.fetch_add(layout.size(), Ordering::SeqCst)
+                + layout.size();
+            PEAK.fetch_max(current, Ordering::SeqCst);
+        }
+
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+
+    /// Moves the block as the default implementation does, both blocks count
+    /// towards the peak
+    unsafe fn realloc(
+        &self,
+        ptr: *mut u8,
+        layout: Layout,
+        new_size: usize,
+    ) -> *mut u8 {
+        REALLOCATIONS.fetch_add(1, Ordering::SeqCst);
+        let new_layout =
+            Layout::from_size_align_unchecked(new_size, layout.align());
+        let new = self.alloc(new_layout);
+        if !new.is_null() {
+            ptr::copy_nonoverlapping(ptr, new, layout.size().min(new_size));
+            self.dealloc(ptr, layout);
+        }
+
+        new
+    }
+}
+
+/// Memory used while running a closure
+#[derive(Debug, Clone, Copy)]
+pub struct Usage {
+    /// Number of allocations performed, reallocations included
+    pub allocations: usize,
+
+    /// Number of blocks grown or shrunk
+    pub reallocations: usize,
+
+    /// Peak heap usage in bytes, above the usage before the call
+    pub peak: usize,
+}
+
+/// Measures the allocations and peak heap usage of `f`
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, Usage) {
+    let start = CURRENT.load(Ordering::SeqCst);
+    let allocations = ALLOCATIONS.load(Ordering::SeqCst);
+    let reallocations = REALLOCATIONS.load(Ordering::SeqCst);
+    PEAK.store(start, Ordering::SeqCst);
+
+    let out = f();
+
+    let usage = Usage {
+        allocations: ALLOCATIONS.load(Ordering::SeqCst) - allocations,
+        reallocations: REALLOCATIONS.load(Ordering::SeqCst) - reallocations,
+        peak: PEAK.load(Ordering::SeqCst) - start,
+    };
+
+    (out, usage)
+}
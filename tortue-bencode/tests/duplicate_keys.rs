@@ -0,0 +1,92 @@
+//! Makes sure that duplicated and unsorted dictionary keys are handled
+//! deterministically
+
+use nom::Err::Failure;
+use serde::Deserialize;
+use tortue_bencode::{
+    error::Error,
+    from_bytes_with_options,
+    parser::{duplicate_key, parse_all_with_options, DUPLICATE_KEY},
+    BencodedValue, ParserOptions,
+};
+
+const TOP_LEVEL: &[u8] = b"d8:announce3:one8:announce3:twoe";
+const NESTED: &[u8] = b"d4:infod6:lengthi1e4:name1:a4:name1:bee";
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Info {
+    name: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Torrent {
+    info: Info,
+}
+
+fn rejecting() -> ParserOptions {
+    ParserOptions {
+        reject_duplicate_keys: true,
+        ..ParserOptions::default()
+    }
+}
+
+fn get<'a>(value: &'a BencodedValue, path: &[&str]) -> &'a BencodedValue<'a> {
+    path.iter().fold(value, |value, key| match value {
+        BencodedValue::Dictionary(dict) => &dict[*key],
+        _ => panic!("expected a dictionary"),
+    })
+}
+
+#[test]
+fn test_reject() {
+    let error = parse_all_with_options(TOP_LEVEL, &rejecting()).unwrap_err();
+    assert_eq!(error, Failure((&TOP_LEVEL[16..], DUPLICATE_KEY)));
+    assert_eq!(duplicate_key(&error), Some("announce"));
+
+    let error = parse_all_with_options(NESTED, &rejecting()).unwrap_err();
+    assert_eq!(duplicate_key(&error), Some("name"));
+
+    assert_eq!(
+        from_bytes_with_options::<Torrent>(NESTED, &rejecting()),
+        Err(Error::DuplicateKey("name".to_owned()))
+    );
+    assert_eq!(
+        from_bytes_with_options::<Torrent>(NESTED, &ParserOptions::strict()),
+        Err(Error::DuplicateKey("name".to_owned()))
+    );
+}
+
+#[test]
+fn test_lenient() {
+    let mut options = ParserOptions::default();
+
+    let (_, value) = parse_all_with_options(TOP_LEVEL, &options).unwrap();
+    assert_eq!(get(&value, &["announce"]), &BencodedValue::from("two"));
+
+    let (_, value) = parse_all_with_options(NESTED, &options).unwrap();
+    assert_eq!(get(&value, &["info", "name"]), &BencodedValue::from("b"));
+
+    options.first_duplicate_wins = true;
+
+    let (_, value) = parse_all_with_options(TOP_LEVEL, &options).unwrap();
+    assert_eq!(get(&value, &["announce"]), &BencodedValue::from("one"));
+
+    let torrent: Torrent = from_bytes_with_options(NESTED, &options).unwrap();
+    assert_eq!(torrent.info.name, "a");
+}
+
+#[test]
+fn test_unsorted() {
+    let input = b"d4:infod4:name1:a6:lengthi1eee";
+    assert_eq!(
+        from_bytes_with_options::<Torrent>(input, &ParserOptions::strict()),
+        Err(Error::UnsortedKeys("name".to_owned(), "length".to_owned()))
+    );
+
+    let torrent = include_bytes!("../benches/test_data");
+    assert!(from_bytes_with_options::<BencodedValue>(
+        torrent,
+        &ParserOptions::strict()
+    )
+    .is_ok());
+}
@@ -0,0 +1,152 @@
+//! Makes sure that enums roundtrip in every representation
+
+use serde::{Deserialize, Serialize};
+use tortue_bencode::{
+    from_bytes, from_value, from_value_ref, parse_all, to_bytes, writer,
+    EnumRepresentation, SerializerBuilder,
+};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+enum Message {
+    Choke,
+    Have(i64),
+    Request(i64, i64, i64),
+    Piece { index: i64, block: String },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+enum Untagged {
+    Have(i64),
+    Request(i64, i64, i64),
+    Piece { index: i64, block: String },
+}
+
+fn messages() -> Vec<Message> {
+    vec![
+        Message::Choke,
+        Message::Have(3),
+        Message::Request(1, 0, 16384),
+        Message::Piece {
+            index: 1,
+            block: "data".to_owned(),
+        },
+    ]
+}
+
+/// Serializes through both the streaming serializer and `to_value`, making
+/// sure that they agree
+fn serialize<T: Serialize>(builder: &SerializerBuilder, value: &T) -> Vec<u8> {
+    let bytes = builder.to_bytes(value).unwrap();
+
+    let mut written = Vec::new();
+    writer::write(&builder.to_value(value).unwrap(), &mut written).unwrap();
+    assert_eq!(bytes, written);
+
+    bytes
+}
+
+#[test]
+fn test_external() {
+    let builder = SerializerBuilder::new()
+        .enum_representation(EnumRepresentation::External);
+    let expected: [&[u8]; 4] = [
+        b"5:Choke",
+        b"d4:Havei3ee",
+        b"d7:Requestli1ei0ei16384eee",
+        b"d5:Pieced5:block4:data5:indexi1eee",
+    ];
+
+    for (message, expected) in messages().iter().zip(&expected) {
+        let bytes = serialize(&builder, message);
+        assert_eq!(bytes, *expected);
+        assert_eq!(to_bytes(message).unwrap(), bytes);
+        assert_eq!(from_bytes::<Message>(&bytes).as_ref(), Ok(message));
+
+        let value = builder.to_value(message).unwrap();
+        assert_eq!(from_value_ref::<Message>(&value).as_ref(), Ok(message));
+        assert_eq!(from_value::<Message>(value).as_ref(), Ok(message));
+    }
+}
+
+#[test]
+fn test_adjacent() {
+    let builder = SerializerBuilder::new()
+        .enum_representation(EnumRepresentation::Adjacent);
+    let expected: [&[u8]; 4] = [
+        b"5:Choke",
+        b"d1:ci3e1:t4:Havee",
+        b"d1:cli1ei0ei16384ee1:t7:Requeste",
+        b"d1:cd5:block4:data5:indexi1ee1:t5:Piecee",
+    ];
+
+    for (message, expected) in messages().iter().zip(&expected) {
+        let bytes = serialize(&builder, message);
+        assert_eq!(bytes, *expected);
+        assert_eq!(from_bytes::<Message>(&bytes).as_ref(), Ok(message));
+
+        let value = builder.to_value(message).unwrap();
+        assert_eq!(from_value_ref::<Message>(&value).as_ref(), Ok(message));
+        assert_eq!(from_value::<Message>(value).as_ref(), Ok(message));
+    }
+
+    // Nested in a dictionary, the tag must still follow the content
+    let nested = vec![("message", Message::Have(3))]
+        .into_iter()
+        .collect::<std::collections::HashMap<_, _>>();
+    assert_eq!(
+        serialize(&builder, &nested),
+        b"d7:messaged1:ci3e1:t4:Haveee"
+    );
+}
+
+#[test]
+fn test_untagged() {
+    let builder = SerializerBuilder::new()
+        .enum_representation(EnumRepresentation::Untagged);
+    let expected: [&[u8]; 4] = [
+        b"5:Choke",
+        b"i3e",
+        b"li1ei0ei16384ee",
+        b"d5:block4:data5:indexi1ee",
+    ];
+
+    for (message, expected) in messages().iter().zip(&expected) {
+        assert_eq!(serialize(&builder, message), *expected);
+    }
+
+    // Unit variants are their name, deserialized as any other enum
+    assert_eq!(from_bytes::<Message>(b"5:Choke"), Ok(Message::Choke));
+
+    let untagged = [
+        Untagged::Have(3),
+        Untagged::Request(1, 0, 16384),
+        Untagged::Piece {
+            index: 1,
+            block: "data".to_owned(),
+        },
+    ];
+    for (message, expected) in untagged.iter().zip(&expected[1..]) {
+        let bytes = serialize(&builder, message);
+        assert_eq!(bytes, *expected);
+        assert_eq!(from_bytes::<Untagged>(&bytes).as_ref(), Ok(message));
+
+        let value = builder.to_value(message).unwrap();
+        assert_eq!(from_value_ref::<Untagged>(&value).as_ref(), Ok(message));
+    }
+}
+
+#[test]
+fn test_invalid() {
+    assert!(from_bytes::<Message>(b"5:Other").is_err());
+    assert!(from_bytes::<Message>(b"i3e").is_err());
+    assert!(from_bytes::<Message>(b"de").is_err());
+    assert!(from_bytes::<Message>(b"d4:Havei3e7:Requesti1ee").is_err());
+    assert!(from_bytes::<Message>(b"d5:Chokei1ee").is_err());
+    assert!(from_bytes::<Message>(b"d4:Have3:abce").is_err());
+
+    let (_, value) = parse_all(b"d4:Havei3e7:Requesti1ee").unwrap();
+    assert!(from_value_ref::<Message>(&value).is_err());
+    let (_, value) = parse_all(b"d5:Chokei1ee").unwrap();
+    assert!(from_value_ref::<Message>(&value).is_err());
+}
@@ -0,0 +1,65 @@
+//! Makes sure that the errors of values of the wrong type are built without
+//! allocating, so that deserializers trying several types stay cheap
+
+use serde::Deserialize;
+use tortue_bencode::{
+    error::Error, from_value, from_value_ref, parse_all, BencodedValue,
+};
+
+mod common;
+
+use common::{measure, CountingAllocator, LOCK};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Peer<'a> {
+    ip: &'a str,
+    port: u16,
+}
+
+#[test]
+fn test_mismatches() {
+    let _guard = LOCK.lock().unwrap();
+
+    let (_, string) = parse_all(b"4:spam").unwrap();
+    let (result, usage) = measure(|| from_value_ref::<i64>(&string));
+    assert_eq!(
+        result,
+        Err(Error::UnexpectedType {
+            expected: "integer",
+            found: "string",
+        })
+    );
+    assert_eq!(usage.allocations, 0, "{:?}", usage);
+
+    let int = BencodedValue::Integer(1);
+    let (result, usage) = measure(|| from_value::<Vec<i64>>(int));
+    assert!(matches!(result, Err(Error::UnexpectedType { .. })));
+    assert_eq!(usage.allocations, 0, "{:?}", usage);
+
+    let (_, peer) = parse_all(b"d2:ip8:10.0.0.14:port4:8080e").unwrap();
+    let (result, usage) = measure(|| from_value_ref::<Peer>(&peer));
+    assert!(matches!(result, Err(Error::UnexpectedType { .. })));
+    assert_eq!(usage.allocations, 0, "{:?}", usage);
+
+    let (result, usage) = measure(|| from_value_ref::<(i64, i64)>(&peer));
+    assert!(matches!(result, Err(Error::UnexpectedType { .. })));
+    assert_eq!(usage.allocations, 0, "{:?}", usage);
+}
+
+#[test]
+fn test_missing_field() {
+    let _guard = LOCK.lock().unwrap();
+
+    let (_, peer) = parse_all(b"d2:ip8:10.0.0.1e").unwrap();
+    let (result, usage) = measure(|| from_value_ref::<Peer>(&peer));
+    assert_eq!(result, Err(Error::MissingField("port")));
+    assert_eq!(usage.allocations, 0, "{:?}", usage);
+
+    let value = BencodedValue::Integer(1);
+    let (result, usage) = measure(|| from_value_ref::<Peer>(&value));
+    assert!(matches!(result, Err(Error::UnexpectedType { .. })));
+    assert_eq!(usage.allocations, 0, "{:?}", usage);
+}
@@ -0,0 +1,138 @@
+//! Makes sure that failures are reported as specific error variants, leaving
+//! `Error::Message` to the custom errors of serde implementations
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use tortue_bencode::{
+    error::Error,
+    from_bytes, from_bytes_with_options,
+    parser::{lazy, parse_from_reader},
+    to_bytes,
+    writer::Encoder,
+    BoolMode, ParserOptions,
+};
+
+mod common;
+
+use common::deserialize;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Peer {
+    ip: String,
+    port: u16,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Strict {
+    port: u16,
+}
+
+#[test]
+fn test_unexpected_type() {
+    let error = Error::UnexpectedType {
+        expected: "integer",
+        found: "list",
+    };
+    assert_eq!(
+        deserialize::<Peer>(b"d2:ip3:::14:portlee"),
+        Err(error.clone())
+    );
+    assert_eq!(error.to_string(), "expected integer, found list");
+
+    assert_eq!(
+        deserialize::<Vec<i64>>(b"i1e"),
+        Err(Error::UnexpectedType {
+            expected: "list",
+            found: "integer",
+        })
+    );
+}
+
+#[test]
+fn test_fields() {
+    let error = Error::MissingField("port");
+    assert_eq!(deserialize::<Peer>(b"d2:ip3:::1e"), Err(error.clone()));
+    assert_eq!(error.to_string(), "missing field `port`");
+
+    let error = Error::UnknownField {
+        field: "ip".to_owned(),
+        expected: &["port"],
+    };
+    assert_eq!(deserialize::<Strict>(b"d2:ip3:::1e"), Err(error.clone()));
+    assert_eq!(
+        error.to_string(),
+        "unknown field `ip`, expected one of `port`"
+    );
+}
+
+#[test]
+fn test_out_of_range() {
+    let error = Error::OutOfRange {
+        value: "65536".to_owned(),
+        target: "u16",
+    };
+    assert_eq!(
+        deserialize::<Peer>(b"d2:ip3:::14:porti65536ee"),
+        Err(error.clone())
+    );
+    assert_eq!(error.to_string(), "integer 65536 is out of range for u16");
+
+    assert_eq!(
+        deserialize::<BTreeMap<u16, i64>>(b"d4:porti1ee"),
+        Err(Error::InvalidKey("port".to_owned()))
+    );
+}
+
+#[test]
+fn test_strings() {
+    assert_eq!(
+        deserialize::<char>(b"2:ab"),
+        Err(Error::InvalidLength {
+            expected: 1,
+            found: 2,
+        })
+    );
+    assert_eq!(
+        from_bytes::<String>(b"3:a\xFFb"),
+        Err(Error::InvalidUtf8 { valid_up_to: 1 })
+    );
+}
+
+#[test]
+fn test_options() {
+    let options = ParserOptions {
+        bools: BoolMode::Rejected,
+        ..ParserOptions::default()
+    };
+    assert_eq!(
+        from_bytes_with_options::<bool>(b"i1e", &options),
+        Err(Error::Rejected("bool"))
+    );
+}
+
+#[test]
+fn test_input() {
+    assert_eq!(lazy(b"i1ei2e"), Err(Error::TrailingData { remaining: 3 }));
+
+    // The reader locates the byte which cannot start a value
+    match parse_from_reader(&b"li1ex"[..]) {
+        Err(Error::Parse(error)) => {
+            assert_eq!(error.offset, 4);
+            assert_eq!(error.preview, b"x");
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn test_writing() {
+    assert_eq!(to_bytes(&vec![None::<i64>]), Err(Error::NestedNone));
+
+    let mut encoder = Encoder::new(Vec::new());
+    encoder.begin_dict().unwrap();
+    assert_eq!(
+        encoder.int(1).err(),
+        Some(Error::Misuse("a dictionary key"))
+    );
+}
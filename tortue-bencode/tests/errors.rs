@@ -0,0 +1,87 @@
+//! Makes sure that serialization failures surface as typed errors
+
+use serde::Serialize;
+use std::{
+    error::Error as _,
+    fmt,
+    io::{self, Write},
+};
+use tortue_bencode::{
+    error::Error, from_bytes, to_bytes, to_writer, to_writer_buffered,
+    SerializerBuilder,
+};
+
+/// Cause of the failures of [`Failing`]
+#[derive(Debug)]
+struct Closed;
+
+impl fmt::Display for Closed {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("closed")
+    }
+}
+
+impl std::error::Error for Closed {}
+
+/// Writer failing on every write
+struct Failing;
+
+impl Write for Failing {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, Closed))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Serialize)]
+struct Unit;
+
+#[test]
+fn test_io() {
+    let error = to_writer(&vec![1, 2], &mut Failing).unwrap_err();
+    let closed = io::Error::new(io::ErrorKind::BrokenPipe, "closed");
+    assert_eq!(error, Error::from(closed));
+    assert_eq!(error.to_string(), "io error: closed");
+
+    match to_writer_buffered(&"spam", &mut Failing) {
+        Err(Error::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::BrokenPipe),
+        result => panic!("unexpected result {:?}", result),
+    }
+
+    // The io error is the source, its own source is the cause of the failure
+    let source = error.source().unwrap().downcast_ref::<io::Error>().unwrap();
+    assert_eq!(source.kind(), io::ErrorKind::BrokenPipe);
+    assert!(source.get_ref().unwrap().is::<Closed>());
+
+    // The original io error is returned when converting back
+    let error: io::Error = error.into();
+    assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+    assert!(error.into_inner().unwrap().is::<Closed>());
+
+    // Other errors are wrapped
+    let error: io::Error = Error::NestedNone.into();
+    assert_eq!(error.kind(), io::ErrorKind::Other);
+    let inner = error.get_ref().unwrap().downcast_ref::<Error>();
+    assert_eq!(inner, Some(&Error::NestedNone));
+    assert!(Error::NestedNone.source().is_none());
+}
+
+#[test]
+fn test_serialize() {
+    // Units have no exact representation, strict serialization rejects them
+    let units = Error::UnsupportedType("unit");
+    let strict = SerializerBuilder::new().deny_lossy(true);
+    assert_eq!(strict.to_bytes(&()), Err(units.clone()));
+    assert_eq!(strict.to_value(&Unit), Err(units.clone()));
+    assert_eq!(strict.to_writer(&vec![()], &mut Vec::new()), Err(units));
+
+    assert_eq!(to_bytes(&vec![None::<i64>]), Err(Error::NestedNone));
+
+    // Serialization and deserialization share the error type
+    let result: Result<i64, Error> = to_bytes(&42)
+        .and_then(|bytes| from_bytes::<String>(&bytes).map(|s| s.len() as i64));
+    assert!(result.is_err());
+}
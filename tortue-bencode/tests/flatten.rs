@@ -0,0 +1,76 @@
+//! Makes sure that flattened structs keep borrowing their strings and
+//! binaries from the input, whatever the deserializer
+
+use serde::{Deserialize, Serialize};
+use tortue_bencode::{
+    from_bytes, from_bytes_with_options, from_value, from_value_ref, parse_all,
+    to_bytes, ParserOptions,
+};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct FileInfo<'a> {
+    name: &'a str,
+    length: i64,
+    #[serde(with = "serde_bytes")]
+    md5sum: &'a [u8],
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Info<'a> {
+    #[serde(rename = "piece length")]
+    piece_length: i64,
+    #[serde(with = "serde_bytes")]
+    pieces: &'a [u8],
+    private: Option<bool>,
+    #[serde(flatten, borrow)]
+    file: FileInfo<'a>,
+}
+
+fn info(md5sum: &[u8]) -> Info<'_> {
+    Info {
+        piece_length: 4,
+        pieces: b"\x01\x02\xFF\xFE",
+        private: None,
+        file: FileInfo {
+            name: "hello",
+            length: 64,
+            md5sum,
+        },
+    }
+}
+
+#[test]
+fn test_roundtrip() {
+    // Text and binary md5sums are parsed as strings and binaries
+    for &md5sum in &[&b"\x01\x02\x03\xFF"[..], b"0123456789abcdef"] {
+        let expected = info(md5sum);
+        let bytes = to_bytes(&expected).unwrap();
+        assert_eq!(from_bytes::<Info>(&bytes), Ok(info(md5sum)));
+
+        let (_, value) = parse_all(&bytes).unwrap();
+        assert_eq!(from_value::<Info>(value.clone()), Ok(info(md5sum)));
+        assert_eq!(from_value_ref::<Info>(&value), Ok(expected));
+    }
+}
+
+#[test]
+fn test_binary_strings() {
+    let options = ParserOptions {
+        binary_strings: true,
+        ..ParserOptions::default()
+    };
+    let info = info(b"0123456789abcdef");
+    let bytes = to_bytes(&info).unwrap();
+    assert_eq!(from_bytes_with_options::<Info>(&bytes, &options), Ok(info));
+}
+
+#[test]
+fn test_owned_value() {
+    // Only owned strings remain once the input is gone, they cannot be
+    // borrowed by the flattened struct
+    let bytes = to_bytes(&info(b"\x01")).unwrap();
+    let (_, value) = parse_all(&bytes).unwrap();
+    let value = value.into_owned();
+    assert!(from_value::<Info>(value.clone()).is_err());
+    assert_eq!(from_value_ref::<Info>(&value), Ok(info(b"\x01")));
+}
@@ -0,0 +1,138 @@
+//! Makes sure that ignored values, however large or nested, are skipped
+//! without disturbing the fields that follow them
+
+use serde::{de::IgnoredAny, Deserialize};
+use tortue_bencode::{
+    from_bytes, from_value, from_value_ref, parse_all, to_bytes,
+};
+
+mod common;
+
+use common::{measure, CountingAllocator, LOCK};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const BLOB: usize = 4 << 20;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Fields<'a> {
+    after: &'a str,
+    before: i64,
+    tail: Vec<i64>,
+}
+
+/// Compared through [`Fields`], `IgnoredAny` is only comparable with recent
+/// serde versions
+#[derive(Deserialize, Debug)]
+struct Ignoring<'a> {
+    after: &'a str,
+    before: i64,
+    #[allow(dead_code)]
+    ignored: IgnoredAny,
+    tail: Vec<i64>,
+}
+
+impl<'a> From<Ignoring<'a>> for Fields<'a> {
+    fn from(ignoring: Ignoring<'a>) -> Self {
+        Fields {
+            after: ignoring.after,
+            before: ignoring.before,
+            tail: ignoring.tail,
+        }
+    }
+}
+
+/// A dictionary of `width` entries holding lists and dictionaries nested
+/// `depth` times
+fn nested(width: usize, depth: usize) -> Vec<u8> {
+    let mut data = b"d".to_vec();
+    for i in 0..width {
+        let key = format!("{:08}", i);
+        data.extend(format!("{}:{}", key.len(), key).bytes());
+        data.extend(b"ld1:k".repeat(depth / 2));
+        data.extend(b"i-1e");
+        data.extend(b"ee".repeat(depth / 2));
+    }
+    data.push(b'e');
+    data
+}
+
+fn input(ignored: &[u8]) -> Vec<u8> {
+    let mut data = b"d5:after4:done6:beforei42e7:ignored".to_vec();
+    data.extend(ignored);
+    data.extend(b"4:tailli1ei2ei3eee");
+    data
+}
+
+fn expected() -> Fields<'static> {
+    Fields {
+        after: "done",
+        before: 42,
+        tail: vec![1, 2, 3],
+    }
+}
+
+/// Checks every deserializer, with the ignored value as an unknown field and
+/// as an [`IgnoredAny`] field
+fn check(data: &[u8]) {
+    let (_, value) = parse_all(data).unwrap();
+
+    assert_eq!(from_bytes::<Fields>(data), Ok(expected()));
+    assert_eq!(from_value::<Fields>(value.clone()), Ok(expected()));
+    assert_eq!(from_value_ref::<Fields>(&value), Ok(expected()));
+
+    let ignoring = from_bytes::<Ignoring>(data).map(Fields::from);
+    assert_eq!(ignoring, Ok(expected()));
+    let ignoring = from_value::<Ignoring>(value.clone()).map(Fields::from);
+    assert_eq!(ignoring, Ok(expected()));
+    let ignoring = from_value_ref::<Ignoring>(&value).map(Fields::from);
+    assert_eq!(ignoring, Ok(expected()));
+}
+
+#[test]
+fn test_nested_dict() {
+    let _guard = LOCK.lock().unwrap();
+
+    let data = input(&nested(2_000, 100));
+    check(&data);
+
+    // The skipped values are neither built nor copied
+    let (fields, usage) = measure(|| from_bytes::<Fields>(&data).unwrap());
+    assert_eq!(fields, expected());
+    assert!(usage.peak < 4096, "{:?}", usage);
+}
+
+#[test]
+fn test_blob() {
+    let _guard = LOCK.lock().unwrap();
+
+    let blob = (0..BLOB).map(|i| i as u8).collect::<Vec<_>>();
+    let data = input(&to_bytes(&serde_bytes::Bytes::new(&blob)).unwrap());
+    check(&data);
+
+    let (fields, usage) = measure(|| from_bytes::<Fields>(&data).unwrap());
+    assert_eq!(fields, expected());
+    assert!(usage.peak < 4096, "{:?}", usage);
+}
+
+#[test]
+fn test_sequence() {
+    type Tuple<'a> = (i64, IgnoredAny, &'a str, Vec<i64>);
+
+    // Compares the other elements, see `Ignoring`
+    fn fields((int, _, str, list): Tuple<'_>) -> (i64, &str, Vec<i64>) {
+        (int, str, list)
+    }
+
+    let data = b"li1ed1:ald1:bi0eeee3:endli2eee";
+    let expected = (1, "end", vec![2]);
+    let (_, value) = parse_all(data).unwrap();
+
+    assert_eq!(from_bytes::<Tuple>(data).map(fields), Ok(expected.clone()));
+    assert_eq!(
+        from_value::<Tuple>(value.clone()).map(fields),
+        Ok(expected.clone())
+    );
+    assert_eq!(from_value_ref::<Tuple>(&value).map(fields), Ok(expected));
+}
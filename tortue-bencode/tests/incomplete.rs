@@ -0,0 +1,69 @@
+//! Truncated inputs are reported apart from invalid ones
+
+use nom::{Err, Needed};
+use tortue_bencode::{
+    error::Error, from_bytes, parse_all, parse_prefix,
+    parser::parse_all_incomplete, BencodedValue,
+};
+
+const TORRENT: &[u8] = include_bytes!("../benches/test_data");
+
+fn needed(input: &[u8]) -> Option<usize> {
+    match from_bytes::<BencodedValue>(input) {
+        Err(Error::Incomplete { needed }) => needed,
+        other => panic!("expected incomplete input, got {:?}", other),
+    }
+}
+
+#[test]
+fn truncated_torrent() {
+    // Cutting anywhere leaves at least the end of the torrent dictionary
+    for len in (1..TORRENT.len()).step_by(97) {
+        assert!(needed(&TORRENT[..len]).unwrap() >= 1, "{}", len);
+    }
+
+    assert_eq!(needed(&TORRENT[..TORRENT.len() - 1]), Some(1));
+
+    // Inside the pieces, the hint covers the rest of the string
+    let pieces = TORRENT
+        .windows(7)
+        .position(|window| window == b"pieces2" || window == b"pieces1")
+        .unwrap();
+    let colon =
+        pieces + TORRENT[pieces..].iter().position(|&b| b == b':').unwrap();
+    let len: usize = std::str::from_utf8(&TORRENT[pieces + 6..colon])
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(needed(&TORRENT[..colon + 11]), Some(len - 10));
+}
+
+#[test]
+fn truncated_values() {
+    assert_eq!(needed(b"3:ab"), Some(1));
+    assert_eq!(needed(b"10:abc"), Some(7));
+    assert_eq!(needed(b"i12"), Some(1));
+    assert_eq!(needed(b"lli1e"), Some(2));
+    assert_eq!(needed(b"d3:key"), Some(1));
+
+    assert_eq!(
+        parse_prefix(b"l4:spa"),
+        Err(Error::Incomplete { needed: Some(1) })
+    );
+    assert_eq!(
+        parse_all_incomplete(b"i1eli2e"),
+        Err(Err::Incomplete(Needed::Size(1)))
+    );
+    assert!(parse_all(b"li1e").is_err());
+}
+
+#[test]
+fn invalid_input() {
+    for input in &[&b"x"[..], b"lxe", b"i1x", b"d1:ai1ei2ee", b"li1eee"] {
+        assert!(
+            matches!(from_bytes::<BencodedValue>(input), Err(Error::Parse(_))),
+            "{:?}",
+            input
+        );
+    }
+}
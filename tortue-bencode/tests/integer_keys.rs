@@ -0,0 +1,115 @@
+//! Makes sure that maps with integer keys roundtrip through their decimal
+//! string form
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tortue_bencode::{
+    error::Error, from_bytes, from_value, from_value_ref, parse_all, to_bytes,
+    to_value,
+};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Port(u16);
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Name(String);
+
+#[test]
+fn test_unsigned_keys() {
+    let map: HashMap<u16, String> = vec![
+        (1, "one".to_owned()),
+        (10, "ten".to_owned()),
+        (65535, "max".to_owned()),
+    ]
+    .into_iter()
+    .collect();
+
+    let bytes = to_bytes(&map).unwrap();
+    assert_eq!(bytes, b"d1:13:one2:103:ten5:655353:maxe");
+    assert_eq!(from_bytes::<HashMap<u16, String>>(&bytes), Ok(map.clone()));
+
+    let value = to_value(&map).unwrap();
+    assert_eq!(from_value::<HashMap<u16, String>>(value), Ok(map));
+}
+
+#[test]
+fn test_signed_keys() {
+    let map: BTreeMap<i64, Vec<u8>> = vec![
+        (-5, vec![1, 2]),
+        (0, vec![]),
+        (i64::MAX, vec![3]),
+        (i64::MIN, vec![4]),
+    ]
+    .into_iter()
+    .collect();
+
+    let bytes = to_bytes(&map).unwrap();
+    assert_eq!(from_bytes::<BTreeMap<i64, Vec<u8>>>(&bytes), Ok(map));
+}
+
+#[test]
+fn test_invalid_keys() {
+    assert!(from_bytes::<HashMap<u16, i64>>(b"d1:ai1ee").is_err());
+    assert!(from_bytes::<HashMap<u8, i64>>(b"d3:256i1ee").is_err());
+    assert!(from_bytes::<HashMap<u16, i64>>(b"d2:-1i1ee").is_err());
+
+    // Integers only read keys, values are not converted
+    assert!(from_bytes::<HashMap<String, u16>>(b"d1:a1:1e").is_err());
+}
+
+#[test]
+fn test_port_keys() {
+    let map: BTreeMap<u16, String> = vec![
+        (0, "zero".to_owned()),
+        (6881, "bittorrent".to_owned()),
+        (8080, "http".to_owned()),
+    ]
+    .into_iter()
+    .collect();
+
+    let bytes = to_bytes(&map).unwrap();
+    assert_eq!(bytes, b"d1:04:zero4:688110:bittorrent4:80804:httpe");
+    assert_eq!(from_bytes::<BTreeMap<u16, String>>(&bytes), Ok(map.clone()));
+
+    let (_, value) = parse_all(&bytes).unwrap();
+    assert_eq!(from_value_ref::<BTreeMap<u16, String>>(&value), Ok(map));
+}
+
+#[test]
+fn test_wrapped_keys() {
+    let ports: BTreeMap<Port, i64> =
+        vec![(Port(80), 1), (Port(443), 2)].into_iter().collect();
+    let bytes = to_bytes(&ports).unwrap();
+    assert_eq!(bytes, b"d3:443i2e2:80i1ee");
+    assert_eq!(from_bytes::<BTreeMap<Port, i64>>(&bytes), Ok(ports));
+
+    let names: BTreeMap<Name, char> =
+        vec![(Name("a".to_owned()), 'b')].into_iter().collect();
+    let value = to_value(&names).unwrap();
+    assert_eq!(from_value::<BTreeMap<Name, char>>(value), Ok(names));
+
+    let chars: BTreeMap<char, u16> =
+        vec![('a', 1), ('é', 2)].into_iter().collect();
+    let bytes = to_bytes(&chars).unwrap();
+    assert_eq!(bytes, b"d1:ai1e2:\xC3\xA9i2ee");
+    assert_eq!(from_bytes::<BTreeMap<char, u16>>(&bytes), Ok(chars));
+    assert!(from_bytes::<BTreeMap<char, u16>>(b"d2:abi1ee").is_err());
+}
+
+#[test]
+fn test_key_errors() {
+    let data = b"d4:8080i1e4:httpi2ee";
+    let error = Err(Error::InvalidKey("http".to_owned()));
+    assert_eq!(from_bytes::<BTreeMap<u16, i64>>(data), error);
+
+    let (_, value) = parse_all(data).unwrap();
+    assert_eq!(from_value::<BTreeMap<u16, i64>>(value.clone()), error);
+    assert_eq!(from_value_ref::<BTreeMap<u16, i64>>(&value), error);
+    assert_eq!(
+        from_bytes::<BTreeMap<Port, i64>>(b"d5:65536i1ee"),
+        Err(Error::OutOfRange {
+            value: "65536".to_owned(),
+            target: "u16",
+        })
+    );
+}
@@ -0,0 +1,140 @@
+//! Roundtrips integers at the boundaries of every width and checks that the
+//! values which do not fit report the target type, whatever the deserializer
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::{collections::BTreeMap, fmt::Debug};
+use tortue_bencode::{
+    error::Error, from_bytes, from_value, to_bytes, to_bytes_strict, to_value,
+};
+
+mod common;
+
+use common::deserialize;
+
+fn roundtrip<T>(value: T)
+where
+    T: Serialize + DeserializeOwned + PartialEq + Debug + Copy,
+{
+    let bytes = to_bytes(&value).unwrap();
+    assert_eq!(bytes, format!("i{:?}e", value).into_bytes());
+    assert_eq!(deserialize::<T>(&bytes), Ok(value));
+    assert_eq!(from_value::<T>(to_value(&value).unwrap()), Ok(value));
+}
+
+fn out_of_range<T>(digits: &str, target: &'static str)
+where
+    T: DeserializeOwned + PartialEq + Debug,
+{
+    let data = format!("i{}e", digits);
+    assert_eq!(
+        deserialize::<T>(data.as_bytes()),
+        Err(Error::OutOfRange {
+            value: digits.to_owned(),
+            target,
+        })
+    );
+}
+
+macro_rules! boundaries {
+    ($($int:ident),*) => {
+        $(
+            roundtrip::<$int>($int::MIN);
+            roundtrip::<$int>($int::MAX);
+            roundtrip::<$int>(0);
+        )*
+    };
+}
+
+#[test]
+fn test_boundaries() {
+    boundaries!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+    out_of_range::<i8>("-129", "i8");
+    out_of_range::<i16>("-32769", "i16");
+    out_of_range::<i32>("-2147483649", "i32");
+    for &digits in &["-1", "-9223372036854775808"] {
+        out_of_range::<u8>(digits, "u8");
+        out_of_range::<u16>(digits, "u16");
+        out_of_range::<u32>(digits, "u32");
+        out_of_range::<u64>(digits, "u64");
+    }
+
+    out_of_range::<i8>("128", "i8");
+    out_of_range::<i16>("32768", "i16");
+    out_of_range::<i32>("2147483648", "i32");
+    out_of_range::<i64>("9223372036854775808", "i64");
+    out_of_range::<u8>("256", "u8");
+    out_of_range::<u16>("65536", "u16");
+    out_of_range::<u32>("4294967296", "u32");
+    out_of_range::<i32>("18446744073709551615", "i32");
+}
+
+#[test]
+fn test_128() {
+    for &int in &[i128::from(i64::MIN), -1, 0, i128::from(u64::MAX)] {
+        roundtrip::<i128>(int);
+    }
+    for &int in &[0, u128::from(u64::MAX)] {
+        roundtrip::<u128>(int);
+    }
+    out_of_range::<u128>("-1", "u128");
+    out_of_range::<u128>("-9223372036854775808", "u128");
+
+    // Bencode integers stop at the bounds of `i64` and `u64`
+    assert!(matches!(
+        from_bytes::<i128>(b"i18446744073709551616e"),
+        Err(Error::IntegerOverflow(_))
+    ));
+    assert_eq!(
+        to_bytes(&i128::MAX),
+        Err(Error::OutOfRange {
+            value: i128::MAX.to_string(),
+            target: "u64",
+        })
+    );
+    assert_eq!(
+        to_bytes(&(i128::from(i64::MIN) - 1)),
+        Err(Error::OutOfRange {
+            value: "-9223372036854775809".to_owned(),
+            target: "i64",
+        })
+    );
+    assert_eq!(
+        to_bytes(&u128::MAX),
+        Err(Error::OutOfRange {
+            value: u128::MAX.to_string(),
+            target: "u64",
+        })
+    );
+    assert!(to_value(&u128::MAX).is_err());
+}
+
+#[test]
+fn test_lossless() {
+    // Strict serialization keeps to `i64`, whatever the width of the type
+    assert_eq!(
+        to_bytes_strict(&u128::from(u64::MAX)),
+        Err(Error::OutOfRange {
+            value: u64::MAX.to_string(),
+            target: "i64",
+        })
+    );
+    assert_eq!(to_bytes_strict(&-1i128), Ok(b"i-1e".to_vec()));
+}
+
+#[test]
+fn test_keys() {
+    let map: BTreeMap<u128, i128> = vec![(u128::from(u64::MAX), -1), (0, 1)]
+        .into_iter()
+        .collect();
+    let bytes = to_bytes(&map).unwrap();
+    assert_eq!(deserialize::<BTreeMap<u128, i128>>(&bytes), Ok(map));
+
+    assert_eq!(
+        from_bytes::<BTreeMap<u8, i64>>(b"d3:256i1ee"),
+        Err(Error::OutOfRange {
+            value: "256".to_owned(),
+            target: "u8",
+        })
+    );
+}
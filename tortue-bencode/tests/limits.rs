@@ -0,0 +1,117 @@
+//! Makes sure that the parser limits bound the resources spent on untrusted
+//! input
+
+use nom::Err::Failure;
+use tortue_bencode::{
+    error::Error,
+    from_bytes, from_bytes_with_options,
+    parser::{parse_all_with_options, Limit},
+    BencodedValue, ParserOptions,
+};
+
+const TORRENT: &[u8] = include_bytes!("../benches/test_data");
+
+fn limited(limit: Limit, value: usize) -> ParserOptions {
+    let mut options = ParserOptions::default();
+    match limit {
+        Limit::Depth => options.max_depth = value,
+        Limit::StringLength => options.max_string_len = value,
+        Limit::TotalLength => options.max_total_len = value,
+        Limit::Items => options.max_items = value,
+        Limit::TotalBytes => options.max_total_bytes = value,
+    }
+
+    options
+}
+
+fn violated(input: &[u8], options: &ParserOptions) -> Option<Limit> {
+    match from_bytes_with_options::<BencodedValue>(input, options) {
+        Err(Error::Limit(limit)) => Some(limit),
+        _ => None,
+    }
+}
+
+#[test]
+fn defaults() {
+    let options = ParserOptions::default();
+    let value: BencodedValue = from_bytes(TORRENT).unwrap();
+    assert_eq!(from_bytes_with_options(TORRENT, &options), Ok(value));
+}
+
+#[test]
+fn string_length() {
+    let options = limited(Limit::StringLength, 3);
+    assert!(from_bytes_with_options::<&str>(b"3:abc", &options).is_ok());
+    assert_eq!(violated(b"4:abcd", &options), Some(Limit::StringLength));
+    assert_eq!(
+        violated(b"d4:abcdi1ee", &options),
+        Some(Limit::StringLength)
+    );
+
+    // The declared length is checked before waiting for the data
+    let huge = b"999999999:abc";
+    assert_eq!(
+        violated(huge, &ParserOptions::default()),
+        Some(Limit::StringLength)
+    );
+    assert_eq!(
+        parse_all_with_options(huge, &options),
+        Err(Failure((&huge[..], Limit::StringLength.error_kind())))
+    );
+}
+
+#[test]
+fn total_length() {
+    let options = limited(Limit::TotalLength, 5);
+    assert!(from_bytes_with_options::<&str>(b"3:abc", &options).is_ok());
+    assert_eq!(violated(b"li1ei2ee", &options), Some(Limit::TotalLength));
+    assert_eq!(
+        violated(TORRENT, &limited(Limit::TotalLength, TORRENT.len() - 1)),
+        Some(Limit::TotalLength)
+    );
+}
+
+#[test]
+fn items() {
+    let options = limited(Limit::Items, 2);
+    assert!(from_bytes_with_options::<Vec<i64>>(b"li1ei2ee", &options).is_ok());
+    assert_eq!(violated(b"li1ei2ei3ee", &options), Some(Limit::Items));
+    assert_eq!(
+        violated(b"d1:ai1e1:bi2e1:ci3ee", &options),
+        Some(Limit::Items)
+    );
+    assert_eq!(violated(b"i1ei2ei3e", &options), Some(Limit::Items));
+
+    let list = [&b"l"[..], &b"i0e".repeat(10_000), b"e"].concat();
+    assert_eq!(violated(&list, &options), Some(Limit::Items));
+    assert_eq!(violated(&list, &ParserOptions::default()), None);
+}
+
+#[test]
+fn depth() {
+    let options = limited(Limit::Depth, 2);
+    assert!(from_bytes_with_options::<BencodedValue>(b"llee", &options).is_ok());
+    assert_eq!(violated(b"llleee", &options), Some(Limit::Depth));
+}
+
+#[test]
+fn total_bytes() {
+    let options = limited(Limit::TotalBytes, 1024);
+    let few = [&b"l"[..], &b"3:abc".repeat(5), b"e"].concat();
+    assert!(from_bytes_with_options::<Vec<&str>>(&few, &options).is_ok());
+
+    // Every string and list is small, but together they exceed the budget
+    let many = [&b"l"[..], &b"l3:abce".repeat(100), b"e"].concat();
+    assert_eq!(violated(&many, &options), Some(Limit::TotalBytes));
+    assert_eq!(violated(&many, &limited(Limit::StringLength, 3)), None);
+    assert_eq!(violated(&many, &limited(Limit::Items, 100)), None);
+
+    let keys = [&b"d"[..], &b"3:keyi0e".repeat(100), b"e"].concat();
+    assert_eq!(violated(&keys, &options), Some(Limit::TotalBytes));
+    let string = [&b"2000:"[..], &[b'a'; 2000]].concat();
+    assert_eq!(violated(&string, &options), Some(Limit::TotalBytes));
+    assert_eq!(
+        violated(TORRENT, &limited(Limit::TotalBytes, TORRENT.len() / 2)),
+        Some(Limit::TotalBytes)
+    );
+}
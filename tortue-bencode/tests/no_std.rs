@@ -0,0 +1,50 @@
+//! Roundtrips through the `no_std` API, run it without `std` with
+//! `cargo test --no-default-features --features alloc --test no_std`
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{borrow::ToOwned, string::String, vec, vec::Vec};
+use serde::{Deserialize, Serialize};
+use tortue_bencode::{
+    from_bytes, parse, to_bytes, writer, BencodedValue, HashMap, Key,
+};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Announce<'a> {
+    #[serde(with = "serde_bytes")]
+    info_hash: &'a [u8],
+    port: u16,
+    name: String,
+    tiers: Vec<Vec<String>>,
+}
+
+#[test]
+fn serde_roundtrip() {
+    let announce = Announce {
+        info_hash: b"\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a",
+        port: 6881,
+        name: "tortue".to_owned(),
+        tiers: vec![vec!["udp://tracker".to_owned()], vec![]],
+    };
+
+    let bytes = to_bytes(&announce).unwrap();
+    assert_eq!(from_bytes::<Announce>(&bytes), Ok(announce));
+}
+
+#[test]
+fn value_roundtrip() {
+    let mut dict = HashMap::new();
+    dict.insert(
+        Key::new("list"),
+        BencodedValue::List(vec![BencodedValue::Integer(1)]),
+    );
+    dict.insert(Key::new("bin"), BencodedValue::from(&b"\xFF\xFE"[..]));
+    let value = BencodedValue::from(dict);
+
+    let mut bytes = Vec::new();
+    writer::write(&value, &mut bytes).unwrap();
+    assert_eq!(bytes, value.to_vec().unwrap());
+    assert_eq!(parse(&bytes).unwrap().1, value);
+}
@@ -0,0 +1,195 @@
+//! Makes sure that parse errors report where they occurred in the input
+
+use nom::error::ErrorKind;
+use tortue_bencode::{
+    error::{Error, ParseError},
+    from_bytes, parse_all,
+    parser::{try_parse, try_parse_all},
+    BencodedValue,
+};
+
+fn locate(input: &[u8]) -> ParseError {
+    let error = parse_all(input).expect_err("input should be invalid");
+    ParseError::new(input, &error)
+}
+
+#[test]
+fn test_offsets() {
+    let error = locate(b"i3ei4eabc");
+    assert_eq!(error.offset, 6);
+    assert_eq!(error.kind, Some(ErrorKind::Eof));
+    assert_eq!(error.preview, b"abc");
+
+    let error = locate(b"4:spami-e");
+    assert_eq!(error.offset, 6);
+
+    // Errors in nested values are reported at the start of the top-level value
+    let error = locate(b"i1ed3:fooi1ex");
+    assert_eq!(error.offset, 3);
+
+    let error = locate(b"i1e10:abc");
+    assert_eq!(error.offset, 9);
+    assert_eq!(error.kind, None);
+    assert!(error.preview.is_empty());
+}
+
+#[test]
+fn test_display() {
+    let error = locate(b"i1ex0123456789");
+    assert_eq!(
+        error.to_string(),
+        "parse error at byte 3 (unexpected data after the values), found \
+         \"x0123456\" after \"i1e\""
+    );
+
+    assert_eq!(
+        locate(b"3:ab").to_string(),
+        "parse error at byte 4 (truncated input) after \"3:ab\""
+    );
+
+    // Bytes which are not printable ASCII are escaped
+    let error = locate(b"i1e\x00\xFF\"");
+    assert_eq!(error.preceding, b"i1e");
+    assert!(
+        error
+            .to_string()
+            .ends_with(r#"found "\x00\xff\"" after "i1e""#),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_from_bytes() {
+    match from_bytes::<BencodedValue>(b"li1ee4:spamx") {
+        Err(Error::Parse(error)) => {
+            assert_eq!(error.offset, 11);
+            assert_eq!(error.preview, b"x");
+
+            let message = Error::Parse(error).to_string();
+            assert!(
+                message.starts_with("parse error at byte 11"),
+                "{}",
+                message
+            );
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_from_bytes_causes() {
+    let parse = |input: &[u8]| match from_bytes::<BencodedValue>(input) {
+        Err(Error::Parse(error)) => error,
+        other => panic!("unexpected result: {:?}", other),
+    };
+
+    assert_eq!(
+        from_bytes::<BencodedValue>(b"d4:infod6:lengthi1e"),
+        Err(Error::Incomplete { needed: Some(2) })
+    );
+
+    // Errors are reported at the offending byte, not the start of the value
+    let error = parse(b"d6:lengthi12a4ee");
+    assert_eq!(error.offset, 12);
+    assert_eq!(error.expected.as_deref(), Some("a valid integer"));
+    assert_eq!(error.preview, b"a4ee");
+    assert_eq!(error.preceding, b"engthi12");
+    assert_eq!(
+        error.to_string(),
+        "parse error at byte 12 (missing delimiter), expected a valid \
+         integer, found \"a4ee\" after \"engthi12\""
+    );
+
+    let error = parse(b"d4:name3x:abce");
+    assert_eq!(error.offset, 8);
+    assert_eq!(error.expected.as_deref(), Some("a valid string"));
+
+    // Values may follow each other, garbage may not
+    let error = parse(b"d4:name3:abcexyz");
+    assert_eq!(error.offset, 13);
+    assert_eq!(
+        error.expected.as_deref(),
+        Some("a value or the end of the input")
+    );
+
+    let error = parse(b"d4:namee");
+    assert_eq!(error.offset, 7);
+    assert_eq!(error.expected.as_deref(), Some("a value"));
+
+    let error = parse(b"di1ei2ee");
+    assert_eq!(error.offset, 1);
+    assert_eq!(error.expected.as_deref(), Some("a key or 'e'"));
+}
+
+#[test]
+fn test_try_parse() {
+    let input = b"i3ei4eabc";
+    let error = try_parse_all(input).unwrap_err();
+    assert_eq!(
+        error,
+        ParseError::new(input, &parse_all(input).unwrap_err())
+    );
+    assert_eq!(error.offset, 6);
+
+    assert_eq!(
+        try_parse(b"i3eabc"),
+        Ok((&b"abc"[..], BencodedValue::Integer(3)))
+    );
+    assert_eq!(try_parse_all(b"i3e"), Ok(BencodedValue::Integer(3)));
+}
+
+#[test]
+fn test_combinators() {
+    let input = b"d1:ai1ex";
+    let header = |i| nom::bytes::complete::tag::<_, _, ParseError>("d1:a")(i);
+    let (rest, _) = header(&input[..]).unwrap();
+
+    match nom::character::complete::char::<_, ParseError>('e')(&rest[3..]) {
+        Err(nom::Err::Error(error)) => {
+            let error = error.locate(input);
+            assert_eq!(error.offset, 7);
+            assert_eq!(error.kind, Some(ErrorKind::Char));
+            assert_eq!(error.expected.as_deref(), Some("'e'"));
+            assert!(error.to_string().contains("expected 'e'"));
+            assert_eq!(
+                Error::from(nom::Err::Failure(error.clone())),
+                Error::Parse(error)
+            );
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let incomplete = nom::Err::<ParseError>::Incomplete(nom::Needed::Size(2));
+    assert_eq!(
+        Error::from(incomplete),
+        Error::Incomplete { needed: Some(2) }
+    );
+}
+
+#[test]
+fn test_buried_integer() {
+    // A torrent of a few megabytes with a malformed length after its pieces
+    let pieces = vec![0xAB; 4 << 20];
+    let mut input = b"d4:infod6:pieces".to_vec();
+    input.extend(format!("{}:", pieces.len()).bytes());
+    input.extend(&pieces);
+    let offset = input.len() + b"6:lengthi12".len();
+    input.extend(b"6:lengthi12-3e4:name4:spamee");
+
+    let error = match from_bytes::<BencodedValue>(&input) {
+        Err(Error::Parse(error)) => error,
+        other => panic!("unexpected result: {:?}", other),
+    };
+    assert_eq!(error.offset, offset);
+    assert_eq!(error.preceding, b"engthi12");
+    assert_eq!(error.preview, b"-3e4:nam");
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "parse error at byte {} (missing delimiter), expected a valid \
+             integer, found \"-3e4:nam\" after \"engthi12\"",
+            offset
+        )
+    );
+}
@@ -0,0 +1,144 @@
+//! Makes sure that missing optional fields are none and that unknown keys
+//! are skipped, whatever the deserializer
+
+use serde::{
+    de::{self, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use std::fmt;
+use tortue_bencode::{
+    error::Error, from_bytes, from_value, from_value_ref, parse_all,
+};
+
+mod common;
+
+use common::deserialize_parsed;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Metainfo<'a> {
+    announce: &'a str,
+    comment: Option<&'a str>,
+    #[serde(rename = "created by")]
+    created_by: Option<&'a str>,
+    #[serde(rename = "creation date")]
+    creation_date: Option<i64>,
+    info: Info<'a>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Info<'a> {
+    name: &'a str,
+    length: Option<i64>,
+    private: Option<bool>,
+}
+
+#[test]
+fn test_more_fields() {
+    let data = b"d8:announce3:url4:infod4:name1:aee";
+    let (_, value) = parse_all(data).unwrap();
+    assert_eq!(
+        deserialize_parsed::<Metainfo>(data, &value),
+        Ok(Metainfo {
+            announce: "url",
+            comment: None,
+            created_by: None,
+            creation_date: None,
+            info: Info {
+                name: "a",
+                length: None,
+                private: None,
+            },
+        })
+    );
+}
+
+#[test]
+fn test_fewer_fields() {
+    let data = b"d8:announce3:url7:comment2:hi10:created by2:me\
+        13:creation datei1e8:encoding5:UTF-84:infod6:lengthi2e4:name1:a\
+        12:piece lengthi3e6:pieces0:7:privatei1ee8:url-listl1:a1:bee";
+    let (_, value) = parse_all(data).unwrap();
+    assert_eq!(
+        deserialize_parsed::<Metainfo>(data, &value),
+        Ok(Metainfo {
+            announce: "url",
+            comment: Some("hi"),
+            created_by: Some("me"),
+            creation_date: Some(1),
+            info: Info {
+                name: "a",
+                length: Some(2),
+                private: Some(true),
+            },
+        })
+    );
+}
+
+#[test]
+fn test_interleaved_keys() {
+    // Unknown keys holding lists and dictionaries between known ones
+    let data = b"d1:ali1eli2eee8:announce3:url1:bd1:cd1:di1eee4:infod\
+        1:ad1:bl1:cee4:name1:a1:zi0ee1:zd1:ai1eee";
+    let (_, value) = parse_all(data).unwrap();
+    assert_eq!(
+        deserialize_parsed::<Metainfo>(data, &value),
+        Ok(Metainfo {
+            announce: "url",
+            comment: None,
+            created_by: None,
+            creation_date: None,
+            info: Info {
+                name: "a",
+                length: None,
+                private: None,
+            },
+        })
+    );
+
+    for data in [&b"d4:infod4:name1:aee"[..], b"d8:announce3:url4:infodee"] {
+        let (_, value) = parse_all(data).unwrap();
+        assert!(deserialize_parsed::<Metainfo>(data, &value).is_err());
+    }
+}
+
+/// Asks for a value before any key, then for a value past the last entry
+struct Misuse;
+
+impl<'de> Deserialize<'de> for Misuse {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        struct MisuseVisitor;
+
+        impl<'de> Visitor<'de> for MisuseVisitor {
+            type Value = Misuse;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a dictionary")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Misuse, A::Error> {
+                assert!(map.next_value::<i64>().is_err());
+                while map.next_entry::<&str, i64>()?.is_some() {}
+                assert!(map.next_value::<i64>().is_err());
+                Err(de::Error::custom("misused"))
+            }
+        }
+
+        deserializer.deserialize_map(MisuseVisitor)
+    }
+}
+
+#[test]
+fn test_value_without_key() {
+    let data = b"d1:ai1e1:bi2ee";
+    let (_, value) = parse_all(data).unwrap();
+    let misused = Err(Error::Message("misused".to_owned()));
+
+    assert_eq!(from_bytes::<Misuse>(data).map(|_| ()), misused);
+    assert_eq!(from_value::<Misuse>(value.clone()).map(|_| ()), misused);
+    assert_eq!(from_value_ref::<Misuse>(&value).map(|_| ()), misused);
+}
@@ -0,0 +1,85 @@
+//! Makes sure that structs with named fields require dictionaries unless the
+//! options allow reading them positionally from lists
+
+use serde::{Deserialize, Serialize};
+use tortue_bencode::{
+    error::Error, from_bytes, from_bytes_with_options, from_value,
+    from_value_ref, parse_all, to_bytes, ParserOptions,
+};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Metainfo<'a> {
+    announce: &'a str,
+    info: Info<'a>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Info<'a> {
+    name: &'a str,
+    length: i64,
+    private: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Point(i64, i64);
+
+fn positional() -> ParserOptions {
+    ParserOptions {
+        positional_structs: true,
+        ..ParserOptions::default()
+    }
+}
+
+#[test]
+fn test_rejected() {
+    let data = b"d8:announce11:example.com4:infol5:helloi64ei1eee";
+    let error = Err(Error::PositionalStruct {
+        name: "Info",
+        len: 3,
+    });
+    assert_eq!(from_bytes::<Metainfo>(data), error);
+
+    let (_, value) = parse_all(data).unwrap();
+    assert_eq!(from_value::<Metainfo>(value.clone()), error);
+    assert_eq!(from_value_ref::<Metainfo>(&value), error);
+
+    let error = Err(Error::PositionalStruct {
+        name: "Metainfo",
+        len: 0,
+    });
+    assert_eq!(from_bytes::<Metainfo>(b"le"), error);
+
+    // Tuple structs have no names to look up, they are still lists
+    assert_eq!(from_bytes::<Point>(b"li1ei2ee"), Ok(Point(1, 2)));
+}
+
+#[test]
+fn test_positional() {
+    let data = b"d8:announce11:example.com4:infol5:helloi64ei1eee";
+    let expected = Metainfo {
+        announce: "example.com",
+        info: Info {
+            name: "hello",
+            length: 64,
+            private: Some(true),
+        },
+    };
+    assert_eq!(
+        from_bytes_with_options::<Metainfo>(data, &positional()),
+        Ok(expected)
+    );
+
+    // Missing trailing fields are errors as for dictionaries
+    assert!(
+        from_bytes_with_options::<Info>(b"l5:helloe", &positional()).is_err()
+    );
+
+    // Dictionaries are still read by name
+    let info = Info {
+        name: "hello",
+        length: 64,
+        private: None,
+    };
+    let bytes = to_bytes(&info).unwrap();
+    assert_eq!(from_bytes_with_options(&bytes, &positional()), Ok(info));
+}
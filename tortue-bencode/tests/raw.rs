@@ -0,0 +1,90 @@
+//! Splices pre-encoded values into otherwise structured trees
+
+use std::{borrow::Cow, collections::HashMap};
+use tortue_bencode::{from_value, parser, to_bytes, writer, BencodedValue};
+
+const TORRENT: &[u8] = include_bytes!("../benches/test_data");
+
+/// Extracts the raw `info` dictionary of the test torrent
+fn raw_info() -> &'static [u8] {
+    let start = TORRENT
+        .windows(7)
+        .position(|window| window == b"4:infod")
+        .unwrap()
+        + 6;
+    let (rest, _) = parser::parse(&TORRENT[start..]).unwrap();
+
+    &TORRENT[start..TORRENT.len() - rest.len()]
+}
+
+#[test]
+fn splice_info() {
+    let info = raw_info();
+    assert!(writer::is_canonical(info));
+
+    let mut torrent = BencodedValue::from(HashMap::new());
+    torrent
+        .insert("info", BencodedValue::Raw(Cow::Borrowed(info)))
+        .unwrap();
+
+    let mut bytes = Vec::new();
+    writer::write_checked(&torrent, &mut bytes).unwrap();
+    assert_eq!(bytes, [b"d4:info" as &[u8], info, b"e"].concat());
+
+    // The info hash is computed over exactly these bytes
+    assert_eq!(&bytes[7..bytes.len() - 1], info);
+
+    let (_, parsed) = parser::parse_all(&bytes).unwrap();
+    assert_eq!(parsed, torrent);
+    assert_eq!(torrent, parsed);
+}
+
+#[test]
+fn splice_in_list() {
+    let info = raw_info();
+    let list = BencodedValue::from(vec![
+        BencodedValue::Integer(1),
+        BencodedValue::Raw(Cow::Owned(info.to_vec())),
+        BencodedValue::from("abc"),
+    ]);
+
+    let mut bytes = Vec::new();
+    writer::write(&list, &mut bytes).unwrap();
+    assert_eq!(bytes, [b"li1e" as &[u8], info, b"3:abce"].concat());
+}
+
+#[test]
+fn serde() {
+    let info = raw_info();
+    let raw = BencodedValue::Raw(Cow::Owned(info.to_vec()));
+    let (_, parsed) = parser::parse_all(info).unwrap();
+
+    assert_eq!(from_value::<BencodedValue>(raw.clone()).unwrap(), parsed);
+    let bytes = to_bytes(&raw).unwrap();
+    assert_eq!(parser::parse_all(&bytes).unwrap().1, parsed);
+
+    let invalid = BencodedValue::Raw(Cow::Borrowed(b"i1"));
+    assert!(from_value::<i64>(invalid.clone()).is_err());
+    assert!(to_bytes(&invalid).is_err());
+}
+
+#[test]
+fn single_value() {
+    let concatenated = BencodedValue::Raw(Cow::Borrowed(b"i1ei2e"));
+    let list = BencodedValue::from(vec![
+        BencodedValue::Integer(1),
+        BencodedValue::Integer(2),
+    ]);
+    assert_ne!(concatenated, list);
+    assert_ne!(list, concatenated);
+    assert!(to_bytes(&concatenated).is_err());
+    assert!(from_value::<BencodedValue>(concatenated).is_err());
+
+    let empty = BencodedValue::Raw(Cow::Borrowed(b""));
+    assert_ne!(empty, BencodedValue::None);
+    assert!(to_bytes(&empty).is_err());
+
+    let single = BencodedValue::Raw(Cow::Borrowed(b"i1e"));
+    assert_eq!(single, BencodedValue::Integer(1));
+    assert_eq!(to_bytes(&single).unwrap(), b"i1e");
+}
@@ -0,0 +1,130 @@
+//! Makes sure that seeds can carry state across the values they deserialize
+
+use serde::{
+    de::{DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use std::fmt;
+use tortue_bencode::{
+    error::Error, from_bytes, from_bytes_seed, from_value_seed, parse_all,
+};
+
+#[derive(Deserialize)]
+struct Peer<'a> {
+    #[allow(dead_code)]
+    ip: &'a str,
+    port: u16,
+}
+
+/// Appends the ports of the peers of a tracker response to a buffer shared
+/// by every response, returns the number of peers read
+struct Ports<'a>(&'a mut Vec<u16>);
+
+impl<'de> DeserializeSeed<'de> for Ports<'_> {
+    type Value = usize;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<usize, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Ports<'_> {
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a tracker response")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<usize, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut count = 0;
+        while let Some(key) = map.next_key::<&str>()? {
+            if key == "peers" {
+                count += map.next_value_seed(PeerList(self.0))?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+
+        Ok(count)
+    }
+}
+
+struct PeerList<'a>(&'a mut Vec<u16>);
+
+impl<'de> DeserializeSeed<'de> for PeerList<'_> {
+    type Value = usize;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<usize, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for PeerList<'_> {
+    type Value = usize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of peers")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<usize, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let start = self.0.len();
+        while let Some(peer) = seq.next_element::<Peer>()? {
+            self.0.push(peer.port);
+        }
+
+        Ok(self.0.len() - start)
+    }
+}
+
+const RESPONSES: &[&[u8]] = &[
+    b"d8:intervali1800e5:peersld2:ip8:10.0.0.14:porti6881eeee",
+    b"d5:peersld2:ip8:10.0.0.24:porti6882eed2:ip8:10.0.0.34:porti80eeee",
+    b"d8:intervali900e5:peerslee",
+];
+
+#[test]
+fn test_shared_buffer() {
+    let mut ports = Vec::new();
+    let counts = RESPONSES
+        .iter()
+        .map(|data| from_bytes_seed(data, Ports(&mut ports)))
+        .collect::<Result<Vec<_>, _>>();
+    assert_eq!(counts, Ok(vec![1, 2, 0]));
+    assert_eq!(ports, [6881, 6882, 80]);
+
+    ports.clear();
+    for data in RESPONSES {
+        let (_, value) = parse_all(data).unwrap();
+        from_value_seed(value, Ports(&mut ports)).unwrap();
+    }
+    assert_eq!(ports, [6881, 6882, 80]);
+}
+
+#[test]
+fn test_errors() {
+    let mut ports = vec![1];
+
+    // Syntax errors are the same as those of the other functions
+    let data = b"d5:peersli1e";
+    let error = from_bytes::<IgnoredAny>(data).unwrap_err();
+    assert_eq!(from_bytes_seed(data, Ports(&mut ports)), Err(error));
+
+    assert!(matches!(
+        from_bytes_seed(b"d5:peersli1eee", Ports(&mut ports)),
+        Err(Error::UnexpectedType { .. })
+    ));
+    assert!(from_bytes_seed(b"le", Ports(&mut ports)).is_err());
+    assert_eq!(ports, [1]);
+}
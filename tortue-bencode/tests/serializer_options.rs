@@ -0,0 +1,143 @@
+//! Runs the serializer with combinations of non default settings, making
+//! sure that every output agrees
+
+use serde::Serialize;
+use std::collections::HashMap;
+use tortue_bencode::{
+    error::Error, from_bytes, writer, EnumRepresentation, KeyOrder,
+    SerializerBuilder, SerializerOptions,
+};
+
+#[derive(Serialize)]
+enum Event {
+    Started,
+    Completed { downloaded: i64 },
+}
+
+#[derive(Serialize)]
+struct Announce {
+    peer_id: &'static str,
+    event: Event,
+    port: u16,
+    key: Option<&'static str>,
+}
+
+/// Serializes through every terminal method, the bytes written must be the
+/// same as the ones returned
+fn serialize<T: Serialize>(builder: &SerializerBuilder, value: &T) -> Vec<u8> {
+    let bytes = builder.to_bytes(value).unwrap();
+
+    let mut written = Vec::new();
+    assert_eq!(builder.to_writer(value, &mut written), Ok(bytes.len()));
+    assert_eq!(written, bytes);
+
+    bytes
+}
+
+fn announce(key: Option<&'static str>) -> Announce {
+    Announce {
+        peer_id: "-TT0001-abcdefghijkl",
+        event: Event::Completed { downloaded: 42 },
+        port: 6881,
+        key,
+    }
+}
+
+#[test]
+fn test_defaults() {
+    let options = SerializerOptions::default();
+    assert_eq!(options.enums, EnumRepresentation::External);
+    assert!(!options.deny_lossy);
+    assert_eq!(options.key_order, KeyOrder::SortedBytewise);
+    assert!(!options.canonical);
+    assert!(options.skip_none);
+
+    let builder = SerializerBuilder::new();
+    assert_eq!(
+        serialize(&builder, &announce(None)),
+        &b"d5:eventd9:Completedd10:downloadedi42eee\
+           7:peer_id20:-TT0001-abcdefghijkl4:porti6881ee"[..]
+    );
+}
+
+#[test]
+fn test_canonical_strict() {
+    let builder = SerializerBuilder::new()
+        .canonical(true)
+        .deny_lossy(true)
+        .key_order(KeyOrder::Declaration);
+
+    // Canonical output is sorted whatever the key order
+    let announce = announce(Some("k"));
+    let bytes = serialize(&builder, &announce);
+    assert_eq!(
+        bytes,
+        &b"d5:eventd9:Completedd10:downloadedi42eee3:key1:k\
+           7:peer_id20:-TT0001-abcdefghijkl4:porti6881ee"[..]
+    );
+
+    let mut expected = Vec::new();
+    let value = builder.to_value(&announce).unwrap();
+    writer::write(&value, &mut expected).unwrap();
+    assert_eq!(bytes, expected);
+
+    let mut lossy = HashMap::new();
+    lossy.insert("ratio", 1.5);
+    assert_eq!(builder.to_bytes(&lossy), Err(Error::UnsupportedType("f64")));
+    assert!(builder.to_value(&lossy).is_err());
+}
+
+#[test]
+fn test_ordered_adjacent() {
+    let builder = SerializerBuilder::new()
+        .enum_representation(EnumRepresentation::Adjacent)
+        .key_order(KeyOrder::Declaration)
+        .skip_none(false);
+
+    assert_eq!(
+        serialize(&builder, &announce(Some("k"))),
+        &b"d7:peer_id20:-TT0001-abcdefghijkl\
+           5:eventd1:cd10:downloadedi42ee1:t9:Completede\
+           4:porti6881e3:key1:ke"[..]
+    );
+
+    // Unit variants are their name in every representation
+    let started = Announce {
+        event: Event::Started,
+        ..announce(Some("k"))
+    };
+    let bytes = serialize(&builder, &started);
+    assert!(bytes.windows(16).any(|w| w == b"5:event7:Started"));
+
+    // A none field cannot be written without being skipped
+    assert!(builder.to_bytes(&announce(None)).is_err());
+    assert!(builder.to_value(&announce(None)).is_err());
+    assert!(builder.skip_none(true).to_bytes(&announce(None)).is_ok());
+}
+
+#[test]
+fn test_roundtrip() {
+    #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Peer {
+        ip: String,
+        port: u16,
+        id: Option<String>,
+    }
+
+    let peer = Peer {
+        ip: "10.0.0.1".to_owned(),
+        port: 51413,
+        id: None,
+    };
+    let builders = [
+        SerializerBuilder::new().canonical(true).deny_lossy(true),
+        SerializerBuilder::new()
+            .key_order(KeyOrder::Declaration)
+            .enum_representation(EnumRepresentation::Untagged),
+    ];
+
+    for builder in &builders {
+        let bytes = serialize(builder, &peer);
+        assert_eq!(from_bytes::<Peer>(&bytes).as_ref(), Ok(&peer));
+    }
+}
@@ -0,0 +1,131 @@
+//! Makes sure that untrusted size hints cannot force huge allocations
+
+use serde::{
+    de::{
+        value::{
+            BorrowedStrDeserializer, Error, MapAccessDeserializer,
+            SeqAccessDeserializer,
+        },
+        DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess,
+    },
+    Deserialize,
+};
+use tortue_bencode::BencodedValue;
+
+mod common;
+
+use common::{measure, CountingAllocator, LOCK};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Upper bound on the memory the visitor may use for three small elements
+const MAX_PEAK: usize = 1024 * 1024;
+
+/// A map claiming to contain `usize::MAX` entries
+struct LyingMap {
+    entries: Vec<(&'static str, i64)>,
+    current: Option<i64>,
+}
+
+impl<'de> MapAccess<'de> for LyingMap {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.entries.is_empty() {
+            return Ok(None);
+        }
+
+        let (key, value) = self.entries.remove(0);
+        self.current = Some(value);
+
+        seed.deserialize(BorrowedStrDeserializer::new(key))
+            .map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.current.take().unwrap().into_deserializer())
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(usize::MAX)
+    }
+}
+
+/// A sequence claiming to contain `usize::MAX` elements
+struct LyingSeq {
+    elements: Vec<i64>,
+}
+
+impl<'de> SeqAccess<'de> for LyingSeq {
+    type Error = Error;
+
+    fn next_element_seed<T>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.elements.is_empty() {
+            return Ok(None);
+        }
+
+        let element = self.elements.remove(0);
+        seed.deserialize(element.into_deserializer()).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(usize::MAX)
+    }
+}
+
+#[test]
+fn test_lying_map() {
+    let _guard = LOCK.lock().unwrap();
+
+    let map = LyingMap {
+        entries: vec![("a", 1), ("b", 2), ("c", 3)],
+        current: None,
+    };
+
+    let (value, usage) =
+        measure(|| BencodedValue::deserialize(MapAccessDeserializer::new(map)));
+
+    assert!(usage.peak < MAX_PEAK, "peak usage of {} bytes", usage.peak);
+
+    let dict = value.unwrap().unwrap_dict();
+    assert_eq!(dict.len(), 3);
+    assert_eq!(dict["a"], BencodedValue::Integer(1));
+    assert_eq!(dict["b"], BencodedValue::Integer(2));
+    assert_eq!(dict["c"], BencodedValue::Integer(3));
+}
+
+#[test]
+fn test_lying_seq() {
+    let _guard = LOCK.lock().unwrap();
+
+    let seq = LyingSeq {
+        elements: vec![1, 2, 3],
+    };
+
+    let (value, usage) =
+        measure(|| BencodedValue::deserialize(SeqAccessDeserializer::new(seq)));
+
+    assert!(usage.peak < MAX_PEAK, "peak usage of {} bytes", usage.peak);
+
+    assert_eq!(
+        value.unwrap(),
+        BencodedValue::List(vec![
+            BencodedValue::Integer(1),
+            BencodedValue::Integer(2),
+            BencodedValue::Integer(3),
+        ])
+    );
+}
@@ -0,0 +1,83 @@
+//! Makes sure that short dictionary keys do not allocate
+
+use serde::Serialize;
+use std::collections::HashMap;
+use tortue_bencode::{to_value, BencodedValue};
+
+mod common;
+
+use common::{measure, CountingAllocator, LOCK};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const FILES: usize = 10_000;
+
+#[derive(Serialize)]
+struct File {
+    length: i64,
+    md5sum: i64,
+    name: String,
+}
+
+fn files() -> Vec<File> {
+    (0..FILES)
+        .map(|i| File {
+            length: i as i64,
+            md5sum: i as i64 * 7,
+            name: format!("file-{}", i),
+        })
+        .collect()
+}
+
+/// Builds the same tree as `to_value` would, using `String` keys
+fn string_keyed(files: &[File]) -> Vec<HashMap<String, BencodedValue<'_>>> {
+    files
+        .iter()
+        .map(|file| {
+            let mut map = HashMap::with_capacity(3);
+            map.insert(
+                "length".to_owned(),
+                BencodedValue::Integer(file.length),
+            );
+            map.insert(
+                "md5sum".to_owned(),
+                BencodedValue::Integer(file.md5sum),
+            );
+            map.insert(
+                "name".to_owned(),
+                BencodedValue::from(file.name.clone()),
+            );
+
+            map
+        })
+        .collect()
+}
+
+#[test]
+fn test_allocations() {
+    let _guard = LOCK.lock().unwrap();
+
+    let files = files();
+
+    let (values, small) = measure(|| {
+        files
+            .iter()
+            .map(|file| to_value(file).unwrap())
+            .collect::<Vec<_>>()
+    });
+    let (baseline, string) = measure(|| string_keyed(&files));
+
+    assert_eq!(values.len(), FILES);
+    assert_eq!(baseline.len(), FILES);
+    assert!(values.iter().all(|value| value.len() == 3));
+
+    // Every key used to be its own allocation
+    assert!(
+        small.allocations + 3 * FILES <= string.allocations,
+        "{:?} vs {:?}",
+        small,
+        string
+    );
+    assert!(small.peak < string.peak, "{:?} vs {:?}", small, string);
+}
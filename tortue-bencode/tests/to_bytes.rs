@@ -0,0 +1,65 @@
+//! Makes sure that `to_bytes` allocates its output once
+
+use serde::Serialize;
+use tortue_bencode::{to_bytes, to_writer};
+
+mod common;
+
+use common::{measure, CountingAllocator, LOCK};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Serialize)]
+struct Info<'a> {
+    length: i64,
+    name: &'a str,
+    #[serde(rename = "piece length")]
+    piece_length: i64,
+    #[serde(with = "serde_bytes")]
+    pieces: &'a [u8],
+}
+
+#[derive(Serialize)]
+struct Metainfo<'a> {
+    announce: &'a str,
+    comment: Option<&'a str>,
+    info: Info<'a>,
+    #[serde(rename = "url-list")]
+    url_list: Option<&'a str>,
+}
+
+#[test]
+fn test_allocations() {
+    let _guard = LOCK.lock().unwrap();
+
+    let pieces = vec![7; 20 * 100_000];
+    let metainfo = Metainfo {
+        announce: "http://tracker.example.com/announce",
+        comment: None,
+        info: Info {
+            length: 1 << 36,
+            name: "archive.tar",
+            piece_length: 1 << 18,
+            pieces: &pieces,
+        },
+        url_list: None,
+    };
+
+    let (bytes, usage) = measure(|| to_bytes(&metainfo).unwrap());
+    let (written, growing) = measure(|| {
+        let mut out = Vec::new();
+        to_writer(&metainfo, &mut out).unwrap();
+        out
+    });
+
+    assert_eq!(bytes, written);
+    // The omitted url list is written last then dropped, which is not
+    // counted in the length but must not grow the output
+    assert_eq!(bytes.capacity(), bytes.len() + b"8:url-list".len());
+
+    // The output and the entries of both dictionaries
+    assert_eq!(usage.allocations, 3, "{:?} vs {:?}", usage, growing);
+    assert!(usage.peak < bytes.len() + 1024, "{:?}", usage);
+    assert!(usage.peak < growing.peak, "{:?} vs {:?}", usage, growing);
+}
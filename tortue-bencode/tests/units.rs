@@ -0,0 +1,85 @@
+//! Makes sure that units roundtrip through the empty value chosen by the
+//! serializer and that every empty value is read back as a unit
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt::Debug};
+use tortue_bencode::{
+    from_value, to_bytes, to_value, SerializerBuilder, UnitRepresentation,
+};
+
+mod common;
+
+use common::deserialize;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+struct Marker;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Flagged {
+    marker: Marker,
+    name: String,
+    unit: (),
+}
+
+#[test]
+fn test_set() {
+    let set: HashMap<String, ()> =
+        vec![("aaaa".to_owned(), ()), ("bb".to_owned(), ())]
+            .into_iter()
+            .collect();
+
+    let bytes = to_bytes(&set).unwrap();
+    assert_eq!(bytes, b"d4:aaaa0:2:bb0:e");
+    assert_eq!(deserialize::<HashMap<String, ()>>(&bytes), Ok(set.clone()));
+
+    let value = to_value(&set).unwrap();
+    assert_eq!(from_value::<HashMap<String, ()>>(value), Ok(set));
+}
+
+#[test]
+fn test_unit_struct() {
+    let flagged = Flagged {
+        marker: Marker,
+        name: "spam".to_owned(),
+        unit: (),
+    };
+
+    let bytes = to_bytes(&flagged).unwrap();
+    assert_eq!(bytes, b"d6:marker0:4:name4:spam4:unit0:e");
+    assert_eq!(deserialize::<Flagged>(&bytes), Ok(flagged.clone()));
+
+    let units = [
+        (UnitRepresentation::EmptyString, &b"0:"[..]),
+        (UnitRepresentation::Zero, b"i0e"),
+        (UnitRepresentation::EmptyDictionary, b"de"),
+    ];
+    for &(units, empty) in &units {
+        let builder = SerializerBuilder::new().unit_representation(units);
+        let bytes = builder.to_bytes(&flagged).unwrap();
+        let mut expected = b"d6:marker".to_vec();
+        expected.extend(empty);
+        expected.extend(b"4:name4:spam4:unit");
+        expected.extend(empty);
+        expected.push(b'e');
+
+        assert_eq!(bytes, expected);
+        assert_eq!(deserialize::<Flagged>(&bytes), Ok(flagged.clone()));
+        assert_eq!(
+            from_value::<Flagged>(builder.to_value(&flagged).unwrap()),
+            Ok(flagged.clone())
+        );
+    }
+}
+
+#[test]
+fn test_empty_values() {
+    for &empty in &[&b"0:"[..], b"i0e", b"de"] {
+        assert_eq!(deserialize::<()>(empty), Ok(()));
+        assert_eq!(deserialize::<Marker>(empty), Ok(Marker));
+    }
+
+    for &data in &[&b"i1e"[..], b"1:a", b"d1:ai0ee", b"le"] {
+        assert!(deserialize::<()>(data).is_err(), "{:?}", data);
+        assert!(deserialize::<Marker>(data).is_err(), "{:?}", data);
+    }
+}
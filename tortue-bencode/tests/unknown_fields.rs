@@ -0,0 +1,105 @@
+//! Makes sure that unknown keys are rejected by `deny_unknown_fields` and
+//! captured by a flattened map, whatever the deserializer
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use tortue_bencode::{
+    error::Error, from_bytes, from_value, from_value_ref, parse_all,
+    BencodedValue,
+};
+
+mod common;
+
+use common::deserialize_parsed;
+
+const TORRENT: &[u8] = include_bytes!("../benches/test_data");
+
+/// Top-level keys of the torrent besides `announce` and `info`, sorted
+const UNKNOWN: [&str; 7] = [
+    "announce-list",
+    "comment",
+    "created by",
+    "creation date",
+    "encoding",
+    "url-list",
+    "website",
+];
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Strict<'a> {
+    announce: &'a str,
+    info: Info<'a>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Info<'a> {
+    name: &'a str,
+    length: i64,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Metainfo<'a> {
+    announce: &'a str,
+    info: Info<'a>,
+
+    /// Every other key, borrowing from the input
+    #[serde(flatten, borrow)]
+    extra: HashMap<&'a str, BencodedValue<'a>>,
+}
+
+/// Returns the key rejected by `deny_unknown_fields`, which depends on the
+/// iteration order of the parsed dictionary
+fn unknown_field(result: Result<Strict, Error>) -> String {
+    match result {
+        Err(Error::UnknownField { field, expected }) => {
+            assert_eq!(expected, ["announce", "info"]);
+            field
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn test_deny_unknown_fields() {
+    let (_, value) = parse_all(TORRENT).unwrap();
+    let results = [
+        from_bytes::<Strict>(TORRENT),
+        from_value::<Strict>(value.to_borrowed()),
+        from_value_ref::<Strict>(&value),
+    ];
+    for result in results {
+        let key = unknown_field(result);
+        assert!(UNKNOWN.contains(&key.as_str()), "{}", key);
+    }
+
+    // Dictionaries without unknown keys are accepted
+    let data = b"d8:announce3:url4:infod6:lengthi1e4:name1:aee";
+    let (_, value) = parse_all(data).unwrap();
+    assert_eq!(
+        deserialize_parsed::<Strict>(data, &value),
+        Ok(Strict {
+            announce: "url",
+            info: Info {
+                name: "a",
+                length: 1,
+            },
+        })
+    );
+}
+
+#[test]
+fn test_capture_extras() {
+    let (_, value) = parse_all(TORRENT).unwrap();
+    let metainfo = deserialize_parsed::<Metainfo>(TORRENT, &value).unwrap();
+
+    assert_eq!(metainfo.info.name, "bbb_sunflower_1080p_60fps_normal.mp4");
+    let mut keys = metainfo.extra.keys().copied().collect::<Vec<_>>();
+    keys.sort_unstable();
+    assert_eq!(keys, UNKNOWN);
+    assert_eq!(
+        metainfo.extra["comment"],
+        BencodedValue::from("Big Buck Bunny, Sunflower version")
+    );
+    assert!(metainfo.extra["url-list"].is_list());
+}
@@ -0,0 +1,66 @@
+//! Makes sure that `from_value_ref` reads a parsed value in place
+
+use serde::Deserialize;
+use tortue_bencode::{from_value, from_value_ref, parse_all, BencodedValue};
+
+mod common;
+
+use common::{measure, CountingAllocator, LOCK};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Metainfo<'a> {
+    announce: &'a str,
+    comment: &'a str,
+    #[serde(rename = "creation date")]
+    creation_date: i64,
+    info: Info<'a>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Info<'a> {
+    name: &'a str,
+    length: i64,
+    #[serde(rename = "piece length")]
+    piece_length: i64,
+    #[serde(with = "serde_bytes")]
+    pieces: &'a [u8],
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Header<'a> {
+    #[serde(rename = "created by")]
+    created_by: &'a str,
+    encoding: &'a str,
+    #[serde(rename = "announce-list")]
+    announce_list: (Vec<&'a str>, Vec<&'a str>),
+}
+
+#[test]
+fn test_views() {
+    let _guard = LOCK.lock().unwrap();
+
+    let torrent = include_bytes!("../benches/test_data");
+    let (_, value) = parse_all(torrent).unwrap();
+    let owned = value.clone().into_owned();
+
+    let (metainfo, usage) =
+        measure(|| from_value_ref::<Metainfo>(&owned).unwrap());
+    assert_eq!(usage.allocations, 0, "{:?}", usage);
+    assert_eq!(metainfo, from_value(value.clone()).unwrap());
+    assert_eq!(metainfo.info.name, "bbb_sunflower_1080p_60fps_normal.mp4");
+
+    // Only the lists of the header are allocated
+    let (header, usage) = measure(|| from_value_ref::<Header>(&owned).unwrap());
+    assert_eq!(usage.allocations, 2, "{:?}", usage);
+    assert_eq!(header, from_value(value.clone()).unwrap());
+
+    let info = match &owned {
+        BencodedValue::Dictionary(dict) => &dict["info"],
+        _ => unreachable!(),
+    };
+    assert_eq!(from_value_ref::<Info>(info), Ok(metainfo.info));
+    assert_eq!(owned, value);
+}
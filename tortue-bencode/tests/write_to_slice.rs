@@ -0,0 +1,94 @@
+//! Makes sure that values are written into caller-provided buffers without
+//! allocating
+
+use maplit::hashmap;
+use tortue_bencode::{
+    error::Error, parse_all, writer::write_to_slice, BencodedValue,
+};
+
+mod common;
+
+use common::{measure, CountingAllocator, LOCK};
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const TORRENT: &[u8] = include_bytes!("../benches/test_data");
+
+#[test]
+fn exact_buffer() {
+    let value = parse_all(TORRENT).unwrap().1;
+    let expected = value.to_vec().unwrap();
+
+    let mut buf = vec![0; expected.len()];
+    assert_eq!(write_to_slice(&value, &mut buf), Ok(expected.len()));
+    assert_eq!(buf, expected);
+
+    // Only the start of a larger buffer is written
+    let mut buf = vec![b'x'; expected.len() + 2];
+    assert_eq!(write_to_slice(&value, &mut buf), Ok(expected.len()));
+    assert_eq!(&buf[..expected.len()], &expected[..]);
+    assert_eq!(&buf[expected.len()..], b"xx");
+}
+
+#[test]
+fn short_buffer() {
+    let value = BencodedValue::from(hashmap! {
+        "b".into() => BencodedValue::Integer(1),
+        "a".into() => BencodedValue::from("spam"),
+    });
+    let len = value.encoded_len();
+
+    let mut buf = vec![b'x'; len - 1];
+    assert_eq!(
+        write_to_slice(&value, &mut buf),
+        Err(Error::BufferTooSmall {
+            needed: len,
+            len: len - 1,
+        })
+    );
+    assert!(buf.iter().all(|&b| b == b'x'));
+
+    let mut buf = vec![0; len];
+    assert_eq!(write_to_slice(&value, &mut buf), Ok(len));
+    assert_eq!(buf, b"d1:a4:spam1:bi1ee");
+}
+
+#[test]
+fn zero_length_values() {
+    let mut empty = [];
+    assert_eq!(write_to_slice(&BencodedValue::None, &mut empty), Ok(0));
+
+    let values = [
+        (BencodedValue::from(""), &b"0:"[..]),
+        (BencodedValue::from(&b""[..]), b"0:"),
+        (BencodedValue::List(vec![]), b"le"),
+        (BencodedValue::from(hashmap! {}), b"de"),
+    ];
+    for (value, expected) in values.iter() {
+        let mut buf = [0; 2];
+        assert_eq!(write_to_slice(value, &mut buf), Ok(2));
+        assert_eq!(&buf, expected);
+        assert!(write_to_slice(value, &mut buf[..1]).is_err());
+    }
+
+    let nested = BencodedValue::from(vec![BencodedValue::None]);
+    assert!(write_to_slice(&nested, &mut [0; 2]).is_err());
+}
+
+#[test]
+fn no_allocation() {
+    let _guard = LOCK.lock().unwrap();
+
+    let value = parse_all(TORRENT).unwrap().1;
+    let mut buf = vec![0; value.encoded_len()];
+
+    let (_, usage) = measure(|| {
+        for _ in 0..100 {
+            write_to_slice(&value, &mut buf).unwrap();
+        }
+    });
+
+    // Any allocation of the writer would be repeated by every write
+    assert!(usage.allocations < 100, "{:?}", usage);
+}
@@ -0,0 +1,464 @@
+//! Goes through the crates of the workspace the way a client does: creates
+//! and loads a torrent, announces it to a tracker with the request and
+//! response traits of `tortue-reqtraits`, then checks the downloaded pieces
+//!
+//! The tracker is a fake one served in-process by hyper, it answers announce
+//! requests with canned bencoded responses. `tests/flow.rs` runs
+//! the same flow and checks every step.
+//!
+//! Run with `cargo run --example announce --features sha1,rand`.
+
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Server,
+};
+use rand::{distr::Alphanumeric, Rng};
+use reqwest::{Client, Method, Request, Response, Url};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::{
+    collections::HashMap,
+    convert::{Infallible, TryFrom},
+    error::Error,
+    fmt::{self, Write as _},
+    fs,
+    future::Future,
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+use tokio::runtime::Runtime;
+use tortue_bencode::{error::Error as BencodeError, from_bytes};
+use tortue_reqtraits::{FromResponse, IntoRequest};
+use tortue_structs::{InfoHash, Metainfo, TorrentBuilder};
+
+/// Piece length of the created torrent
+pub const PIECE: usize = 16 * 1024;
+
+/// Port the client announces
+pub const PORT: u16 = 6881;
+
+/// Prefix of the generated peer ids, in the Azureus style
+pub const PEER_ID_PREFIX: &[u8; 8] = b"-TT0001-";
+
+/// Peers the fake tracker knows about for every torrent
+pub fn seeds() -> Vec<SocketAddrV4> {
+    vec![
+        SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 2), 6882),
+        SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 3), 6883),
+    ]
+}
+
+/// Content of the torrent, two pieces and a half
+pub fn content() -> Vec<u8> {
+    (0..PIECE * 5 / 2).map(|i| (i % 251) as u8).collect()
+}
+
+/// A peer id made of [`PEER_ID_PREFIX`] and random alphanumeric characters
+pub fn peer_id() -> [u8; 20] {
+    let mut peer_id = [0; 20];
+    peer_id[..8].copy_from_slice(PEER_ID_PREFIX);
+    for (byte, random) in peer_id[8..]
+        .iter_mut()
+        .zip(rand::rng().sample_iter(Alphanumeric))
+    {
+        *byte = random;
+    }
+
+    peer_id
+}
+
+/// Error of a request to the tracker
+#[derive(Debug)]
+pub enum TrackerError {
+    Http(reqwest::Error),
+    Bencode(BencodeError),
+    Failure(String),
+}
+
+impl fmt::Display for TrackerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TrackerError::Http(e) => write!(f, "http error: {}", e),
+            TrackerError::Bencode(e) => write!(f, "invalid response: {}", e),
+            TrackerError::Failure(reason) => {
+                write!(f, "tracker failure: {}", reason)
+            }
+        }
+    }
+}
+
+impl Error for TrackerError {}
+
+impl From<reqwest::Error> for TrackerError {
+    fn from(e: reqwest::Error) -> Self {
+        TrackerError::Http(e)
+    }
+}
+
+/// An announce to the tracker of a torrent
+pub struct Announce {
+    pub url: String,
+    pub info_hash: InfoHash,
+    pub peer_id: [u8; 20],
+    pub port: u16,
+    pub left: u64,
+}
+
+impl IntoRequest for Announce {
+    type ResponseType = AnnounceResponse;
+
+    /// The trait cannot fail, an invalid announce URL panics
+    fn into_request(self) -> Request {
+        let mut url = self.url;
+        url.push_str(if url.contains('?') { "&" } else { "?" });
+        url.push_str("info_hash=");
+        percent_encode(&mut url, self.info_hash.as_bytes());
+        url.push_str("&peer_id=");
+        percent_encode(&mut url, &self.peer_id);
+        write!(
+            url,
+            "&port={}&uploaded=0&downloaded=0&left={}&compact=1&event=started",
+            self.port, self.left
+        )
+        .unwrap();
+
+        Request::new(Method::GET, Url::parse(&url).expect("invalid URL"))
+    }
+}
+
+/// A compact announce response (BEP 23)
+#[derive(Deserialize, Debug)]
+pub struct AnnounceResponse {
+    #[serde(rename = "failure reason")]
+    failure_reason: Option<String>,
+
+    /// Seconds to wait before the next announce
+    #[serde(default)]
+    pub interval: i64,
+
+    /// Number of peers having the whole content
+    #[serde(default)]
+    pub complete: i64,
+
+    /// Number of peers still downloading
+    #[serde(default)]
+    pub incomplete: i64,
+
+    #[serde(default, with = "serde_bytes")]
+    peers: Vec<u8>,
+}
+
+impl AnnounceResponse {
+    /// The peers, 4 bytes of IP address and 2 bytes of port each
+    pub fn peers(&self) -> Vec<SocketAddrV4> {
+        self.peers
+            .chunks_exact(6)
+            .map(|peer| {
+                SocketAddrV4::new(
+                    Ipv4Addr::new(peer[0], peer[1], peer[2], peer[3]),
+                    u16::from_be_bytes([peer[4], peer[5]]),
+                )
+            })
+            .collect()
+    }
+}
+
+impl FromResponse for AnnounceResponse {
+    type Error = TrackerError;
+
+    fn from_response(response: Response) -> Result<Self, Self::Error> {
+        let response = decode::<AnnounceResponse>(response)?;
+        match response.failure_reason {
+            Some(reason) => Err(TrackerError::Failure(reason)),
+            None => Ok(response),
+        }
+    }
+}
+
+/// Sends a request on the runtime and reads its response
+///
+/// The response is read on the calling thread, see [`decode`].
+pub fn send<R>(
+    runtime: &mut Runtime,
+    client: &Client,
+    request: R,
+) -> Result<R::ResponseType, <R::ResponseType as FromResponse>::Error>
+where
+    R: IntoRequest,
+    <R::ResponseType as FromResponse>::Error: From<reqwest::Error>,
+{
+    let response = runtime.block_on(client.execute(request.into_request()))?;
+    R::ResponseType::from_response(response)
+}
+
+/// Reads and decodes the body of a response
+///
+/// `FromResponse` is synchronous while the body of a reqwest response can
+/// only be awaited, the calling thread is parked until the body is read by
+/// the runtime and must thus not be a thread of the runtime.
+fn decode<T: DeserializeOwned>(response: Response) -> Result<T, TrackerError> {
+    let body = block_on(response.bytes())?;
+    from_bytes(&body).map_err(TrackerError::Bencode)
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Polls a future on the calling thread until it completes
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// Escapes everything but the unreserved characters of RFC 3986
+fn percent_encode(output: &mut String, input: &[u8]) {
+    for &byte in input {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~' => output.push(byte as char),
+            _ => write!(output, "%{:02X}", byte).unwrap(),
+        }
+    }
+}
+
+/// Reverses [`percent_encode`], `None` if an escape is malformed
+fn percent_decode(input: &str) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(input.len());
+    let mut bytes = input.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next()?, bytes.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            output.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            output.push(byte);
+        }
+    }
+
+    Some(output)
+}
+
+/// Peers announced for a torrent
+#[derive(Default)]
+struct Swarm {
+    seeds: Vec<SocketAddrV4>,
+    leechers: Vec<[u8; 20]>,
+}
+
+type Swarms = Arc<Mutex<HashMap<InfoHash, Swarm>>>;
+
+/// A tracker served in-process, which only knows about the torrents it is
+/// given
+pub struct FakeTracker {
+    addr: SocketAddr,
+    swarms: Swarms,
+}
+
+impl FakeTracker {
+    /// Serves the tracker on a free local port
+    pub fn start(runtime: &Runtime) -> Self {
+        let swarms = Swarms::default();
+        let shared = swarms.clone();
+        let make_service = make_service_fn(move |_| {
+            let swarms = shared.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let response = FakeTracker::answer(&swarms, &request);
+                    async move { Ok::<_, Infallible>(response) }
+                }))
+            }
+        });
+
+        let server = runtime
+            .enter(|| Server::bind(&([127, 0, 0, 1], 0).into()))
+            .serve(make_service);
+        let addr = server.local_addr();
+        runtime.spawn(server);
+
+        FakeTracker { addr, swarms }
+    }
+
+    /// The announce URL of the tracker
+    pub fn announce_url(&self) -> String {
+        format!("http://{}/announce", self.addr)
+    }
+
+    /// Adds a torrent along with the peers seeding it
+    pub fn add_torrent(&self, info_hash: InfoHash, seeds: Vec<SocketAddrV4>) {
+        self.swarms.lock().unwrap().insert(
+            info_hash,
+            Swarm {
+                seeds,
+                leechers: Vec::new(),
+            },
+        );
+    }
+
+    fn answer(
+        swarms: &Swarms,
+        request: &hyper::Request<Body>,
+    ) -> hyper::Response<Body> {
+        let mut params = HashMap::new();
+        for pair in request.uri().query().unwrap_or("").split('&') {
+            let mut pair = pair.splitn(2, '=');
+            if let (Some(key), Some(value)) = (pair.next(), pair.next()) {
+                params.insert(key, percent_decode(value));
+            }
+        }
+
+        let info_hash = params
+            .get("info_hash")
+            .and_then(|hash| InfoHash::from_slice(hash.as_deref()?));
+        let mut swarms = swarms.lock().unwrap();
+        let swarm = match info_hash.and_then(|hash| swarms.get_mut(&hash)) {
+            Some(swarm) => swarm,
+            None => return failure("unknown info hash"),
+        };
+
+        match request.uri().path() {
+            "/announce" => {
+                let peer_id = params
+                    .get("peer_id")
+                    .and_then(|id| <[u8; 20]>::try_from(id.as_deref()?).ok());
+                let peer_id = match peer_id {
+                    Some(peer_id) => peer_id,
+                    None => return failure("invalid peer id"),
+                };
+                if !swarm.leechers.contains(&peer_id) {
+                    swarm.leechers.push(peer_id);
+                }
+
+                let mut peers = Vec::new();
+                for seed in &swarm.seeds {
+                    peers.extend_from_slice(&seed.ip().octets());
+                    peers.extend_from_slice(&seed.port().to_be_bytes());
+                }
+
+                let mut body = format!(
+                    concat!(
+                        "d8:completei{}e10:incompletei{}e",
+                        "8:intervali1800e5:peers{}:"
+                    ),
+                    swarm.seeds.len(),
+                    swarm.leechers.len(),
+                    peers.len()
+                )
+                .into_bytes();
+                body.extend_from_slice(&peers);
+                body.push(b'e');
+                hyper::Response::new(Body::from(body))
+            }
+            _ => failure("unknown path"),
+        }
+    }
+}
+
+/// A response telling that a request failed
+fn failure(reason: &str) -> hyper::Response<Body> {
+    hyper::Response::new(Body::from(format!(
+        "d14:failure reason{}:{}e",
+        reason.len(),
+        reason
+    )))
+}
+
+/// What a run of the flow went through
+pub struct Flow {
+    pub info_hash: InfoHash,
+    pub peer_id: [u8; 20],
+    pub announce: AnnounceResponse,
+    pub verified: Vec<bool>,
+}
+
+/// Creates a torrent announcing to the tracker, loads it back from its file,
+/// announces it, then checks the pieces of its content
+pub fn run(
+    runtime: &mut Runtime,
+    tracker: &FakeTracker,
+) -> Result<Flow, Box<dyn Error>> {
+    let dir = tempfile::tempdir()?;
+    let content = content();
+    let path = dir.path().join("content.bin");
+    fs::write(&path, &content)?;
+
+    let created = TorrentBuilder::new()
+        .piece_length(PIECE as i64)
+        .add_file(&path)
+        .announce(tracker.announce_url())
+        .build()?;
+    let path = dir.path().join("content.torrent");
+    fs::write(&path, &created.bytes)?;
+
+    let file = Metainfo::from_path(&path)?;
+    let torrent = file.torrent();
+    let metainfo = torrent.metainfo();
+    let info_hash = torrent.info_hash_v1();
+    tracker.add_torrent(info_hash, seeds());
+
+    let client = Client::new();
+    let peer_id = peer_id();
+    let announce = send(
+        runtime,
+        &client,
+        Announce {
+            url: metainfo.announce.ok_or("no announce URL")?.to_owned(),
+            info_hash,
+            peer_id,
+            port: PORT,
+            left: metainfo.info.total_size()?,
+        },
+    )?;
+    // The content is "downloaded" from the file the torrent was created from
+    let verified = content
+        .chunks(metainfo.info.piece_length() as usize)
+        .enumerate()
+        .map(|(index, piece)| metainfo.info.verify_piece(index, piece))
+        .collect::<Result<_, _>>()?;
+
+    Ok(Flow {
+        info_hash,
+        peer_id,
+        announce,
+        verified,
+    })
+}
+
+#[allow(dead_code)]
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut runtime = Runtime::new()?;
+    let tracker = FakeTracker::start(&runtime);
+    let flow = run(&mut runtime, &tracker)?;
+
+    println!("info hash: {}", flow.info_hash);
+    println!("peer id: {}", String::from_utf8_lossy(&flow.peer_id));
+    println!("announce interval: {}s", flow.announce.interval);
+    for peer in flow.announce.peers() {
+        println!("peer: {}", peer);
+    }
+    println!(
+        "swarm: {} seeds, {} leechers",
+        flow.announce.complete, flow.announce.incomplete
+    );
+    println!("verified pieces: {:?}", flow.verified);
+
+    Ok(())
+}
@@ -0,0 +1,370 @@
+use crate::{
+    FileEntryOwned, FileInfoOwned, InfoOwned, MetainfoError, MetainfoOwned,
+};
+use sha1::{Digest, Sha1};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tortue_bencode::to_bytes;
+
+/// Smallest piece length, both picked automatically and accepted
+const MIN_PIECE_LENGTH: i64 = 16 * 1024;
+
+/// Largest piece length picked automatically
+const MAX_PIECE_LENGTH: i64 = 16 * 1024 * 1024;
+
+/// Number of pieces above which a larger piece length is picked
+const TARGET_PIECES: i64 = 1500;
+
+/// Creates a metainfo file from files on disk
+///
+/// Files added with [`TorrentBuilder::add_dir`] are sorted by path, symbolic
+/// links found in directories are skipped so that the torrent cannot contain
+/// files from outside of them or loop. Files added one by one are followed
+/// if they are symbolic links.
+#[derive(Debug, Clone, Default)]
+pub struct TorrentBuilder {
+    piece_length: Option<i64>,
+    sources: Vec<Source>,
+    name: Option<String>,
+    announce: Option<String>,
+    announce_list: Option<Vec<Vec<String>>>,
+    comment: Option<String>,
+    created_by: Option<String>,
+    creation_date: Option<i64>,
+    private: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Source {
+    File(PathBuf),
+    Dir(PathBuf),
+}
+
+/// A metainfo created by a [`TorrentBuilder`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatedTorrent {
+    /// The metainfo
+    pub metainfo: MetainfoOwned,
+
+    /// The content of the metainfo file, with sorted keys
+    pub bytes: Vec<u8>,
+}
+
+/// A file of the torrent and its path in the torrent
+struct Entry {
+    source: PathBuf,
+    path: Vec<String>,
+    length: i64,
+}
+
+impl TorrentBuilder {
+    /// Creates an empty builder
+    pub fn new() -> Self {
+        TorrentBuilder::default()
+    }
+
+    /// Sets the number of bytes in each piece, a power of two of at least 16
+    /// KiB, it is picked from the total size of the files otherwise
+    pub fn piece_length(mut self, piece_length: i64) -> Self {
+        self.piece_length = Some(piece_length);
+        self
+    }
+
+    /// Adds a file, it is stored under its file name
+    pub fn add_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.sources.push(Source::File(path.as_ref().to_owned()));
+        self
+    }
+
+    /// Adds the files of a directory and of its subdirectories, they are
+    /// stored under the name of the directory
+    pub fn add_dir(mut self, path: impl AsRef<Path>) -> Self {
+        self.sources.push(Source::Dir(path.as_ref().to_owned()));
+        self
+    }
+
+    /// Sets the name of the torrent, required when several files or
+    /// directories are added. Defaults to the name of the single file or
+    /// directory added otherwise.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the announce URL of the tracker
+    pub fn announce(mut self, url: impl Into<String>) -> Self {
+        self.announce = Some(url.into());
+        self
+    }
+
+    /// Sets the tiers of announce URLs (BEP 12)
+    pub fn announce_list(mut self, tiers: Vec<Vec<String>>) -> Self {
+        self.announce_list = Some(tiers);
+        self
+    }
+
+    /// Sets the free-form comment of the torrent
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    /// Sets the name of the program creating the torrent, defaults to this
+    /// crate
+    pub fn created_by(mut self, created_by: impl Into<String>) -> Self {
+        self.created_by = Some(created_by.into());
+        self
+    }
+
+    /// Sets the creation time in seconds since the UNIX epoch, defaults to
+    /// the time the torrent is built
+    pub fn creation_date(mut self, creation_date: i64) -> Self {
+        self.creation_date = Some(creation_date);
+        self
+    }
+
+    /// Restricts peers to the ones given by the trackers
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+
+    /// Reads and hashes the files
+    pub fn build(self) -> Result<CreatedTorrent, MetainfoError> {
+        let announce = self
+            .announce
+            .ok_or(MetainfoError::Builder("no announce URL was given"))?;
+
+        let (single_file, single_dir) = match self.sources.as_slice() {
+            [] => return Err(MetainfoError::Builder("no file was added")),
+            [Source::File(_)] => (true, false),
+            [Source::Dir(_)] => (false, true),
+            _ => (false, false),
+        };
+
+        let name = match (self.name, self.sources.as_slice()) {
+            (Some(name), _) => name,
+            (None, [Source::File(path)]) | (None, [Source::Dir(path)]) => {
+                file_name(path)?
+            }
+            (None, _) => {
+                return Err(MetainfoError::Builder(
+                    "a name is needed for several files",
+                ))
+            }
+        };
+
+        let mut entries = Vec::new();
+        for source in &self.sources {
+            match source {
+                Source::File(path) => {
+                    entries.push(Entry {
+                        source: path.clone(),
+                        path: vec![file_name(path)?],
+                        length: fs::metadata(path)?.len() as i64,
+                    });
+                }
+                Source::Dir(path) => {
+                    // A single directory is the directory of the torrent
+                    let mut prefix = if single_dir {
+                        Vec::new()
+                    } else {
+                        vec![file_name(path)?]
+                    };
+
+                    let start = entries.len();
+                    walk(path, &mut prefix, &mut entries)?;
+                    entries[start..].sort_by(|a, b| a.path.cmp(&b.path));
+                }
+            }
+        }
+
+        if entries.is_empty() {
+            return Err(MetainfoError::Builder("no file was found"));
+        }
+
+        let total = entries.iter().map(|entry| entry.length).sum();
+        let piece_length = match self.piece_length {
+            Some(length) if length < MIN_PIECE_LENGTH => {
+                return Err(MetainfoError::Builder(
+                    "the piece length must be at least 16 KiB",
+                ))
+            }
+            Some(length) if length.count_ones() != 1 => {
+                return Err(MetainfoError::Builder(
+                    "the piece length must be a power of two",
+                ))
+            }
+            Some(length) => length,
+            None => auto_piece_length(total),
+        };
+        let pieces = hash_pieces(&entries, piece_length as usize)?;
+
+        let info = if single_file {
+            InfoOwned::SingleFile {
+                piece_length,
+                pieces,
+                private: Some(self.private).filter(|&p| p),
+                similar: None,
+                collections: None,
+                meta_version: None,
+                info: FileInfoOwned {
+                    file_name: name,
+                    file_size: total,
+                    md5sum: None,
+                },
+                extra: HashMap::new(),
+            }
+        } else {
+            InfoOwned::MultiFile {
+                piece_length,
+                pieces,
+                private: Some(self.private).filter(|&p| p),
+                similar: None,
+                collections: None,
+                meta_version: None,
+                dir_name: name,
+                files: entries
+                    .into_iter()
+                    .map(|entry| FileEntryOwned {
+                        file_size: entry.length,
+                        md5sum: None,
+                        path: entry.path,
+                        extra: HashMap::new(),
+                    })
+                    .collect(),
+                extra: HashMap::new(),
+            }
+        };
+
+        let creation_date = match self.creation_date {
+            Some(date) => date,
+            None => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs() as i64),
+        };
+        let metainfo = MetainfoOwned {
+            announce: Some(announce),
+            announce_list: self.announce_list,
+            creation_date: Some(creation_date),
+            comment: self.comment,
+            created_by: Some(self.created_by.unwrap_or_else(|| {
+                concat!("tortue/", env!("CARGO_PKG_VERSION")).to_owned()
+            })),
+            encoding: None,
+            url_list: None,
+            httpseeds: None,
+            website: None,
+            nodes: None,
+            info,
+            extra: HashMap::new(),
+        };
+        let bytes = to_bytes(&metainfo).map_err(MetainfoError::Invalid)?;
+
+        Ok(CreatedTorrent { metainfo, bytes })
+    }
+}
+
+/// The name of a file or directory, which must be valid UTF-8
+fn file_name(path: &Path) -> Result<String, MetainfoError> {
+    let name = match path.file_name() {
+        Some(name) => name.to_owned(),
+        // Paths such as `.` are named after the directory they point to
+        None => fs::canonicalize(path)?
+            .file_name()
+            .map(|name| name.to_owned())
+            .unwrap_or_default(),
+    };
+
+    name.into_string()
+        .ok()
+        .filter(|name| !name.is_empty())
+        .ok_or(MetainfoError::Builder("file names must be valid UTF-8"))
+}
+
+/// Adds the files of a directory, skipping symbolic links
+fn walk(
+    dir: &Path,
+    prefix: &mut Vec<String>,
+    entries: &mut Vec<Entry>,
+) -> Result<(), MetainfoError> {
+    for child in fs::read_dir(dir)? {
+        let child = child?;
+        let file_type = child.file_type()?;
+        let name = child.file_name().into_string().map_err(|_| {
+            MetainfoError::Builder("file names must be valid UTF-8")
+        })?;
+
+        prefix.push(name);
+        if file_type.is_dir() {
+            walk(&child.path(), prefix, entries)?;
+        } else if file_type.is_file() {
+            entries.push(Entry {
+                source: child.path(),
+                path: prefix.clone(),
+                length: child.metadata()?.len() as i64,
+            });
+        }
+        prefix.pop();
+    }
+
+    Ok(())
+}
+
+/// The smallest power of two giving at most [`TARGET_PIECES`] pieces
+fn auto_piece_length(total: i64) -> i64 {
+    let mut piece_length = MIN_PIECE_LENGTH;
+    while piece_length < MAX_PIECE_LENGTH
+        && total > piece_length * TARGET_PIECES
+    {
+        piece_length *= 2;
+    }
+
+    piece_length
+}
+
+/// Hashes the content of the files, one after the other, piece by piece
+fn hash_pieces(
+    entries: &[Entry],
+    piece_length: usize,
+) -> Result<Vec<u8>, MetainfoError> {
+    let mut pieces = Vec::new();
+    let mut piece = vec![0; piece_length];
+    let mut filled = 0;
+
+    for entry in entries {
+        let mut file = File::open(&entry.source)?.take(entry.length as u64);
+        let mut remaining = entry.length;
+        loop {
+            let read = match file.read(&mut piece[filled..]) {
+                Ok(0) if remaining == 0 => break,
+                Ok(0) => {
+                    return Err(
+                        io::Error::from(io::ErrorKind::UnexpectedEof).into()
+                    )
+                }
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            filled += read;
+            remaining -= read as i64;
+            if filled == piece_length {
+                pieces.extend(Sha1::digest(&piece));
+                filled = 0;
+            }
+        }
+    }
+
+    if filled > 0 {
+        pieces.extend(Sha1::digest(&piece[..filled]));
+    }
+
+    Ok(pieces)
+}
@@ -0,0 +1,102 @@
+use serde::{
+    de::{Error, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
+
+/// The 20 bytes SHA-1 hash identifying a torrent, computed over its bencoded
+/// info dictionary
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InfoHash(pub [u8; 20]);
+
+impl InfoHash {
+    /// Creates an info hash from a slice, returns `None` if the slice is not
+    /// exactly 20 bytes long
+    pub fn from_slice(slice: &[u8]) -> Option<Self> {
+        if slice.len() == 20 {
+            let mut hash = [0; 20];
+            hash.copy_from_slice(slice);
+
+            Some(InfoHash(hash))
+        } else {
+            None
+        }
+    }
+
+    /// Computes the version 1 info hash of a bencoded info dictionary, the
+    /// bytes must be exactly those of the metainfo file
+    #[cfg(feature = "sha1")]
+    pub fn from_info(raw_info: &[u8]) -> Self {
+        use sha1::{Digest, Sha1};
+
+        InfoHash(Sha1::digest(raw_info).into())
+    }
+
+    /// The raw bytes of the hash
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for InfoHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "InfoHash({})", self)
+    }
+}
+
+impl fmt::Display for InfoHash {
+    /// Formats the hash as lowercase hexadecimal
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in self.0.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Serialize for InfoHash {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+struct InfoHashVisitor;
+
+impl<'de> Visitor<'de> for InfoHashVisitor {
+    type Value = InfoHash;
+
+    fn expecting(
+        &self,
+        formatter: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        formatter.write_str("20 bytes")
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        InfoHash::from_slice(v)
+            .ok_or_else(|| Error::invalid_length(v.len(), &self))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: Error,
+    {
+        self.visit_bytes(v.as_bytes())
+    }
+}
+
+impl<'de> Deserialize<'de> for InfoHash {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(InfoHashVisitor)
+    }
+}
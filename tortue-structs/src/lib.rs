@@ -1,8 +1,23 @@
+#[cfg(feature = "sha1")]
+mod builder;
+mod info_hash;
+mod magnet;
 mod metainfo;
-mod tracker;
+mod node;
+mod owned;
+mod tiers;
+pub mod unix_seconds;
+mod url_list;
 
+#[cfg(feature = "sha1")]
+pub use builder::*;
+pub use info_hash::*;
+pub use magnet::*;
 pub use metainfo::*;
-pub use tracker::*;
+pub use node::*;
+pub use owned::*;
+pub use tiers::*;
+pub use url_list::*;
 
 #[cfg(test)]
 mod tests {
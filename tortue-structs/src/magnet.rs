@@ -0,0 +1,460 @@
+use crate::InfoHash;
+#[cfg(feature = "sha1")]
+use crate::Torrent;
+use std::{collections::BTreeMap, fmt, fmt::Write};
+
+/// Parameters written in magnet links, see [`Torrent::magnet_link`]
+#[cfg(feature = "sha1")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MagnetOptions {
+    /// Writes the name of the torrent (`dn`)
+    pub name: bool,
+
+    /// Writes the announce URLs (`tr`), the ones of the announce list
+    /// following the main one
+    pub trackers: bool,
+
+    /// Writes the total size of the files (`xl`)
+    pub exact_length: bool,
+
+    /// Writes the info hash in base32 rather than hexadecimal
+    pub base32: bool,
+
+    /// Writes the v2 info hash of hybrid torrents (`urn:btmh`), requires the
+    /// `sha2` feature
+    pub v2: bool,
+}
+
+#[cfg(feature = "sha1")]
+impl Default for MagnetOptions {
+    fn default() -> Self {
+        MagnetOptions {
+            name: true,
+            trackers: true,
+            exact_length: false,
+            base32: false,
+            v2: true,
+        }
+    }
+}
+
+#[cfg(feature = "sha1")]
+impl Torrent<'_> {
+    /// Builds the magnet link of the torrent (BEP 9)
+    pub fn magnet_link(&self, options: &MagnetOptions) -> String {
+        let hash = self.info_hash_v1();
+        let mut link = String::from("magnet:?xt=urn:btih:");
+        if options.base32 {
+            link.push_str(&base32(hash.as_bytes()));
+        } else {
+            write!(link, "{}", hash).unwrap();
+        }
+
+        #[cfg(feature = "sha2")]
+        if let Some(hash) = self.info_hash_v2().filter(|_| options.v2) {
+            // Multihash of a 32 bytes SHA-256 digest
+            link.push_str("&xt=urn:btmh:1220");
+            for byte in hash.iter() {
+                write!(link, "{:02x}", byte).unwrap();
+            }
+        }
+
+        let info = &self.metainfo().info;
+        if options.name {
+            link.push_str("&dn=");
+            percent_encode(&mut link, info.name());
+        }
+
+        // Left out when the lengths of the files are invalid
+        if let (true, Ok(length)) = (options.exact_length, info.total_size()) {
+            write!(link, "&xl={}", length).unwrap();
+        }
+
+        if options.trackers {
+            let metainfo = self.metainfo();
+            let mut trackers =
+                metainfo.announce.into_iter().collect::<Vec<_>>();
+            for tracker in metainfo.announce_list.iter().flatten().flatten() {
+                if !trackers.contains(tracker) {
+                    trackers.push(tracker);
+                }
+            }
+
+            for tracker in trackers {
+                link.push_str("&tr=");
+                percent_encode(&mut link, tracker);
+            }
+        }
+
+        link
+    }
+}
+
+/// Escapes everything but the unreserved characters of RFC 3986
+pub(crate) fn percent_encode(output: &mut String, input: &str) {
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'-'
+            | b'.'
+            | b'_'
+            | b'~' => output.push(byte as char),
+            _ => write!(output, "%{:02X}", byte).unwrap(),
+        }
+    }
+}
+
+/// Encodes in unpadded base32 (RFC 4648), 20 bytes giving 32 characters
+#[cfg(feature = "sha1")]
+fn base32(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+    let mut output = String::new();
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for &byte in input {
+        buffer = (buffer << 8) | u16::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            output.push(ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+    }
+
+    if bits > 0 {
+        output.push(ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+
+    output
+}
+
+/// A magnet link (BEP 9)
+///
+/// The parameters which are not known are kept as they were, decoded, and
+/// written back after the known ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Magnet {
+    /// The v1 info hash (`xt=urn:btih:`)
+    pub info_hash: Option<InfoHash>,
+
+    /// The v2 info hash (`xt=urn:btmh:`) of hybrid and v2 torrents
+    pub info_hash_v2: Option<[u8; 32]>,
+
+    /// The name of the torrent (`dn`)
+    pub name: Option<String>,
+
+    /// The announce URLs (`tr`)
+    pub trackers: Vec<String>,
+
+    /// The web seeds (`ws`)
+    pub web_seeds: Vec<String>,
+
+    /// The total size of the files (`xl`)
+    pub exact_length: Option<u64>,
+
+    /// The other parameters, by name
+    pub extra: BTreeMap<String, Vec<String>>,
+}
+
+/// The error returned when a magnet link cannot be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MagnetError {
+    /// The link does not start with `magnet:?`
+    NotMagnet,
+
+    /// The link has neither a v1 nor a v2 info hash
+    MissingHash,
+
+    /// The link has several hashes of the same version
+    DuplicateHash,
+
+    /// A v1 hash is neither 40 hexadecimal nor 32 base32 characters long
+    HashLength(usize),
+
+    /// A character of a hash is not a hexadecimal or base32 digit
+    HashDigit { position: usize, found: char },
+
+    /// A v2 hash is not a 32 bytes SHA-256 multihash (`1220` followed by 64
+    /// hexadecimal digits)
+    UnsupportedMultihash,
+
+    /// A `%` is not followed by two hexadecimal digits
+    InvalidEscape { position: usize },
+
+    /// A decoded parameter is not valid UTF-8
+    InvalidUtf8 { parameter: String },
+
+    /// The exact length is not a number
+    InvalidLength(String),
+}
+
+impl fmt::Display for MagnetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MagnetError::NotMagnet => f.write_str("not a magnet link"),
+            MagnetError::MissingHash => f.write_str("missing info hash"),
+            MagnetError::DuplicateHash => f.write_str("duplicate info hash"),
+            MagnetError::HashLength(length) => write!(
+                f,
+                "info hash of {} characters, expected 40 (hex) or 32 (base32)",
+                length
+            ),
+            MagnetError::HashDigit { position, found } => write!(
+                f,
+                "invalid character {:?} at position {} of the info hash",
+                found, position
+            ),
+            MagnetError::UnsupportedMultihash => {
+                f.write_str("v2 info hash is not a SHA-256 multihash")
+            }
+            MagnetError::InvalidEscape { position } => {
+                write!(f, "invalid percent escape at byte {}", position)
+            }
+            MagnetError::InvalidUtf8 { parameter } => {
+                write!(f, "parameter `{}` is not valid UTF-8", parameter)
+            }
+            MagnetError::InvalidLength(length) => {
+                write!(f, "invalid exact length `{}`", length)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MagnetError {}
+
+impl Magnet {
+    /// Parses a magnet link
+    pub fn parse(link: &str) -> Result<Magnet, MagnetError> {
+        let query = link
+            .strip_prefix("magnet:?")
+            .ok_or(MagnetError::NotMagnet)?;
+        let mut offset = "magnet:?".len();
+
+        let mut magnet = Magnet::default();
+        for parameter in query.split('&') {
+            let start = offset;
+            offset += parameter.len() + 1;
+            if parameter.is_empty() {
+                continue;
+            }
+
+            let (key, value) = match parameter.find('=') {
+                Some(i) => (&parameter[..i], &parameter[i + 1..]),
+                None => (parameter, ""),
+            };
+            let value_start = start + parameter.len() - value.len();
+            let key = percent_decode(key, start)?;
+            let value = percent_decode(value, value_start)?;
+
+            match key.as_str() {
+                "xt" if value.starts_with("urn:btih:") => {
+                    let hash = parse_v1(&value["urn:btih:".len()..])?;
+                    if magnet.info_hash.replace(hash).is_some() {
+                        return Err(MagnetError::DuplicateHash);
+                    }
+                }
+                "xt" if value.starts_with("urn:btmh:") => {
+                    let hash = parse_v2(&value["urn:btmh:".len()..])?;
+                    if magnet.info_hash_v2.replace(hash).is_some() {
+                        return Err(MagnetError::DuplicateHash);
+                    }
+                }
+                "dn" => magnet.name = Some(value),
+                "tr" => magnet.trackers.push(value),
+                "ws" => magnet.web_seeds.push(value),
+                "xl" => {
+                    let length = value
+                        .parse()
+                        .map_err(|_| MagnetError::InvalidLength(value))?;
+                    magnet.exact_length = Some(length);
+                }
+                _ => magnet.extra.entry(key).or_default().push(value),
+            }
+        }
+
+        if magnet.info_hash.is_none() && magnet.info_hash_v2.is_none() {
+            return Err(MagnetError::MissingHash);
+        }
+
+        Ok(magnet)
+    }
+}
+
+impl fmt::Display for Magnet {
+    /// Writes the link with the hashes in hexadecimal
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut link = String::from("magnet:?");
+        if let Some(hash) = &self.info_hash {
+            write!(link, "xt=urn:btih:{}&", hash)?;
+        }
+        if let Some(hash) = &self.info_hash_v2 {
+            link.push_str("xt=urn:btmh:1220");
+            for byte in hash.iter() {
+                write!(link, "{:02x}", byte)?;
+            }
+            link.push('&');
+        }
+
+        let mut parameter = |key: &str, value: &str| {
+            percent_encode(&mut link, key);
+            link.push('=');
+            percent_encode(&mut link, value);
+            link.push('&');
+        };
+        if let Some(name) = &self.name {
+            parameter("dn", name);
+        }
+        if let Some(length) = self.exact_length {
+            parameter("xl", &length.to_string());
+        }
+        for tracker in &self.trackers {
+            parameter("tr", tracker);
+        }
+        for seed in &self.web_seeds {
+            parameter("ws", seed);
+        }
+        for (key, values) in &self.extra {
+            for value in values {
+                parameter(key, value);
+            }
+        }
+
+        if link.ends_with('&') {
+            link.pop();
+        }
+        f.write_str(&link)
+    }
+}
+
+/// Parses a v1 hash, in hexadecimal or in base32
+fn parse_v1(hash: &str) -> Result<InfoHash, MagnetError> {
+    let bytes = match hash.len() {
+        40 => decode_hex(hash)?,
+        32 => decode_base32(hash)?,
+        length => return Err(MagnetError::HashLength(length)),
+    };
+
+    Ok(InfoHash::from_slice(&bytes).expect("the hash is 20 bytes long"))
+}
+
+/// Parses a v2 hash, a SHA-256 multihash in hexadecimal
+fn parse_v2(hash: &str) -> Result<[u8; 32], MagnetError> {
+    if hash.len() != 68 || !hash.starts_with("1220") {
+        return Err(MagnetError::UnsupportedMultihash);
+    }
+
+    let mut bytes = [0; 32];
+    bytes.copy_from_slice(&decode_hex(hash)?[2..]);
+    Ok(bytes)
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, MagnetError> {
+    let digit = |position: usize| {
+        let found = char::from(hex.as_bytes()[position]);
+        found
+            .to_digit(16)
+            .map(|d| d as u8)
+            .ok_or(MagnetError::HashDigit { position, found })
+    };
+
+    (0..hex.len() / 2)
+        .map(|i| Ok(digit(2 * i)? << 4 | digit(2 * i + 1)?))
+        .collect()
+}
+
+/// Decodes unpadded base32 (RFC 4648), lowercase letters are accepted
+fn decode_base32(input: &str) -> Result<Vec<u8>, MagnetError> {
+    let mut output = Vec::new();
+    let mut buffer = 0u16;
+    let mut bits = 0;
+    for (position, found) in input.chars().enumerate() {
+        let value = match found.to_ascii_uppercase() {
+            c @ 'A'..='Z' => c as u16 - 'A' as u16,
+            c @ '2'..='7' => c as u16 - '2' as u16 + 26,
+            _ => return Err(MagnetError::HashDigit { position, found }),
+        };
+
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+        }
+    }
+
+    Ok(output)
+}
+
+/// Decodes `%XX` escapes and `+` as a space, `offset` is the position of the
+/// input in the link for errors
+fn percent_decode(input: &str, offset: usize) -> Result<String, MagnetError> {
+    let bytes = input.as_bytes();
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let byte = input
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .filter(|_| bytes[i + 1].is_ascii_hexdigit())
+                    .ok_or(MagnetError::InvalidEscape {
+                        position: offset + i,
+                    })?;
+                output.push(byte);
+                i += 3;
+            }
+            b'+' => {
+                output.push(b' ');
+                i += 1;
+            }
+            byte => {
+                output.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(output).map_err(|_| MagnetError::InvalidUtf8 {
+        parameter: input.to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod magnet_tests {
+    use super::{decode_base32, percent_decode, percent_encode};
+
+    #[cfg(feature = "sha1")]
+    #[test]
+    fn test_base32() {
+        use super::base32;
+
+        assert_eq!(base32(b""), "");
+        assert_eq!(base32(b"f"), "MY");
+        assert_eq!(base32(b"foobar"), "MZXW6YTBOI");
+        assert_eq!(base32(&[0xff; 20]), "7".repeat(32));
+
+        let bytes = (0..20).collect::<Vec<u8>>();
+        assert_eq!(decode_base32(&base32(&bytes)), Ok(bytes));
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode_base32("MZXW6YTBOI"), Ok(b"foobar".to_vec()));
+        assert_eq!(decode_base32("mzxw6ytboi"), Ok(b"foobar".to_vec()));
+        assert_eq!(percent_decode("a%20b+c%C3%A9", 0), Ok("a b cé".to_owned()));
+        assert!(percent_decode("%+1", 0).is_err());
+        assert!(percent_decode("%4", 0).is_err());
+        assert!(percent_decode("%FF", 0).is_err());
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        let mut output = String::new();
+        percent_encode(&mut output, "a b/c?d=é~");
+        assert_eq!(output, "a%20b%2Fc%3Fd%3D%C3%A9~");
+    }
+}
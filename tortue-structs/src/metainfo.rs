@@ -1,8 +1,27 @@
+use crate::{magnet::percent_encode, unix_seconds, InfoHash, Node, UrlList};
 use serde::{
     de::{Error, MapAccess, Unexpected, Visitor},
+    ser::SerializeMap,
     Deserialize, Serialize,
 };
-use tortue_bencode::{de::Deserializer, from_value, BencodedValue};
+use serde_bytes::Bytes;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    convert::TryFrom,
+    fmt, fs, io,
+    iter::Peekable,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+use tortue_bencode::{
+    de::Deserializer,
+    error::Error as BencodeError,
+    from_value,
+    parser::{lazy, parse_file, OwnedDocument},
+    to_bytes, to_value, BencodedValue,
+};
 
 /// All data in a metainfo file is bencoded. The specification for bencoding is defined above.
 ///
@@ -14,8 +33,9 @@ use tortue_bencode::{de::Deserializer, from_value, BencodedValue};
 /// [source](https://wiki.theory.org/index.php/BitTorrentSpecification#Identification)
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct Metainfo<'a> {
-    /// The announce URL of the tracker
-    pub announce: &'a str,
+    /// The announce URL of the tracker, trackerless torrents only give DHT
+    /// nodes (BEP 5)
+    pub announce: Option<&'a str>,
 
     /// This is an extention to the official specification, offering backwards-compatibility.
     ///
@@ -37,7 +57,274 @@ pub struct Metainfo<'a> {
     /// The string encoding format used to generate the **pieces** part of the **info** dictionary in the .torrent metafile
     pub encoding: Option<&'a str>,
 
+    /// URLs of HTTP servers hosting the files (BEP 19), given either as a
+    /// single string or as a list
+    #[serde(rename = "url-list")]
+    pub url_list: Option<UrlList<&'a str>>,
+
+    /// URLs of seeds speaking the HTTP seeding protocol of BEP 17
+    pub httpseeds: Option<Vec<&'a str>>,
+
+    /// Website of the content, added by some torrent creators
+    pub website: Option<&'a str>,
+
+    /// DHT nodes to bootstrap from, given by trackerless torrents (BEP 5)
+    pub nodes: Option<Vec<Node<'a>>>,
+
     pub info: Info<'a>,
+
+    /// Keys not listed above, such as `azureus_properties` or `publisher`,
+    /// they are written back with the metainfo
+    #[serde(flatten, borrow)]
+    pub extra: HashMap<String, BencodedValue<'a>>,
+}
+
+impl<'a> Metainfo<'a> {
+    /// The creation time of the torrent, `None` if it is missing or out of
+    /// the range of [`unix_seconds`]
+    pub fn created_at(&self) -> Option<SystemTime> {
+        self.creation_date.and_then(unix_seconds::to_system_time)
+    }
+
+    /// The URLs of `url-list` (BEP 19) followed by the ones of `httpseeds`
+    /// (BEP 17) which are not already listed
+    ///
+    /// Note that both kinds of seeds do not speak the same protocol.
+    pub fn all_web_sources(&self) -> Vec<&'a str> {
+        let mut sources = Vec::new();
+        let url_list = self.url_list.iter().map(UrlList::urls);
+        let httpseeds = self.httpseeds.iter().map(Vec::as_slice);
+        for &url in url_list.chain(httpseeds).flatten() {
+            if !sources.contains(&url) {
+                sources.push(url);
+            }
+        }
+
+        sources
+    }
+}
+
+impl Metainfo<'_> {
+    /// Reads and validates the metainfo file at `path`, the returned file owns
+    /// the content the metainfo borrows from
+    pub fn from_path(
+        path: impl AsRef<Path>,
+    ) -> Result<MetainfoFile, MetainfoError> {
+        let document = parse_file(path).map_err(|e| match e {
+            BencodeError::Io(e) => MetainfoError::Io(e.into_inner()),
+            e => MetainfoError::Parse(e),
+        })?;
+
+        // Safety: the bytes live in the buffer of the document, which never
+        // moves and is dropped after the torrent, see `MetainfoFile`
+        let bytes: &'static [u8] =
+            unsafe { &*(document.bytes() as *const [u8]) };
+        let torrent =
+            Torrent::from_bytes(bytes).map_err(MetainfoError::Invalid)?;
+
+        Ok(MetainfoFile { torrent, document })
+    }
+
+    /// Writes the metainfo to the file at `path`, with the keys of the
+    /// dictionaries sorted
+    ///
+    /// The info hash is kept as long as the info dictionary read was itself
+    /// canonical, [`Torrent::write_to_path`] keeps it in any case.
+    pub fn write_to_path(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<(), MetainfoError> {
+        let bytes = to_bytes(self).map_err(MetainfoError::Invalid)?;
+        fs::write(path, bytes).map_err(MetainfoError::Io)
+    }
+}
+
+/// The error returned when reading or writing a metainfo file
+#[derive(Debug)]
+pub enum MetainfoError {
+    /// The file could not be read or written
+    Io(io::Error),
+
+    /// The file is not valid bencode
+    Parse(BencodeError),
+
+    /// The content of the file is not a valid metainfo
+    Invalid(BencodeError),
+
+    /// The torrent builder was not given what it needs
+    Builder(&'static str),
+}
+
+impl From<io::Error> for MetainfoError {
+    fn from(e: io::Error) -> Self {
+        MetainfoError::Io(e)
+    }
+}
+
+impl fmt::Display for MetainfoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MetainfoError::Io(e) => write!(f, "io error: {}", e),
+            MetainfoError::Parse(e) => write!(f, "invalid bencode: {}", e),
+            MetainfoError::Invalid(e) => write!(f, "invalid metainfo: {}", e),
+            MetainfoError::Builder(e) => {
+                write!(f, "cannot build the torrent: {}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MetainfoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MetainfoError::Io(e) => Some(e),
+            MetainfoError::Parse(e) | MetainfoError::Invalid(e) => Some(e),
+            MetainfoError::Builder(_) => None,
+        }
+    }
+}
+
+/// A metainfo file read from disk, see [`Metainfo::from_path`]
+///
+/// The metainfo is decoded once when the file is read. It is only handed out
+/// with the lifetime of the file, a `Deref` would have to expose it as
+/// `Metainfo<'static>` and let its strings outlive the file.
+#[derive(Debug)]
+pub struct MetainfoFile {
+    // Borrows from `document`, declared first so that it is dropped first
+    torrent: Torrent<'static>,
+    document: OwnedDocument,
+}
+
+impl MetainfoFile {
+    /// The metainfo, borrowing from the file
+    pub fn metainfo(&self) -> &Metainfo<'_> {
+        self.torrent.metainfo()
+    }
+
+    /// The parsed content of the file
+    pub fn document(&self) -> &OwnedDocument {
+        &self.document
+    }
+
+    /// The metainfo along with the bytes of its info dictionary
+    pub fn torrent(&self) -> &Torrent<'_> {
+        &self.torrent
+    }
+}
+
+/// A metainfo along with its info dictionary as found in the input, which is
+/// what the info hash is computed over
+///
+/// Re-encoding the info dictionary would not give back the same bytes if the
+/// keys of the original are not sorted, the torrent is thus written with the
+/// original bytes as long as the info dictionary is not replaced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Torrent<'a> {
+    metainfo: Metainfo<'a>,
+    raw_info: Cow<'a, [u8]>,
+}
+
+impl<'a> Torrent<'a> {
+    /// Reads a metainfo, keeping track of the bytes of its info dictionary
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, BencodeError> {
+        let value = lazy(data)?;
+        let metainfo = value.deserialize()?;
+        let raw_info = value
+            .get("info")
+            .ok_or(BencodeError::MissingField("info"))?
+            .raw();
+
+        Ok(Torrent {
+            metainfo,
+            raw_info: Cow::Borrowed(raw_info),
+        })
+    }
+
+    /// The decoded metainfo
+    pub fn metainfo(&self) -> &Metainfo<'a> {
+        &self.metainfo
+    }
+
+    /// The decoded metainfo, to change the keys outside of the info
+    /// dictionary. The info dictionary must be changed with
+    /// [`Torrent::set_info`], changes made here are not written.
+    pub fn metainfo_mut(&mut self) -> &mut Metainfo<'a> {
+        &mut self.metainfo
+    }
+
+    /// Replaces the info dictionary, its bytes are then the canonical
+    /// encoding of `info`
+    pub fn set_info(&mut self, info: Info<'a>) -> Result<(), BencodeError> {
+        self.raw_info = Cow::Owned(to_bytes(&info)?);
+        self.metainfo.info = info;
+        Ok(())
+    }
+
+    /// Whether the info dictionary was replaced, the info hash is then the
+    /// one of the new dictionary
+    pub fn is_info_modified(&self) -> bool {
+        matches!(self.raw_info, Cow::Owned(_))
+    }
+
+    /// The decoded metainfo, dropping the info dictionary bytes
+    pub fn into_metainfo(self) -> Metainfo<'a> {
+        self.metainfo
+    }
+
+    /// The bencoded info dictionary, exactly as found in the input unless
+    /// it was replaced
+    pub fn raw_info(&self) -> &[u8] {
+        &self.raw_info
+    }
+
+    /// Encodes the metainfo with sorted keys, except in the info dictionary
+    /// which is written as found in the input
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BencodeError> {
+        let mut value = to_value(&self.metainfo)?;
+        value.insert(
+            "info",
+            BencodedValue::Raw(Cow::Borrowed(self.raw_info())),
+        )?;
+        value.to_vec()
+    }
+
+    /// Writes the metainfo to the file at `path`, see [`Torrent::to_bytes`]
+    pub fn write_to_path(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> Result<(), MetainfoError> {
+        let bytes = self.to_bytes().map_err(MetainfoError::Invalid)?;
+        fs::write(path, bytes).map_err(MetainfoError::Io)
+    }
+
+    /// The SHA-1 hash of the info dictionary, identifying the torrent
+    #[cfg(feature = "sha1")]
+    pub fn info_hash_v1(&self) -> InfoHash {
+        InfoHash::from_info(&self.raw_info)
+    }
+
+    /// The SHA-256 hash of the info dictionary, identifying hybrid and v2
+    /// torrents (BEP 52), `None` for v1 torrents
+    #[cfg(feature = "sha2")]
+    pub fn info_hash_v2(&self) -> Option<[u8; 32]> {
+        use sha2::{Digest, Sha256};
+
+        match self.metainfo.info.meta_version() {
+            Some(version) if version >= 2 => {
+                Some(Sha256::digest(&self.raw_info).into())
+            }
+            _ => None,
+        }
+    }
+
+    /// The v2 info hash truncated to 20 bytes, used where v1 info hashes are
+    /// expected such as in tracker announces
+    #[cfg(feature = "sha2")]
+    pub fn truncated_v2(&self) -> Option<InfoHash> {
+        self.info_hash_v2()
+            .and_then(|hash| InfoHash::from_slice(&hash[..20]))
+    }
 }
 
 /// This is the section of the metainfo file that contains information about the file
@@ -46,16 +333,14 @@ pub struct Metainfo<'a> {
 /// **⚠ Note that this uses a lifetime to do zero copy deserialization**
 ///
 /// [source](https://wiki.theory.org/index.php/BitTorrentSpecification#Identification)
-#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Info<'a> {
     /// The torrent contains a single file
     SingleFile {
         /// Number of bytes in each piece
-        #[serde(rename = "piece length")]
         piece_length: i64,
 
         /// 20 bytes SHA-1 hash value, one per piece
-        #[serde(with = "serde_bytes")]
         pieces: &'a [u8],
 
         /// if `Some(true)` the client MUST publish its presence to get other
@@ -65,19 +350,30 @@ pub enum Info<'a> {
         /// read as "no external peer source".
         private: Option<bool>,
 
+        /// Info hashes of torrents sharing files with this one (BEP 38)
+        similar: Option<Vec<InfoHash>>,
+
+        /// Names of the collections this torrent belongs to (BEP 38)
+        collections: Option<Vec<&'a str>>,
+
+        /// Version of the torrent format, 2 or more for hybrid and v2
+        /// torrents (BEP 52)
+        meta_version: Option<i64>,
+
         /// See the structure description for its fields, not that it is flattened!
-        #[serde(flatten)]
         info: FileInfo<'a>,
+
+        /// Keys not listed above, such as `source`, they are written back so
+        /// that the info hash does not change
+        extra: HashMap<String, BencodedValue<'a>>,
     },
 
     /// The torrent contains multiple files
     MultiFile {
         /// Number of bytes in each piece
-        #[serde(rename = "piece length")]
         piece_length: i64,
 
         /// 20 bytes SHA-1 hash value, one per piece
-        #[serde(with = "serde_bytes")]
         pieces: &'a [u8],
 
         /// if `Some(true)` the client MUST publish its presence to get other
@@ -87,16 +383,29 @@ pub enum Info<'a> {
         /// read as "no external peer source".
         private: Option<bool>,
 
+        /// Info hashes of torrents sharing files with this one (BEP 38)
+        similar: Option<Vec<InfoHash>>,
+
+        /// Names of the collections this torrent belongs to (BEP 38)
+        collections: Option<Vec<&'a str>>,
+
+        /// Version of the torrent format, 2 or more for hybrid and v2
+        /// torrents (BEP 52)
+        meta_version: Option<i64>,
+
         /// Directory name containing the files
-        #[serde(rename = "name")]
         dir_name: &'a str,
 
         /// List of files in the torrent
-        files: Vec<FileInfo<'a>>,
+        files: Vec<FileEntry<'a>>,
+
+        /// Keys not listed above, such as `source`, they are written back so
+        /// that the info hash does not change
+        extra: HashMap<String, BencodedValue<'a>>,
     },
 }
-/// This is the section of the metainfo file that contains information about a file
-/// being transferred
+/// This is the section of the metainfo file that contains information about the file
+/// of a single file torrent
 ///
 /// **⚠ Note that this uses a lifetime to do zero copy deserialization**
 ///
@@ -116,6 +425,146 @@ pub struct FileInfo<'a> {
     pub md5sum: Option<&'a [u8]>,
 }
 
+/// A file of a multi-file torrent, its path is relative to the directory
+/// named by the info dictionary
+///
+/// **⚠ Note that this uses a lifetime to do zero copy deserialization**
+///
+/// [source](https://wiki.theory.org/index.php/BitTorrentSpecification#Info_in_Multiple_File_Mode)
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileEntry<'a> {
+    /// Size (in bytes) of the file
+    #[serde(rename = "length")]
+    pub file_size: i64,
+
+    /// md5 checksum of the file (optional)
+    #[serde(with = "serde_bytes", default)]
+    pub md5sum: Option<&'a [u8]>,
+
+    /// Path of the file, one element per directory and the last one being
+    /// the file name
+    pub path: Vec<&'a str>,
+
+    /// Keys not listed above, such as `attr` (BEP 47)
+    #[serde(flatten, borrow)]
+    pub extra: HashMap<String, BencodedValue<'a>>,
+}
+
+impl<'a> FileInfo<'a> {
+    /// Creates the description of the file of a single file torrent
+    pub fn new(name: &'a str, length: i64, md5sum: Option<&'a [u8]>) -> Self {
+        FileInfo {
+            file_name: name,
+            file_size: length,
+            md5sum,
+        }
+    }
+
+    /// Name of the file
+    pub fn name(&self) -> &'a str {
+        self.file_name
+    }
+
+    /// Size (in bytes) of the file
+    pub fn length(&self) -> i64 {
+        self.file_size
+    }
+
+    /// md5 checksum of the file
+    pub fn md5sum(&self) -> Option<&'a [u8]> {
+        self.md5sum
+    }
+}
+
+impl<'a> FileEntry<'a> {
+    /// Creates a file of a multi-file torrent
+    pub fn new(
+        path: Vec<&'a str>,
+        length: i64,
+        md5sum: Option<&'a [u8]>,
+    ) -> Self {
+        FileEntry {
+            file_size: length,
+            md5sum,
+            path,
+            extra: HashMap::new(),
+        }
+    }
+
+    /// Path of the file, one element per directory
+    pub fn path(&self) -> &[&'a str] {
+        &self.path
+    }
+
+    /// Size (in bytes) of the file
+    pub fn length(&self) -> i64 {
+        self.file_size
+    }
+
+    /// md5 checksum of the file
+    pub fn md5sum(&self) -> Option<&'a [u8]> {
+        self.md5sum
+    }
+
+    /// The path of the file relative to the directory of the torrent, `None`
+    /// if the path is empty or one of its elements is not a plain name, such
+    /// as `..` or an absolute path, which could escape that directory
+    pub fn path_buf(&self) -> Option<PathBuf> {
+        safe_path(&self.path)
+    }
+}
+
+/// Joins the elements of a path, `None` if one of them is not a plain name
+fn safe_path(path: &[&str]) -> Option<PathBuf> {
+    if path.is_empty() {
+        return None;
+    }
+
+    path.iter()
+        .map(|element| {
+            let mut components = Path::new(element).components();
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(name)), None) if name == *element => {
+                    Some(name)
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// A file of a torrent, be it a single file or a multi-file torrent, see
+/// [`Info::files`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TorrentFile<'i, 'a> {
+    path: &'i [&'a str],
+    length: i64,
+    md5sum: Option<&'a [u8]>,
+}
+
+impl<'i, 'a> TorrentFile<'i, 'a> {
+    /// Path of the file, one element per directory, the name of the file for
+    /// single file torrents
+    pub fn path(&self) -> &'i [&'a str] {
+        self.path
+    }
+
+    /// Size (in bytes) of the file
+    pub fn length(&self) -> i64 {
+        self.length
+    }
+
+    /// md5 checksum of the file
+    pub fn md5sum(&self) -> Option<&'a [u8]> {
+        self.md5sum
+    }
+
+    /// The path of the file, see [`FileEntry::path_buf`]
+    pub fn path_buf(&self) -> Option<PathBuf> {
+        safe_path(self.path)
+    }
+}
+
 impl<'a> Info<'a> {
     /// Is the transfer containing a single file?
     pub fn is_single_file(&self) -> bool {
@@ -129,6 +578,571 @@ impl<'a> Info<'a> {
     pub fn is_multi_file(&self) -> bool {
         !self.is_single_file()
     }
+
+    /// Number of bytes in each piece
+    pub fn piece_length(&self) -> i64 {
+        match self {
+            Info::SingleFile { piece_length, .. }
+            | Info::MultiFile { piece_length, .. } => *piece_length,
+        }
+    }
+
+    /// The 20 bytes SHA-1 hashes of the pieces, one after the other
+    pub fn pieces(&self) -> &'a [u8] {
+        match self {
+            Info::SingleFile { pieces, .. }
+            | Info::MultiFile { pieces, .. } => pieces,
+        }
+    }
+
+    /// The SHA-1 hashes of the pieces, trailing bytes are skipped if the
+    /// length of `pieces` is not a multiple of 20, which [`Info::validate`]
+    /// reports. The hashes of v2 torrents are in their piece layers instead.
+    pub fn piece_hashes(&self) -> impl Iterator<Item = &'a [u8; 20]> {
+        self.pieces()
+            .chunks_exact(20)
+            .map(|hash| <&[u8; 20]>::try_from(hash).unwrap())
+    }
+
+    /// The SHA-1 hash of a piece, see [`Info::piece_hashes`]
+    pub fn piece_hash(&self, index: usize) -> Option<&'a [u8; 20]> {
+        let start = index.checked_mul(20)?;
+        let hash = self.pieces().get(start..start.checked_add(20)?)?;
+        Some(<&[u8; 20]>::try_from(hash).unwrap())
+    }
+
+    /// Checks a downloaded piece against its hash, its length must be the
+    /// piece length, or what is left of the total size for the last piece
+    #[cfg(feature = "sha1")]
+    pub fn verify_piece(
+        &self,
+        index: usize,
+        data: &[u8],
+    ) -> Result<bool, VerifyError> {
+        use sha1::{Digest, Sha1};
+
+        let count = self.piece_count()?;
+        if index >= count {
+            return Err(VerifyError::Index { index, count });
+        }
+
+        let expected = if index + 1 == count {
+            self.last_piece_len()?
+        } else {
+            self.piece_length() as u64
+        };
+        if data.len() as u64 != expected {
+            return Err(VerifyError::Length {
+                expected,
+                found: data.len(),
+            });
+        }
+
+        let hash = self.piece_hash(index).expect("the index is in range");
+        Ok(Sha1::digest(data)[..] == hash[..])
+    }
+
+    /// Must peers only be obtained from the trackers of the metainfo file?
+    pub fn is_private(&self) -> bool {
+        match self {
+            Info::SingleFile { private, .. }
+            | Info::MultiFile { private, .. } => private.unwrap_or(false),
+        }
+    }
+
+    /// Name of the file for single file torrents, of the directory holding
+    /// the files otherwise
+    pub fn name(&self) -> &'a str {
+        match self {
+            Info::SingleFile { info, .. } => info.file_name,
+            Info::MultiFile { dir_name, .. } => dir_name,
+        }
+    }
+
+    /// The files of the torrent, in the order their data is found in the
+    /// pieces
+    pub fn files(&self) -> impl Iterator<Item = TorrentFile<'_, 'a>> {
+        let (single, multi) = match self {
+            Info::SingleFile { info, .. } => (
+                Some(TorrentFile {
+                    path: std::slice::from_ref(&info.file_name),
+                    length: info.file_size,
+                    md5sum: info.md5sum,
+                }),
+                &[][..],
+            ),
+            Info::MultiFile { files, .. } => (None, &files[..]),
+        };
+
+        single
+            .into_iter()
+            .chain(multi.iter().map(|file| TorrentFile {
+                path: &file.path,
+                length: file.file_size,
+                md5sum: file.md5sum,
+            }))
+    }
+
+    /// Total number of bytes of the files
+    pub fn total_size(&self) -> Result<u64, InfoError> {
+        self.files().try_fold(0u64, |total, file| {
+            let length = u64::try_from(file.length())
+                .map_err(|_| InfoError::FileLength(file.length()))?;
+            total.checked_add(length).ok_or(InfoError::TotalSize)
+        })
+    }
+
+    /// Number of pieces, checked against the total size of the files
+    pub fn piece_count(&self) -> Result<usize, InfoError> {
+        let pieces = self.pieces().len();
+        if !pieces.is_multiple_of(20) {
+            return Err(InfoError::PiecesLength(pieces));
+        }
+
+        let expected = self.expected_piece_count()?;
+        if pieces / 20 != expected {
+            return Err(InfoError::PieceCount {
+                expected,
+                found: pieces / 20,
+            });
+        }
+
+        Ok(expected)
+    }
+
+    /// Number of bytes of the last piece, which is shorter than the others
+    /// unless the total size is a multiple of the piece length
+    pub fn last_piece_len(&self) -> Result<u64, InfoError> {
+        let count = self.piece_count()? as u64;
+        let total = self.total_size()?;
+
+        Ok(total - count.saturating_sub(1) * self.piece_length() as u64)
+    }
+
+    /// The parts of the files making up a piece, in order, a piece straddling
+    /// file boundaries spanning several files. Empty files are skipped and
+    /// an index past the last piece gives no span.
+    pub fn piece_ranges(
+        &self,
+        index: usize,
+    ) -> Result<Vec<FileSpan>, InfoError> {
+        let count = self.piece_count()?;
+        if index >= count {
+            return Ok(Vec::new());
+        }
+
+        let piece_length = self.piece_length() as u64;
+        let start = index as u64 * piece_length;
+        let end = (start + piece_length).min(self.total_size()?);
+
+        let mut spans = Vec::new();
+        let mut file_start = 0;
+        for (file_index, file) in self.files().enumerate() {
+            let file_end = file_start + file.length() as u64;
+            if file_start >= end {
+                break;
+            }
+
+            let offset = start.max(file_start);
+            if file_end > offset {
+                spans.push(FileSpan {
+                    file_index,
+                    offset: offset - file_start,
+                    length: end.min(file_end) - offset,
+                });
+            }
+            file_start = file_end;
+        }
+
+        Ok(spans)
+    }
+
+    /// The pieces holding data of a file, from the first to the last one
+    /// touching it. Empty files and unknown files give an empty range.
+    pub fn file_pieces(
+        &self,
+        file_index: usize,
+    ) -> Result<Range<usize>, InfoError> {
+        let count = self.piece_count()?;
+        let piece_length = self.piece_length() as u64;
+
+        let mut file_start = 0;
+        for (index, file) in self.files().enumerate() {
+            let length = file.length() as u64;
+            if index == file_index {
+                let first = (file_start / piece_length) as usize;
+                if length == 0 {
+                    let first = first.min(count);
+                    return Ok(first..first);
+                }
+
+                let last = ((file_start + length - 1) / piece_length) as usize;
+                return Ok(first..last + 1);
+            }
+            file_start += length;
+        }
+
+        Ok(0..0)
+    }
+
+    /// Runs all the consistency checks, returning every problem found
+    pub fn validate(&self) -> Vec<InfoError> {
+        let mut problems = Vec::new();
+
+        if self.piece_length() <= 0 {
+            problems.push(InfoError::PieceLength(self.piece_length()));
+        }
+
+        for file in self.files() {
+            if file.length() < 0 {
+                problems.push(InfoError::FileLength(file.length()));
+            }
+        }
+
+        if !self.pieces().len().is_multiple_of(20) {
+            problems.push(InfoError::PiecesLength(self.pieces().len()));
+        }
+
+        if problems.is_empty() {
+            if let Err(e) = self.piece_count() {
+                problems.push(e);
+            }
+        }
+
+        problems
+    }
+
+    /// Number of pieces needed for the total size of the files
+    fn expected_piece_count(&self) -> Result<usize, InfoError> {
+        let piece_length = match self.piece_length() {
+            length if length > 0 => length as u64,
+            length => return Err(InfoError::PieceLength(length)),
+        };
+
+        let total = self.total_size()?;
+        let count = total / piece_length + (total % piece_length != 0) as u64;
+        usize::try_from(count).map_err(|_| InfoError::TotalSize)
+    }
+
+    /// The URLs of the files on a web seed (BEP 19), in the order of
+    /// [`Info::files`]
+    ///
+    /// The name of the torrent and the path of the files are appended to
+    /// the URL of the seed, except for single file torrents whose seed URL
+    /// does not end with a `/` as it is the URL of the file itself.
+    pub fn web_seed_urls(&self, seed: &str) -> Vec<String> {
+        if self.is_single_file() && !seed.ends_with('/') {
+            return vec![seed.to_owned()];
+        }
+
+        let mut base = seed.to_owned();
+        if !base.ends_with('/') {
+            base.push('/');
+        }
+        percent_encode(&mut base, self.name());
+
+        self.files()
+            .map(|file| {
+                let mut url = base.clone();
+                if self.is_multi_file() {
+                    for element in file.path() {
+                        url.push('/');
+                        percent_encode(&mut url, element);
+                    }
+                }
+
+                url
+            })
+            .collect()
+    }
+
+    /// Info hashes of torrents sharing files with this one (BEP 38)
+    pub fn similar(&self) -> Option<&[InfoHash]> {
+        match self {
+            Info::SingleFile { similar, .. }
+            | Info::MultiFile { similar, .. } => similar.as_deref(),
+        }
+    }
+
+    /// Names of the collections this torrent belongs to (BEP 38)
+    pub fn collections(&self) -> Option<&[&'a str]> {
+        match self {
+            Info::SingleFile { collections, .. }
+            | Info::MultiFile { collections, .. } => collections.as_deref(),
+        }
+    }
+
+    /// Version of the torrent format, `None` for v1 torrents (BEP 52)
+    pub fn meta_version(&self) -> Option<i64> {
+        match self {
+            Info::SingleFile { meta_version, .. }
+            | Info::MultiFile { meta_version, .. } => *meta_version,
+        }
+    }
+
+    /// Keys of the info dictionary this crate does not know about
+    pub fn extra(&self) -> &HashMap<String, BencodedValue<'a>> {
+        match self {
+            Info::SingleFile { extra, .. } | Info::MultiFile { extra, .. } => {
+                extra
+            }
+        }
+    }
+
+    /// Torrents which may contain files identical to the ones of this torrent
+    /// and can be used to deduplicate downloads (BEP 38): first the torrents
+    /// listed in `similar`, then the named `collections`.
+    pub fn dedup_candidates(&self) -> Vec<DedupCandidate<'a>> {
+        self.similar()
+            .unwrap_or_default()
+            .iter()
+            .copied()
+            .map(DedupCandidate::Similar)
+            .chain(
+                self.collections()
+                    .unwrap_or_default()
+                    .iter()
+                    .copied()
+                    .map(DedupCandidate::Collection),
+            )
+            .collect()
+    }
+}
+
+/// The part of a file covered by a piece, see [`Info::piece_ranges`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileSpan {
+    /// Index of the file in [`Info::files`]
+    pub file_index: usize,
+
+    /// Offset (in bytes) of the span in the file
+    pub offset: u64,
+
+    /// Number of bytes of the span
+    pub length: u64,
+}
+
+/// An inconsistency of an [`Info`], see [`Info::validate`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InfoError {
+    /// The piece length is not positive
+    PieceLength(i64),
+
+    /// The length of a file is negative
+    FileLength(i64),
+
+    /// The total size of the files does not fit in 64 bits
+    TotalSize,
+
+    /// The length of `pieces` is not a multiple of 20 bytes
+    PiecesLength(usize),
+
+    /// The number of hashes in `pieces` does not match the total size
+    PieceCount { expected: usize, found: usize },
+}
+
+impl fmt::Display for InfoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InfoError::PieceLength(length) => {
+                write!(f, "invalid piece length {}", length)
+            }
+            InfoError::FileLength(length) => {
+                write!(f, "invalid file length {}", length)
+            }
+            InfoError::TotalSize => f.write_str("total size is too large"),
+            InfoError::PiecesLength(length) => write!(
+                f,
+                "pieces of {} bytes, not a multiple of 20 bytes",
+                length
+            ),
+            InfoError::PieceCount { expected, found } => write!(
+                f,
+                "{} piece hashes for a total size needing {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InfoError {}
+
+/// The error returned when a piece cannot be checked, see
+/// [`Info::verify_piece`]
+#[cfg(feature = "sha1")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VerifyError {
+    /// The torrent has no piece at this index
+    Index { index: usize, count: usize },
+
+    /// The data is not as long as the piece
+    Length { expected: u64, found: usize },
+
+    /// The info is inconsistent, the pieces cannot be located
+    Info(InfoError),
+}
+
+#[cfg(feature = "sha1")]
+impl From<InfoError> for VerifyError {
+    fn from(e: InfoError) -> Self {
+        VerifyError::Info(e)
+    }
+}
+
+#[cfg(feature = "sha1")]
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerifyError::Index { index, count } => {
+                write!(f, "no piece {} in a torrent of {}", index, count)
+            }
+            VerifyError::Length { expected, found } => write!(
+                f,
+                "piece of {} bytes, expected {} bytes",
+                found, expected
+            ),
+            VerifyError::Info(e) => write!(f, "inconsistent info: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "sha1")]
+impl std::error::Error for VerifyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VerifyError::Info(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A torrent possibly sharing files with another one, see
+/// [`Info::dedup_candidates`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DedupCandidate<'a> {
+    /// A torrent identified by its info hash
+    Similar(InfoHash),
+
+    /// All the torrents belonging to a named collection
+    Collection(&'a str),
+}
+
+impl<'a> Serialize for Info<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let (piece_length, pieces, private, similar, collections, version) =
+            match self {
+                Info::SingleFile {
+                    piece_length,
+                    pieces,
+                    private,
+                    similar,
+                    collections,
+                    meta_version,
+                    ..
+                }
+                | Info::MultiFile {
+                    piece_length,
+                    pieces,
+                    private,
+                    similar,
+                    collections,
+                    meta_version,
+                    ..
+                } => (
+                    piece_length,
+                    pieces,
+                    private,
+                    similar,
+                    collections,
+                    meta_version,
+                ),
+            };
+
+        let mut extra = self.extra().iter().collect::<Vec<_>>();
+        extra.sort_unstable_by_key(|(key, _)| *key);
+        let mut extra = extra.into_iter().peekable();
+
+        // Keys are written in lexicographical order, absent keys are skipped
+        // and unknown keys are written between the known ones
+        let mut map = serializer.serialize_map(None)?;
+
+        write_extra(&mut map, &mut extra, Some("collections"))?;
+        if let Some(collections) = collections {
+            map.serialize_entry("collections", collections)?;
+        }
+
+        let name = match self {
+            Info::SingleFile { info, .. } => {
+                write_extra(&mut map, &mut extra, Some("length"))?;
+                map.serialize_entry("length", &info.file_size)?;
+
+                write_extra(&mut map, &mut extra, Some("md5sum"))?;
+                if let Some(md5sum) = info.md5sum {
+                    map.serialize_entry("md5sum", Bytes::new(md5sum))?;
+                }
+
+                info.file_name
+            }
+            Info::MultiFile {
+                dir_name, files, ..
+            } => {
+                write_extra(&mut map, &mut extra, Some("files"))?;
+                map.serialize_entry("files", files)?;
+                dir_name
+            }
+        };
+
+        write_extra(&mut map, &mut extra, Some("meta version"))?;
+        if let Some(version) = version {
+            map.serialize_entry("meta version", version)?;
+        }
+
+        write_extra(&mut map, &mut extra, Some("name"))?;
+        map.serialize_entry("name", name)?;
+
+        write_extra(&mut map, &mut extra, Some("piece length"))?;
+        map.serialize_entry("piece length", piece_length)?;
+        write_extra(&mut map, &mut extra, Some("pieces"))?;
+        map.serialize_entry("pieces", Bytes::new(pieces))?;
+
+        write_extra(&mut map, &mut extra, Some("private"))?;
+        if let Some(private) = private {
+            map.serialize_entry("private", private)?;
+        }
+
+        write_extra(&mut map, &mut extra, Some("similar"))?;
+        if let Some(similar) = similar {
+            map.serialize_entry("similar", similar)?;
+        }
+
+        write_extra(&mut map, &mut extra, None)?;
+        map.end()
+    }
+}
+
+/// Writes the sorted unknown keys coming before `key`, or all of them
+fn write_extra<'e, M, I>(
+    map: &mut M,
+    extra: &mut Peekable<I>,
+    key: Option<&str>,
+) -> Result<(), M::Error>
+where
+    M: SerializeMap,
+    I: Iterator<Item = (&'e String, &'e BencodedValue<'e>)>,
+{
+    while let Some((k, v)) = extra.next_if(|(k, _)| match key {
+        Some(key) => k.as_str() < key,
+        None => true,
+    }) {
+        map.serialize_entry(k, v)?;
+    }
+
+    Ok(())
 }
 
 struct FileInfoVisitor;
@@ -157,6 +1171,13 @@ impl<'de> Visitor<'de> for FileInfoVisitor {
 
         let mut files = None;
 
+        let mut similar = None;
+        let mut collections = None;
+
+        let mut meta_version = None;
+
+        let mut extra = HashMap::new();
+
         while let Some((k, v)) =
             map.next_entry::<String, BencodedValue<'de>>()?
         {
@@ -218,27 +1239,48 @@ impl<'de> Visitor<'de> for FileInfoVisitor {
                 }
                 "files" => {
                     files.replace(
-                        from_value::<Vec<FileInfo<'de>>>(v).map_err(|_e| {
+                        from_value::<Vec<FileEntry<'de>>>(v).map_err(|_e| {
                             Error::invalid_type(
-                                Unexpected::Other("not a list of FileInfo"),
+                                Unexpected::Other("not a list of FileEntry"),
                                 &self,
                             )
                         })?,
                     );
                 }
-                key => {
-                    return Err(Error::unknown_field(
-                        key,
-                        &[
-                            "piece length",
-                            "pieces",
-                            "private",
-                            "name",
-                            "length",
-                            "md5sum",
-                            "files",
-                        ],
-                    ))
+                "similar" => {
+                    similar.replace(from_value::<Vec<InfoHash>>(v).map_err(
+                        |_e| {
+                            Error::invalid_type(
+                                Unexpected::Other("not a list of info hashes"),
+                                &self,
+                            )
+                        },
+                    )?);
+                }
+                "collections" => {
+                    collections.replace(
+                        from_value::<Vec<&'de str>>(v).map_err(|_e| {
+                            Error::invalid_type(
+                                Unexpected::Other("not a list of strings"),
+                                &self,
+                            )
+                        })?,
+                    );
+                }
+                "meta version" => {
+                    meta_version.replace(from_value::<i64>(v).map_err(
+                        |_e| {
+                            Error::invalid_type(
+                                Unexpected::Other("not an i64"),
+                                &self,
+                            )
+                        },
+                    )?);
+                }
+                // Including the v2 file tree of hybrid torrents, it is only
+                // needed to verify v2 pieces
+                _ => {
+                    extra.insert(k, v);
                 }
             }
         }
@@ -260,8 +1302,12 @@ impl<'de> Visitor<'de> for FileInfoVisitor {
                 piece_length: pieces_length.unwrap(),
                 pieces: pieces.unwrap(),
                 private,
+                similar,
+                collections,
+                meta_version,
                 dir_name: name.unwrap(),
                 files,
+                extra,
             })
         } else {
             if files_size.is_none() {
@@ -272,11 +1318,15 @@ impl<'de> Visitor<'de> for FileInfoVisitor {
                 piece_length: pieces_length.unwrap(),
                 pieces: pieces.unwrap(),
                 private,
+                similar,
+                collections,
+                meta_version,
                 info: FileInfo {
                     file_name: name.unwrap(),
                     file_size: files_size.unwrap(),
                     md5sum,
                 },
+                extra,
             })
         }
     }
@@ -293,8 +1343,102 @@ impl<'de: 'a, 'a> Deserialize<'de> for Info<'a> {
 
 #[cfg(test)]
 mod simple_test {
-    use crate::Metainfo;
-    use tortue_bencode::from_bytes;
+    use crate::{
+        AnnounceTiers, DedupCandidate, FileEntry, FileInfo, FileSpan, Info,
+        InfoError, InfoHash, Metainfo, MetainfoError, MetainfoFile,
+        MetainfoOwned, Torrent, UrlList,
+    };
+    use std::{
+        collections::HashMap,
+        env, io,
+        io::Write,
+        time::{Duration, UNIX_EPOCH},
+    };
+    use tortue_bencode::{from_bytes, parse_all, to_bytes, to_value};
+
+    const SIMILAR: &[u8] = b"d8:announce11:example.com4:infod11:collectionsl5:linux6:debiane6:lengthi64e4:name5:hello12:piece lengthi4e6:pieces20:\x01\x02\x03\x04\x05\x06\x07\x08\x09\x10\x11\x12\x13\x14\x15\x16\x17\x18\x19\x207:similarl20:\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa\xaa20:\xbb\xbb\xbb\xbb\xbb\xbb\xbb\xbb\xbb\xbb\xbb\xbb\xbb\xbb\xbb\xbb\xbb\xbb\xbb\xbbeee";
+
+    fn load(content: &[u8]) -> MetainfoFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content).unwrap();
+        Metainfo::from_path(file.path()).unwrap()
+    }
+
+    #[test]
+    fn from_path() {
+        let file = load(SIMILAR);
+        let metainfo = file.metainfo();
+
+        assert_eq!(metainfo, &from_bytes::<Metainfo>(SIMILAR).unwrap());
+        assert_eq!(file.document().bytes(), SIMILAR);
+
+        let path = env::temp_dir().join("tortue-structs-missing.torrent");
+        assert!(matches!(
+            Metainfo::from_path(path),
+            Err(MetainfoError::Io(e)) if e.kind() == io::ErrorKind::NotFound
+        ));
+    }
+
+    #[test]
+    fn raw_info() {
+        let torrent = Torrent::from_bytes(SIMILAR).unwrap();
+        let start = SIMILAR.windows(6).position(|w| w == b"4:info").unwrap();
+
+        assert_eq!(torrent.raw_info(), &SIMILAR[start + 6..SIMILAR.len() - 1]);
+        assert_eq!(
+            torrent.metainfo(),
+            &from_bytes::<Metainfo>(SIMILAR).unwrap()
+        );
+        assert_eq!(load(SIMILAR).torrent(), &torrent);
+
+        assert_eq!(
+            Torrent::from_bytes(b"d8:announce11:example.come").err(),
+            from_bytes::<Metainfo>(b"d8:announce11:example.come").err()
+        );
+    }
+
+    #[test]
+    fn deserialize_similar() {
+        let val = from_bytes::<Metainfo>(SIMILAR).unwrap();
+
+        assert_eq!(
+            val.info.similar(),
+            Some(&[InfoHash([0xaa; 20]), InfoHash([0xbb; 20])] as &[_])
+        );
+        assert_eq!(val.info.collections(), Some(&["linux", "debian"] as &[_]));
+        assert_eq!(
+            val.info.dedup_candidates(),
+            vec![
+                DedupCandidate::Similar(InfoHash([0xaa; 20])),
+                DedupCandidate::Similar(InfoHash([0xbb; 20])),
+                DedupCandidate::Collection("linux"),
+                DedupCandidate::Collection("debian"),
+            ]
+        );
+    }
+
+    #[test]
+    fn roundtrip_similar() {
+        let val = from_bytes::<Metainfo>(SIMILAR).unwrap();
+
+        // The keys must be written back inside of the info dictionary,
+        // exactly once, for the info hash to be preserved
+        let raw = parse_all(SIMILAR).unwrap().1.unwrap_dict().remove("info");
+        assert_eq!(Some(to_value(&val.info).unwrap()), raw);
+
+        let bytes = to_bytes(&val.info).unwrap();
+        assert_eq!(parse_all(&bytes).unwrap().1, raw.unwrap());
+        assert_eq!(from_bytes::<Info>(&bytes).unwrap(), val.info);
+    }
+    #[test]
+    fn roundtrip_meta_version() {
+        let info = b"d6:lengthi64e12:meta versioni2e4:name5:hello12:piece lengthi4e6:pieces4:abcde";
+        let val = from_bytes::<Info>(info).unwrap();
+
+        assert_eq!(val.meta_version(), Some(2));
+        assert_eq!(to_bytes(&val).unwrap(), &info[..]);
+    }
+
     #[test]
     fn deserialize_single_file() {
         let single_file = b"d8:announce11:example.com4:infod12:piece lengthi4e6:pieces4:\x01\x02\x03\x044:name5:hello6:lengthi64e6:md5sum32:\x01\x02\x03\x04\x05\x06\x07\x08\x09\x10\x11\x12\x13\x14\x15\x16\x17\x18\x19\x20\x21\x22\x23\x24\x25\x26\x27\x28\x29\x30\x31\x32ee";
@@ -308,7 +1452,7 @@ mod simple_test {
 
     #[test]
     fn deserialize_multi_file() {
-        let multi_file = b"d8:announce11:example.com4:infod12:piece lengthi4e6:pieces4:\x01\x02\x03\x044:name5:hello5:filesld4:name5:world6:lengthi64e6:md5sum32:\x01\x02\x03\x04\x05\x06\x07\x08\x09\x10\x11\x12\x13\x14\x15\x16\x17\x18\x19\x20\x21\x22\x23\x24\x25\x26\x27\x28\x29\x30\x31\x32eeee";
+        let multi_file = b"d8:announce11:example.com4:infod12:piece lengthi4e6:pieces4:\x01\x02\x03\x044:name5:hello5:filesld4:pathl5:worlde6:lengthi64e6:md5sum32:\x01\x02\x03\x04\x05\x06\x07\x08\x09\x10\x11\x12\x13\x14\x15\x16\x17\x18\x19\x20\x21\x22\x23\x24\x25\x26\x27\x28\x29\x30\x31\x32eeee";
 
         if let Ok(val) = from_bytes::<Metainfo>(multi_file) {
             assert!(val.info.is_multi_file());
@@ -316,4 +1460,459 @@ mod simple_test {
             assert!(false, "could not deserialize matainfo");
         }
     }
+
+    #[test]
+    fn accessors() {
+        let pieces = [0xaa; 40];
+        let md5sum = [0xbb; 16];
+        let info = Info::MultiFile {
+            piece_length: 16384,
+            pieces: &pieces,
+            private: Some(true),
+            similar: None,
+            collections: None,
+            meta_version: None,
+            dir_name: "dataset",
+            files: vec![
+                FileEntry::new(vec!["README"], 64, None),
+                FileEntry::new(
+                    vec!["data", "part-1.csv"],
+                    20000,
+                    Some(&md5sum),
+                ),
+            ],
+            extra: HashMap::new(),
+        };
+
+        assert!(info.is_multi_file());
+        assert_eq!(info.piece_length(), 16384);
+        assert_eq!(info.pieces(), &pieces[..]);
+        assert!(info.is_private());
+        assert_eq!(info.name(), "dataset");
+
+        let files = info.files().collect::<Vec<_>>();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path(), &["README"]);
+        assert_eq!(files[0].length(), 64);
+        assert_eq!(files[0].md5sum(), None);
+        assert_eq!(files[1].path(), &["data", "part-1.csv"]);
+        assert_eq!(
+            files[1].path_buf(),
+            Some(["data", "part-1.csv"].iter().collect())
+        );
+        assert_eq!(files[1].length(), 20000);
+        assert_eq!(files[1].md5sum(), Some(&md5sum[..]));
+
+        let bytes = to_bytes(&info).unwrap();
+        assert_eq!(from_bytes::<Info>(&bytes).unwrap(), info);
+
+        let file = FileInfo::new("hello", 64, Some(&md5sum));
+        assert_eq!(
+            (file.name(), file.length(), file.md5sum()),
+            ("hello", 64, Some(&md5sum[..]))
+        );
+
+        let info = Info::SingleFile {
+            piece_length: 4,
+            pieces: &pieces,
+            private: None,
+            similar: None,
+            collections: None,
+            meta_version: None,
+            info: file,
+            extra: HashMap::new(),
+        };
+        assert!(!info.is_private());
+        assert_eq!(info.name(), "hello");
+        let files = info.files().collect::<Vec<_>>();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path(), &["hello"]);
+        assert_eq!(files[0].length(), 64);
+    }
+
+    fn single_file(length: i64, pieces: &[u8]) -> Info<'_> {
+        Info::SingleFile {
+            piece_length: 16,
+            pieces,
+            private: None,
+            similar: None,
+            collections: None,
+            meta_version: None,
+            info: FileInfo::new("hello", length, None),
+            extra: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn metrics() {
+        let pieces = [0; 60];
+
+        let info = single_file(40, &pieces);
+        assert_eq!(info.total_size(), Ok(40));
+        assert_eq!(info.piece_count(), Ok(3));
+        assert_eq!(info.last_piece_len(), Ok(8));
+        assert!(info.validate().is_empty());
+
+        // Exact multiple of the piece length
+        let info = single_file(48, &pieces);
+        assert_eq!(info.piece_count(), Ok(3));
+        assert_eq!(info.last_piece_len(), Ok(16));
+
+        let info = single_file(0, &[]);
+        assert_eq!(info.piece_count(), Ok(0));
+        assert_eq!(info.last_piece_len(), Ok(0));
+
+        let info = Info::MultiFile {
+            piece_length: 16,
+            pieces: &pieces[..40],
+            private: None,
+            similar: None,
+            collections: None,
+            meta_version: None,
+            dir_name: "dir",
+            files: vec![
+                FileEntry::new(vec!["a"], 10, None),
+                FileEntry::new(vec!["b"], 0, None),
+                FileEntry::new(vec!["c"], 15, None),
+            ],
+            extra: HashMap::new(),
+        };
+        assert_eq!(info.total_size(), Ok(25));
+        assert_eq!(info.piece_count(), Ok(2));
+        assert_eq!(info.last_piece_len(), Ok(9));
+        assert!(info.validate().is_empty());
+    }
+
+    #[test]
+    fn piece_ranges() {
+        let pieces = [0; 80];
+        let info = Info::MultiFile {
+            piece_length: 16,
+            pieces: &pieces,
+            private: None,
+            similar: None,
+            collections: None,
+            meta_version: None,
+            dir_name: "dir",
+            files: vec![
+                FileEntry::new(vec!["byte"], 1, None),
+                FileEntry::new(vec!["piece"], 16, None),
+                FileEntry::new(vec!["pieces"], 40, None),
+                FileEntry::new(vec!["empty"], 0, None),
+                FileEntry::new(vec!["tail"], 3, None),
+            ],
+            extra: HashMap::new(),
+        };
+        let span = |file_index, offset, length| FileSpan {
+            file_index,
+            offset,
+            length,
+        };
+
+        // Files start at 0, 1, 17, 57 and 57 in pieces of 16 bytes
+        assert_eq!(
+            info.piece_ranges(0),
+            Ok(vec![span(0, 0, 1), span(1, 0, 15)])
+        );
+        assert_eq!(
+            info.piece_ranges(1),
+            Ok(vec![span(1, 15, 1), span(2, 0, 15)])
+        );
+        assert_eq!(info.piece_ranges(2), Ok(vec![span(2, 15, 16)]));
+        assert_eq!(
+            info.piece_ranges(3),
+            Ok(vec![span(2, 31, 9), span(4, 0, 3)])
+        );
+        assert_eq!(info.piece_ranges(4), Ok(vec![]));
+
+        assert_eq!(info.file_pieces(0), Ok(0..1));
+        assert_eq!(info.file_pieces(1), Ok(0..2));
+        assert_eq!(info.file_pieces(2), Ok(1..4));
+        assert_eq!(info.file_pieces(3), Ok(3..3));
+        assert_eq!(info.file_pieces(4), Ok(3..4));
+        assert_eq!(info.file_pieces(5), Ok(0..0));
+
+        // Both mappings agree with each other and with the sizes
+        let count = info.piece_count().unwrap();
+        let mut total = 0;
+        for index in 0..count {
+            let spans = info.piece_ranges(index).unwrap();
+            let length = spans.iter().map(|span| span.length).sum::<u64>();
+            if index + 1 == count {
+                assert_eq!(length, info.last_piece_len().unwrap());
+            } else {
+                assert_eq!(length, 16);
+            }
+            for span in spans {
+                assert!(info
+                    .file_pieces(span.file_index)
+                    .unwrap()
+                    .contains(&index));
+            }
+            total += length;
+        }
+        assert_eq!(total, info.total_size().unwrap());
+
+        let info = single_file(40, &pieces[..60]);
+        assert_eq!(info.piece_ranges(2), Ok(vec![span(0, 32, 8)]));
+        assert_eq!(info.file_pieces(0), Ok(0..3));
+
+        // The pieces must match the files
+        let info = single_file(40, &pieces);
+        assert!(info.piece_ranges(0).is_err());
+        assert!(info.file_pieces(0).is_err());
+    }
+
+    #[test]
+    fn web_sources() {
+        let data: &[u8] = b"d8:announce11:example.com9:httpseedsl21:http://seed.example/a21:http://seed.example/be4:infod6:lengthi4e4:name5:hello12:piece lengthi4e6:pieces0:e8:url-listl21:http://seed.example/b21:http://seed.example/ce7:website19:https://example.come";
+        let metainfo = from_bytes::<Metainfo>(data).unwrap();
+
+        assert_eq!(metainfo.website, Some("https://example.com"));
+        assert_eq!(
+            metainfo.httpseeds,
+            Some(vec!["http://seed.example/a", "http://seed.example/b"])
+        );
+        assert_eq!(
+            metainfo.all_web_sources(),
+            vec![
+                "http://seed.example/b",
+                "http://seed.example/c",
+                "http://seed.example/a",
+            ]
+        );
+        assert_eq!(to_bytes(&metainfo).unwrap(), data);
+
+        // Absent fields are not written
+        let metainfo = from_bytes::<Metainfo>(SIMILAR).unwrap();
+        assert_eq!(metainfo.all_web_sources(), Vec::<&str>::new());
+        let bytes = to_bytes(&metainfo).unwrap();
+        assert!(!bytes.windows(7).any(|w| w == b"website"));
+        assert!(!bytes.windows(9).any(|w| w == b"httpseeds"));
+    }
+
+    #[test]
+    fn created_at() {
+        let mut metainfo = from_bytes::<Metainfo>(SIMILAR).unwrap();
+        assert_eq!(metainfo.created_at(), None);
+
+        metainfo.creation_date = Some(1_429_786_237);
+        assert_eq!(
+            metainfo.created_at(),
+            Some(UNIX_EPOCH + Duration::from_secs(1_429_786_237))
+        );
+
+        metainfo.creation_date = Some(-1);
+        assert_eq!(
+            metainfo.created_at(),
+            Some(UNIX_EPOCH - Duration::from_secs(1))
+        );
+
+        metainfo.creation_date = Some(i64::MIN);
+        assert_eq!(metainfo.created_at(), None);
+    }
+
+    #[test]
+    fn nodes() {
+        let data: &[u8] = b"d8:announce11:example.com4:infod6:lengthi4e4:name5:hello12:piece lengthi4e6:pieces0:e5:nodesll18:router.example.comi6881eel7:1.2.3.4i6881eel3:::1i51413eeee";
+        let metainfo = from_bytes::<Metainfo>(data).unwrap();
+        let nodes = metainfo.nodes.as_ref().unwrap();
+
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[0].host, "router.example.com");
+        assert_eq!(nodes[0].socket_addr(), None);
+        assert_eq!(nodes[1].socket_addr(), Some(([1, 2, 3, 4], 6881).into()));
+        assert_eq!(nodes[2].port, 51413);
+        assert_eq!(to_bytes(&metainfo).unwrap(), data);
+
+        let malformed = b"d8:announce11:example.com4:infod6:lengthi4e4:name5:hello12:piece lengthi4e6:pieces0:e5:nodesl7:1.2.3.4i6881eee";
+        assert!(from_bytes::<Metainfo>(malformed).is_err());
+    }
+
+    #[test]
+    fn trackerless() {
+        let data: &[u8] = b"d4:infod6:lengthi4e4:name5:hello12:piece lengthi4e6:pieces0:e5:nodesll7:1.2.3.4i6881eeee";
+        let metainfo = from_bytes::<Metainfo>(data).unwrap();
+        assert_eq!(metainfo.announce, None);
+        assert_eq!(metainfo.nodes.as_ref().unwrap()[0].host, "1.2.3.4");
+        assert!(AnnounceTiers::new(&metainfo).is_empty());
+        assert_eq!(to_bytes(&metainfo).unwrap(), data);
+
+        let owned = from_bytes::<MetainfoOwned>(data).unwrap();
+        assert_eq!(owned.announce, None);
+        assert_eq!(owned.nodes, Some(vec![("1.2.3.4".to_owned(), 6881)]));
+        assert_eq!(to_bytes(&owned).unwrap(), data);
+
+        #[cfg(feature = "sha1")]
+        {
+            let torrent = Torrent::from_bytes(data).unwrap();
+            let link = torrent.magnet_link(&Default::default());
+            assert!(!link.contains("&tr="), "{}", link);
+        }
+    }
+
+    #[test]
+    fn url_list() {
+        let torrent = |url_list: &str| {
+            format!(
+                "d8:announce11:example.com4:infod5:filesld6:lengthi4e4:pathl1:a5:b c.de\
+                 ee4:name3:dir12:piece lengthi4e6:pieces0:e8:url-list{}e",
+                url_list
+            )
+        };
+
+        let single = torrent("23:http://seed.example.com");
+        let single = from_bytes::<Metainfo>(single.as_bytes()).unwrap();
+        assert_eq!(
+            single.url_list,
+            Some(UrlList::Single("http://seed.example.com"))
+        );
+        assert_eq!(single.all_web_sources(), vec!["http://seed.example.com"]);
+
+        let list =
+            torrent("l23:http://seed.example.com21:ftp://mirror.example/e");
+        let list = from_bytes::<Metainfo>(list.as_bytes()).unwrap();
+        assert_eq!(
+            list.url_list,
+            Some(UrlList::List(vec![
+                "http://seed.example.com",
+                "ftp://mirror.example/"
+            ]))
+        );
+
+        // Written back in the form it was read in
+        let bytes = to_bytes(&single).unwrap();
+        assert!(bytes.ends_with(b"8:url-list23:http://seed.example.come"));
+        assert_eq!(from_bytes::<Metainfo>(&bytes).unwrap(), single);
+
+        let mut listed = single.clone();
+        listed.url_list = Some(vec!["http://seed.example.com"].into());
+        let bytes = to_bytes(&listed).unwrap();
+        assert!(bytes.ends_with(b"8:url-listl23:http://seed.example.comee"));
+        assert_eq!(from_bytes::<Metainfo>(&bytes).unwrap(), listed);
+
+        let owned = MetainfoOwned::from(single.clone());
+        assert_eq!(
+            owned.url_list,
+            Some(UrlList::Single("http://seed.example.com".to_owned()))
+        );
+        assert_eq!(owned.metainfo(), single);
+
+        assert_eq!(
+            list.info.web_seed_urls("http://seed.example.com"),
+            vec!["http://seed.example.com/dir/a/b%20c.d"]
+        );
+        assert_eq!(
+            list.info.web_seed_urls("ftp://mirror.example/"),
+            vec!["ftp://mirror.example/dir/a/b%20c.d"]
+        );
+
+        let file = Info::SingleFile {
+            piece_length: 4,
+            pieces: &[],
+            private: None,
+            similar: None,
+            collections: None,
+            meta_version: None,
+            info: FileInfo::new("file.iso", 0, None),
+            extra: HashMap::new(),
+        };
+        assert_eq!(
+            file.web_seed_urls("http://seed.example.com/file.iso"),
+            vec!["http://seed.example.com/file.iso"]
+        );
+        assert_eq!(
+            file.web_seed_urls("http://seed.example.com/"),
+            vec!["http://seed.example.com/file.iso"]
+        );
+
+        assert!(from_bytes::<Metainfo>(torrent("i1e").as_bytes()).is_err());
+        assert_eq!(from_bytes::<Metainfo>(SIMILAR).unwrap().url_list, None);
+    }
+
+    #[test]
+    fn piece_hashes() {
+        let pieces = (0..60).collect::<Vec<u8>>();
+
+        let info = single_file(40, &pieces);
+        assert_eq!(info.piece_hashes().count(), 3);
+        assert_eq!(info.piece_hashes().last(), info.piece_hash(2));
+        assert_eq!(info.piece_hash(2).unwrap()[0], 40);
+        assert_eq!(info.piece_hash(2).unwrap()[19], 59);
+        assert_eq!(info.piece_hash(3), None);
+        assert_eq!(info.piece_hash(usize::MAX), None);
+
+        // Trailing bytes are not a hash
+        let info = single_file(40, &pieces[..50]);
+        assert_eq!(info.piece_hashes().count(), 2);
+        assert_eq!(info.piece_hash(2), None);
+        assert_eq!(info.validate(), vec![InfoError::PiecesLength(50)]);
+    }
+
+    #[test]
+    fn inconsistencies() {
+        let pieces = [0; 60];
+
+        let info = single_file(40, &pieces[..50]);
+        assert_eq!(info.piece_count(), Err(InfoError::PiecesLength(50)));
+        assert_eq!(info.last_piece_len(), Err(InfoError::PiecesLength(50)));
+        assert_eq!(info.validate(), vec![InfoError::PiecesLength(50)]);
+
+        let info = single_file(100, &pieces);
+        let error = InfoError::PieceCount {
+            expected: 7,
+            found: 3,
+        };
+        assert_eq!(info.total_size(), Ok(100));
+        assert_eq!(info.piece_count(), Err(error));
+        assert_eq!(info.validate(), vec![error]);
+
+        let info = single_file(-1, &pieces[..50]);
+        assert_eq!(info.total_size(), Err(InfoError::FileLength(-1)));
+        assert_eq!(
+            info.validate(),
+            vec![InfoError::FileLength(-1), InfoError::PiecesLength(50)]
+        );
+    }
+
+    #[test]
+    fn path_buf() {
+        let entry = |path: Vec<&'static str>| FileEntry {
+            file_size: 0,
+            md5sum: None,
+            path,
+            extra: HashMap::new(),
+        };
+
+        assert_eq!(
+            entry(vec!["a", "b.txt"]).path_buf(),
+            Some(["a", "b.txt"].iter().collect())
+        );
+        assert_eq!(entry(vec![]).path_buf(), None);
+        assert_eq!(entry(vec!["a", "..", "b.txt"]).path_buf(), None);
+        assert_eq!(entry(vec!["/etc", "passwd"]).path_buf(), None);
+        assert_eq!(entry(vec!["a/../../b.txt"]).path_buf(), None);
+        assert_eq!(entry(vec!["a", ".", "b.txt"]).path_buf(), None);
+        assert_eq!(entry(vec!["", "b.txt"]).path_buf(), None);
+    }
+
+    #[test]
+    fn deserialize_private() {
+        let private = |value: &str| {
+            let data = format!(
+                "d8:announce11:example.com4:infod12:piece lengthi4e6:pieces4:abcd4:name5:hello6:lengthi64e7:private{}ee",
+                value
+            );
+            match from_bytes::<Metainfo>(data.as_bytes()).unwrap().info {
+                Info::SingleFile { private, .. } => private,
+                Info::MultiFile { .. } => panic!("expected a single file"),
+            }
+        };
+
+        assert_eq!(private("i1e"), Some(true));
+        assert_eq!(private("i0e"), Some(false));
+        assert_eq!(private("1:1"), Some(true));
+        assert_eq!(private("4:true"), Some(true));
+        assert_eq!(private("1:0"), Some(false));
+    }
 }
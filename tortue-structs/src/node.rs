@@ -0,0 +1,143 @@
+use serde::{
+    de::{Error, SeqAccess, Visitor},
+    ser::SerializeSeq,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+};
+
+/// A DHT node to bootstrap from, given by trackerless torrents (BEP 5)
+///
+/// It is encoded as a list of its host and its port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Node<'a> {
+    /// A host name or an IP address
+    pub host: &'a str,
+
+    /// The UDP port of the node
+    pub port: u16,
+}
+
+impl Node<'_> {
+    /// The address of the node if its host is an IP address, IPv6 addresses
+    /// may be enclosed in brackets
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        let host = self
+            .host
+            .strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+            .unwrap_or(self.host);
+
+        host.parse::<IpAddr>()
+            .ok()
+            .map(|ip| SocketAddr::new(ip, self.port))
+    }
+}
+
+impl Serialize for Node<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(2))?;
+        seq.serialize_element(self.host)?;
+        seq.serialize_element(&self.port)?;
+        seq.end()
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a list of a host and a port")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let host = seq
+            .next_element()?
+            .ok_or_else(|| Error::invalid_length(0, &self))?;
+        let port = seq
+            .next_element()?
+            .ok_or_else(|| Error::invalid_length(1, &self))?;
+
+        if seq.next_element::<serde::de::IgnoredAny>()?.is_some() {
+            return Err(Error::invalid_length(3, &self));
+        }
+
+        Ok(Node { host, port })
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Node<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(NodeVisitor)
+    }
+}
+
+#[cfg(test)]
+mod node_tests {
+    use super::Node;
+    use std::net::SocketAddr;
+    use tortue_bencode::{from_bytes, to_bytes};
+
+    #[test]
+    fn test_nodes() {
+        let nodes = from_bytes::<Vec<Node>>(
+            b"ll19:router.utorrent.comi6881eel7:1.2.3.4i6881eel3:::1i51413eel5:[::1]i1eee",
+        )
+        .unwrap();
+
+        assert_eq!(
+            nodes[0],
+            Node {
+                host: "router.utorrent.com",
+                port: 6881,
+            }
+        );
+        assert_eq!(nodes[0].socket_addr(), None);
+        assert_eq!(
+            nodes[1].socket_addr(),
+            Some("1.2.3.4:6881".parse::<SocketAddr>().unwrap())
+        );
+        assert_eq!(
+            nodes[2].socket_addr(),
+            Some("[::1]:51413".parse::<SocketAddr>().unwrap())
+        );
+        assert_eq!(
+            nodes[3].socket_addr(),
+            Some("[::1]:1".parse::<SocketAddr>().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(from_bytes::<Node>(b"l7:1.2.3.4e").is_err());
+        assert!(from_bytes::<Node>(b"li6881e7:1.2.3.4e").is_err());
+        assert!(from_bytes::<Node>(b"l7:1.2.3.4i6881ei1ee").is_err());
+        assert!(from_bytes::<Node>(b"l7:1.2.3.4i65536ee").is_err());
+        assert!(from_bytes::<Node>(b"d4:host7:1.2.3.4e").is_err());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let node = Node {
+            host: "1.2.3.4",
+            port: 6881,
+        };
+        let bytes = to_bytes(&node).unwrap();
+
+        assert_eq!(bytes, b"l7:1.2.3.4i6881ee");
+        assert_eq!(from_bytes::<Node>(&bytes).unwrap(), node);
+    }
+}
@@ -0,0 +1,451 @@
+use crate::{FileEntry, FileInfo, Info, InfoHash, Metainfo, Node, UrlList};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use tortue_bencode::{from_value_ref, BencodedValue};
+
+/// A [`Metainfo`] owning its content, it can be kept after the bytes it was
+/// read from are dropped and deserialized with
+/// [`tortue_bencode::from_reader`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetainfoOwned {
+    /// The announce URL of the tracker, missing from trackerless torrents
+    pub announce: Option<String>,
+
+    /// Tiers of announce URLs (BEP 12)
+    pub announce_list: Option<Vec<Vec<String>>>,
+
+    /// The creation time of the torrent, in standard UNIX epoch format
+    pub creation_date: Option<i64>,
+
+    /// Free-form textual comments of the author
+    pub comment: Option<String>,
+
+    /// Name and version of the program used to create the .torrent
+    pub created_by: Option<String>,
+
+    /// The string encoding format used to generate the **pieces**
+    pub encoding: Option<String>,
+
+    /// URLs of HTTP servers hosting the files (BEP 19)
+    pub url_list: Option<UrlList<String>>,
+
+    /// URLs of seeds speaking the HTTP seeding protocol of BEP 17
+    pub httpseeds: Option<Vec<String>>,
+
+    /// Website of the content
+    pub website: Option<String>,
+
+    /// DHT nodes to bootstrap from, as hosts and ports (BEP 5)
+    pub nodes: Option<Vec<(String, u16)>>,
+
+    pub info: InfoOwned,
+
+    /// Keys this crate does not know about
+    pub extra: HashMap<String, BencodedValue<'static>>,
+}
+
+/// An [`Info`] owning its content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InfoOwned {
+    /// The torrent contains a single file
+    SingleFile {
+        /// Number of bytes in each piece
+        piece_length: i64,
+
+        /// 20 bytes SHA-1 hash value, one per piece
+        pieces: Vec<u8>,
+
+        /// Whether peers must only be obtained from the trackers
+        private: Option<bool>,
+
+        /// Info hashes of torrents sharing files with this one (BEP 38)
+        similar: Option<Vec<InfoHash>>,
+
+        /// Names of the collections this torrent belongs to (BEP 38)
+        collections: Option<Vec<String>>,
+
+        /// Version of the torrent format (BEP 52)
+        meta_version: Option<i64>,
+
+        /// The file of the torrent
+        info: FileInfoOwned,
+
+        /// Keys this crate does not know about
+        extra: HashMap<String, BencodedValue<'static>>,
+    },
+
+    /// The torrent contains multiple files
+    MultiFile {
+        /// Number of bytes in each piece
+        piece_length: i64,
+
+        /// 20 bytes SHA-1 hash value, one per piece
+        pieces: Vec<u8>,
+
+        /// Whether peers must only be obtained from the trackers
+        private: Option<bool>,
+
+        /// Info hashes of torrents sharing files with this one (BEP 38)
+        similar: Option<Vec<InfoHash>>,
+
+        /// Names of the collections this torrent belongs to (BEP 38)
+        collections: Option<Vec<String>>,
+
+        /// Version of the torrent format (BEP 52)
+        meta_version: Option<i64>,
+
+        /// Directory name containing the files
+        dir_name: String,
+
+        /// List of files in the torrent
+        files: Vec<FileEntryOwned>,
+
+        /// Keys this crate does not know about
+        extra: HashMap<String, BencodedValue<'static>>,
+    },
+}
+
+/// A [`FileInfo`] owning its content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileInfoOwned {
+    /// Name of the file
+    pub file_name: String,
+
+    /// Size (in bytes) of the file
+    pub file_size: i64,
+
+    /// md5 checksum of the file (optional)
+    pub md5sum: Option<Vec<u8>>,
+}
+
+/// A [`FileEntry`] owning its content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEntryOwned {
+    /// Size (in bytes) of the file
+    pub file_size: i64,
+
+    /// md5 checksum of the file (optional)
+    pub md5sum: Option<Vec<u8>>,
+
+    /// Path of the file, one element per directory
+    pub path: Vec<String>,
+
+    /// Keys this crate does not know about
+    pub extra: HashMap<String, BencodedValue<'static>>,
+}
+
+fn strings(strings: &[&str]) -> Vec<String> {
+    strings.iter().map(|s| (*s).to_owned()).collect()
+}
+
+fn borrowed(strings: &[String]) -> Vec<&str> {
+    strings.iter().map(String::as_str).collect()
+}
+
+fn owned_extra(
+    extra: HashMap<String, BencodedValue<'_>>,
+) -> HashMap<String, BencodedValue<'static>> {
+    extra
+        .into_iter()
+        .map(|(key, value)| (key, value.into_owned()))
+        .collect()
+}
+
+fn borrowed_extra<'a>(
+    extra: &'a HashMap<String, BencodedValue<'static>>,
+) -> HashMap<String, BencodedValue<'a>> {
+    extra
+        .iter()
+        .map(|(key, value)| (key.clone(), value.to_borrowed()))
+        .collect()
+}
+
+impl MetainfoOwned {
+    /// Borrows the content as a [`Metainfo`]
+    pub fn metainfo(&self) -> Metainfo<'_> {
+        Metainfo {
+            announce: self.announce.as_deref(),
+            announce_list: self.announce_list.as_ref().map(|tiers| {
+                tiers.iter().map(Vec::as_slice).map(borrowed).collect()
+            }),
+            creation_date: self.creation_date,
+            comment: self.comment.as_deref(),
+            created_by: self.created_by.as_deref(),
+            encoding: self.encoding.as_deref(),
+            url_list: self
+                .url_list
+                .as_ref()
+                .map(|urls| urls.map(String::as_str)),
+            httpseeds: self.httpseeds.as_deref().map(borrowed),
+            website: self.website.as_deref(),
+            nodes: self.nodes.as_ref().map(|nodes| {
+                nodes
+                    .iter()
+                    .map(|(host, port)| Node { host, port: *port })
+                    .collect()
+            }),
+            info: self.info.info(),
+            extra: borrowed_extra(&self.extra),
+        }
+    }
+}
+
+impl InfoOwned {
+    /// Borrows the content as an [`Info`]
+    pub fn info(&self) -> Info<'_> {
+        match self {
+            InfoOwned::SingleFile {
+                piece_length,
+                pieces,
+                private,
+                similar,
+                collections: c,
+                meta_version,
+                info,
+                extra,
+            } => Info::SingleFile {
+                piece_length: *piece_length,
+                pieces,
+                private: *private,
+                similar: similar.clone(),
+                collections: c.as_deref().map(borrowed),
+                meta_version: *meta_version,
+                info: info.file_info(),
+                extra: borrowed_extra(extra),
+            },
+            InfoOwned::MultiFile {
+                piece_length,
+                pieces,
+                private,
+                similar,
+                collections: c,
+                meta_version,
+                dir_name,
+                files,
+                extra,
+            } => Info::MultiFile {
+                piece_length: *piece_length,
+                pieces,
+                private: *private,
+                similar: similar.clone(),
+                collections: c.as_deref().map(borrowed),
+                meta_version: *meta_version,
+                dir_name,
+                files: files.iter().map(FileEntryOwned::file_entry).collect(),
+                extra: borrowed_extra(extra),
+            },
+        }
+    }
+}
+
+impl FileInfoOwned {
+    /// Borrows the content as a [`FileInfo`]
+    pub fn file_info(&self) -> FileInfo<'_> {
+        FileInfo::new(&self.file_name, self.file_size, self.md5sum.as_deref())
+    }
+}
+
+impl FileEntryOwned {
+    /// Borrows the content as a [`FileEntry`]
+    pub fn file_entry(&self) -> FileEntry<'_> {
+        FileEntry {
+            extra: borrowed_extra(&self.extra),
+            ..FileEntry::new(
+                borrowed(&self.path),
+                self.file_size,
+                self.md5sum.as_deref(),
+            )
+        }
+    }
+}
+
+impl From<Metainfo<'_>> for MetainfoOwned {
+    fn from(metainfo: Metainfo<'_>) -> Self {
+        MetainfoOwned {
+            announce: metainfo.announce.map(str::to_owned),
+            announce_list: metainfo.announce_list.map(|tiers| {
+                tiers.iter().map(Vec::as_slice).map(strings).collect()
+            }),
+            creation_date: metainfo.creation_date,
+            comment: metainfo.comment.map(str::to_owned),
+            created_by: metainfo.created_by.map(str::to_owned),
+            encoding: metainfo.encoding.map(str::to_owned),
+            url_list: metainfo
+                .url_list
+                .map(|urls| urls.map(|url| (*url).to_owned())),
+            httpseeds: metainfo.httpseeds.as_deref().map(strings),
+            website: metainfo.website.map(str::to_owned),
+            nodes: metainfo.nodes.map(|nodes| {
+                nodes
+                    .iter()
+                    .map(|node| (node.host.to_owned(), node.port))
+                    .collect()
+            }),
+            info: metainfo.info.into(),
+            extra: owned_extra(metainfo.extra),
+        }
+    }
+}
+
+impl From<Info<'_>> for InfoOwned {
+    fn from(info: Info<'_>) -> Self {
+        match info {
+            Info::SingleFile {
+                piece_length,
+                pieces,
+                private,
+                similar,
+                collections,
+                meta_version,
+                info,
+                extra,
+            } => InfoOwned::SingleFile {
+                piece_length,
+                pieces: pieces.to_vec(),
+                private,
+                similar,
+                collections: collections.as_deref().map(strings),
+                meta_version,
+                info: info.into(),
+                extra: owned_extra(extra),
+            },
+            Info::MultiFile {
+                piece_length,
+                pieces,
+                private,
+                similar,
+                collections,
+                meta_version,
+                dir_name,
+                files,
+                extra,
+            } => InfoOwned::MultiFile {
+                piece_length,
+                pieces: pieces.to_vec(),
+                private,
+                similar,
+                collections: collections.as_deref().map(strings),
+                meta_version,
+                dir_name: dir_name.to_owned(),
+                files: files.into_iter().map(FileEntryOwned::from).collect(),
+                extra: owned_extra(extra),
+            },
+        }
+    }
+}
+
+impl From<FileInfo<'_>> for FileInfoOwned {
+    fn from(info: FileInfo<'_>) -> Self {
+        FileInfoOwned {
+            file_name: info.file_name.to_owned(),
+            file_size: info.file_size,
+            md5sum: info.md5sum.map(<[u8]>::to_vec),
+        }
+    }
+}
+
+impl From<FileEntry<'_>> for FileEntryOwned {
+    fn from(entry: FileEntry<'_>) -> Self {
+        FileEntryOwned {
+            file_size: entry.file_size,
+            md5sum: entry.md5sum.map(<[u8]>::to_vec),
+            path: strings(&entry.path),
+            extra: owned_extra(entry.extra),
+        }
+    }
+}
+
+impl Metainfo<'_> {
+    /// Copies the content of the metainfo, see [`MetainfoOwned`]
+    pub fn into_owned(self) -> MetainfoOwned {
+        self.into()
+    }
+}
+
+impl Serialize for MetainfoOwned {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.metainfo().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MetainfoOwned {
+    /// Reads the metainfo as a value first, the borrowed metainfo is then
+    /// validated and copied out of it
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = BencodedValue::deserialize(deserializer)?;
+        from_value_ref::<Metainfo>(&value)
+            .map(MetainfoOwned::from)
+            .map_err(D::Error::custom)
+    }
+}
+
+impl Serialize for InfoOwned {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.info().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for InfoOwned {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = BencodedValue::deserialize(deserializer)?;
+        from_value_ref::<Info>(&value)
+            .map(InfoOwned::from)
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod owned_tests {
+    use crate::{InfoOwned, Metainfo, MetainfoOwned};
+    use tortue_bencode::{from_bytes, from_reader, to_bytes};
+
+    const MULTI_FILE: &[u8] =
+        include_bytes!("../tests/data/multi_file.torrent");
+
+    #[test]
+    fn outlives_buffer() {
+        let metainfo = {
+            let buffer = MULTI_FILE.to_vec();
+            from_bytes::<Metainfo>(&buffer).unwrap().into_owned()
+        };
+
+        assert_eq!(
+            metainfo.announce.as_deref(),
+            Some("udp://tracker.opentrackr.org:1337/announce")
+        );
+        match &metainfo.info {
+            InfoOwned::MultiFile { pieces, files, .. } => {
+                assert_eq!(pieces.len(), 20);
+                assert_eq!(files[1].path, vec!["data", "part-0001.csv"]);
+            }
+            InfoOwned::SingleFile { .. } => panic!("expected multiple files"),
+        }
+
+        assert_eq!(metainfo.metainfo(), from_bytes(MULTI_FILE).unwrap());
+        assert_eq!(to_bytes(&metainfo).unwrap(), MULTI_FILE);
+    }
+
+    #[test]
+    fn from_reader_owned() {
+        let metainfo = from_reader::<MetainfoOwned, _>(MULTI_FILE).unwrap();
+        assert_eq!(
+            metainfo,
+            from_bytes::<Metainfo>(MULTI_FILE).unwrap().into_owned()
+        );
+        assert_eq!(metainfo.info.info().pieces().len(), 20);
+
+        assert!(from_reader::<MetainfoOwned, _>(&b"d4:infoi1ee"[..]).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use crate::Metainfo;
+
+/// The trackers of a torrent grouped in tiers, in the order a client should
+/// try them (BEP 12)
+///
+/// Tiers are tried one after the other and the trackers of a tier are
+/// shuffled once, see [`AnnounceTiers::shuffle`]. A tracker which answers is
+/// moved to the front of its tier with [`AnnounceTiers::promote`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnounceTiers<'a> {
+    tiers: Vec<Vec<&'a str>>,
+}
+
+impl<'a> AnnounceTiers<'a> {
+    /// The tiers of the announce list, empty tiers being skipped, or a
+    /// single tier made of the announce URL if there is no list
+    pub fn new(metainfo: &Metainfo<'a>) -> Self {
+        let tiers = metainfo
+            .announce_list
+            .iter()
+            .flatten()
+            .filter(|tier| !tier.is_empty())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if !tiers.is_empty() {
+            AnnounceTiers { tiers }
+        } else if let Some(announce) =
+            metainfo.announce.filter(|announce| !announce.is_empty())
+        {
+            AnnounceTiers {
+                tiers: vec![vec![announce]],
+            }
+        } else {
+            AnnounceTiers { tiers: Vec::new() }
+        }
+    }
+
+    /// The trackers of each tier
+    pub fn tiers(&self) -> &[Vec<&'a str>] {
+        &self.tiers
+    }
+
+    /// The trackers in the order they should be tried
+    pub fn iter(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.tiers.iter().flatten().copied()
+    }
+
+    /// Number of trackers
+    pub fn len(&self) -> usize {
+        self.tiers.iter().map(Vec::len).sum()
+    }
+
+    /// Whether there is no tracker at all
+    pub fn is_empty(&self) -> bool {
+        self.tiers.is_empty()
+    }
+
+    /// Shuffles the trackers of each tier, the order of the tiers is kept
+    #[cfg(feature = "rand")]
+    pub fn shuffle<R>(&mut self, rng: &mut R)
+    where
+        R: rand::Rng + ?Sized,
+    {
+        use rand::seq::SliceRandom;
+
+        for tier in &mut self.tiers {
+            tier.shuffle(rng);
+        }
+    }
+
+    /// Moves a tracker which answered to the front of its tier, returns
+    /// whether it was found
+    pub fn promote(&mut self, url: &str) -> bool {
+        for tier in &mut self.tiers {
+            if let Some(index) = tier.iter().position(|&tracker| tracker == url)
+            {
+                tier[..=index].rotate_right(1);
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+impl<'a> From<&Metainfo<'a>> for AnnounceTiers<'a> {
+    fn from(metainfo: &Metainfo<'a>) -> Self {
+        AnnounceTiers::new(metainfo)
+    }
+}
+
+#[cfg(test)]
+mod tiers_tests {
+    use super::AnnounceTiers;
+    use crate::Metainfo;
+    use tortue_bencode::from_bytes;
+
+    const TIERS: &[u8] = b"d8:announce9:http://a/13:announce-listll9:http://a/9:http://b/9:http://c/el9:http://d/9:http://e/ee4:infod6:lengthi4e4:name5:hello12:piece lengthi4e6:pieces0:ee";
+
+    fn metainfo(data: &[u8]) -> Metainfo<'_> {
+        from_bytes(data).unwrap()
+    }
+
+    #[test]
+    fn test_fallback() {
+        let data = b"d8:announce9:http://a/4:infod6:lengthi4e4:name5:hello12:piece lengthi4e6:pieces0:ee";
+        let tiers = AnnounceTiers::new(&metainfo(data));
+        assert_eq!(tiers.tiers(), &[vec!["http://a/"]]);
+
+        // Empty tiers are skipped, an empty list falls back to the announce
+        let data = b"d8:announce9:http://a/13:announce-listle4:infod6:lengthi4e4:name5:hello12:piece lengthi4e6:pieces0:ee";
+        let tiers = AnnounceTiers::new(&metainfo(data));
+        assert_eq!(tiers.iter().collect::<Vec<_>>(), ["http://a/"]);
+
+        let data = b"d8:announce0:4:infod6:lengthi4e4:name5:hello12:piece lengthi4e6:pieces0:ee";
+        let tiers = AnnounceTiers::new(&metainfo(data));
+        assert!(tiers.is_empty());
+        assert_eq!(tiers.len(), 0);
+    }
+
+    #[test]
+    fn test_order() {
+        let tiers = AnnounceTiers::from(&metainfo(TIERS));
+
+        assert_eq!(tiers.len(), 5);
+        assert_eq!(
+            tiers.iter().collect::<Vec<_>>(),
+            [
+                "http://a/",
+                "http://b/",
+                "http://c/",
+                "http://d/",
+                "http://e/"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_promote() {
+        let mut tiers = AnnounceTiers::new(&metainfo(TIERS));
+
+        assert!(tiers.promote("http://c/"));
+        assert_eq!(
+            tiers.tiers(),
+            &[
+                vec!["http://c/", "http://a/", "http://b/"],
+                vec!["http://d/", "http://e/"]
+            ]
+        );
+
+        // Promotion stays within the tier of the tracker
+        assert!(tiers.promote("http://e/"));
+        assert!(tiers.promote("http://b/"));
+        assert_eq!(
+            tiers.iter().collect::<Vec<_>>(),
+            [
+                "http://b/",
+                "http://c/",
+                "http://a/",
+                "http://e/",
+                "http://d/"
+            ]
+        );
+
+        assert!(!tiers.promote("http://unknown/"));
+        assert!(tiers.promote("http://b/"));
+        assert_eq!(tiers.iter().next(), Some("http://b/"));
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_shuffle() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let original = AnnounceTiers::new(&metainfo(TIERS));
+        let shuffled = |seed| {
+            let mut tiers = original.clone();
+            tiers.shuffle(&mut StdRng::seed_from_u64(seed));
+            tiers
+        };
+
+        // The same seed gives the same order
+        assert_eq!(shuffled(7), shuffled(7));
+
+        // Trackers are only shuffled within their tier
+        for seed in 0..32 {
+            let mut tiers = shuffled(seed).tiers().to_vec();
+            for tier in &mut tiers {
+                tier.sort_unstable();
+            }
+            assert_eq!(tiers, original.tiers());
+        }
+
+        assert!((0..32).any(|seed| shuffled(seed) != original));
+    }
+}
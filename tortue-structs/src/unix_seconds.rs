@@ -0,0 +1,159 @@
+//! Reads and writes an `Option<SystemTime>` as an integer number of seconds
+//! since the UNIX epoch, as found in the `creation date` of metainfo files
+//!
+//! To be used with `#[serde(with = "tortue_structs::unix_seconds", default)]`.
+//! Times before the epoch are negative. Times outside of the years 1 to 9999,
+//! or which cannot be represented by a `SystemTime`, are errors rather than
+//! being clamped.
+
+use serde::{
+    de::{Error, Unexpected},
+    ser::Error as _,
+    Deserialize, Deserializer, Serializer,
+};
+use std::{
+    convert::TryFrom,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Seconds from the epoch to the first second of the year 1
+pub const MIN_SECONDS: i64 = -62_135_596_800;
+
+/// Seconds from the epoch to the last second of the year 9999
+pub const MAX_SECONDS: i64 = 253_402_300_799;
+
+/// The time `seconds` after the epoch, `None` if it is out of range
+pub fn to_system_time(seconds: i64) -> Option<SystemTime> {
+    if !(MIN_SECONDS..=MAX_SECONDS).contains(&seconds) {
+        return None;
+    }
+
+    let duration = Duration::from_secs(seconds.unsigned_abs());
+    if seconds < 0 {
+        UNIX_EPOCH.checked_sub(duration)
+    } else {
+        UNIX_EPOCH.checked_add(duration)
+    }
+}
+
+/// The number of whole seconds from the epoch to `time`, rounded down, `None`
+/// if it is out of range
+pub fn from_system_time(time: SystemTime) -> Option<i64> {
+    let seconds = match time.duration_since(UNIX_EPOCH) {
+        Ok(after) => i64::try_from(after.as_secs()).ok(),
+        Err(before) => {
+            let before = before.duration();
+            let seconds =
+                before.as_secs() + u64::from(before.subsec_nanos() > 0);
+            i64::try_from(seconds).ok().map(|seconds| -seconds)
+        }
+    }?;
+
+    Some(seconds)
+        .filter(|seconds| (MIN_SECONDS..=MAX_SECONDS).contains(seconds))
+}
+
+/// Writes the time as seconds, a missing time is skipped by the serializer
+pub fn serialize<S>(
+    time: &Option<SystemTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match time {
+        Some(time) => match from_system_time(*time) {
+            Some(seconds) => serializer.serialize_some(&seconds),
+            None => {
+                Err(S::Error::custom("time outside of the years 1 to 9999"))
+            }
+        },
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Reads seconds as a time
+pub fn deserialize<'de, D>(
+    deserializer: D,
+) -> Result<Option<SystemTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<i64>::deserialize(deserializer)? {
+        Some(seconds) => to_system_time(seconds).map(Some).ok_or_else(|| {
+            D::Error::invalid_value(
+                Unexpected::Signed(seconds),
+                &"a time between the years 1 and 9999",
+            )
+        }),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod unix_seconds_tests {
+    use super::{from_system_time, to_system_time, MAX_SECONDS, MIN_SECONDS};
+    use serde::{Deserialize, Serialize};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use tortue_bencode::{from_bytes, to_bytes};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Dated {
+        #[serde(rename = "creation date", with = "super", default)]
+        creation_date: Option<SystemTime>,
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let data = b"d13:creation datei1429786237ee";
+        let dated = from_bytes::<Dated>(data).unwrap();
+
+        assert_eq!(
+            dated.creation_date,
+            Some(UNIX_EPOCH + Duration::from_secs(1_429_786_237))
+        );
+        assert_eq!(to_bytes(&dated).unwrap(), data);
+
+        let missing = from_bytes::<Dated>(b"de").unwrap();
+        assert_eq!(missing.creation_date, None);
+        assert_eq!(to_bytes(&missing).unwrap(), b"de");
+    }
+
+    #[test]
+    fn test_negative() {
+        let data = b"d13:creation datei-86400ee";
+        let dated = from_bytes::<Dated>(data).unwrap();
+
+        assert_eq!(
+            dated.creation_date,
+            Some(UNIX_EPOCH - Duration::from_secs(86400))
+        );
+        assert_eq!(to_bytes(&dated).unwrap(), data);
+
+        // Times before the epoch are rounded down to whole seconds
+        let time = UNIX_EPOCH - Duration::from_millis(1500);
+        assert_eq!(from_system_time(time), Some(-2));
+        assert_eq!(from_system_time(UNIX_EPOCH), Some(0));
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        assert!(to_system_time(MIN_SECONDS).is_some());
+        assert!(to_system_time(MAX_SECONDS).is_some());
+        assert_eq!(to_system_time(MIN_SECONDS - 1), None);
+        assert_eq!(to_system_time(MAX_SECONDS + 1), None);
+        assert_eq!(to_system_time(i64::MIN), None);
+        assert_eq!(to_system_time(i64::MAX), None);
+
+        let far = UNIX_EPOCH + Duration::from_secs(MAX_SECONDS as u64 + 1);
+        assert_eq!(from_system_time(far), None);
+        assert!(to_bytes(&Dated {
+            creation_date: Some(far)
+        })
+        .is_err());
+        assert!(from_bytes::<Dated>(
+            b"d13:creation datei-9223372036854775808ee"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{
+    de::{Error, SeqAccess, Unexpected, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{fmt, marker::PhantomData};
+
+/// The URLs of HTTP servers hosting the files of a torrent (BEP 19)
+///
+/// The `url-list` key holds either a single URL or a list of URLs, the form
+/// it was read in is kept so that it is written back the same way.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UrlList<T> {
+    /// A single URL written as a string
+    Single(T),
+
+    /// URLs written as a list, even if there is only one
+    List(Vec<T>),
+}
+
+impl<T> UrlList<T> {
+    /// The URLs, whatever the form of the list
+    pub fn urls(&self) -> &[T] {
+        match self {
+            UrlList::Single(url) => std::slice::from_ref(url),
+            UrlList::List(urls) => urls,
+        }
+    }
+
+    /// Converts every URL, keeping the form of the list
+    pub fn map<'s, U, F>(&'s self, mut f: F) -> UrlList<U>
+    where
+        F: FnMut(&'s T) -> U,
+    {
+        match self {
+            UrlList::Single(url) => UrlList::Single(f(url)),
+            UrlList::List(urls) => UrlList::List(urls.iter().map(f).collect()),
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for UrlList<T> {
+    fn from(urls: Vec<T>) -> Self {
+        UrlList::List(urls)
+    }
+}
+
+impl<T: Serialize> Serialize for UrlList<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            UrlList::Single(url) => url.serialize(serializer),
+            UrlList::List(urls) => urls.serialize(serializer),
+        }
+    }
+}
+
+struct UrlListVisitor<'a>(PhantomData<&'a str>);
+
+impl<'de: 'a, 'a> Visitor<'de> for UrlListVisitor<'a> {
+    type Value = UrlList<&'a str>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a URL or a list of URLs")
+    }
+
+    fn visit_borrowed_str<E: Error>(
+        self,
+        v: &'de str,
+    ) -> Result<Self::Value, E> {
+        Ok(UrlList::Single(v))
+    }
+
+    fn visit_borrowed_bytes<E: Error>(
+        self,
+        v: &'de [u8],
+    ) -> Result<Self::Value, E> {
+        std::str::from_utf8(v)
+            .map(UrlList::Single)
+            .map_err(|_| Error::invalid_value(Unexpected::Bytes(v), &self))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut urls = Vec::new();
+        while let Some(url) = seq.next_element()? {
+            urls.push(url);
+        }
+
+        Ok(UrlList::List(urls))
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for UrlList<&'a str> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(UrlListVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod url_list_tests {
+    use super::UrlList;
+    use tortue_bencode::{from_bytes, to_bytes};
+
+    #[test]
+    fn test_forms() {
+        let single = from_bytes::<UrlList<&str>>(b"8:http://a").unwrap();
+        assert_eq!(single, UrlList::Single("http://a"));
+        assert_eq!(single.urls(), ["http://a"]);
+        assert_eq!(to_bytes(&single).unwrap(), b"8:http://a");
+
+        let list = from_bytes::<UrlList<&str>>(b"l8:http://ae").unwrap();
+        assert_eq!(list, UrlList::List(vec!["http://a"]));
+        assert_eq!(list.urls(), ["http://a"]);
+        assert_eq!(to_bytes(&list).unwrap(), b"l8:http://ae");
+
+        let owned = list.map(|url| (*url).to_owned());
+        assert_eq!(to_bytes(&owned).unwrap(), b"l8:http://ae");
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(from_bytes::<UrlList<&str>>(b"i1e").is_err());
+        assert!(from_bytes::<UrlList<&str>>(b"2:\xFF\xFE").is_err());
+        assert!(from_bytes::<UrlList<&str>>(b"li1ee").is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! Creates torrents from temporary files, checking the pieces against hashes
+//! computed over the concatenated content of the files
+#![cfg(feature = "sha1")]
+
+use sha1::{Digest, Sha1};
+use std::fs;
+use tempfile::TempDir;
+use tortue_bencode::{from_bytes, to_bytes};
+use tortue_structs::{
+    InfoOwned, Metainfo, MetainfoError, MetainfoOwned, Torrent, TorrentBuilder,
+};
+
+const PIECE: usize = 16 * 1024;
+
+fn content(length: usize, seed: u8) -> Vec<u8> {
+    (0..length).map(|i| (i as u8).wrapping_mul(seed)).collect()
+}
+
+fn hashes(data: &[u8], piece_length: usize) -> Vec<u8> {
+    data.chunks(piece_length)
+        .flat_map(|piece| Sha1::digest(piece).to_vec())
+        .collect()
+}
+
+#[test]
+fn test_single_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("data.bin");
+    let data = content(3 * PIECE + 100, 7);
+    fs::write(&path, &data).unwrap();
+
+    let torrent = TorrentBuilder::new()
+        .piece_length(PIECE as i64)
+        .add_file(&path)
+        .announce("http://tracker.example.com/announce")
+        .comment("test data")
+        .creation_date(1_600_000_000)
+        .private(true)
+        .build()
+        .unwrap();
+
+    let metainfo = &torrent.metainfo;
+    assert_eq!(
+        metainfo.announce.as_deref(),
+        Some("http://tracker.example.com/announce")
+    );
+    assert_eq!(metainfo.comment.as_deref(), Some("test data"));
+    assert_eq!(metainfo.creation_date, Some(1_600_000_000));
+    assert!(metainfo.created_by.is_some());
+
+    let info = metainfo.info.info();
+    assert!(info.is_single_file());
+    assert!(info.is_private());
+    assert_eq!(info.name(), "data.bin");
+    assert_eq!(info.piece_length(), PIECE as i64);
+    assert_eq!(info.pieces(), &hashes(&data, PIECE)[..]);
+    assert_eq!(info.pieces().len(), 4 * 20);
+
+    // The bytes are canonical and read back to the same metainfo
+    assert_eq!(to_bytes(&metainfo.metainfo()).unwrap(), torrent.bytes);
+    assert_eq!(
+        from_bytes::<MetainfoOwned>(&torrent.bytes).unwrap(),
+        torrent.metainfo
+    );
+    assert_eq!(
+        Torrent::from_bytes(&torrent.bytes).unwrap().metainfo(),
+        &from_bytes::<Metainfo>(&torrent.bytes).unwrap()
+    );
+}
+
+#[test]
+fn test_dir() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().join("dataset");
+    fs::create_dir_all(root.join("b/nested")).unwrap();
+
+    let files = [
+        ("a.txt", content(PIECE / 2, 3)),
+        ("b/empty", Vec::new()),
+        ("b/nested/c.bin", content(2 * PIECE, 5)),
+        ("d.bin", content(PIECE + 1, 11)),
+    ];
+    for (name, data) in files.iter() {
+        fs::write(root.join(name), data).unwrap();
+    }
+
+    let torrent = TorrentBuilder::new()
+        .piece_length(PIECE as i64)
+        .add_dir(&root)
+        .announce("udp://tracker.example.com:1337")
+        .build()
+        .unwrap();
+
+    let (name, entries) = match &torrent.metainfo.info {
+        InfoOwned::MultiFile {
+            dir_name, files, ..
+        } => (dir_name, files),
+        InfoOwned::SingleFile { .. } => panic!("expected a directory"),
+    };
+    assert_eq!(name, "dataset");
+
+    let paths = entries.iter().map(|e| e.path.join("/")).collect::<Vec<_>>();
+    assert_eq!(paths, vec!["a.txt", "b/empty", "b/nested/c.bin", "d.bin"]);
+    for ((_, data), entry) in files.iter().zip(entries) {
+        assert_eq!(entry.file_size, data.len() as i64);
+    }
+
+    // Pieces span file boundaries and ignore empty files
+    let all = files
+        .iter()
+        .flat_map(|(_, data)| data.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        torrent.metainfo.info.info().pieces(),
+        &hashes(&all, PIECE)[..]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlinks() {
+    let dir = TempDir::new().unwrap();
+    let outside = dir.path().join("outside.bin");
+    fs::write(&outside, content(100, 13)).unwrap();
+
+    let root = dir.path().join("root");
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("inside.bin"), content(200, 17)).unwrap();
+    std::os::unix::fs::symlink(&outside, root.join("link.bin")).unwrap();
+    std::os::unix::fs::symlink(&root, root.join("loop")).unwrap();
+
+    // Links found in directories are skipped
+    let torrent = TorrentBuilder::new()
+        .add_dir(&root)
+        .announce("http://tracker.example.com/announce")
+        .build()
+        .unwrap();
+    let info = torrent.metainfo.info.info();
+    let paths = info.files().map(|file| file.path()[0]).collect::<Vec<_>>();
+    assert_eq!(paths, vec!["inside.bin"]);
+
+    // Links given explicitly are followed, under the name of the link
+    let torrent = TorrentBuilder::new()
+        .add_file(root.join("link.bin"))
+        .announce("http://tracker.example.com/announce")
+        .build()
+        .unwrap();
+    let info = torrent.metainfo.info.info();
+    assert_eq!(info.name(), "link.bin");
+    assert_eq!(info.pieces(), &hashes(&content(100, 13), PIECE)[..]);
+}
+
+#[test]
+fn test_piece_length() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("empty");
+    fs::write(&path, b"").unwrap();
+
+    let build = |piece_length: Option<i64>| {
+        let builder = TorrentBuilder::new()
+            .add_file(&path)
+            .announce("http://tracker.example.com/announce");
+        match piece_length {
+            Some(length) => builder.piece_length(length),
+            None => builder,
+        }
+        .build()
+    };
+
+    // An empty file has no pieces
+    let info = build(None).unwrap().metainfo.info;
+    assert_eq!(info.info().piece_length(), PIECE as i64);
+    assert!(info.info().pieces().is_empty());
+
+    assert!(matches!(build(Some(1000)), Err(MetainfoError::Builder(_))));
+    assert!(matches!(
+        build(Some(PIECE as i64 * 3)),
+        Err(MetainfoError::Builder(_))
+    ));
+}
+
+#[test]
+fn test_errors() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("file");
+    fs::write(&path, b"data").unwrap();
+
+    let error = TorrentBuilder::new().add_file(&path).build().unwrap_err();
+    assert!(matches!(error, MetainfoError::Builder(_)), "{:?}", error);
+
+    let error = TorrentBuilder::new()
+        .announce("http://tracker.example.com/announce")
+        .add_file(&path)
+        .add_file(&path)
+        .build()
+        .unwrap_err();
+    assert!(matches!(error, MetainfoError::Builder(_)), "{:?}", error);
+
+    let error = TorrentBuilder::new()
+        .announce("http://tracker.example.com/announce")
+        .add_file(dir.path().join("missing"))
+        .build()
+        .unwrap_err();
+    assert!(matches!(error, MetainfoError::Io(_)), "{:?}", error);
+
+    let empty = dir.path().join("empty");
+    fs::create_dir(&empty).unwrap();
+    let error = TorrentBuilder::new()
+        .announce("http://tracker.example.com/announce")
+        .add_dir(&empty)
+        .build()
+        .unwrap_err();
+    assert!(matches!(error, MetainfoError::Builder(_)), "{:?}", error);
+}
@@ -0,0 +1,84 @@
+//! Keeps the keys unknown to the metainfo structures so that writing back a
+//! torrent gives the same bytes and the same info hash
+
+use tortue_bencode::{from_bytes, to_bytes, BencodedValue};
+use tortue_structs::{Info, Metainfo, MetainfoOwned, Torrent};
+
+/// Unknown keys at the top level, in the info dictionary and in a file
+const MULTI_FILE: &[u8] = b"d8:announce35:http://tracker.example.com/announce18:azureus_propertiesd17:dht_backup_enablei1ee4:infod7:entropy6:random5:filesld4:attr1:x6:lengthi3e4:pathl5:a.exeeed6:lengthi5e4:pathl5:b.txteee4:name7:dataset12:piece lengthi16384e6:pieces20:AAAAAAAAAAAAAAAAAAAA6:source7:TRACKERe9:publisher7:examplee";
+
+/// Unknown info keys sorted before and after all the known ones
+const SINGLE_FILE: &[u8] = b"d8:announce35:http://tracker.example.com/announce4:infod5:crc328:d87f7e0c6:lengthi4e4:name5:a.txt12:piece lengthi16384e6:pieces20:BBBBBBBBBBBBBBBBBBBB12:x_cross_seed3:abcee";
+
+#[test]
+fn test_capture() {
+    let metainfo = from_bytes::<Metainfo>(MULTI_FILE).unwrap();
+
+    let mut keys = metainfo.extra.keys().collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(keys, ["azureus_properties", "publisher"]);
+    assert_eq!(metainfo.extra["publisher"], BencodedValue::from("example"));
+
+    let info = &metainfo.info;
+    let mut keys = info.extra().keys().collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(keys, ["entropy", "source"]);
+    assert_eq!(info.extra()["source"], BencodedValue::from("TRACKER"));
+
+    let files = match info {
+        Info::MultiFile { files, .. } => files,
+        Info::SingleFile { .. } => panic!("expected multiple files"),
+    };
+    assert_eq!(files[0].extra["attr"], BencodedValue::from("x"));
+    assert!(files[1].extra.is_empty());
+}
+
+#[test]
+fn test_roundtrip() {
+    for data in [MULTI_FILE, SINGLE_FILE].iter() {
+        let metainfo = from_bytes::<Metainfo>(data).unwrap();
+        assert_eq!(to_bytes(&metainfo).unwrap(), *data);
+
+        let owned = from_bytes::<MetainfoOwned>(data).unwrap();
+        assert_eq!(owned, metainfo.clone().into_owned());
+        assert_eq!(to_bytes(&owned).unwrap(), *data);
+
+        let torrent = Torrent::from_bytes(data).unwrap();
+        assert_eq!(to_bytes(&metainfo.info).unwrap(), torrent.raw_info());
+    }
+}
+
+#[test]
+fn test_modify() {
+    let mut metainfo = from_bytes::<Metainfo>(SINGLE_FILE).unwrap();
+    metainfo.comment = Some("edited");
+    let bytes = to_bytes(&metainfo).unwrap();
+
+    let edited = from_bytes::<Metainfo>(&bytes).unwrap();
+    assert_eq!(edited.comment, Some("edited"));
+    assert_eq!(edited.info, metainfo.info);
+    assert_eq!(
+        Torrent::from_bytes(&bytes).unwrap().raw_info(),
+        Torrent::from_bytes(SINGLE_FILE).unwrap().raw_info()
+    );
+}
+
+#[cfg(feature = "sha1")]
+#[test]
+fn test_info_hash() {
+    use tortue_structs::InfoHash;
+
+    for data in [MULTI_FILE, SINGLE_FILE].iter() {
+        let metainfo = from_bytes::<Metainfo>(data).unwrap();
+        let bytes = to_bytes(&metainfo).unwrap();
+
+        assert_eq!(
+            Torrent::from_bytes(&bytes).unwrap().info_hash_v1(),
+            Torrent::from_bytes(data).unwrap().info_hash_v1()
+        );
+        assert_eq!(
+            InfoHash::from_info(&to_bytes(&metainfo.info).unwrap()),
+            Torrent::from_bytes(data).unwrap().info_hash_v1()
+        );
+    }
+}
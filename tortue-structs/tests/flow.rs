@@ -0,0 +1,77 @@
+//! End to end flow going through the different crates of the workspace, see
+//! `examples/announce.rs`: a torrent is created and loaded back, announced
+//! through the request and response traits of `tortue-reqtraits` against an
+//! in-process fake tracker, and its pieces are verified.
+//!
+//! It stops compiling or fails as soon as a public API used by a client
+//! drifts.
+#![cfg(all(feature = "sha1", feature = "rand"))]
+
+#[path = "../examples/announce.rs"]
+mod announce;
+
+use announce::{
+    content, run, seeds, send, Announce, FakeTracker, TrackerError,
+    PEER_ID_PREFIX, PIECE, PORT,
+};
+use reqwest::Client;
+use tokio::runtime::Runtime;
+use tortue_structs::{InfoHash, Metainfo};
+
+#[test]
+fn test_flow() {
+    let mut runtime = Runtime::new().unwrap();
+    let tracker = FakeTracker::start(&runtime);
+    let flow = run(&mut runtime, &tracker).unwrap();
+
+    assert_eq!(&flow.peer_id[..8], PEER_ID_PREFIX);
+    assert!(flow.peer_id[8..].iter().all(u8::is_ascii_alphanumeric));
+
+    assert_eq!(flow.announce.interval, 1800);
+    assert_eq!(flow.announce.peers(), seeds());
+    assert_eq!(flow.announce.complete, 2);
+    assert_eq!(flow.announce.incomplete, 1);
+    assert_eq!(flow.verified, [true, true, true]);
+}
+
+#[test]
+fn test_unknown_torrent() {
+    let mut runtime = Runtime::new().unwrap();
+    let tracker = FakeTracker::start(&runtime);
+
+    let announce = Announce {
+        url: tracker.announce_url(),
+        info_hash: InfoHash([0; 20]),
+        peer_id: announce::peer_id(),
+        port: PORT,
+        left: 0,
+    };
+    match send(&mut runtime, &Client::new(), announce) {
+        Err(TrackerError::Failure(reason)) => {
+            assert_eq!(reason, "unknown info hash")
+        }
+        result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn test_corrupted_piece() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("content.bin");
+    let content = content();
+    std::fs::write(&path, &content).unwrap();
+
+    let created = tortue_structs::TorrentBuilder::new()
+        .piece_length(PIECE as i64)
+        .add_file(&path)
+        .announce("http://127.0.0.1/announce")
+        .build()
+        .unwrap();
+    let metainfo =
+        tortue_bencode::from_bytes::<Metainfo>(&created.bytes).unwrap();
+
+    let mut piece = content[PIECE..2 * PIECE].to_vec();
+    assert_eq!(metainfo.info.verify_piece(1, &piece), Ok(true));
+    piece[42] ^= 1;
+    assert_eq!(metainfo.info.verify_piece(1, &piece), Ok(false));
+}
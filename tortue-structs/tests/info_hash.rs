@@ -0,0 +1,42 @@
+//! Checks the info hashes against the ones of the magnet links of known
+//! torrents
+#![cfg(feature = "sha1")]
+
+use tortue_bencode::to_bytes;
+use tortue_structs::{InfoHash, Torrent};
+
+const TORRENT: &[u8] = include_bytes!("../benches/test_data");
+
+/// Info dictionary whose keys are not sorted, re-encoding it would change the
+/// hash
+const UNSORTED_INFO: &[u8] = b"d6:pieces20:\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a\x0b\x0c\x0d\x0e\x0f\x10\x11\x12\x13\x144:name5:hello12:piece lengthi4e6:lengthi64ee";
+
+#[test]
+fn test_ubuntu() {
+    let torrent = Torrent::from_bytes(TORRENT).unwrap();
+
+    assert_eq!(
+        torrent.info_hash_v1().to_string(),
+        "fc8a15a2faf2734dbb1dc5f7afdc5c9beaeb1f59"
+    );
+    assert_eq!(
+        torrent.info_hash_v1(),
+        InfoHash::from_info(torrent.raw_info())
+    );
+}
+
+#[test]
+fn test_unsorted() {
+    let mut data = b"d4:info".to_vec();
+    data.extend(UNSORTED_INFO);
+    data.extend(b"8:announce11:example.come");
+
+    let torrent = Torrent::from_bytes(&data).unwrap();
+    assert_eq!(torrent.raw_info(), UNSORTED_INFO);
+    assert_eq!(torrent.metainfo().announce, Some("example.com"));
+    assert_ne!(to_bytes(&torrent.metainfo().info).unwrap(), UNSORTED_INFO);
+    assert_eq!(
+        torrent.info_hash_v1().to_string(),
+        "5bf676dd8fb886ae33ced48bf67301b7fc0ce088"
+    );
+}
@@ -0,0 +1,47 @@
+//! Checks the v2 info hashes of hybrid torrents and that v1 torrents do not
+//! have any (BEP 52)
+#![cfg(feature = "sha2")]
+
+use tortue_structs::Torrent;
+
+const TORRENT: &[u8] = include_bytes!("../benches/test_data");
+
+/// Info dictionary of a hybrid torrent (BEP 52)
+const HYBRID_INFO: &[u8] = b"d9:file treed5:hellod0:d6:lengthi64e11:pieces root32:\x20\x21\x22\x23\x24\x25\x26\x27\x28\x29\x2a\x2b\x2c\x2d\x2e\x2f\x30\x31\x32\x33\x34\x35\x36\x37\x38\x39\x3a\x3b\x3c\x3d\x3e\x3feee6:lengthi64e12:meta versioni2e4:name5:hello12:piece lengthi16384e6:pieces20:\x01\x02\x03\x04\x05\x06\x07\x08\x09\x0a\x0b\x0c\x0d\x0e\x0f\x10\x11\x12\x13\x14e";
+
+#[test]
+fn test_hybrid() {
+    let mut data = b"d8:announce11:example.com4:info".to_vec();
+    data.extend(HYBRID_INFO);
+    data.push(b'e');
+
+    let torrent = Torrent::from_bytes(&data).unwrap();
+    assert_eq!(torrent.raw_info(), HYBRID_INFO);
+    assert_eq!(torrent.metainfo().info.meta_version(), Some(2));
+
+    let hash =
+        "feb42f4c1e235329cfa13489f35d2d99b592f77964b4364a4c0a9d2417ed0e49";
+    let hex = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    };
+    assert_eq!(hex(&torrent.info_hash_v2().unwrap()), hash);
+    assert_eq!(torrent.truncated_v2().unwrap().to_string(), hash[..40]);
+
+    #[cfg(feature = "sha1")]
+    assert_eq!(
+        torrent.info_hash_v1().to_string(),
+        "cc448c6b37596d482876ab84c83d250bcf431b1f"
+    );
+}
+
+#[test]
+fn test_v1_only() {
+    let torrent = Torrent::from_bytes(TORRENT).unwrap();
+
+    assert_eq!(torrent.metainfo().info.meta_version(), None);
+    assert_eq!(torrent.info_hash_v2(), None);
+    assert_eq!(torrent.truncated_v2(), None);
+}
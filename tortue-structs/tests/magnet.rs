@@ -0,0 +1,111 @@
+//! Compares magnet links against the ones published for known torrents
+#![cfg(feature = "sha1")]
+
+use std::fs;
+use tempfile::TempDir;
+use tortue_structs::{Magnet, MagnetOptions, Torrent, TorrentBuilder};
+
+const TORRENT: &[u8] = include_bytes!("../benches/test_data");
+
+#[test]
+fn test_ubuntu() {
+    let torrent = Torrent::from_bytes(TORRENT).unwrap();
+
+    // The tracker given both as announce and in the list is written once
+    assert_eq!(
+        torrent.magnet_link(&MagnetOptions::default()),
+        "magnet:?xt=urn:btih:fc8a15a2faf2734dbb1dc5f7afdc5c9beaeb1f59\
+         &dn=ubuntu-15.04-desktop-amd64.iso\
+         &tr=http%3A%2F%2Flinuxtracker.org%3A2710%2F00000000000000000000000000000000%2Fannounce\
+         &tr=http%3A%2F%2Ftorrent.ubuntu.com%3A6969%2Fannounce\
+         &tr=http%3A%2F%2Fipv6.torrent.ubuntu.com%3A6969%2Fannounce"
+    );
+
+    let magnet =
+        Magnet::parse(&torrent.magnet_link(&MagnetOptions::default())).unwrap();
+    assert_eq!(magnet.info_hash, Some(torrent.info_hash_v1()));
+    assert_eq!(magnet.trackers.len(), 3);
+
+    let options = MagnetOptions {
+        name: false,
+        trackers: false,
+        exact_length: true,
+        base32: true,
+        ..MagnetOptions::default()
+    };
+    assert_eq!(
+        torrent.magnet_link(&options),
+        "magnet:?xt=urn:btih:7SFBLIX26JZU3OY5YX327XC4TPVOWH2Z&xl=1150844928"
+    );
+}
+
+#[test]
+fn test_names() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("Vidéo de vacances.mkv");
+    fs::write(&path, b"content").unwrap();
+
+    let torrent = TorrentBuilder::new()
+        .add_file(&path)
+        .announce("udp://tracker.example.com:1337/announce")
+        .creation_date(0)
+        .build()
+        .unwrap();
+    let torrent = Torrent::from_bytes(&torrent.bytes).unwrap();
+
+    let link = torrent.magnet_link(&MagnetOptions::default());
+    assert!(
+        link.ends_with(
+            "&dn=Vid%C3%A9o%20de%20vacances.mkv\
+             &tr=udp%3A%2F%2Ftracker.example.com%3A1337%2Fannounce"
+        ),
+        "{}",
+        link
+    );
+}
+
+#[test]
+fn test_invalid_lengths() {
+    let options = MagnetOptions {
+        exact_length: true,
+        ..MagnetOptions::default()
+    };
+
+    let negative = b"d4:infod6:lengthi-1e4:name1:a12:piece lengthi16384e6:pieces0:ee";
+    let torrent = Torrent::from_bytes(negative).unwrap();
+    assert!(!torrent.magnet_link(&options).contains("&xl="));
+
+    // The total size overflows
+    let mut data = b"d4:infod5:filesl".to_vec();
+    for _ in 0..3 {
+        data.extend(&b"d6:lengthi9223372036854775807e4:pathl1:aee"[..]);
+    }
+    data.extend(&b"e4:name1:a12:piece lengthi16384e6:pieces0:ee"[..]);
+    let torrent = Torrent::from_bytes(&data).unwrap();
+    assert!(!torrent.magnet_link(&options).contains("&xl="));
+}
+
+#[cfg(feature = "sha2")]
+#[test]
+fn test_hybrid() {
+    let mut data = b"d8:announce11:example.com4:info".to_vec();
+    data.extend(&b"d9:file treed5:hellod0:d6:lengthi64e11:pieces root32:"[..]);
+    data.extend(0x20..0x40);
+    data.extend(&b"eee6:lengthi64e12:meta versioni2e4:name5:hello12:piece lengthi16384e6:pieces20:"[..]);
+    data.extend(1..21);
+    data.extend(b"ee");
+
+    let torrent = Torrent::from_bytes(&data).unwrap();
+    assert_eq!(
+        torrent.magnet_link(&MagnetOptions::default()),
+        "magnet:?xt=urn:btih:cc448c6b37596d482876ab84c83d250bcf431b1f\
+         &xt=urn:btmh:1220feb42f4c1e235329cfa13489f35d2d99b592f77964b4364a4c0a9d2417ed0e49\
+         &dn=hello&tr=example.com"
+    );
+
+    let options = MagnetOptions {
+        v2: false,
+        ..MagnetOptions::default()
+    };
+    assert!(!torrent.magnet_link(&options).contains("btmh"));
+}
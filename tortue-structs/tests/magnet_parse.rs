@@ -0,0 +1,128 @@
+//! Parses magnet links, checking that they are written back without losing
+//! anything
+
+use tortue_structs::{InfoHash, Magnet, MagnetError};
+
+const HASH: &str = "fc8a15a2faf2734dbb1dc5f7afdc5c9beaeb1f59";
+
+fn info_hash() -> InfoHash {
+    let bytes = (0..20)
+        .map(|i| u8::from_str_radix(&HASH[2 * i..2 * i + 2], 16).unwrap())
+        .collect::<Vec<_>>();
+    InfoHash::from_slice(&bytes).unwrap()
+}
+
+fn roundtrip(magnet: &Magnet) {
+    assert_eq!(Magnet::parse(&magnet.to_string()).as_ref(), Ok(magnet));
+}
+
+#[test]
+fn test_hex() {
+    let link = format!(
+        "magnet:?xt=urn:btih:{}&dn=ubuntu-15.04-desktop-amd64.iso\
+         &tr=http%3A%2F%2Ftorrent.ubuntu.com%3A6969%2Fannounce\
+         &tr=http%3A%2F%2Fipv6.torrent.ubuntu.com%3A6969%2Fannounce",
+        HASH
+    );
+    let magnet = Magnet::parse(&link).unwrap();
+
+    assert_eq!(magnet.info_hash, Some(info_hash()));
+    assert_eq!(magnet.info_hash_v2, None);
+    assert_eq!(
+        magnet.name.as_deref(),
+        Some("ubuntu-15.04-desktop-amd64.iso")
+    );
+    assert_eq!(
+        magnet.trackers,
+        vec![
+            "http://torrent.ubuntu.com:6969/announce",
+            "http://ipv6.torrent.ubuntu.com:6969/announce",
+        ]
+    );
+    assert_eq!(magnet.to_string(), link);
+    roundtrip(&magnet);
+
+    // Uppercase hexadecimal is accepted too
+    let upper =
+        Magnet::parse(&format!("magnet:?xt=urn:btih:{}", HASH.to_uppercase()));
+    assert_eq!(upper.unwrap().info_hash, Some(info_hash()));
+}
+
+#[test]
+fn test_base32() {
+    let magnet =
+        Magnet::parse("magnet:?xt=urn:btih:7SFBLIX26JZU3OY5YX327XC4TPVOWH2Z")
+            .unwrap();
+
+    assert_eq!(magnet.info_hash, Some(info_hash()));
+    assert_eq!(magnet.name, None);
+    assert!(magnet.trackers.is_empty());
+    assert_eq!(magnet.to_string(), format!("magnet:?xt=urn:btih:{}", HASH));
+    roundtrip(&magnet);
+}
+
+#[test]
+fn test_parameters() {
+    let magnet = Magnet::parse(
+        "magnet:?xt=urn:btmh:1220feb42f4c1e235329cfa13489f35d2d99b592f77964b4364a4c0a9d2417ed0e49\
+         &dn=Vid%C3%A9o+de%20vacances\
+         &xl=1150844928\
+         &ws=https%3A%2F%2Fseed.example.com%2Ffiles%2F\
+         &x.pe=10.0.0.1%3A6881&x.pe=10.0.0.2%3A6881&so=0,2-4",
+    )
+    .unwrap();
+
+    assert_eq!(magnet.info_hash, None);
+    assert_eq!(magnet.info_hash_v2.unwrap()[..2], [0xfe, 0xb4]);
+    assert_eq!(magnet.name.as_deref(), Some("Vidéo de vacances"));
+    assert_eq!(magnet.exact_length, Some(1150844928));
+    assert_eq!(magnet.web_seeds, vec!["https://seed.example.com/files/"]);
+    assert_eq!(magnet.extra["x.pe"], vec!["10.0.0.1:6881", "10.0.0.2:6881"]);
+    assert_eq!(magnet.extra["so"], vec!["0,2-4"]);
+    roundtrip(&magnet);
+}
+
+#[test]
+fn test_errors() {
+    let parse = |link: &str| Magnet::parse(link).unwrap_err();
+
+    assert_eq!(parse("http://example.com"), MagnetError::NotMagnet);
+    assert_eq!(parse("magnet:?dn=name"), MagnetError::MissingHash);
+    assert_eq!(
+        parse("magnet:?xt=urn:btih:fc8a15a2"),
+        MagnetError::HashLength(8)
+    );
+    assert_eq!(
+        parse("magnet:?xt=urn:btih:fc8a15a2faf2734dbb1dc5f7afdc5c9beaeb1fzz"),
+        MagnetError::HashDigit {
+            position: 38,
+            found: 'z'
+        }
+    );
+    assert_eq!(
+        parse("magnet:?xt=urn:btih:7SFBLIX26JZU3OY5YX327XC4TPVOWH1Z"),
+        MagnetError::HashDigit {
+            position: 30,
+            found: '1'
+        }
+    );
+    assert_eq!(
+        parse(&format!(
+            "magnet:?xt=urn:btih:{}&xt=urn:btih:{}",
+            HASH, HASH
+        )),
+        MagnetError::DuplicateHash
+    );
+    assert_eq!(
+        parse("magnet:?xt=urn:btmh:1114feb42f4c1e235329cfa13489f35d2d99b592"),
+        MagnetError::UnsupportedMultihash
+    );
+    assert_eq!(
+        parse(&format!("magnet:?xt=urn:btih:{}&dn=a%2", HASH)),
+        MagnetError::InvalidEscape { position: 65 }
+    );
+    assert_eq!(
+        parse(&format!("magnet:?xt=urn:btih:{}&xl=-1", HASH)),
+        MagnetError::InvalidLength("-1".to_owned())
+    );
+}
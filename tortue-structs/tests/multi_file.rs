@@ -0,0 +1,62 @@
+//! Reads a multi-file torrent laid out the way common torrent creators write
+//! them: nested directories, no md5sum and the files sorted by path
+
+use std::path::PathBuf;
+use tortue_bencode::{from_bytes, parser::lazy, to_bytes};
+use tortue_structs::{Info, Metainfo};
+
+const TORRENT: &[u8] = include_bytes!("data/multi_file.torrent");
+
+#[test]
+fn test_files() {
+    let metainfo = from_bytes::<Metainfo>(TORRENT).unwrap();
+    assert_eq!(metainfo.created_by, Some("mktorrent 1.1"));
+
+    let (piece_length, pieces, dir_name, files) = match &metainfo.info {
+        Info::MultiFile {
+            piece_length,
+            pieces,
+            dir_name,
+            files,
+            ..
+        } => (*piece_length, *pieces, *dir_name, files),
+        Info::SingleFile { .. } => panic!("fixture is a multi file torrent"),
+    };
+    assert_eq!(dir_name, "sample-dataset");
+
+    let paths = files
+        .iter()
+        .map(|file| file.path_buf().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        paths,
+        vec![
+            PathBuf::from("README.txt"),
+            ["data", "part-0001.csv"].iter().collect(),
+            ["data", "part-0002.csv"].iter().collect(),
+            ["docs", "images", "logo.svg"].iter().collect(),
+        ]
+    );
+    assert_eq!(files[3].path, vec!["docs", "images", "logo.svg"]);
+    assert!(files.iter().all(|file| file.md5sum.is_none()));
+
+    // The pieces span all the files, one after the other
+    let size = files.iter().map(|file| file.file_size).sum::<i64>();
+    assert_eq!(size, 6203);
+    assert_eq!(
+        (pieces.len() / 20) as i64,
+        (size + piece_length - 1) / piece_length
+    );
+    assert_eq!(metainfo.info.total_size(), Ok(6203));
+    assert_eq!(metainfo.info.piece_count(), Ok(1));
+    assert_eq!(metainfo.info.last_piece_len(), Ok(6203));
+    assert!(metainfo.info.validate().is_empty());
+}
+
+#[test]
+fn test_roundtrip() {
+    let metainfo = from_bytes::<Metainfo>(TORRENT).unwrap();
+    let raw_info = lazy(TORRENT).unwrap().get("info").unwrap().raw();
+
+    assert_eq!(to_bytes(&metainfo.info).unwrap(), raw_info);
+}
@@ -0,0 +1,132 @@
+//! Reads and writes metainfo files, checking that the info hash survives
+//! the round trip
+
+use std::fs;
+use tempfile::TempDir;
+use tortue_structs::{Metainfo, MetainfoError, Torrent};
+
+const SINGLE_FILE: &[u8] = include_bytes!("../benches/test_data");
+const MULTI_FILE: &[u8] = include_bytes!("data/multi_file.torrent");
+
+#[test]
+fn test_roundtrip() {
+    let dir = TempDir::new().unwrap();
+
+    for (name, content) in &[("single", SINGLE_FILE), ("multi", MULTI_FILE)] {
+        let original = dir.path().join(format!("{}.torrent", name));
+        let copy = dir.path().join(format!("{}-copy.torrent", name));
+        fs::write(&original, content).unwrap();
+
+        let file = Metainfo::from_path(&original).unwrap();
+        file.metainfo().write_to_path(&copy).unwrap();
+        let written = Metainfo::from_path(&copy).unwrap();
+
+        assert_eq!(written.metainfo(), file.metainfo());
+        assert_eq!(written.torrent().raw_info(), file.torrent().raw_info());
+
+        #[cfg(feature = "sha1")]
+        assert_eq!(
+            written.torrent().info_hash_v1(),
+            Torrent::from_bytes(content).unwrap().info_hash_v1()
+        );
+    }
+}
+
+#[test]
+fn test_keep_raw_info() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("stripped.torrent");
+
+    // The info dictionary is not canonical, its keys are not sorted
+    let mut original = b"d8:announce11:example.com13:announce-listll11:example.comel7:backup1ee4:infod6:pieces20:".to_vec();
+    original.extend(&[0xaa; 20]);
+    original.extend(b"4:name5:hello12:piece lengthi4e6:lengthi64eee");
+
+    let mut torrent = Torrent::from_bytes(&original).unwrap();
+    torrent.metainfo_mut().announce_list = None;
+    torrent.write_to_path(&path).unwrap();
+    assert!(!torrent.is_info_modified());
+
+    let written = Metainfo::from_path(&path).unwrap();
+    assert_eq!(written.metainfo().announce_list, None);
+    assert_eq!(written.metainfo().info, torrent.metainfo().info);
+    assert_eq!(
+        written.torrent().raw_info(),
+        Torrent::from_bytes(&original).unwrap().raw_info()
+    );
+
+    #[cfg(feature = "sha1")]
+    assert_eq!(
+        written.torrent().info_hash_v1(),
+        Torrent::from_bytes(&original).unwrap().info_hash_v1()
+    );
+
+    let mut torrent = Torrent::from_bytes(SINGLE_FILE).unwrap();
+    torrent.metainfo_mut().announce_list = None;
+    torrent.write_to_path(&path).unwrap();
+    let written = Metainfo::from_path(&path).unwrap();
+    assert_eq!(written.metainfo().announce_list, None);
+
+    #[cfg(feature = "sha1")]
+    assert_eq!(
+        written.torrent().info_hash_v1().to_string(),
+        "fc8a15a2faf2734dbb1dc5f7afdc5c9beaeb1f59"
+    );
+
+    // Writing the metainfo itself sorts the keys of the info dictionary
+    let written = Torrent::from_bytes(&original).unwrap();
+    written.metainfo().write_to_path(&path).unwrap();
+    assert_ne!(
+        Metainfo::from_path(&path).unwrap().torrent().raw_info(),
+        Torrent::from_bytes(&original).unwrap().raw_info()
+    );
+}
+
+#[test]
+fn test_set_info() {
+    let mut torrent = Torrent::from_bytes(SINGLE_FILE).unwrap();
+    let mut info = torrent.metainfo().info.clone();
+    if let tortue_structs::Info::SingleFile { private, .. } = &mut info {
+        *private = Some(true);
+    }
+
+    torrent.set_info(info).unwrap();
+    assert!(torrent.is_info_modified());
+    assert!(torrent.metainfo().info.is_private());
+
+    let bytes = torrent.to_bytes().unwrap();
+    let written = Torrent::from_bytes(&bytes).unwrap();
+    assert_eq!(written.raw_info(), torrent.raw_info());
+    assert_eq!(written.metainfo(), torrent.metainfo());
+
+    #[cfg(feature = "sha1")]
+    assert_ne!(
+        written.info_hash_v1(),
+        Torrent::from_bytes(SINGLE_FILE).unwrap().info_hash_v1()
+    );
+}
+
+#[test]
+fn test_errors() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("file.torrent");
+
+    let error = Metainfo::from_path(&path).unwrap_err();
+    assert!(matches!(error, MetainfoError::Io(_)), "{:?}", error);
+
+    fs::write(&path, b"d8:announce").unwrap();
+    let error = Metainfo::from_path(&path).unwrap_err();
+    assert!(matches!(error, MetainfoError::Parse(_)), "{:?}", error);
+
+    fs::write(&path, b"d8:announcei1ee").unwrap();
+    let error = Metainfo::from_path(&path).unwrap_err();
+    assert!(matches!(error, MetainfoError::Invalid(_)), "{:?}", error);
+    assert!(
+        error.to_string().starts_with("invalid metainfo: "),
+        "{}",
+        error
+    );
+
+    let error = Metainfo::from_path(dir.path()).unwrap_err();
+    assert!(matches!(error, MetainfoError::Io(_)), "{:?}", error);
+}
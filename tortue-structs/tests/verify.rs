@@ -0,0 +1,64 @@
+//! Checks downloaded pieces against the hashes of a torrent created from
+//! temporary files
+#![cfg(feature = "sha1")]
+
+use std::fs;
+use tempfile::TempDir;
+use tortue_structs::{InfoError, TorrentBuilder, VerifyError};
+
+const PIECE: usize = 16 * 1024;
+
+#[test]
+fn test_verify_piece() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("data.bin");
+    let data = (0..PIECE + 100).map(|i| (i / 3) as u8).collect::<Vec<_>>();
+    fs::write(&path, &data).unwrap();
+
+    let torrent = TorrentBuilder::new()
+        .piece_length(PIECE as i64)
+        .add_file(&path)
+        .announce("http://tracker.example.com/announce")
+        .build()
+        .unwrap();
+    let info = torrent.metainfo.info.info();
+    assert_eq!(info.piece_count(), Ok(2));
+
+    let (first, last) = data.split_at(PIECE);
+    assert_eq!(info.verify_piece(0, first), Ok(true));
+    assert_eq!(info.verify_piece(1, last), Ok(true));
+
+    let mut corrupted = first.to_vec();
+    corrupted[1234] ^= 0x10;
+    assert_eq!(info.verify_piece(0, &corrupted), Ok(false));
+    assert_eq!(info.verify_piece(1, &first[..100]), Ok(false));
+
+    assert_eq!(
+        info.verify_piece(0, last),
+        Err(VerifyError::Length {
+            expected: PIECE as u64,
+            found: 100,
+        })
+    );
+    assert_eq!(
+        info.verify_piece(1, first),
+        Err(VerifyError::Length {
+            expected: 100,
+            found: PIECE,
+        })
+    );
+    assert_eq!(
+        info.verify_piece(2, last),
+        Err(VerifyError::Index { index: 2, count: 2 })
+    );
+
+    // Pieces cannot be located in an inconsistent torrent
+    let mut info = torrent.metainfo.info.clone();
+    if let tortue_structs::InfoOwned::SingleFile { pieces, .. } = &mut info {
+        pieces.truncate(30);
+    }
+    assert_eq!(
+        info.info().verify_piece(0, first),
+        Err(VerifyError::Info(InfoError::PiecesLength(30)))
+    );
+}